@@ -0,0 +1,107 @@
+//! Timings for the board operations every frame leans on, to weigh changes
+//! to how the board is kept or drawn against. Run with `cargo bench`.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use ggez::graphics::{Color, DrawMode, MeshBuilder, Rect};
+use nalgebra::Point2;
+use tetris_rs::board::{Board, FixedBlock, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use tetris_rs::tetromino::{Rotation, Tetromino};
+
+/// Cell size the flat board mesh is built at, as drawn by default.
+const CELL_SIZE: f32 = 32.0;
+
+/// A board with its bottom `rows` rows full but for one column, staggered
+/// so no two rows leave the same gap.
+fn stacked(rows: usize) -> Board {
+    let mut board = Board::new(DEFAULT_WIDTH, DEFAULT_HEIGHT);
+    for y in DEFAULT_HEIGHT - rows..DEFAULT_HEIGHT {
+        for x in (0..DEFAULT_WIDTH).filter(|&x| x != y % DEFAULT_WIDTH) {
+            board.set(Point2::new(x as i32, y as i32), FixedBlock::Garbage);
+        }
+    }
+    board
+}
+
+/// `stacked` with the bottom `full` rows filled in, ready to clear.
+fn with_full_rows(rows: usize, full: usize) -> Board {
+    let mut board = stacked(rows);
+    for y in DEFAULT_HEIGHT - full..DEFAULT_HEIGHT {
+        let gap = Point2::new((y % DEFAULT_WIDTH) as i32, y as i32);
+        board.set(gap, FixedBlock::Garbage);
+    }
+    board
+}
+
+/// Drops each piece in each column and facing straight down onto the
+/// stack, checking every cell on the way, as the ghost piece and the bot
+/// do.
+fn collision(c: &mut Criterion) {
+    let board = stacked(DEFAULT_HEIGHT / 2);
+    c.bench_function("drop every piece in every column", |b| {
+        b.iter(|| {
+            let mut landed = 0;
+            for &tetromino in &Tetromino::ALL {
+                for &facing in &Rotation::ALL {
+                    for x in 0..DEFAULT_WIDTH as i32 {
+                        let mut pos = Point2::new(x, 0);
+                        let fits = |pos: Point2<i32>| {
+                            tetromino
+                                .blocks(pos, facing)
+                                .into_iter()
+                                .all(|block| board.is_free(block))
+                        };
+                        if !fits(pos) {
+                            continue;
+                        }
+                        while fits(Point2::new(pos[0], pos[1] + 1)) {
+                            pos[1] += 1;
+                        }
+                        landed += pos[1];
+                    }
+                }
+            }
+            black_box(landed)
+        })
+    });
+}
+
+fn line_clears(c: &mut Criterion) {
+    let board = stacked(DEFAULT_HEIGHT - 2);
+    c.bench_function("find full rows", |b| {
+        b.iter(|| black_box(&board).full_rows().count())
+    });
+    for &full in &[1, 4] {
+        let board = with_full_rows(DEFAULT_HEIGHT - 2, full);
+        c.bench_function(&format!("clear {} rows", full), |b| {
+            b.iter_batched(
+                || board.clone(),
+                |mut board| board.clear_lines(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+}
+
+/// Builds the flat mesh of a full board the way the renderer does, short
+/// of handing it to the GPU, which needs a window.
+fn board_mesh(c: &mut Criterion) {
+    let board = stacked(DEFAULT_HEIGHT);
+    c.bench_function("build full board mesh", |b| {
+        b.iter(|| {
+            let mut builder = MeshBuilder::new();
+            for (position, block) in board.visible_blocks() {
+                let rect = Rect::new(
+                    CELL_SIZE * position[0] as f32,
+                    CELL_SIZE * position[1] as f32,
+                    CELL_SIZE,
+                    CELL_SIZE,
+                );
+                builder.rectangle(DrawMode::fill(), rect, Color::from(block.rgb()));
+            }
+            black_box(builder)
+        })
+    });
+}
+
+criterion_group!(benches, collision, line_clears, board_mesh);
+criterion_main!(benches);
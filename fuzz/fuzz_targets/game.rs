@@ -0,0 +1,82 @@
+//! Plays arbitrary input a frame at a time and checks the round never
+//! panics and the board never ends up in a state play can't reach: the
+//! falling piece inside the stack, or a full row left standing.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tetris_rs::board::{Board, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use tetris_rs::game::{Input, Settings};
+use tetris_rs::replay::{Action, GameState};
+use tetris_rs::rules::{Randomizer, Rules};
+
+/// Frames past the end of the input, to let the last pieces lock.
+const SETTLE_FRAMES: usize = 120;
+
+/// The action one byte of input stands for: the low three bits pick the
+/// input, the next one whether it's pressed or let go, and the top ones
+/// garbage from an opponent or coming back from pause. Low bits past the
+/// last input leave the frame idle.
+fn action(byte: u8) -> Option<Action> {
+    let input = match Input::ALL.get((byte & 0x7) as usize) {
+        Some(&input) => input,
+        None => return None,
+    };
+    Some(match byte >> 4 {
+        0xf => Action::Garbage(u32::from(byte & 0x7) + 1),
+        0xe => Action::Resume,
+        _ if byte & 0x8 == 0 => Action::Press(input),
+        _ => Action::Release(input),
+    })
+}
+
+fn check(state: &GameState) {
+    let game = state.game();
+    assert!(
+        game.board.full_rows().next().is_none(),
+        "full row left on the board at frame {}:\n{}",
+        state.frame(),
+        game.board
+    );
+    if !game.game_over {
+        for block in game.active_blocks() {
+            assert!(
+                game.board.is_free(block),
+                "piece overlaps the stack at {:?} at frame {}:\n{}",
+                block,
+                state.frame(),
+                game.board
+            );
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 3 {
+        return;
+    }
+    let settings = Settings {
+        seed: u64::from(data[0]),
+        rules: if data[1] & 1 == 0 {
+            Rules::Modern
+        } else {
+            Rules::Classic
+        },
+        randomizer: Some(Randomizer::ALL[(data[1] >> 1) as usize % Randomizer::ALL.len()]),
+        ..Settings::default()
+    };
+    let mut state = GameState::new(Board::new(DEFAULT_WIDTH, DEFAULT_HEIGHT), settings);
+    // Each byte is a frame, with whatever action it stands for.
+    for &byte in &data[2..] {
+        let actions: Vec<Action> = action(byte).into_iter().collect();
+        state.step(&actions);
+        check(&state);
+        if state.game().game_over {
+            return;
+        }
+    }
+    for _ in 0..SETTLE_FRAMES {
+        state.step(&[]);
+        check(&state);
+    }
+});
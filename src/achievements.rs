@@ -0,0 +1,124 @@
+use crate::highscores;
+use ggez::filesystem;
+use ggez::{Context, GameResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tetris_rs::events::Observer;
+use tetris_rs::game::{Event, Game};
+use tetris_rs::mode::Mode;
+
+const ACHIEVEMENTS_FILE: &str = "achievements.toml";
+
+/// Something done in a round that is worth a mention, once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Achievement {
+    Tetris,
+    TSpin,
+    PerfectClear,
+    BackToBack,
+    Century,
+    HighScore,
+    PastLevel15,
+    Sprint,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 8] = [
+        Achievement::Tetris,
+        Achievement::TSpin,
+        Achievement::PerfectClear,
+        Achievement::BackToBack,
+        Achievement::Century,
+        Achievement::HighScore,
+        Achievement::PastLevel15,
+        Achievement::Sprint,
+    ];
+
+    /// Stable name written to the achievements file.
+    fn id(self) -> &'static str {
+        match self {
+            Achievement::Tetris => "tetris",
+            Achievement::TSpin => "t_spin",
+            Achievement::PerfectClear => "perfect_clear",
+            Achievement::BackToBack => "back_to_back_5",
+            Achievement::Century => "lines_100",
+            Achievement::HighScore => "score_100000",
+            Achievement::PastLevel15 => "past_level_15",
+            Achievement::Sprint => "sprint",
+        }
+    }
+
+    fn is_earned(self, game: &Game) -> bool {
+        let stats = game.stats();
+        match self {
+            Achievement::Tetris => stats.clears[3] > 0,
+            Achievement::TSpin => stats.t_spins > 0,
+            Achievement::PerfectClear => stats.perfect_clears > 0,
+            Achievement::BackToBack => stats.best_back_to_back >= 5,
+            Achievement::Century => game.lines >= 100,
+            Achievement::HighScore => game.score >= 100_000,
+            Achievement::PastLevel15 => game.level > 15,
+            Achievement::Sprint => game.mode() == Mode::Sprint && game.cleared_goal(),
+        }
+    }
+}
+
+/// The achievements unlocked so far, by name, with the day each was,
+/// stored in the user data directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Achievements {
+    #[serde(default)]
+    unlocked: BTreeMap<String, String>,
+    #[serde(skip)]
+    path: PathBuf,
+    /// Unlocked and not yet announced.
+    #[serde(skip)]
+    earned: Vec<Achievement>,
+}
+
+impl Achievements {
+    pub fn load(ctx: &Context) -> GameResult<Achievements> {
+        let path = filesystem::user_data_dir(ctx).join(ACHIEVEMENTS_FILE);
+        let mut achievements: Achievements = highscores::load_table(&path)?;
+        achievements.path = path;
+        Ok(achievements)
+    }
+
+    /// The achievements unlocked since the last call, to announce.
+    pub fn take_earned(&mut self) -> Vec<Achievement> {
+        std::mem::take(&mut self.earned)
+    }
+
+    /// Unlocks whatever `game` has earned that wasn't already, saving them
+    /// and returning them to announce.
+    pub fn check(&mut self, game: &Game) -> Vec<Achievement> {
+        let earned: Vec<Achievement> = Achievement::ALL
+            .iter()
+            .cloned()
+            .filter(|a| !self.unlocked.contains_key(a.id()) && a.is_earned(game))
+            .collect();
+        if earned.is_empty() {
+            return earned;
+        }
+        for achievement in &earned {
+            self.unlocked
+                .insert(achievement.id().to_string(), highscores::today());
+        }
+        if let Err(e) = highscores::save_table(&self.path, self) {
+            eprintln!("Could not save achievements: {}", e);
+        }
+        earned
+    }
+}
+
+/// Looks for anything newly earned once a piece locks, when everything an
+/// achievement counts has changed, and at the end of the round.
+impl Observer for Achievements {
+    fn notify(&mut self, event: Event, _: u64, game: &Game) {
+        if let Event::Lock { .. } | Event::GameOver = event {
+            let earned = self.check(game);
+            self.earned.extend(earned);
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! The built-in computer player. For each piece it tries every column and
+//! facing the piece could be dropped in and scores the board each would
+//! leave. Its `Difficulty` holds it back to give a person a game.
+
+use crate::board::{Board, FixedBlock};
+use crate::bot::{Bot, BotView, Decision, Placement};
+use crate::rules::Attack;
+use crate::tetromino::{Rotation, Tetromino};
+use nalgebra::{Point2, Vector2};
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// How much each feature of a board counts towards its score, higher
+/// scoring boards being preferred. Written in a file as in
+///
+/// ```toml
+/// holes = -0.5
+/// well_depth = 0.1
+/// ```
+///
+/// with any left out keeping their defaults.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Weights {
+    /// Summed height of every column.
+    pub aggregate_height: f32,
+    pub lines_cleared: f32,
+    /// Empty cells with a block somewhere above them.
+    pub holes: f32,
+    /// Summed height differences between neighbouring columns.
+    pub bumpiness: f32,
+    /// How far the deepest column sits below both its neighbours, the
+    /// walls counting as full.
+    pub well_depth: f32,
+    /// Garbage rows the clear would send by the guideline attack table.
+    pub attack: f32,
+}
+
+impl Default for Weights {
+    /// Weights tuned by genetic search in Yiyuan Lee's well-known
+    /// "Tetris AI – The (Near) Perfect Bot", which didn't look at wells or
+    /// attack.
+    fn default() -> Self {
+        Weights {
+            aggregate_height: -0.510_066,
+            lines_cleared: 0.760_666,
+            holes: -0.356_63,
+            bumpiness: -0.184_483,
+            well_depth: 0.0,
+            attack: 0.0,
+        }
+    }
+}
+
+impl Weights {
+    /// Weights from a file laid out as above.
+    pub fn parse(text: &str) -> Result<Weights, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+}
+
+/// How well the built-in bot plays.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    #[default]
+    Hard,
+    Inhuman,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Inhuman,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+            Difficulty::Inhuman => "Inhuman",
+        }
+    }
+
+    /// How many pieces, the current one first, each placement is judged
+    /// by. The bot is only ever shown the next piece, so at most two.
+    pub fn depth(self) -> usize {
+        match self {
+            Difficulty::Easy | Difficulty::Medium => 1,
+            Difficulty::Hard | Difficulty::Inhuman => 2,
+        }
+    }
+
+    /// The chance, in percent, of putting a piece somewhere at random
+    /// instead of the best place found.
+    pub fn misdrop_percent(self) -> u32 {
+        match self {
+            Difficulty::Easy => 15,
+            Difficulty::Medium => 4,
+            Difficulty::Hard | Difficulty::Inhuman => 0,
+        }
+    }
+
+    /// Logic steps to wait between presses, for a `BotDriver`.
+    pub fn frames_per_input(self) -> u32 {
+        match self {
+            Difficulty::Easy => 14,
+            Difficulty::Medium => 9,
+            Difficulty::Hard => 6,
+            Difficulty::Inhuman => 1,
+        }
+    }
+}
+
+/// How many rows below the spawn a piece may need to drop before it has room
+/// to turn.
+const ROTATION_ROOM: i32 = 3;
+
+/// Scores the board left behind, with `cleared` rows having just been
+/// cleared by the piece.
+pub fn evaluate(board: &Board, cleared: usize, weights: &Weights) -> f32 {
+    let heights: Vec<usize> = (0..board.width())
+        .map(|x| column_height(board, x))
+        .collect();
+    let aggregate_height: usize = heights.iter().sum();
+    let bumpiness: usize = heights
+        .windows(2)
+        .map(|pair| (pair[0] as i32 - pair[1] as i32).unsigned_abs() as usize)
+        .sum();
+    let holes: usize = (0..board.width())
+        .map(|x| {
+            let top = board.height() - heights[x];
+            (top..board.height())
+                .filter(|&y| board.is_free(Point2::new(x as i32, y as i32)))
+                .count()
+        })
+        .sum();
+    let wall = board.height();
+    let well_depth = (0..heights.len())
+        .map(|x| {
+            let left = if x == 0 { wall } else { heights[x - 1] };
+            let right = heights.get(x + 1).copied().unwrap_or(wall);
+            left.min(right).saturating_sub(heights[x])
+        })
+        .max()
+        .unwrap_or(0);
+    let attack = Attack::Guideline
+        .table()
+        .lines_sent(cleared, false, false, 0);
+    weights.aggregate_height * aggregate_height as f32
+        + weights.lines_cleared * cleared as f32
+        + weights.holes * holes as f32
+        + weights.bumpiness * bumpiness as f32
+        + weights.well_depth * well_depth as f32
+        + weights.attack * attack as f32
+}
+
+fn column_height(board: &Board, x: usize) -> usize {
+    (0..board.height())
+        .find(|&y| !board.is_free(Point2::new(x as i32, y as i32)))
+        .map_or(0, |top| board.height() - top)
+}
+
+/// Every resting place for `piece` dropped straight down from row
+/// `from_y`, each with the board it leaves and the rows it clears. Facings
+/// that stick out of the top of the board at `from_y` are tried a little
+/// lower, as a `BotDriver` moves the piece down when it has no room to turn.
+pub fn placements(board: &Board, piece: Tetromino, from_y: i32) -> Vec<(Placement, Board, usize)> {
+    let fits = |pos: Point2<i32>, facing: Rotation| {
+        piece
+            .blocks(pos, facing)
+            .into_iter()
+            .all(|block| board.is_free(block))
+    };
+    let mut results = Vec::new();
+    for facing in Rotation::ALL {
+        for x in -4..board.width() as i32 + 4 {
+            let start = (from_y..from_y + ROTATION_ROOM)
+                .map(|y| Point2::new(x, y))
+                .find(|&pos| fits(pos, facing));
+            let mut pos = match start {
+                Some(pos) => pos,
+                None => continue,
+            };
+            while fits(pos + Vector2::new(0, 1), facing) {
+                pos[1] += 1;
+            }
+            let mut after = board.clone();
+            for block in piece.blocks(pos, facing) {
+                after.set(block, FixedBlock::Piece(piece));
+            }
+            let cleared = after.clear_lines();
+            results.push((Placement { pos, facing }, after, cleared));
+        }
+    }
+    results
+}
+
+/// The built-in bot: picks the placement of the current piece that leaves
+/// the best scoring board, looking ahead to where the next piece would go
+/// unless its difficulty says otherwise.
+pub struct Ai {
+    weights: Weights,
+    depth: usize,
+    misdrop_percent: u32,
+    rng: XorShiftRng,
+}
+
+impl Ai {
+    /// The bot at its best.
+    pub fn new(weights: Weights) -> Ai {
+        Ai::with_difficulty(weights, Difficulty::Inhuman, 0)
+    }
+
+    /// The bot held back to `difficulty`, its misdrops drawn from `seed`.
+    pub fn with_difficulty(weights: Weights, difficulty: Difficulty, seed: u64) -> Ai {
+        Ai {
+            weights,
+            depth: difficulty.depth(),
+            misdrop_percent: difficulty.misdrop_percent(),
+            rng: XorShiftRng::seed_from_u64(seed),
+        }
+    }
+
+    /// What the board `after` a placement that cleared `cleared` rows is
+    /// worth, looking ahead to where `next` would go as deep as the
+    /// difficulty allows.
+    pub fn rate(&self, after: &Board, cleared: usize, next: Tetromino, from_y: i32) -> f32 {
+        let now = evaluate(after, cleared, &self.weights);
+        if self.depth < 2 {
+            return now;
+        }
+        // A placement that leaves no room for the next piece is only worth
+        // what it scores by itself.
+        self.best_score(after, next, from_y)
+            .map_or(now, |then| now + then)
+    }
+
+    /// The best score reachable by placing `piece` on `board`.
+    fn best_score(&self, board: &Board, piece: Tetromino, from_y: i32) -> Option<f32> {
+        placements(board, piece, from_y)
+            .into_iter()
+            .map(|(_, board, cleared)| evaluate(&board, cleared, &self.weights))
+            .max_by(compare)
+    }
+}
+
+impl Bot for Ai {
+    fn decide(&mut self, view: &BotView) -> Option<Decision> {
+        let from_y = view.pos[1];
+        let options = placements(view.board, view.current, from_y);
+        if !options.is_empty() && self.rng.gen_range(0, 100) < self.misdrop_percent {
+            let target = options[self.rng.gen_range(0, options.len())].0;
+            return Some(Decision {
+                target,
+                inputs: Vec::new(),
+            });
+        }
+        options
+            .into_iter()
+            .map(|(placement, board, cleared)| {
+                (placement, self.rate(&board, cleared, view.next, from_y))
+            })
+            .max_by(|a, b| compare(&a.1, &b.1))
+            .map(|(target, _)| Decision {
+                target,
+                inputs: Vec::new(),
+            })
+    }
+}
+
+fn compare(a: &f32, b: &f32) -> Ordering {
+    a.partial_cmp(b).unwrap_or(Ordering::Equal)
+}
@@ -0,0 +1,142 @@
+use crate::locale::Locale;
+use crate::text;
+use ggez::graphics::{self, Color};
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tetris_rs::events::Observer;
+use tetris_rs::game::{Event, Game};
+
+/// How long each announcement stays up, fading over the last part.
+const SHOWN_MILLIS: u64 = 1500;
+const FADE_MILLIS: u64 = 300;
+/// The most shown at once, one under another; the rest wait their turn.
+const MAX_SHOWN: usize = 3;
+const LINE_HEIGHT: f32 = 24.0;
+const SIZE: f32 = 18.0;
+
+/// A special action worth calling out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Announcement {
+    /// A T-spin, with the rows it cleared.
+    TSpin(usize),
+    /// A hard clear after another, the second and on.
+    BackToBack(u32),
+    /// Clears in a row, after the first.
+    Combo(usize),
+    PerfectClear,
+}
+
+impl Announcement {
+    fn text(self, locale: &Locale) -> String {
+        match self {
+            Announcement::TSpin(0) => locale.get("action.t_spin").to_string(),
+            Announcement::TSpin(1) => locale.get("action.t_spin_single").to_string(),
+            Announcement::TSpin(2) => locale.get("action.t_spin_double").to_string(),
+            Announcement::TSpin(_) => locale.get("action.t_spin_triple").to_string(),
+            Announcement::BackToBack(1) => locale.get("action.back_to_back").to_string(),
+            Announcement::BackToBack(chain) => {
+                locale.format("action.back_to_back_chain", &[&chain])
+            }
+            Announcement::Combo(combo) => locale.format("action.combo", &[&combo]),
+            Announcement::PerfectClear => locale.get("action.perfect_clear").to_string(),
+        }
+    }
+}
+
+/// Calls out T-spins, back-to-backs, combos and perfect clears in the side
+/// panel, a few at a time, so those that come together are still each
+/// readable.
+#[derive(Default)]
+pub struct Announcer {
+    /// Up now, with when each went up.
+    shown: VecDeque<(Announcement, Instant)>,
+    waiting: VecDeque<Announcement>,
+    /// A T-spin that hasn't yet been found to clear anything.
+    t_spin: bool,
+    /// Perfect clears so far, to spot the next.
+    perfect_clears: u32,
+    /// When the round being watched started, to start over with the next.
+    round: Option<Instant>,
+}
+
+impl Announcer {
+    /// Draws what is up at `now`, from `top` down at `left`, taking down
+    /// what has been up long enough and putting up what's waiting.
+    pub fn draw(
+        &mut self,
+        ctx: &mut Context,
+        now: Instant,
+        left: f32,
+        top: f32,
+        locale: &Locale,
+    ) -> GameResult {
+        let shown_for = Duration::from_millis(SHOWN_MILLIS);
+        self.shown
+            .retain(|&(_, at)| now.saturating_duration_since(at) < shown_for);
+        while self.shown.len() < MAX_SHOWN {
+            match self.waiting.pop_front() {
+                Some(announcement) => self.shown.push_back((announcement, now)),
+                None => break,
+            }
+        }
+        let fade = Duration::from_millis(FADE_MILLIS).as_secs_f32();
+        for (i, &(announcement, at)) in self.shown.iter().enumerate() {
+            let left_for = shown_for.saturating_sub(now.saturating_duration_since(at));
+            let color = Color {
+                a: (left_for.as_secs_f32() / fade).min(1.0),
+                ..graphics::WHITE
+            };
+            let at = Point2::new(left, top + LINE_HEIGHT * i as f32);
+            text::draw(ctx, &announcement.text(locale), SIZE, color, at)?;
+        }
+        Ok(())
+    }
+
+    fn announce(&mut self, announcement: Announcement) {
+        self.waiting.push_back(announcement);
+    }
+}
+
+impl Observer for Announcer {
+    fn notify(&mut self, event: Event, _: u64, game: &Game) {
+        if self.round != Some(game.started_at()) {
+            *self = Announcer {
+                round: Some(game.started_at()),
+                perfect_clears: game.stats().perfect_clears,
+                ..Announcer::default()
+            };
+        }
+        match event {
+            Event::TSpin => self.t_spin = true,
+            Event::LineClear(lines) => {
+                if self.t_spin {
+                    self.t_spin = false;
+                    self.announce(Announcement::TSpin(lines));
+                }
+                let stats = game.stats();
+                if stats.back_to_back > 0 {
+                    self.announce(Announcement::BackToBack(stats.back_to_back));
+                }
+                if game.combo() > 1 {
+                    self.announce(Announcement::Combo(game.combo() - 1));
+                }
+                if stats.perfect_clears > self.perfect_clears {
+                    self.perfect_clears = stats.perfect_clears;
+                    self.announce(Announcement::PerfectClear);
+                }
+            }
+            Event::Spawn(_) => {
+                // Undoing can take perfect clears back off the count.
+                self.perfect_clears = game.stats().perfect_clears;
+                // A T-spin that cleared nothing is over by the next piece.
+                if self.t_spin {
+                    self.t_spin = false;
+                    self.announce(Announcement::TSpin(0));
+                }
+            }
+            _ => (),
+        }
+    }
+}
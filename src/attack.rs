@@ -0,0 +1,119 @@
+//! How many garbage rows a clear sends to the other side in a versus round,
+//! and how the rows that come in are laid out. Like the kick tables these
+//! are data, built in from `attack.toml` and replaceable by the player's
+//! own, so house rules and other games' metas can be played.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+const BUILT_IN: &str = include_str!("attack.toml");
+
+/// The most rows any one entry may send.
+const MAX_ROWS: u32 = 40;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttackTable {
+    /// Rows sent for clearing one to four lines at once.
+    #[serde(default)]
+    lines: Vec<u32>,
+    /// Rows sent for a T-spin single, double or triple.
+    #[serde(default)]
+    t_spin: Vec<u32>,
+    /// Rows added for emptying the board.
+    #[serde(default)]
+    perfect_clear: u32,
+    /// Extra rows for each clear in a row after the first; the last entry
+    /// holds from there on.
+    #[serde(default)]
+    combo: Vec<u32>,
+    /// Whether each garbage row has its gap somewhere of its own, rather
+    /// than every row of a batch sharing one.
+    #[serde(default)]
+    pub messy: bool,
+}
+
+/// The entry for `count` in `list`, counted from 1, the last holding past
+/// the end. Nothing for 0 or an empty list.
+fn entry(list: &[u32], count: usize) -> u32 {
+    match count {
+        0 => 0,
+        _ => list
+            .get(count - 1)
+            .or_else(|| list.last())
+            .copied()
+            .unwrap_or(0),
+    }
+}
+
+impl AttackTable {
+    /// Rows sent by a piece that cleared `lines`, `combo` clears after the
+    /// previous piece that didn't clear anything.
+    pub fn lines_sent(&self, lines: usize, t_spin: bool, perfect_clear: bool, combo: usize) -> u32 {
+        if lines == 0 {
+            return 0;
+        }
+        let base = if t_spin && !self.t_spin.is_empty() {
+            entry(&self.t_spin, lines)
+        } else {
+            entry(&self.lines, lines)
+        };
+        let bonus = if perfect_clear { self.perfect_clear } else { 0 };
+        let combo = match self.combo.len() {
+            0 => 0,
+            len => self.combo[combo.min(len - 1)],
+        };
+        base.saturating_add(bonus).saturating_add(combo)
+    }
+
+    fn check(&self, name: &str) -> Result<(), String> {
+        let mut entries = self
+            .lines
+            .iter()
+            .chain(&self.t_spin)
+            .chain(&self.combo)
+            .chain([&self.perfect_clear]);
+        if entries.any(|&rows| rows > MAX_ROWS) {
+            return Err(format!(
+                "{:?} sends more than {} rows for one thing",
+                name, MAX_ROWS
+            ));
+        }
+        Ok(())
+    }
+}
+
+static TABLES: OnceLock<BTreeMap<String, AttackTable>> = OnceLock::new();
+
+fn parse(text: &str) -> Result<BTreeMap<String, AttackTable>, String> {
+    let tables: BTreeMap<String, AttackTable> = toml::from_str(text).map_err(|e| e.to_string())?;
+    for (name, table) in &tables {
+        table.check(name)?;
+    }
+    Ok(tables)
+}
+
+fn built_in() -> BTreeMap<String, AttackTable> {
+    parse(BUILT_IN).expect("built-in attack tables are valid")
+}
+
+/// Replaces built-in tables with those in `text`, in the layout of
+/// `attack.toml`, by name. Call it the way
+/// [`kicks::define`](crate::kicks::define) is.
+pub fn define(text: &str) -> Result<(), String> {
+    let mut tables = built_in();
+    tables.extend(parse(text)?);
+    TABLES
+        .set(tables)
+        .map_err(|_| "attack tables are already in use".to_string())
+}
+
+/// The table called `name`, or one that sends nothing if there isn't one.
+pub fn table(name: &str) -> &'static AttackTable {
+    static NONE: OnceLock<AttackTable> = OnceLock::new();
+    TABLES
+        .get_or_init(built_in)
+        .get(name)
+        .unwrap_or_else(|| NONE.get_or_init(AttackTable::default))
+}
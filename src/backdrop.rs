@@ -0,0 +1,184 @@
+//! Backdrops: what's drawn around the board in place of the plain
+//! background color, as the config's `backdrop` picks. Over anything but
+//! the plain color the board sits on a darkened panel, so the blocks stay
+//! as easy to make out on a busy picture as on the plain color.
+
+use ggez::graphics::{self, Color, DrawMode, Image, Mesh, MeshBuilder, Rect, Vertex};
+use ggez::nalgebra::{Point2, Vector2};
+use ggez::{Context, GameResult};
+use rand::Rng;
+use std::time::Duration;
+
+const STARFIELD: &str = "starfield";
+const GRADIENT: &str = "gradient";
+const STARS: usize = 150;
+/// How far the nearest stars fall in a second, in screen heights. The rest
+/// fall slower the further off they are.
+const STAR_SPEED: f32 = 0.05;
+/// The size of the nearest stars, in pixels.
+const STAR_SIZE: f32 = 3.0;
+/// How far round the color wheel the foot of the gradient turns each
+/// level, in degrees.
+const HUE_PER_LEVEL: f32 = 36.0;
+/// How far the gradient's hue drifts either way, in degrees, and how many
+/// seconds it takes to drift there and back.
+const HUE_DRIFT: f32 = 12.0;
+const DRIFT_SECS: f32 = 20.0;
+/// How much brighter the foot of the gradient is than the background.
+const GRADIENT_LIFT: f32 = 2.5;
+const PANEL: Color = Color {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.6,
+};
+
+/// A star, where it sits at the start in fractions of the screen, and how
+/// near it is, from 0 far off to 1 nearest.
+struct Star {
+    x: f32,
+    y: f32,
+    depth: f32,
+}
+
+enum Kind {
+    Plain,
+    Image(Image),
+    Starfield(Vec<Star>),
+    Gradient,
+}
+
+pub struct Backdrop {
+    kind: Kind,
+}
+
+impl Backdrop {
+    /// The backdrop `name` picks: `starfield`, `gradient`, an image file
+    /// in `/themes` from the resources or a pack, or the plain color if
+    /// it's empty or the image can't be loaded.
+    pub fn load(ctx: &mut Context, name: &str) -> Backdrop {
+        let kind = match name {
+            "" => Kind::Plain,
+            STARFIELD => {
+                // Only for show, so not from the round's seed.
+                let mut rng = rand::thread_rng();
+                let stars = (0..STARS)
+                    .map(|_| Star {
+                        x: rng.gen(),
+                        y: rng.gen(),
+                        depth: rng.gen_range(0.2, 1.0),
+                    })
+                    .collect();
+                Kind::Starfield(stars)
+            }
+            GRADIENT => Kind::Gradient,
+            file => {
+                let path = format!("/themes/{}", file);
+                match Image::new(ctx, &path) {
+                    Ok(image) => Kind::Image(image),
+                    Err(e) => {
+                        eprintln!("Could not load {}: {}", path, e);
+                        Kind::Plain
+                    }
+                }
+            }
+        };
+        Backdrop { kind }
+    }
+
+    /// Draws the backdrop across `view` on the `background` color, as it
+    /// is `elapsed` in at `level`, then the darkened panel under `board`.
+    pub fn draw(
+        &self,
+        ctx: &mut Context,
+        view: Rect,
+        board: Rect,
+        background: Color,
+        level: u32,
+        elapsed: Duration,
+    ) -> GameResult {
+        let secs = elapsed.as_secs_f32();
+        match self.kind {
+            Kind::Plain => return Ok(()),
+            Kind::Image(ref image) => {
+                // Scaled to cover the whole view, cropping what's over.
+                let (width, height) = (f32::from(image.width()), f32::from(image.height()));
+                let scale = (view.w / width).max(view.h / height);
+                let at = Point2::new(
+                    view.x + (view.w - width * scale) / 2.0,
+                    view.y + (view.h - height * scale) / 2.0,
+                );
+                let param = graphics::DrawParam::new()
+                    .dest(at)
+                    .scale(Vector2::new(scale, scale));
+                graphics::draw(ctx, image, param)?;
+            }
+            Kind::Starfield(ref stars) => {
+                let mut builder = MeshBuilder::new();
+                for star in stars {
+                    let y = (star.y + secs * STAR_SPEED * star.depth).fract();
+                    let size = STAR_SIZE * star.depth;
+                    let cell = Rect::new(view.x + star.x * view.w, view.y + y * view.h, size, size);
+                    let color = Color::new(1.0, 1.0, 1.0, 0.2 + 0.8 * star.depth);
+                    builder.rectangle(DrawMode::fill(), cell, color);
+                }
+                let mesh = builder.build(ctx)?;
+                graphics::draw(ctx, &mesh, (Point2::new(0.0, 0.0),))?;
+            }
+            Kind::Gradient => {
+                let drift = HUE_DRIFT * (secs / DRIFT_SECS * std::f32::consts::TAU).sin();
+                let foot = lift(turn_hue(background, HUE_PER_LEVEL * level as f32 + drift));
+                let corner = |x: f32, y: f32, color: Color| Vertex {
+                    pos: [x, y],
+                    uv: [0.0, 0.0],
+                    color: color.into(),
+                };
+                let (right, bottom) = (view.x + view.w, view.y + view.h);
+                let corners = [
+                    corner(view.x, view.y, background),
+                    corner(right, view.y, background),
+                    corner(right, bottom, foot),
+                    corner(view.x, bottom, foot),
+                ];
+                let mesh = Mesh::from_raw(ctx, &corners, &[0, 1, 2, 0, 2, 3], None);
+                graphics::draw(ctx, &mesh, (Point2::new(0.0, 0.0),))?;
+            }
+        }
+        let panel = Mesh::new_rectangle(ctx, DrawMode::fill(), board, PANEL)?;
+        graphics::draw(ctx, &panel, (Point2::new(0.0, 0.0),))
+    }
+}
+
+/// `color` turned `degrees` round the color wheel, keeping its brightness.
+fn turn_hue(color: Color, degrees: f32) -> Color {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let Color { r, g, b, a } = color;
+    let mix = |wr: f32, wg: f32, wb: f32| (wr * r + wg * g + wb * b).clamp(0.0, 1.0);
+    Color::new(
+        mix(
+            0.213 + cos * 0.787 - sin * 0.213,
+            0.715 - cos * 0.715 - sin * 0.715,
+            0.072 - cos * 0.072 + sin * 0.928,
+        ),
+        mix(
+            0.213 - cos * 0.213 + sin * 0.143,
+            0.715 + cos * 0.285 + sin * 0.140,
+            0.072 - cos * 0.072 - sin * 0.283,
+        ),
+        mix(
+            0.213 - cos * 0.213 - sin * 0.787,
+            0.715 - cos * 0.715 + sin * 0.715,
+            0.072 + cos * 0.928 + sin * 0.072,
+        ),
+        a,
+    )
+}
+
+fn lift(color: Color) -> Color {
+    Color::new(
+        (color.r * GRADIENT_LIFT).min(1.0),
+        (color.g * GRADIENT_LIFT).min(1.0),
+        (color.b * GRADIENT_LIFT).min(1.0),
+        color.a,
+    )
+}
@@ -0,0 +1,345 @@
+use crate::tetromino::Tetromino;
+#[cfg(not(target_arch = "wasm32"))]
+use ggez::graphics;
+use nalgebra::Point2;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The guideline-standard playfield size.
+pub const DEFAULT_WIDTH: usize = 10;
+pub const DEFAULT_HEIGHT: usize = 20;
+
+/// Widest board the row masks can hold.
+pub const MAX_WIDTH: usize = 32;
+
+/// Tallest board taken from outside, such as from the other side of a
+/// network game.
+pub const MAX_HEIGHT: usize = 100;
+
+/// Rows above the top of the board that aren't drawn, for pieces to turn
+/// into at the top and the stack to be pushed up into by garbage. Enough
+/// for any piece, custom ones reaching up to 4 cells from their centre.
+pub const HIDDEN_ROWS: usize = 4;
+
+/// How close the stack has to come to the top for it to be in danger.
+pub const DANGER_ROWS: usize = 4;
+
+/// The playfield: every cell is either empty or holds a locked block.
+/// Occupancy is kept as one bit mask per row, bit `x` for column `x`, so
+/// collision and full-row checks are bitwise operations; `cells` holds what
+/// each occupied cell is, for drawing. Rows are indexed from 0 at the top
+/// of the visible rows, with the `HIDDEN_ROWS` above them at -1 and up.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "SavedBoard", into = "SavedBoard")]
+pub struct Board {
+    width: usize,
+    /// Visible rows; `rows` and `cells` have the hidden ones first.
+    height: usize,
+    /// The mask of a row with every column filled.
+    full_row: u32,
+    rows: Vec<u32>,
+    cells: Vec<Vec<Option<FixedBlock>>>,
+}
+
+impl Board {
+    /// Panics if `width` is zero or more than `MAX_WIDTH`.
+    pub fn new(width: usize, height: usize) -> Board {
+        assert!(
+            width > 0 && width <= MAX_WIDTH,
+            "board width must be 1 to {}",
+            MAX_WIDTH
+        );
+        Board {
+            width,
+            height,
+            full_row: (u64::MAX >> (64 - width)) as u32,
+            rows: vec![0; HIDDEN_ROWS + height],
+            cells: vec![vec![None; width]; HIDDEN_ROWS + height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The index into `rows` and `cells` of row `y`.
+    fn index(y: i32) -> usize {
+        (y + HIDDEN_ROWS as i32) as usize
+    }
+
+    /// Whether `block` is on the board, hidden rows included.
+    fn contains(&self, block: Point2<i32>) -> bool {
+        block[0] >= 0
+            && block[1] >= -(HIDDEN_ROWS as i32)
+            && (block[0] as usize) < self.width
+            && block[1] < self.height as i32
+    }
+
+    /// Whether `block` is in the rows that get drawn.
+    pub fn is_visible(&self, block: Point2<i32>) -> bool {
+        self.contains(block) && block[1] >= 0
+    }
+
+    /// What is locked at `block`, or `None` if it is off the board.
+    pub fn get(&self, block: Point2<i32>) -> Option<Option<FixedBlock>> {
+        if self.contains(block) {
+            Some(self.cells[Board::index(block[1])][block[0] as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Whether `block` is on the board and empty.
+    pub fn is_free(&self, block: Point2<i32>) -> bool {
+        self.contains(block) && self.rows[Board::index(block[1])] & (1 << block[0]) == 0
+    }
+
+    /// Locks `fixed` into the cell at `block`. Returns false, leaving the
+    /// board as it was, if the cell is taken or off the board.
+    pub fn set(&mut self, block: Point2<i32>, fixed: FixedBlock) -> bool {
+        if !self.is_free(block) {
+            return false;
+        }
+        let (x, y) = (block[0] as usize, Board::index(block[1]));
+        self.rows[y] |= 1 << x;
+        self.cells[y][x] = Some(fixed);
+        true
+    }
+
+    /// Empties the cell at `block`. Returns false if there was nothing
+    /// there or it is off the board.
+    pub fn remove(&mut self, block: Point2<i32>) -> bool {
+        if !self.contains(block) || self.is_free(block) {
+            return false;
+        }
+        let (x, y) = (block[0] as usize, Board::index(block[1]));
+        self.rows[y] &= !(1 << x);
+        self.cells[y][x] = None;
+        true
+    }
+
+    /// The occupancy mask of visible row `y`, bit `x` set for each filled
+    /// column.
+    pub fn row_mask(&self, y: usize) -> u32 {
+        self.rows[HIDDEN_ROWS + y]
+    }
+
+    /// Every locked cell and what is in it, row by row from the top of the
+    /// hidden rows.
+    pub fn blocks<'a>(&'a self) -> impl Iterator<Item = (Point2<i32>, FixedBlock)> + 'a {
+        self.cells.iter().enumerate().flat_map(|(i, row)| {
+            let y = i as i32 - HIDDEN_ROWS as i32;
+            row.iter()
+                .enumerate()
+                .filter_map(move |(x, cell)| cell.map(|block| (Point2::new(x as i32, y), block)))
+        })
+    }
+
+    /// The locked cells in the visible rows.
+    pub fn visible_blocks<'a>(&'a self) -> impl Iterator<Item = (Point2<i32>, FixedBlock)> + 'a {
+        self.blocks().filter(|(block, _)| block[1] >= 0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.iter().all(|&row| row == 0)
+    }
+
+    /// How many rows from the bottom up to and including the highest
+    /// occupied one, which is more than `height` once the stack is into
+    /// the hidden rows.
+    pub fn stack_height(&self) -> usize {
+        self.rows
+            .iter()
+            .position(|&row| row != 0)
+            .map_or(0, |top| self.rows.len() - top)
+    }
+
+    /// Whether the stack is within `DANGER_ROWS` of the top, about to top
+    /// out.
+    pub fn in_danger(&self) -> bool {
+        self.stack_height() + DANGER_ROWS >= self.height
+    }
+
+    /// The rows with no gaps, top first.
+    pub fn full_rows<'a>(&'a self) -> impl Iterator<Item = i32> + 'a {
+        let full_row = self.full_row;
+        (-(HIDDEN_ROWS as i32)..self.height as i32)
+            .filter(move |&y| self.rows[Board::index(y)] == full_row)
+    }
+
+    /// Removes every full row at once, dropping the rows above them into
+    /// place, and returns how many were cleared.
+    pub fn clear_lines(&mut self) -> usize {
+        let full_row = self.full_row;
+        let mut y = 0;
+        let mut cleared = 0;
+        while y < self.rows.len() {
+            if self.rows[y] == full_row {
+                self.rows.remove(y);
+                self.cells.remove(y);
+                cleared += 1;
+            } else {
+                y += 1;
+            }
+        }
+        for _ in 0..cleared {
+            self.rows.insert(0, 0);
+            self.cells.insert(0, vec![None; self.width]);
+        }
+        cleared
+    }
+
+    /// Inserts `rows` garbage rows at the bottom of the board, each full
+    /// except for `hole_column`, shifting the stack up. Returns true if any
+    /// locked blocks were pushed out of the top of the hidden rows. A hole
+    /// past the right edge is put in the last column, so no row comes in
+    /// full.
+    pub fn push_garbage(&mut self, rows: usize, hole_column: usize) -> bool {
+        debug_assert!(hole_column < self.width(), "no column {}", hole_column);
+        let hole_column = hole_column.min(self.width - 1);
+        let rows = rows.min(self.height);
+        let overflowed = self.rows[..rows].iter().any(|&row| row != 0);
+        self.rows.drain(..rows);
+        self.cells.drain(..rows);
+        for _ in 0..rows {
+            let row: Vec<_> = (0..self.width)
+                .map(|x| {
+                    if x == hole_column {
+                        None
+                    } else {
+                        Some(FixedBlock::Garbage)
+                    }
+                })
+                .collect();
+            let mask = (0..self.width)
+                .filter(|&x| row[x].is_some())
+                .fold(0, |mask, x| mask | 1 << x);
+            self.rows.push(mask);
+            self.cells.push(row);
+        }
+        overflowed
+    }
+}
+
+/// How a board is written out: one string per visible row from the top, a
+/// character per cell as given by `FixedBlock::to_char`, `.` for empty, and
+/// the same for the hidden rows only if there's anything in them. The masks
+/// are rebuilt on load.
+#[derive(Serialize, Deserialize)]
+struct SavedBoard {
+    width: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    hidden: Vec<String>,
+    rows: Vec<String>,
+}
+
+fn row_text(row: &[Option<FixedBlock>]) -> String {
+    row.iter()
+        .map(|cell| cell.map_or('.', FixedBlock::to_char))
+        .collect()
+}
+
+impl From<Board> for SavedBoard {
+    fn from(board: Board) -> SavedBoard {
+        let (hidden, visible) = board.cells.split_at(HIDDEN_ROWS);
+        let hidden = if board.rows[..HIDDEN_ROWS].iter().any(|&row| row != 0) {
+            hidden.iter().map(|row| row_text(row)).collect()
+        } else {
+            Vec::new()
+        };
+        SavedBoard {
+            width: board.width,
+            hidden,
+            rows: visible.iter().map(|row| row_text(row)).collect(),
+        }
+    }
+}
+
+impl TryFrom<SavedBoard> for Board {
+    type Error = String;
+
+    fn try_from(saved: SavedBoard) -> Result<Board, String> {
+        if saved.width == 0 || saved.width > MAX_WIDTH {
+            return Err(format!("board width must be 1 to {}", MAX_WIDTH));
+        }
+        if saved.hidden.len() > HIDDEN_ROWS {
+            return Err(format!("there are only {} hidden rows", HIDDEN_ROWS));
+        }
+        let mut board = Board::new(saved.width, saved.rows.len());
+        // Hidden rows are saved all or nothing, but fewer would go just above
+        // the visible ones.
+        let hidden_top = -(saved.hidden.len() as i32);
+        let rows = saved.hidden.iter().chain(&saved.rows);
+        for (y, row) in (hidden_top..).zip(rows) {
+            if row.chars().count() != saved.width {
+                return Err(format!("row {} is not {} cells wide", y, saved.width));
+            }
+            for (x, c) in row.chars().enumerate() {
+                if c == '.' {
+                    continue;
+                }
+                let block = FixedBlock::from_char(c)
+                    .ok_or_else(|| format!("unknown cell {:?} in row {}", c, y))?;
+                board.set(Point2::new(x as i32, y), block);
+            }
+        }
+        Ok(board)
+    }
+}
+
+/// One line per visible row, as in a saved board.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in &self.cells[HIDDEN_ROWS..] {
+            writeln!(f, "{}", row_text(row))?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new(DEFAULT_WIDTH, DEFAULT_HEIGHT)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FixedBlock {
+    Piece(Tetromino),
+    Garbage,
+}
+
+impl FixedBlock {
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            FixedBlock::Piece(tetromino) => tetromino.rgb(),
+            FixedBlock::Garbage => (128, 128, 128),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn color(self) -> graphics::Color {
+        graphics::Color::from(self.rgb())
+    }
+
+    /// A single letter for the block: the piece's letter, or `G` for
+    /// garbage.
+    pub fn to_char(self) -> char {
+        match self {
+            FixedBlock::Piece(tetromino) => tetromino.symbol(),
+            FixedBlock::Garbage => 'G',
+        }
+    }
+
+    pub fn from_char(c: char) -> Option<FixedBlock> {
+        match c {
+            'G' => Some(FixedBlock::Garbage),
+            _ => Tetromino::from_symbol(c).map(FixedBlock::Piece),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! The interface for computer players. A `Bot` only decides where each
+//! piece goes; a `BotDriver` asks it once per piece and makes the presses,
+//! so a bot can be dropped into any game without touching the frontend.
+
+use crate::board::Board;
+use crate::clock::Instant;
+use crate::game::{Game, Input};
+use crate::tetromino::{Rotation, Tetromino};
+use nalgebra::Point2;
+use std::collections::VecDeque;
+
+/// Where to put a piece: the position it comes to rest at and its facing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Placement {
+    pub pos: Point2<i32>,
+    pub facing: Rotation,
+}
+
+/// What a bot is shown when a new piece comes into play.
+#[derive(Clone, Copy, Debug)]
+pub struct BotView<'a> {
+    pub board: &'a Board,
+    pub current: Tetromino,
+    pub next: Tetromino,
+    pub hold: Option<Tetromino>,
+    /// Where the current piece spawned and which way it faces.
+    pub pos: Point2<i32>,
+    pub facing: Rotation,
+}
+
+/// A bot's answer for one piece.
+#[derive(Clone, Debug)]
+pub struct Decision {
+    pub target: Placement,
+    /// The presses to make, in order. If empty, the driver steers the piece
+    /// to `target` itself: rotating, then shifting, then hard dropping.
+    pub inputs: Vec<Input>,
+}
+
+pub trait Bot {
+    /// Decides where the piece in `view` should go, or `None` to let it
+    /// fall where it is.
+    fn decide(&mut self, view: &BotView) -> Option<Decision>;
+}
+
+/// Plays a game with a `Bot`, one press at a time at a fixed pace.
+pub struct BotDriver {
+    bot: Box<dyn Bot>,
+    /// Frames to wait between presses.
+    frames_per_input: u32,
+    wait: u32,
+    /// The decision for the current piece, once the bot has been asked.
+    decision: Option<Decision>,
+    /// The bot's presses still to make.
+    inputs: VecDeque<Input>,
+    /// Where the piece was before the last press and what the press was,
+    /// to notice when it had no effect.
+    last: Option<((Point2<i32>, Rotation), Input)>,
+}
+
+impl BotDriver {
+    pub fn new(bot: Box<dyn Bot>, frames_per_input: u32) -> BotDriver {
+        BotDriver {
+            bot,
+            frames_per_input,
+            wait: 0,
+            decision: None,
+            inputs: VecDeque::new(),
+            last: None,
+        }
+    }
+
+    /// Where the current piece is headed, if the bot has picked a place.
+    pub fn target(&self) -> Option<Placement> {
+        self.decision.as_ref().map(|decision| decision.target)
+    }
+
+    /// Called once per frame, before `game.update`. Asks the bot about each
+    /// new piece as it spawns and makes at most one press.
+    pub fn step(&mut self, game: &mut Game, now: Instant) {
+        if game.game_over {
+            return;
+        }
+        if game.active_blocks().is_empty() {
+            // Between pieces; the next one needs a new decision.
+            self.decision = None;
+            self.inputs.clear();
+            self.last = None;
+            return;
+        }
+        if self.decision.is_none() {
+            let (pos, facing) = game.piece();
+            let view = BotView {
+                board: &game.board,
+                current: game.tetromino(),
+                next: game.next(),
+                hold: game.hold,
+                pos,
+                facing,
+            };
+            match self.bot.decide(&view) {
+                Some(decision) => {
+                    self.inputs = decision.inputs.iter().cloned().collect();
+                    self.decision = Some(decision);
+                }
+                None => return,
+            }
+        }
+        if self.wait > 0 {
+            self.wait -= 1;
+            return;
+        }
+        let input = match self.inputs.pop_front() {
+            Some(input) => input,
+            None => match self.decision {
+                Some(ref decision) if decision.inputs.is_empty() => self.steer(game),
+                _ => return,
+            },
+        };
+        self.last = Some((game.piece(), input));
+        game.press(input, now);
+        // Moves come from taps, not the auto-repeat of a held key.
+        game.release(input);
+        self.wait = self.frames_per_input;
+    }
+
+    /// The next press to take the piece towards the target.
+    fn steer(&self, game: &Game) -> Input {
+        let (pos, facing) = game.piece();
+        // A rotation that didn't take may fit once the piece is lower down,
+        // but a blocked move means making do with where the piece is.
+        if let Some((piece, last)) = self.last {
+            if piece == game.piece() {
+                return match last {
+                    Input::RotateCw => Input::SoftDrop,
+                    _ => Input::HardDrop,
+                };
+            }
+        }
+        let target = match self.target() {
+            Some(target) => target,
+            None => return Input::HardDrop,
+        };
+        if facing != target.facing {
+            Input::RotateCw
+        } else if pos[0] > target.pos[0] {
+            Input::Left
+        } else if pos[0] < target.pos[0] {
+            Input::Right
+        } else {
+            Input::HardDrop
+        }
+    }
+}
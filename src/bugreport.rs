@@ -0,0 +1,108 @@
+//! Bug reports: the last minute or so of the round in play, kept as it
+//! goes so it can be written out with a key press and attached to an
+//! issue. A report has a snapshot of the round every `SNAPSHOT_SECS` and
+//! every input since the oldest, each on the frame it happened, which is
+//! enough to play the round on from that snapshot the way it went. Rounds
+//! younger than that have every input from the start, and the seed in the
+//! snapshots to deal them the same pieces.
+
+use ggez::filesystem;
+use ggez::{Context, GameError, GameResult};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tetris_rs::game::{Game, Snapshot};
+use tetris_rs::replay::{self, Action, TimedAction};
+
+const REPORT_DIR: &str = "bug-reports";
+/// How far back a report goes, give or take a snapshot.
+const KEPT_SECS: u64 = 60;
+const SNAPSHOT_SECS: u64 = 10;
+
+#[derive(Clone, Debug, Serialize)]
+struct TimedSnapshot {
+    /// The frame the round stood like this after.
+    frame: u64,
+    snapshot: Snapshot,
+}
+
+/// The layout of a report file.
+#[derive(Serialize)]
+struct Report<'a> {
+    game_version: &'a str,
+    /// The frame it was written on, and how the round stood then.
+    frame: u64,
+    now: Snapshot,
+    snapshots: &'a VecDeque<TimedSnapshot>,
+    actions: &'a VecDeque<TimedAction>,
+}
+
+/// The rolling record of the round in play a report is written from.
+#[derive(Default)]
+pub struct InputLog {
+    actions: VecDeque<TimedAction>,
+    snapshots: VecDeque<TimedSnapshot>,
+    /// When the round being logged started, to start over with the next.
+    round: Option<Instant>,
+}
+
+impl InputLog {
+    pub fn record(&mut self, frame: u64, action: Action) {
+        self.actions.push_back(TimedAction { frame, action });
+    }
+
+    /// Keeps up with `game` as it stands after `frame`: a snapshot every
+    /// `SNAPSHOT_SECS`, and letting go of what's older than a report needs.
+    pub fn update(&mut self, frame: u64, game: &Game) {
+        if self.round != Some(game.started_at()) {
+            *self = InputLog {
+                round: Some(game.started_at()),
+                ..InputLog::default()
+            };
+        }
+        let fps = u64::from(replay::FRAMES_PER_SECOND);
+        if frame.is_multiple_of(SNAPSHOT_SECS * fps) {
+            self.snapshots.push_back(TimedSnapshot {
+                frame,
+                snapshot: game.snapshot(),
+            });
+        }
+        let oldest = frame.saturating_sub(KEPT_SECS * fps);
+        while self.snapshots.len() > 1 && self.snapshots[1].frame <= oldest {
+            self.snapshots.pop_front();
+        }
+        // Inputs on a snapshot's frame come after it.
+        let from = self.snapshots.front().map_or(0, |snapshot| snapshot.frame);
+        while self.actions.front().is_some_and(|timed| timed.frame < from) {
+            self.actions.pop_front();
+        }
+    }
+
+    /// Writes a report on `game` at `frame` to a new file in the
+    /// `bug-reports` folder of the user data directory, named after the
+    /// time it was written, and returns the path.
+    pub fn save(&self, ctx: &Context, frame: u64, game: &Game) -> GameResult<PathBuf> {
+        let report = Report {
+            game_version: env!("CARGO_PKG_VERSION"),
+            frame,
+            now: game.snapshot(),
+            snapshots: &self.snapshots,
+            actions: &self.actions,
+        };
+        let contents = toml::Value::try_from(&report)
+            .and_then(|value| toml::to_string(&value))
+            .map_err(|e| {
+                GameError::ResourceLoadError(format!("Could not write bug report: {}", e))
+            })?;
+        let dir = filesystem::user_data_dir(ctx).join(REPORT_DIR);
+        fs::create_dir_all(&dir)?;
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let path = dir.join(format!("{}.toml", secs));
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,85 @@
+//! The campaign: the missions in `resources/missions`, played in order,
+//! each unlocked by completing the one before, and the stars earned on
+//! them so far.
+
+use crate::highscores;
+use crate::puzzles;
+use ggez::filesystem;
+use ggez::{Context, GameResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tetris_rs::mission::Mission;
+
+const MISSION_DIR: &str = "missions";
+const CAMPAIGN_FILE: &str = "campaign.toml";
+
+/// The missions in `resources/missions` and the packs that are on, in
+/// order of file name, on a board `width` by `height`. Those that can't be
+/// read or don't fit are left out.
+pub fn load(ctx: &mut Context, width: usize, height: usize) -> Vec<Mission> {
+    puzzles::read_bundled(ctx, MISSION_DIR)
+        .into_iter()
+        .filter_map(|(path, text)| match Mission::parse(&text, width, height) {
+            Ok(mission) => Some(mission),
+            Err(e) => {
+                eprintln!("Invalid mission {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// The most stars earned on each mission completed, by name, stored in the
+/// user data directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Campaign {
+    #[serde(default)]
+    stars: BTreeMap<String, u32>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Campaign {
+    pub fn load(ctx: &Context) -> GameResult<Campaign> {
+        let path = filesystem::user_data_dir(ctx).join(CAMPAIGN_FILE);
+        let mut campaign: Campaign = highscores::load_table(&path)?;
+        campaign.path = path;
+        Ok(campaign)
+    }
+
+    /// The most stars earned on the mission called `name`, none if it
+    /// hasn't been completed.
+    pub fn stars(&self, name: &str) -> u32 {
+        self.stars.get(name).copied().unwrap_or(0)
+    }
+
+    /// Whether the mission at `at` in `missions` can be played: the first
+    /// always, the rest once the one before has been completed.
+    pub fn is_unlocked(&self, missions: &[Mission], at: usize) -> bool {
+        at == 0
+            || missions
+                .get(at - 1)
+                .is_some_and(|before| self.stars(&before.puzzle.name) > 0)
+    }
+
+    /// The stars earned across `missions`.
+    pub fn total(&self, missions: &[Mission]) -> u32 {
+        missions
+            .iter()
+            .map(|mission| self.stars(&mission.puzzle.name))
+            .sum()
+    }
+
+    /// Gives the mission called `name` `stars`, saving them if that's the
+    /// most yet.
+    pub fn record(&mut self, name: &str, stars: u32) {
+        if stars <= self.stars(name) {
+            return;
+        }
+        self.stars.insert(name.to_string(), stars);
+        if let Err(e) = highscores::save_table(&self.path, self) {
+            eprintln!("Could not save the campaign: {}", e);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Talking to the other player in a network game: short lines typed out,
+//! or stock ones a key each, shown for a while in a corner of the screen.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// The stock lines, on F5 to F8.
+pub const EMOTES: [&str; 4] = ["Good luck!", "Nice!", "Oops", "GG"];
+
+/// The longest line that can be sent; longer ones that come in are cut.
+pub const MAX_LEN: usize = 60;
+
+/// How many lines are kept on screen, and how long each stays.
+const SHOWN_LINES: usize = 5;
+const SHOWN_SECS: u64 = 8;
+/// The last part of that spent fading out.
+const FADE_MILLIS: u64 = 1000;
+
+struct Line {
+    text: String,
+    mine: bool,
+    at: Instant,
+}
+
+#[derive(Default)]
+pub struct Chat {
+    lines: VecDeque<Line>,
+    /// The line being typed, while one is.
+    typing: Option<String>,
+}
+
+impl Chat {
+    /// Puts up a line, sent by this player if `mine`.
+    pub fn add(&mut self, text: &str, mine: bool) {
+        if self.lines.len() == SHOWN_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(Line {
+            text: text.to_string(),
+            mine,
+            at: Instant::now(),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.typing = None;
+    }
+
+    /// The line being typed, if one is.
+    pub fn typing(&self) -> Option<&str> {
+        self.typing.as_deref()
+    }
+
+    pub fn start_typing(&mut self) {
+        self.typing = Some(String::new());
+    }
+
+    pub fn stop_typing(&mut self) {
+        self.typing = None;
+    }
+
+    /// Adds `c` to the line being typed, if there's room and it's
+    /// something that can be shown.
+    pub fn type_char(&mut self, c: char) {
+        if let Some(ref mut typing) = self.typing {
+            if !c.is_control() && typing.chars().count() < MAX_LEN {
+                typing.push(c);
+            }
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(ref mut typing) = self.typing {
+            typing.pop();
+        }
+    }
+
+    /// Finishes the line being typed, returning it unless it's blank.
+    pub fn finish(&mut self) -> Option<String> {
+        let typed = self.typing.take()?;
+        let typed = typed.trim();
+        if typed.is_empty() {
+            None
+        } else {
+            Some(typed.to_string())
+        }
+    }
+
+    /// The lines still up at `now`, oldest first, each with who said it
+    /// and how opaque to draw it.
+    pub fn shown(&self, now: Instant) -> impl Iterator<Item = (&str, bool, f32)> + '_ {
+        let shown = Duration::from_secs(SHOWN_SECS);
+        let fade = Duration::from_millis(FADE_MILLIS);
+        self.lines.iter().filter_map(move |line| {
+            let age = now.saturating_duration_since(line.at);
+            let left = shown.checked_sub(age)?;
+            let alpha = (left.as_secs_f32() / fade.as_secs_f32()).min(1.0);
+            Some((line.text.as_str(), line.mine, alpha))
+        })
+    }
+}
@@ -0,0 +1,138 @@
+use clap::Parser;
+use ggez::{GameError, GameResult};
+use std::fs;
+use std::path::PathBuf;
+use tetris_rs::ai::Weights;
+use tetris_rs::game::Settings;
+use tetris_rs::mode::Mode;
+
+/// Command-line options. Anything given here overrides the config file for
+/// this run only.
+#[derive(Debug, Parser)]
+#[command(name = "tetris-rs", version, about = "A falling block game")]
+pub struct Cli {
+    /// Seed for the piece sequence, so a run can be replayed exactly.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Level to start each game at, and marathons at instead of the level
+    /// last picked on the menu. Level 1 if unset.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub level: Option<u32>,
+    /// Run in a window, whatever the config says.
+    #[arg(long, conflicts_with = "fullscreen")]
+    pub windowed: bool,
+    /// Run borderless fullscreen, whatever the config says.
+    #[arg(long)]
+    pub fullscreen: bool,
+    /// Read and save settings at this path instead of the user directory.
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Play back a recorded `.replay` file instead of opening the menu.
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+    /// Instead of playing the --replay back, write it to FILE as an
+    /// animated GIF, without opening a window.
+    #[arg(long, value_name = "FILE", requires = "replay")]
+    pub gif: Option<PathBuf>,
+    /// Run without a window, playing the --script, and print the final
+    /// board and score.
+    #[arg(long, requires = "script")]
+    pub headless: bool,
+    /// Play the inputs in FILE instead of taking them from the keyboard,
+    /// one `<frame> <press|release> <action>` per line, and watch the round
+    /// play out.
+    #[arg(
+        long,
+        alias = "moves",
+        value_name = "FILE",
+        conflicts_with_all = ["replay", "tui", "host", "join", "watch"]
+    )]
+    pub script: Option<PathBuf>,
+    /// Start practice on the board and pieces of a fumen, given as the
+    /// code or its URL.
+    #[arg(
+        long,
+        value_name = "CODE",
+        conflicts_with_all = ["replay", "script", "tui", "host", "join", "watch"]
+    )]
+    pub fumen: Option<String>,
+    /// Start a round of MODE straight away instead of opening the menu.
+    #[arg(
+        long,
+        value_enum,
+        conflicts_with_all = ["replay", "script", "fumen", "tui", "host", "join", "watch"]
+    )]
+    pub mode: Option<Mode>,
+    /// Play in the terminal instead of a window.
+    #[arg(long, conflicts_with_all = ["replay", "headless"])]
+    pub tui: bool,
+    /// Wait for another player to join a network game, listening on ADDR
+    /// (all interfaces on port 7878 if not given), and show the room code
+    /// to join it with.
+    #[arg(
+        long,
+        value_name = "ADDR",
+        num_args = 0..=1,
+        default_missing_value = "0.0.0.0:7878",
+        conflicts_with_all = ["join", "replay", "headless", "tui"]
+    )]
+    pub host: Option<String>,
+    /// Join the network game hosted at ADDR, as `host`, `host:port` or the
+    /// room code the host was given.
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["replay", "headless", "tui"])]
+    pub join: Option<String>,
+    /// Look on at the network game hosted at ADDR without playing.
+    #[arg(
+        long,
+        value_name = "ADDR",
+        conflicts_with_all = ["host", "join", "replay", "headless", "tui"]
+    )]
+    pub watch: Option<String>,
+    /// Score boards by the weights in FILE, as TOML, for the computer
+    /// player instead of the built-in ones.
+    #[arg(long, value_name = "FILE")]
+    pub ai_weights: Option<PathBuf>,
+    /// Run N versus rounds of the computer against itself without a
+    /// window, the --ai-weights against the built-in ones, and print how
+    /// each side did.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with_all = ["replay", "headless", "script", "tui", "host", "join", "watch"]
+    )]
+    pub ai_selfplay: Option<u32>,
+}
+
+impl Cli {
+    /// How a round played from a --script is set up, the same in a window
+    /// as headless: the defaults, with any --seed and --level.
+    pub fn script_settings(&self) -> Settings {
+        Settings {
+            start_level: self.level.unwrap_or(1),
+            seed: self.seed.unwrap_or_else(rand::random),
+            ..Settings::default()
+        }
+    }
+
+    /// The computer player's weights: those in the --ai-weights file, or
+    /// the defaults.
+    pub fn ai_weights(&self) -> GameResult<Weights> {
+        let path = match self.ai_weights {
+            Some(ref path) => path,
+            None => return Ok(Weights::default()),
+        };
+        Weights::parse(&fs::read_to_string(path)?)
+            .map_err(|e| GameError::ResourceLoadError(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn fullscreen(&self, configured: bool) -> bool {
+        if self.fullscreen {
+            true
+        } else if self.windowed {
+            false
+        } else {
+            configured
+        }
+    }
+}
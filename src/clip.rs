@@ -0,0 +1,139 @@
+use crate::config::Colors;
+use crate::replays;
+use ggez::{GameError, GameResult};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use tetris_rs::board::FixedBlock;
+use tetris_rs::game::Game;
+use tetris_rs::replay::{self, Playback};
+use tetris_rs::tetromino::Tetromino;
+
+/// Width and height of a board cell in the clip, in pixels.
+const CELL_SIZE: usize = 16;
+/// Logic steps to each frame of the clip. GIF delays are in hundredths of
+/// a second, so 3 steps at 60 a second is 5 of them.
+const STEPS_PER_FRAME: u64 = 3;
+const FRAME_DELAY: u16 = 5;
+/// How long the final board stays up before the clip loops, in frames.
+const HOLD_FRAMES: usize = 40;
+
+/// The clip's colors: the background, garbage, then every piece in the
+/// shape table.
+struct Palette {
+    rgb: Vec<u8>,
+}
+
+impl Palette {
+    fn new(colors: &Colors) -> Palette {
+        let mut rgb = Vec::new();
+        let mut push = |(r, g, b): (u8, u8, u8)| rgb.extend_from_slice(&[r, g, b]);
+        push(colors.background().to_rgb());
+        push(colors.block(FixedBlock::Garbage).to_rgb());
+        for tetromino in Tetromino::every() {
+            push(colors.piece(tetromino).to_rgb());
+        }
+        // Padded out to a whole color table.
+        rgb.resize(256 * 3, 0);
+        Palette { rgb }
+    }
+
+    fn background() -> u8 {
+        0
+    }
+
+    fn block(block: FixedBlock) -> u8 {
+        match block {
+            FixedBlock::Garbage => 1,
+            FixedBlock::Piece(tetromino) => Palette::piece(tetromino),
+        }
+    }
+
+    fn piece(tetromino: Tetromino) -> u8 {
+        let index = Tetromino::every().position(|t| t == tetromino).unwrap_or(0);
+        (index + 2) as u8
+    }
+}
+
+/// Paints the board and the falling piece of `game` into `pixels`, one
+/// palette index a pixel.
+fn paint(game: &Game, pixels: &mut [u8]) {
+    let width = game.board.width() * CELL_SIZE;
+    for pixel in pixels.iter_mut() {
+        *pixel = Palette::background();
+    }
+    let mut fill = |x: i32, y: i32, index: u8| {
+        if x < 0 || y < 0 || x as usize >= game.board.width() || y as usize >= game.board.height() {
+            return;
+        }
+        let (left, top) = (x as usize * CELL_SIZE, y as usize * CELL_SIZE);
+        // A pixel's gap between cells, as on screen.
+        for row in top..top + CELL_SIZE - 1 {
+            let start = row * width + left;
+            for pixel in &mut pixels[start..start + CELL_SIZE - 1] {
+                *pixel = index;
+            }
+        }
+    };
+    for (block, fixed) in game.board.blocks() {
+        fill(block[0], block[1], Palette::block(fixed));
+    }
+    if !game.game_over {
+        let piece = Palette::piece(game.tetromino());
+        for block in game.active_blocks() {
+            fill(block[0], block[1], piece);
+        }
+    }
+}
+
+/// Plays the replay at `path` back without a window and writes it to `out`
+/// as a looping animated GIF, in the built-in colors.
+pub fn export(path: &Path, out: &Path) -> GameResult {
+    let mut playback = Playback::new(replays::load(path)?);
+    let mut game = playback.new_game();
+    let (width, height) = (
+        game.board.width() * CELL_SIZE,
+        game.board.height() * CELL_SIZE,
+    );
+    let failed = |e: std::io::Error| {
+        GameError::ResourceLoadError(format!("Could not write {}: {}", out.display(), e))
+    };
+    let palette = Palette::new(&Colors::default());
+    let file = BufWriter::new(File::create(out)?);
+    let mut encoder =
+        gif::Encoder::new(file, width as u16, height as u16, &palette.rgb).map_err(failed)?;
+    gif::SetParameter::set(&mut encoder, gif::Repeat::Infinite).map_err(failed)?;
+
+    let mut pixels = vec![Palette::background(); width * height];
+    let mut write = |pixels: &[u8], delay: u16| {
+        let frame = gif::Frame {
+            width: width as u16,
+            height: height as u16,
+            delay,
+            buffer: Cow::Borrowed(pixels),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame).map_err(failed)
+    };
+    let mut frame = 0;
+    let mut frames = 0;
+    while !game.game_over && !playback.finished() {
+        let now = game.started_at() + replay::frame_time(frame);
+        for action in playback.actions_at(frame) {
+            action.apply(&mut game, now);
+        }
+        frame += 1;
+        game.update(game.started_at() + replay::frame_time(frame));
+        game.take_events();
+        if frame % STEPS_PER_FRAME == 0 {
+            paint(&game, &mut pixels);
+            write(&pixels, FRAME_DELAY)?;
+            frames += 1;
+        }
+    }
+    paint(&game, &mut pixels);
+    write(&pixels, FRAME_DELAY * HOLD_FRAMES as u16)?;
+    println!("Wrote {} frames to {}", frames + 1, out.display());
+    Ok(())
+}
@@ -0,0 +1,59 @@
+//! The clock the game runs on. Natively that's `std::time::Instant`; in a
+//! browser, where `Instant::now` panics, it's read from the page's
+//! `performance.now()` instead.
+
+#[cfg(not(all(target_arch = "wasm32", feature = "web")))]
+pub use std::time::Instant;
+
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+pub use self::performance::Instant;
+
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+mod performance {
+    use std::ops::{Add, AddAssign, Sub};
+    use std::time::Duration;
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = performance)]
+        fn now() -> f64;
+    }
+
+    /// Time since the page loaded. Only has the parts of
+    /// `std::time::Instant` the game uses.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Instant(Duration);
+
+    impl Instant {
+        pub fn now() -> Instant {
+            Instant(Duration::from_micros((now() * 1000.0) as u64))
+        }
+
+        pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+            self.0.checked_sub(earlier.0).unwrap_or_default()
+        }
+    }
+
+    impl Add<Duration> for Instant {
+        type Output = Instant;
+
+        fn add(self, other: Duration) -> Instant {
+            Instant(self.0 + other)
+        }
+    }
+
+    impl AddAssign<Duration> for Instant {
+        fn add_assign(&mut self, other: Duration) {
+            self.0 += other;
+        }
+    }
+
+    impl Sub<Instant> for Instant {
+        type Output = Duration;
+
+        fn sub(self, other: Instant) -> Duration {
+            self.saturating_duration_since(other)
+        }
+    }
+}
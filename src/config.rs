@@ -0,0 +1,610 @@
+use crate::keymap::KeyMap;
+use crate::layout::Layout;
+use crate::locale;
+use ggez::filesystem;
+use ggez::graphics::Color;
+use ggez::{Context, GameError, GameResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use tetris_rs::ai::Difficulty;
+use tetris_rs::board::{self, FixedBlock};
+use tetris_rs::game::{Handling, Settings};
+use tetris_rs::gravity;
+use tetris_rs::handicap::Handicap;
+use tetris_rs::rules::{LockReset, Randomizer, RotationSystem, Rules};
+use tetris_rs::targeting::Targeting;
+use tetris_rs::tetromino::{PieceSet, Tetromino};
+
+const CONFIG_PATH: &str = "/config.toml";
+
+const DEFAULT_CELL_SIZE: i32 = 32;
+const DEFAULT_PROFILE: &str = "Player 1";
+const DEFAULT_VOLUME: u32 = 70;
+
+/// The NES's piece colors at each of its levels from 0.
+const NES_LEVEL_COLORS: [[&str; 2]; 10] = [
+    ["#0058f8", "#3cbcfc"],
+    ["#00a800", "#b8f818"],
+    ["#d800cc", "#f878f8"],
+    ["#0058f8", "#58d854"],
+    ["#e40058", "#58f898"],
+    ["#58f898", "#6888fc"],
+    ["#f83800", "#7c7c7c"],
+    ["#6844fc", "#a80020"],
+    ["#0058f8", "#f83800"],
+    ["#f83800", "#fca044"],
+];
+
+/// Settings the game is launched with, read from `config.toml` in the user
+/// config directory or from a path given on the command line. Missing fields
+/// take their default values.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub board_width: usize,
+    pub board_height: usize,
+    /// Width and height of one board cell, in pixels.
+    pub cell_size: i32,
+    /// Time between gravity steps.
+    pub gravity_millis: u64,
+    /// The fall speed in cells per frame at 60 frames a second, from a
+    /// fraction to 20G, instead of `gravity_millis` if set.
+    pub gravity_g: Option<f64>,
+    /// From before profiles, read into the first one if there are none.
+    #[serde(skip_serializing)]
+    das_millis: u64,
+    #[serde(skip_serializing)]
+    arr_millis: u64,
+    /// The name of the profile in use, one of `profiles`.
+    pub profile: String,
+    pub fullscreen: bool,
+    /// Whether frames wait for the display to refresh. Changing it takes
+    /// effect the next time the game starts.
+    pub vsync: bool,
+    /// The most frames drawn a second, if there's a limit.
+    pub fps_cap: Option<u32>,
+    /// Whether a round pauses when the window goes into the background or
+    /// is minimized.
+    pub pause_on_focus_loss: bool,
+    /// Whether all sound is turned off.
+    pub muted: bool,
+    /// Volumes in percent.
+    pub music_volume: u32,
+    pub sfx_volume: u32,
+    /// The sound pack in use, a folder in `soundpacks` in the config
+    /// directory, or the game's own sounds if empty.
+    pub sound_pack: String,
+    /// Whether sound effects are panned left or right to the column they
+    /// happened in.
+    pub pan_sounds: bool,
+    /// Which pieces rounds on this machine deal from.
+    pub pieces: PieceSet,
+    pub rules: Rules,
+    /// How pieces turn, if not the way `rules` have them.
+    pub rotation: Option<RotationSystem>,
+    /// How pieces are dealt, if not the way `rules` have them.
+    pub randomizer: Option<Randomizer>,
+    /// What puts off a resting piece's lock, in modes with a lock delay.
+    pub lock_reset: LockReset,
+    /// How many upcoming pieces are shown, from none to `MAX_PREVIEWS`,
+    /// if not as many as the rules show.
+    pub previews: Option<usize>,
+    /// Time between a piece locking and the next spawning, zero for none.
+    pub entry_delay_millis: u64,
+    /// Time full rows stay before the stack collapses, zero for none.
+    pub line_clear_delay_millis: u64,
+    /// Whether the live stats panel is shown during play.
+    pub show_stats: bool,
+    /// Whether faint lines mark out the board's cells.
+    pub show_grid: bool,
+    /// The skin minos are drawn with, an image in `resources/skins` or a
+    /// pack's `skins`, or flat colors if empty.
+    pub skin: String,
+    /// What's drawn around the board: an image file in `resources/themes`
+    /// or a pack's `themes`, `starfield` for stars drifting past at
+    /// different depths, `gradient` for the background color washing into
+    /// another hue that turns with the level, or the plain background color
+    /// if empty. Picking a theme picks its backdrop.
+    pub backdrop: String,
+    pub marks: Marks,
+    /// Outlines minos and the ghost and darkens the background, for
+    /// players who find the board hard to make out.
+    pub high_contrast: bool,
+    /// Whether hard drops and tetrises shake the screen.
+    pub screen_shake: bool,
+    /// Leaves out everything that moves only for show: screen shake,
+    /// particles, hard drop trails and the falling piece sliding between
+    /// rows.
+    pub reduced_motion: bool,
+    /// Plays with the mouse instead of by gestures: the falling piece
+    /// follows the pointer across the board, the wheel turns it and a
+    /// click drops it.
+    pub mouse_placement: bool,
+    /// Whether what the other player says in a network game is shown. Off
+    /// until turned on.
+    pub chat: bool,
+    /// Whether rounds played alone outline where the computer would put
+    /// each piece and grade where it went.
+    pub tutor: bool,
+    /// Whether a piece placed in more presses than it needed flashes up
+    /// the shortest way there.
+    pub finesse_hints: bool,
+    /// The code of the language the game's text is shown in, one of the
+    /// files in `resources/locales`.
+    pub language: String,
+    /// The pieces a practice round deals, as letters, ending in
+    /// `repeated` to go round them again.
+    pub practice_queue: String,
+    /// The bottom rows of the practice board, top first, as in a saved
+    /// board.
+    pub practice_board: Vec<String>,
+    /// A fumen to practice on instead of the queue and board above, if
+    /// set.
+    pub practice_fumen: String,
+    /// The key that drops the round in play for a fresh one like it, by
+    /// name as in `[keys]`, or empty for none. It takes precedence over
+    /// any action bound to it.
+    pub restart_key: String,
+    /// How long the restart key has to be held, so a stray press doesn't
+    /// throw a run away; 0 restarts at once.
+    pub restart_hold_millis: u64,
+    /// How fast practice rounds run, as a percentage of the usual speed:
+    /// gravity, lock delay and every timer alike.
+    pub practice_speed_percent: u32,
+    /// The level marathons start at and the puzzle to play, as last picked
+    /// on the menu. The first puzzle if there's none by that name.
+    pub marathon_level: u32,
+    pub puzzle: String,
+    /// The folder names of content packs turned off, see `packs`.
+    pub disabled_packs: Vec<String>,
+    /// How well the computer plays in versus.
+    pub cpu_difficulty: Difficulty,
+    /// How many CPUs versus is played against, each against all the rest.
+    pub cpus: usize,
+    /// Who the player attacks first in versus against more than one CPU.
+    pub targeting: Targeting,
+    // Tables go after everything else, as TOML has the values first.
+    pub colors: Colors,
+    /// The player's handicap in versus, against the computer or online,
+    /// and the computer's.
+    pub handicap: Handicap,
+    pub cpu_handicap: Handicap,
+    /// Action name to key names, see `KeyMap`.
+    pub keys: BTreeMap<String, Vec<String>>,
+    /// The second player's keys in co-op, as in `[keys]`. Where the two
+    /// share a key, it's the second player's.
+    pub partner_keys: BTreeMap<String, Vec<String>>,
+    /// Each player's handling. After loading there's always at least one.
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+    /// Where the config was read from, if not the default location.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let settings = Settings::default();
+        let handling = settings.handling;
+        Config {
+            board_width: board::DEFAULT_WIDTH,
+            board_height: board::DEFAULT_HEIGHT,
+            cell_size: DEFAULT_CELL_SIZE,
+            gravity_millis: settings.gravity.as_millis() as u64,
+            gravity_g: None,
+            das_millis: handling.das.as_millis() as u64,
+            arr_millis: handling.arr.as_millis() as u64,
+            profiles: vec![Profile::default()],
+            profile: DEFAULT_PROFILE.to_string(),
+            fullscreen: false,
+            vsync: true,
+            fps_cap: None,
+            pause_on_focus_loss: true,
+            muted: false,
+            music_volume: DEFAULT_VOLUME,
+            sfx_volume: DEFAULT_VOLUME,
+            sound_pack: String::new(),
+            pan_sounds: true,
+            colors: Colors::default(),
+            cpu_difficulty: Difficulty::default(),
+            cpus: 1,
+            targeting: Targeting::default(),
+            handicap: Handicap::default(),
+            cpu_handicap: Handicap::default(),
+            pieces: PieceSet::Tetrominoes,
+            rules: Rules::Modern,
+            rotation: None,
+            randomizer: None,
+            lock_reset: LockReset::Move,
+            previews: None,
+            entry_delay_millis: settings.entry_delay.as_millis() as u64,
+            line_clear_delay_millis: settings.line_clear_delay.as_millis() as u64,
+            show_stats: false,
+            show_grid: true,
+            skin: String::new(),
+            backdrop: String::new(),
+            marks: Marks::Off,
+            high_contrast: false,
+            screen_shake: true,
+            reduced_motion: false,
+            mouse_placement: false,
+            chat: false,
+            tutor: false,
+            finesse_hints: true,
+            language: locale::DEFAULT_LANGUAGE.to_string(),
+            practice_queue: "IJLOSTZ repeated".to_string(),
+            practice_board: Vec::new(),
+            practice_fumen: String::new(),
+            practice_speed_percent: 100,
+            restart_key: "F4".to_string(),
+            restart_hold_millis: 0,
+            marathon_level: 1,
+            puzzle: String::new(),
+            disabled_packs: Vec::new(),
+            keys: KeyMap::default().to_config(),
+            partner_keys: KeyMap::partner().to_config(),
+            path: None,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file at `path`, or `config.toml` in the user
+    /// directory if no path is given, writing out the defaults if there
+    /// isn't one yet.
+    pub fn load(ctx: &mut Context, path: Option<PathBuf>) -> GameResult<Config> {
+        let contents = match path {
+            Some(ref path) if path.exists() => Some(fs::read_to_string(path)?),
+            None if filesystem::exists(ctx, CONFIG_PATH) => {
+                let mut contents = String::new();
+                filesystem::open(ctx, CONFIG_PATH)?.read_to_string(&mut contents)?;
+                Some(contents)
+            }
+            _ => None,
+        };
+        let mut config: Config = match contents {
+            Some(contents) => toml::from_str(&contents).map_err(|e| {
+                GameError::ResourceLoadError(format!("Invalid {}: {}", CONFIG_PATH, e))
+            })?,
+            None => {
+                let config = Config {
+                    path,
+                    ..Config::default()
+                };
+                config.save(ctx)?;
+                return Ok(config);
+            }
+        };
+        if config.board_width == 0 || config.board_width > board::MAX_WIDTH {
+            return Err(GameError::ResourceLoadError(format!(
+                "Invalid {}: board_width must be 1 to {}",
+                CONFIG_PATH,
+                board::MAX_WIDTH
+            )));
+        }
+        if config.profiles.is_empty() {
+            config.profiles.push(Profile {
+                das_millis: config.das_millis,
+                arr_millis: config.arr_millis,
+                ..Profile::default()
+            });
+        }
+        config.path = path;
+        Ok(config)
+    }
+
+    pub fn save(&self, ctx: &mut Context) -> GameResult {
+        let contents = toml::to_string(self).map_err(|e| {
+            GameError::ResourceLoadError(format!("Could not write {}: {}", CONFIG_PATH, e))
+        })?;
+        match self.path {
+            Some(ref path) => fs::write(path, contents)?,
+            None => filesystem::create(ctx, CONFIG_PATH)?.write_all(contents.as_bytes())?,
+        }
+        Ok(())
+    }
+
+    /// The window size that fits `boards` boards side by side exactly.
+    pub fn screen_size(&self, boards: usize) -> (f32, f32) {
+        let layout = Layout::sized(self.board_width, self.board_height, self.cell_size);
+        (boards as f32 * layout.width(), layout.height())
+    }
+
+    pub fn gravity(&self) -> Duration {
+        match self.gravity_g {
+            Some(g) if g > 0.0 => gravity::from_g(g, gravity::DEFAULT_FRAMES_PER_SECOND),
+            _ => Duration::from_millis(self.gravity_millis),
+        }
+    }
+
+    pub fn entry_delay(&self) -> Duration {
+        Duration::from_millis(self.entry_delay_millis)
+    }
+
+    pub fn line_clear_delay(&self) -> Duration {
+        Duration::from_millis(self.line_clear_delay_millis)
+    }
+
+    /// The profile in use, or the first if none has its name.
+    pub fn profile(&self) -> &Profile {
+        let at = self.profile_index();
+        &self.profiles[at]
+    }
+
+    pub fn profile_mut(&mut self) -> &mut Profile {
+        let at = self.profile_index();
+        &mut self.profiles[at]
+    }
+
+    fn profile_index(&self) -> usize {
+        self.profiles
+            .iter()
+            .position(|profile| profile.name == self.profile)
+            .unwrap_or(0)
+    }
+
+    /// Moves on to the next profile, or back to the one before.
+    pub fn switch_profile(&mut self, forward: bool) {
+        let count = self.profiles.len();
+        let at = self.profile_index();
+        let at = if forward {
+            (at + 1) % count
+        } else {
+            (at + count - 1) % count
+        };
+        self.profile = self.profiles[at].name.clone();
+    }
+
+    /// Adds a profile with the handling of the one in use and switches to
+    /// it, for another player to make their own.
+    pub fn add_profile(&mut self) {
+        let name = (self.profiles.len() + 1..)
+            .map(|n| format!("Player {}", n))
+            .find(|name| self.profiles.iter().all(|profile| &profile.name != name))
+            .unwrap();
+        let profile = Profile {
+            name: name.clone(),
+            ..self.profile().clone()
+        };
+        self.profiles.push(profile);
+        self.profile = name;
+    }
+
+    pub fn handling(&self) -> Handling {
+        self.profile().handling()
+    }
+}
+
+/// One player's handling, so players sharing a machine each keep their
+/// own and can switch between them on the options screen.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    pub das_millis: u64,
+    pub arr_millis: u64,
+    /// Time between rows while soft drop is held, zero to go straight
+    /// down. Left out, each press drops a row.
+    pub soft_drop_millis: Option<u64>,
+    /// How long a held move waits after a turn or a new piece.
+    pub dcd_millis: u64,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        let handling = Handling::default();
+        Profile {
+            name: DEFAULT_PROFILE.to_string(),
+            das_millis: handling.das.as_millis() as u64,
+            arr_millis: handling.arr.as_millis() as u64,
+            soft_drop_millis: handling.soft_drop.map(|rate| rate.as_millis() as u64),
+            dcd_millis: handling.dcd.as_millis() as u64,
+        }
+    }
+}
+
+impl Profile {
+    pub fn handling(&self) -> Handling {
+        Handling {
+            das: Duration::from_millis(self.das_millis),
+            arr: Duration::from_millis(self.arr_millis),
+            soft_drop: self.soft_drop_millis.map(Duration::from_millis),
+            dcd: Duration::from_millis(self.dcd_millis),
+        }
+    }
+}
+
+/// What's drawn over each mino so pieces can be told apart by more than
+/// their color.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Marks {
+    #[default]
+    Off,
+    /// The piece's letter.
+    Letters,
+    /// A line, cross, dot or box for each of the seven tetrominoes, and
+    /// the letter for any other piece.
+    Patterns,
+}
+
+/// Colors as `#rrggbb` strings, or `#rrggbbaa` to see through. Anything
+/// that doesn't parse falls back to the built-in color.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Colors {
+    pub background: String,
+    /// The lines between the board's cells.
+    pub grid: String,
+    /// The frame around the board.
+    pub border: String,
+    /// Titles and whatever is selected or needs noticing.
+    pub accent: String,
+    /// Secondary text, and the other side's board in versus.
+    pub subdued: String,
+    pub i: String,
+    pub o: String,
+    pub t: String,
+    pub s: String,
+    pub z: String,
+    pub j: String,
+    pub l: String,
+    pub garbage: String,
+    /// For rules that change the pieces' colors with the level, a pair for
+    /// each level in turn, starting over after the last. J and S take the
+    /// first, Z and L the second, and I, O and T the first lightened, as on
+    /// the NES. With none the pieces keep their colors above.
+    pub levels: Vec<[String; 2]>,
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Colors {
+            background: "#1a334d".to_string(),
+            grid: "#ffffff14".to_string(),
+            border: "#999999".to_string(),
+            accent: "#ffd600".to_string(),
+            subdued: "#ffffff80".to_string(),
+            i: "#42f1f4".to_string(),
+            o: "#e9ed2a".to_string(),
+            t: "#b62aed".to_string(),
+            s: "#58ed2a".to_string(),
+            z: "#e2321b".to_string(),
+            j: "#164bdd".to_string(),
+            l: "#db6c11".to_string(),
+            garbage: "#808080".to_string(),
+            levels: NES_LEVEL_COLORS
+                .iter()
+                .map(|pair| [pair[0].to_string(), pair[1].to_string()])
+                .collect(),
+        }
+    }
+}
+
+impl Colors {
+    pub fn background(&self) -> Color {
+        parse_hex(&self.background).unwrap_or_else(|| [0.1, 0.2, 0.3, 1.0].into())
+    }
+
+    pub fn grid(&self) -> Color {
+        parse_hex(&self.grid).unwrap_or_else(|| [1.0, 1.0, 1.0, 0.08].into())
+    }
+
+    pub fn border(&self) -> Color {
+        parse_hex(&self.border).unwrap_or_else(|| [0.6, 0.6, 0.6, 1.0].into())
+    }
+
+    pub fn accent(&self) -> Color {
+        parse_hex(&self.accent).unwrap_or_else(|| [1.0, 0.84, 0.0, 1.0].into())
+    }
+
+    pub fn subdued(&self) -> Color {
+        parse_hex(&self.subdued).unwrap_or_else(|| [1.0, 1.0, 1.0, 0.5].into())
+    }
+
+    pub fn piece(&self, tetromino: Tetromino) -> Color {
+        let hex = match tetromino {
+            Tetromino::IBlock => &self.i,
+            Tetromino::OBlock => &self.o,
+            Tetromino::TBlock => &self.t,
+            Tetromino::SBlock => &self.s,
+            Tetromino::ZBlock => &self.z,
+            Tetromino::JBlock => &self.j,
+            Tetromino::LBlock => &self.l,
+            // Only the tetrominoes have configurable colors.
+            _ => return tetromino.color(),
+        };
+        parse_hex(hex).unwrap_or_else(|| tetromino.color())
+    }
+
+    /// These colors with the pieces in the pair for `level`, counted from
+    /// 1, if there are any.
+    pub fn at_level(&self, level: u32) -> Colors {
+        if self.levels.is_empty() {
+            return self.clone();
+        }
+        let [first, second] = &self.levels[(level.max(1) as usize - 1) % self.levels.len()];
+        let light = parse_hex(first)
+            .map(|color| {
+                let (r, g, b, a) = color.to_rgba();
+                let lighten = |channel: u8| channel / 2 + 128;
+                format!(
+                    "#{:02x}{:02x}{:02x}{:02x}",
+                    lighten(r),
+                    lighten(g),
+                    lighten(b),
+                    a
+                )
+            })
+            .unwrap_or_else(|| first.clone());
+        Colors {
+            i: light.clone(),
+            o: light.clone(),
+            t: light,
+            j: first.clone(),
+            s: first.clone(),
+            z: second.clone(),
+            l: second.clone(),
+            ..self.clone()
+        }
+    }
+
+    /// These colors made see-through by `alpha`, for a board drawn
+    /// faintly.
+    pub fn faded(&self, alpha: f32) -> Colors {
+        let fade = |hex: &String| match parse_hex(hex) {
+            Some(color) => {
+                let (r, g, b, a) = color.to_rgba();
+                let a = (f32::from(a) * alpha) as u8;
+                format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+            }
+            None => hex.clone(),
+        };
+        Colors {
+            background: fade(&self.background),
+            grid: fade(&self.grid),
+            border: fade(&self.border),
+            accent: fade(&self.accent),
+            subdued: fade(&self.subdued),
+            i: fade(&self.i),
+            o: fade(&self.o),
+            t: fade(&self.t),
+            s: fade(&self.s),
+            z: fade(&self.z),
+            j: fade(&self.j),
+            l: fade(&self.l),
+            garbage: fade(&self.garbage),
+            levels: self
+                .levels
+                .iter()
+                .map(|[first, second]| [fade(first), fade(second)])
+                .collect(),
+        }
+    }
+
+    pub fn block(&self, block: FixedBlock) -> Color {
+        match block {
+            FixedBlock::Piece(tetromino) => self.piece(tetromino),
+            FixedBlock::Garbage => parse_hex(&self.garbage).unwrap_or_else(|| block.color()),
+        }
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    let hex = hex.trim_start_matches('#');
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    let alpha = match hex.len() {
+        6 => 255,
+        8 => channel(6)?,
+        _ => return None,
+    };
+    Some(Color::from_rgba(
+        channel(0)?,
+        channel(2)?,
+        channel(4)?,
+        alpha,
+    ))
+}
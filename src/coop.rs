@@ -0,0 +1,123 @@
+//! Co-op: two players at one keyboard on a board twice the usual width,
+//! each with a piece of their own coming in over their half and keys of
+//! their own, playing for one score. Each player is a `Game` of their own
+//! on a copy of the board; whenever either changes it the other takes it
+//! on, and each piece is in the way of the other's as the stack is, so a
+//! piece can come to rest, and lock, on the other player's.
+
+use crate::keymap::KeyMap;
+use ggez::event::KeyCode;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+use tetris_rs::board::{Board, MAX_WIDTH};
+use tetris_rs::game::{Game, Input, Settings};
+use tetris_rs::replay::Action;
+
+/// The width of a co-op board for players used to boards `width` across.
+pub fn board_width(width: usize) -> usize {
+    (width * 2).min(MAX_WIDTH)
+}
+
+/// The columns each player's pieces come in over on a board `width`
+/// across, the player's on the left and the second player's on the right.
+fn lanes(width: usize) -> (Range<i32>, Range<i32>) {
+    let middle = width as i32 / 2;
+    (0..middle, middle..width as i32)
+}
+
+/// Sets up a co-op round on `board`: the player's game, and the second
+/// player's with their keys. The second player is dealt from the next seed
+/// on, so the two don't get the same pieces.
+pub fn start(board: Board, settings: Settings, keymap: KeyMap) -> (Game, Partner) {
+    let (left, right) = lanes(board.width());
+    // Rows are taken out as they fill, so the other player's next lock
+    // can't find them still there and count them again.
+    let settings = Settings {
+        line_clear_delay: Duration::from_millis(0),
+        ..settings
+    };
+    let theirs = Settings {
+        seed: settings.seed.wrapping_add(1),
+        ..settings
+    };
+    let game = Game::coop(board.clone(), settings, left);
+    let partner = Partner {
+        game: Game::coop(board.clone(), theirs, right),
+        keymap,
+        board,
+        score: 0,
+        lines: 0,
+    };
+    (game, partner)
+}
+
+/// The second player in co-op, played in step with the player's frames.
+pub struct Partner {
+    pub game: Game,
+    keymap: KeyMap,
+    /// The board as the two games last agreed on it.
+    board: Board,
+    /// The points and lines the two have earned between them.
+    score: u32,
+    lines: u32,
+}
+
+impl Partner {
+    pub fn input_for(&self, key: KeyCode) -> Option<Input> {
+        self.keymap.input_for(key)
+    }
+
+    /// Applies the second player's `action` at `now`.
+    pub fn act(&mut self, game: &mut Game, action: Action, now: Instant) {
+        self.sync(game);
+        action.apply(&mut self.game, now);
+        self.sync(game);
+    }
+
+    /// Keeps up with `action` taken on the player's game: coming back from
+    /// a pause with it, and taking on whatever it did to the board.
+    pub fn follow(&mut self, game: &mut Game, action: Action, now: Instant) {
+        if let Action::Resume = action {
+            action.apply(&mut self.game, now);
+        }
+        self.sync(game);
+    }
+
+    /// Runs the second player's game up to `now`, after the player's.
+    pub fn advance(&mut self, game: &mut Game, now: Instant) {
+        self.sync(game);
+        self.game.update(now);
+        self.sync(game);
+    }
+
+    /// Brings the two games back into agreement after either has moved:
+    /// the board as the one that changed it left it, the points and lines
+    /// either has earned since, and each piece in the other's way. The
+    /// round is over for both once either tops out.
+    fn sync(&mut self, game: &mut Game) {
+        if game.board != self.board {
+            self.board = game.board.clone();
+            self.game.share_board(&self.board);
+        } else if self.game.board != self.board {
+            self.board = self.game.board.clone();
+            game.share_board(&self.board);
+        }
+        let earned =
+            game.score.saturating_sub(self.score) + self.game.score.saturating_sub(self.score);
+        let cleared =
+            game.lines.saturating_sub(self.lines) + self.game.lines.saturating_sub(self.lines);
+        self.score += earned;
+        self.lines += cleared;
+        let level = game.level.max(self.game.level);
+        for each in [&mut *game, &mut self.game] {
+            each.score = self.score;
+            each.lines = self.lines;
+            each.level = level;
+        }
+        if self.game.game_over {
+            game.end_round();
+        }
+        game.set_blocked(self.game.active_blocks());
+        self.game.set_blocked(game.active_blocks());
+    }
+}
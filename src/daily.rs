@@ -0,0 +1,75 @@
+use crate::highscores;
+use ggez::filesystem;
+use ggez::{Context, GameResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tetris_rs::game::Game;
+
+const DAILY_FILE: &str = "daily.toml";
+
+/// The seed for today's challenge, the same for everyone playing on the
+/// same UTC day.
+pub fn seed() -> u64 {
+    // Spread consecutive days across the seed space.
+    highscores::epoch_days().wrapping_mul(0x9e37_79b9_7f4a_7c15)
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Attempt {
+    pub score: u32,
+    pub lines: u32,
+}
+
+/// The one attempt at each day's challenge, by date, stored in the user
+/// data directory. An attempt is written when it starts, so quitting
+/// doesn't earn another go.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Daily {
+    #[serde(default)]
+    attempts: BTreeMap<String, Attempt>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Daily {
+    pub fn load(ctx: &Context) -> GameResult<Daily> {
+        let path = filesystem::user_data_dir(ctx).join(DAILY_FILE);
+        let mut daily: Daily = highscores::load_table(&path)?;
+        daily.path = path;
+        Ok(daily)
+    }
+
+    /// Today's attempt, if it has been made.
+    pub fn today(&self) -> Option<Attempt> {
+        self.attempts.get(&highscores::today()).cloned()
+    }
+
+    /// Uses up today's attempt, returning the date it is for, or `None` if
+    /// it was already.
+    pub fn start(&mut self) -> Option<String> {
+        let date = highscores::today();
+        if self.attempts.contains_key(&date) {
+            return None;
+        }
+        self.attempts.insert(date.clone(), Attempt::default());
+        self.save();
+        Some(date)
+    }
+
+    /// Records how the attempt for `date` went.
+    pub fn record(&mut self, date: String, game: &Game) {
+        let attempt = Attempt {
+            score: game.score,
+            lines: game.lines,
+        };
+        self.attempts.insert(date, attempt);
+        self.save();
+    }
+
+    fn save(&self) {
+        if let Err(e) = highscores::save_table(&self.path, self) {
+            eprintln!("Could not save the daily challenge: {}", e);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Finding network games on the local network. A host announces its game
+//! on a multicast group every so often, and anyone looking for a game
+//! listens there and lists the hosts it has heard from lately.
+
+use crate::net::DEFAULT_PORT;
+use std::io::{self, ErrorKind};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// The group and port announcements go to. Organisation-local scope, so
+/// routers keep them on the local network.
+const GROUP: Ipv4Addr = Ipv4Addr::new(239, 255, 78, 78);
+const PORT: u16 = 7879;
+
+/// What every announcement starts with, ahead of the port the game is on.
+const MAGIC: &str = "tetris-rs game";
+
+const ANNOUNCE_MILLIS: u64 = 1000;
+
+/// How long a host goes unheard before it's dropped from the list.
+const FORGET_SECS: u64 = 4;
+
+/// Hops an announcement may take: 1 keeps it on this network.
+const ANNOUNCE_TTL: u32 = 1;
+
+/// Tells the local network about a game waiting for an opponent.
+pub struct Announcer {
+    socket: UdpSocket,
+    message: String,
+    sent_at: Option<Instant>,
+}
+
+impl Announcer {
+    /// Announces the game listening on `port`.
+    pub fn new(port: u16) -> io::Result<Announcer> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_multicast_ttl_v4(ANNOUNCE_TTL)?;
+        socket.set_nonblocking(true)?;
+        Ok(Announcer {
+            socket,
+            message: format!("{} {}", MAGIC, port),
+            sent_at: None,
+        })
+    }
+
+    /// Sends the announcement if it's time to again.
+    pub fn poll(&mut self) {
+        let now = Instant::now();
+        let due = self.sent_at.is_none_or(|sent_at| {
+            now.duration_since(sent_at) >= Duration::from_millis(ANNOUNCE_MILLIS)
+        });
+        if !due {
+            return;
+        }
+        self.sent_at = Some(now);
+        match self.socket.send_to(self.message.as_bytes(), (GROUP, PORT)) {
+            Ok(_) => (),
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => (),
+            Err(e) => eprintln!("Could not announce the game: {}", e),
+        }
+    }
+}
+
+/// A game heard from on the local network.
+#[derive(Clone, Copy, Debug)]
+pub struct LocalGame {
+    pub addr: SocketAddr,
+    heard_at: Instant,
+}
+
+impl LocalGame {
+    /// The host's address as it's listed, leaving out the port when it's
+    /// the usual one.
+    pub fn label(&self) -> String {
+        match self.addr.ip() {
+            IpAddr::V4(ip) if self.addr.port() == DEFAULT_PORT => ip.to_string(),
+            _ => self.addr.to_string(),
+        }
+    }
+}
+
+/// Listens for games announced on the local network.
+pub struct Browser {
+    socket: UdpSocket,
+    games: Vec<LocalGame>,
+}
+
+impl Browser {
+    pub fn new() -> io::Result<Browser> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, PORT))?;
+        socket.join_multicast_v4(&GROUP, &Ipv4Addr::UNSPECIFIED)?;
+        socket.set_nonblocking(true)?;
+        Ok(Browser {
+            socket,
+            games: Vec::new(),
+        })
+    }
+
+    /// Takes in the announcements that have come since the last call and
+    /// forgets hosts that have gone quiet.
+    pub fn poll(&mut self) {
+        let now = Instant::now();
+        let mut buffer = [0; 64];
+        while let Ok((read, from)) = self.socket.recv_from(&mut buffer) {
+            let text = String::from_utf8_lossy(&buffer[..read]);
+            let port = match text.strip_prefix(MAGIC).map(|rest| rest.trim().parse()) {
+                Some(Ok(port)) => port,
+                _ => continue,
+            };
+            let addr = SocketAddr::new(from.ip(), port);
+            match self.games.iter_mut().find(|game| game.addr == addr) {
+                Some(game) => game.heard_at = now,
+                None => self.games.push(LocalGame {
+                    addr,
+                    heard_at: now,
+                }),
+            }
+        }
+        self.games
+            .retain(|game| now.duration_since(game.heard_at) < Duration::from_secs(FORGET_SECS));
+    }
+
+    /// The games heard from lately, in the order they were first heard.
+    pub fn games(&self) -> &[LocalGame] {
+        &self.games
+    }
+}
@@ -0,0 +1,168 @@
+//! Setting up a board to play on: cells painted in and rubbed out with a
+//! cursor or the mouse, the pieces to deal in order, and for saving it as
+//! a puzzle, what it asks for.
+
+use ggez::nalgebra::Point2;
+use tetris_rs::board::{Board, FixedBlock};
+use tetris_rs::puzzle::{self, Goal, Practice, Puzzle};
+use tetris_rs::tetromino::Tetromino;
+
+/// The most pieces a setup deals.
+pub const MAX_PIECES: usize = 32;
+
+pub struct Editor {
+    board: Board,
+    cursor: Point2<i32>,
+    /// What painted cells are filled with.
+    brush: FixedBlock,
+    /// The pieces dealt, in order, the first being the one in play.
+    pieces: Vec<Tetromino>,
+    goal: Goal,
+    /// While the mouse is held down, whether it's painting cells in or
+    /// rubbing them out.
+    stroke: Option<bool>,
+    /// What came of the last thing done, such as saving, to show.
+    pub note: Option<String>,
+}
+
+impl Editor {
+    /// An empty `width` by `height` board with the cursor in its bottom
+    /// left corner.
+    pub fn new(width: usize, height: usize) -> Editor {
+        Editor {
+            board: Board::new(width, height),
+            cursor: Point2::new(0, height as i32 - 1),
+            brush: FixedBlock::Garbage,
+            pieces: Vec::new(),
+            goal: Goal::PerfectClear,
+            stroke: None,
+            note: None,
+        }
+    }
+
+    pub fn cursor(&self) -> Point2<i32> {
+        self.cursor
+    }
+
+    pub fn brush(&self) -> FixedBlock {
+        self.brush
+    }
+
+    pub fn pieces(&self) -> &[Tetromino] {
+        &self.pieces
+    }
+
+    pub fn goal(&self) -> Goal {
+        self.goal
+    }
+
+    /// Moves the cursor, keeping it on the visible rows.
+    pub fn move_cursor(&mut self, dx: i32, dy: i32) {
+        self.cursor = Point2::new(
+            (self.cursor[0] + dx).clamp(0, self.board.width() as i32 - 1),
+            (self.cursor[1] + dy).clamp(0, self.board.height() as i32 - 1),
+        );
+    }
+
+    /// Fills the cell under the cursor with the brush, or empties it if
+    /// there's something there.
+    pub fn toggle(&mut self) {
+        if !self.board.remove(self.cursor) {
+            self.board.set(self.cursor, self.brush);
+        }
+    }
+
+    /// Empties the cell under the cursor.
+    pub fn erase(&mut self) {
+        self.board.remove(self.cursor);
+    }
+
+    /// Starts painting with the mouse at `cell`, filling cells in or, with
+    /// `fill` unset, rubbing them out until `end_stroke`.
+    pub fn start_stroke(&mut self, cell: Point2<i32>, fill: bool) {
+        self.stroke = Some(fill);
+        self.stroke_to(cell);
+    }
+
+    /// Paints `cell` as the stroke under way does, if there is one.
+    /// Returns whether the board changed.
+    pub fn stroke_to(&mut self, cell: Point2<i32>) -> bool {
+        if !self.board.is_visible(cell) {
+            return false;
+        }
+        self.cursor = cell;
+        match self.stroke {
+            Some(true) => self.board.set(cell, self.brush),
+            Some(false) => self.board.remove(cell),
+            None => false,
+        }
+    }
+
+    pub fn end_stroke(&mut self) {
+        self.stroke = None;
+    }
+
+    /// Switches the brush to the next of garbage and `pieces`' blocks.
+    pub fn next_brush(&mut self, pieces: &[Tetromino]) {
+        let brushes: Vec<FixedBlock> = std::iter::once(FixedBlock::Garbage)
+            .chain(pieces.iter().map(|&piece| FixedBlock::Piece(piece)))
+            .collect();
+        let at = brushes.iter().position(|&brush| brush == self.brush);
+        self.brush = brushes[at.map_or(0, |at| (at + 1) % brushes.len())];
+    }
+
+    /// Deals `piece` after the others.
+    pub fn push_piece(&mut self, piece: Tetromino) {
+        if self.pieces.len() < MAX_PIECES {
+            self.pieces.push(piece);
+        }
+    }
+
+    pub fn pop_piece(&mut self) {
+        self.pieces.pop();
+    }
+
+    /// Steps the goal through a perfect clear and clearing one line up to
+    /// the height of the board.
+    pub fn change_goal(&mut self, up: bool) {
+        let most = self.board.height() as u32;
+        self.goal = match (self.goal, up) {
+            (Goal::PerfectClear, true) => Goal::Lines(1),
+            (Goal::PerfectClear, false) => Goal::PerfectClear,
+            (Goal::Lines(lines), true) => Goal::Lines((lines + 1).min(most)),
+            (Goal::Lines(lines), false) if lines <= 1 => Goal::PerfectClear,
+            (Goal::Lines(lines), false) => Goal::Lines(lines - 1),
+            // Only missions ask for these.
+            (Goal::TSpins(_), _) | (Goal::Survive(_), _) => Goal::PerfectClear,
+        };
+    }
+
+    /// The setup as a drill: its pieces in order, then dealt at random.
+    pub fn practice(&self) -> Practice {
+        Practice {
+            board: self.board.clone(),
+            queue: self.pieces.clone(),
+            repeat: false,
+        }
+    }
+
+    /// The board as `Practice::parse` takes it.
+    pub fn rows(&self) -> Vec<String> {
+        puzzle::board_rows(&self.board)
+    }
+
+    /// The setup as a puzzle called `name`, or `None` with no pieces to
+    /// play.
+    pub fn puzzle(&self, name: String) -> Option<Puzzle> {
+        if self.pieces.is_empty() {
+            return None;
+        }
+        Some(Puzzle {
+            name,
+            board: self.board.clone(),
+            pieces: self.pieces.clone(),
+            goal: self.goal,
+            par: None,
+        })
+    }
+}
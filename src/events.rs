@@ -0,0 +1,25 @@
+//! Passing what happens in a round on to whatever wants to hear about it:
+//! the sound, the effects, the opponent, the recording. Each says what it
+//! does with an event by implementing `Observer`, and `dispatch` hands the
+//! events of a step to every one of them in turn, so none has to be
+//! called from the game loop by name.
+
+use crate::game::{Event, Game};
+
+pub trait Observer {
+    /// Hears about `event`, which happened on `frame` of `game`.
+    fn notify(&mut self, event: Event, frame: u64, game: &Game);
+}
+
+/// Takes the events `game` has turned up and passes each, in the order
+/// they happened, to each of `observers` in turn. Returns them for anything
+/// else to look over.
+pub fn dispatch(game: &mut Game, frame: u64, observers: &mut [&mut dyn Observer]) -> Vec<Event> {
+    let events = game.take_events();
+    for &event in &events {
+        for observer in observers.iter_mut() {
+            observer.notify(event, frame, game);
+        }
+    }
+    events
+}
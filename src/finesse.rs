@@ -0,0 +1,178 @@
+//! Finesse: putting each piece where it goes in the fewest key presses. A
+//! `Finesse` watches each piece from spawn to lock and, when one took more
+//! presses than it needed, has the shortest way there to show.
+
+use crate::board::Board;
+use crate::events::Observer;
+use crate::game::{Event, Game};
+use crate::tetromino::{Rotation, Tetromino};
+use nalgebra::Point2;
+
+/// One press of a finesse sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    Left,
+    Right,
+    /// Holding left until the piece reaches the wall.
+    DasLeft,
+    DasRight,
+    RotateCw,
+}
+
+impl Key {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Key::Left => "←",
+            Key::Right => "→",
+            Key::DasLeft => "⇤",
+            Key::DasRight => "⇥",
+            Key::RotateCw => "↻",
+        }
+    }
+}
+
+/// The fewest presses that take `tetromino` from `from` to rest in the
+/// cells it would cover at `to`, turning first and then moving, on a board
+/// `width` wide. Turns that land on the same cells, as the O's all do,
+/// count as the same.
+pub fn optimal(
+    tetromino: Tetromino,
+    width: usize,
+    from: (Point2<i32>, Rotation),
+    to: (Point2<i32>, Rotation),
+) -> Vec<Key> {
+    let (start, facing) = from;
+    let target = sorted(tetromino.blocks(to.0, to.1));
+    let mut best: Option<Vec<Key>> = None;
+    let mut turned = facing;
+    for turns in 0..4 {
+        // Where the piece would be to cover the target cells facing this
+        // way, if it can.
+        let shape = sorted(tetromino.blocks(Point2::new(0, 0), turned));
+        let offset = target[0] - shape[0];
+        let covers = shape
+            .iter()
+            .zip(&target)
+            .all(|(&cell, &goal)| cell + offset == goal);
+        if covers {
+            let mut keys = vec![Key::RotateCw; turns];
+            keys.extend(shifts(tetromino, turned, width, start[0], offset[0]));
+            if best.as_ref().is_none_or(|best| keys.len() < best.len()) {
+                best = Some(keys);
+            }
+        }
+        turned = turned.cw();
+    }
+    best.unwrap_or_default()
+}
+
+/// The fewest presses to move `tetromino` facing `facing` from column `x`
+/// to column `to`: taps, or holding to the wall and tapping back.
+fn shifts(tetromino: Tetromino, facing: Rotation, width: usize, x: i32, to: i32) -> Vec<Key> {
+    let left_wall = -tetromino.min_x(facing);
+    let right_wall = width as i32 - 1 - tetromino.max_x(facing);
+    let (tap, das, back, wall) = if to < x {
+        (Key::Left, Key::DasLeft, Key::Right, left_wall)
+    } else {
+        (Key::Right, Key::DasRight, Key::Left, right_wall)
+    };
+    let taps = (to - x).unsigned_abs() as usize;
+    let from_wall = (to - wall).unsigned_abs() as usize;
+    if taps <= 1 + from_wall {
+        vec![tap; taps]
+    } else {
+        let mut keys = vec![das];
+        keys.extend(vec![back; from_wall]);
+        keys
+    }
+}
+
+fn sorted(mut cells: Vec<Point2<i32>>) -> Vec<Point2<i32>> {
+    cells.sort_by_key(|cell| (cell[0], cell[1]));
+    cells
+}
+
+/// A piece that took more presses than it needed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fault {
+    pub tetromino: Tetromino,
+    pub presses: u32,
+    /// The shortest way there.
+    pub keys: Vec<Key>,
+}
+
+/// The piece in play as it came in.
+struct Spawned {
+    tetromino: Tetromino,
+    pos: Point2<i32>,
+    facing: Rotation,
+    board: Board,
+}
+
+#[derive(Default)]
+pub struct Finesse {
+    spawned: Option<Spawned>,
+    hard_dropped: bool,
+    fault: Option<Fault>,
+}
+
+impl Finesse {
+    /// The last piece's fault, if it had one and it hasn't been taken.
+    pub fn take_fault(&mut self) -> Option<Fault> {
+        self.fault.take()
+    }
+
+    /// Judges `tetromino`, which took `presses` to lock at `pos` facing
+    /// `facing`. Only pieces hard dropped straight down from the spawn row
+    /// are judged; slides and spins under the stack take what they take.
+    fn judge(&mut self, tetromino: Tetromino, pos: Point2<i32>, facing: Rotation, presses: u32) {
+        let spawned = match self.spawned.take() {
+            Some(spawned) if spawned.tetromino == tetromino && self.hard_dropped => spawned,
+            _ => return,
+        };
+        let straight = (spawned.pos[1]..=pos[1]).all(|y| {
+            tetromino
+                .blocks(Point2::new(pos[0], y), facing)
+                .into_iter()
+                .all(|block| block[1] < 0 || spawned.board.is_free(block))
+        });
+        if !straight {
+            return;
+        }
+        let from = (spawned.pos, spawned.facing);
+        let width = spawned.board.width();
+        let keys = optimal(tetromino, width, from, (pos, facing));
+        if presses as usize > keys.len() {
+            self.fault = Some(Fault {
+                tetromino,
+                presses,
+                keys,
+            });
+        }
+    }
+}
+
+impl Observer for Finesse {
+    fn notify(&mut self, event: Event, _: u64, game: &Game) {
+        match event {
+            Event::Spawn(tetromino) => {
+                let (pos, facing) = game.piece();
+                self.spawned = Some(Spawned {
+                    tetromino,
+                    pos,
+                    facing,
+                    board: game.board.clone(),
+                });
+                self.hard_dropped = false;
+            }
+            Event::HardDrop { .. } => self.hard_dropped = true,
+            Event::Lock {
+                tetromino,
+                pos,
+                facing,
+                presses,
+            } => self.judge(tetromino, pos, facing, presses),
+            _ => (),
+        }
+    }
+}
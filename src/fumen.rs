@@ -0,0 +1,333 @@
+//! Fumen, the encoding the community shares setups in. A board is read
+//! from the first page of a v115 diagram, and the pieces to play from its
+//! quiz comment (`#Q=[hold](current)next...`) or, failing that, from the
+//! pieces placed on each page. Boards are written back out the same way,
+//! as a single page.
+
+use crate::board::{Board, FixedBlock};
+use crate::puzzle::{parse_pieces, Practice};
+use crate::tetromino::Tetromino;
+use nalgebra::Point2;
+
+const VERSION: &str = "v115@";
+const TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// The characters comments are written in, after JavaScript's `escape`.
+const COMMENT_TABLE: &[u8] =
+    b" !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+const COMMENT_BASE: u32 = COMMENT_TABLE.len() as u32 + 1;
+const FIELD_WIDTH: usize = 10;
+/// Rows of the field, not counting the garbage row fumen keeps under it.
+const FIELD_HEIGHT: usize = 23;
+const FIELD_BLOCKS: usize = FIELD_WIDTH * (FIELD_HEIGHT + 1);
+/// A field run of this value is a page that doesn't change the field, and
+/// is followed by how many pages after it don't either.
+const UNCHANGED: u32 = 8 * FIELD_BLOCKS as u32 + FIELD_BLOCKS as u32 - 1;
+/// Fumen's block numbers from 1, as letters. 8 is garbage.
+const PIECES: [char; 7] = ['I', 'L', 'O', 'Z', 'T', 'J', 'S'];
+const GARBAGE: u8 = 8;
+/// Flags in a page's action, which counts up from the piece placed (of 8),
+/// its rotation (4), where it is and then the raise and mirror flags.
+const GUIDELINE_COLORS: u32 = 8 * 4 * FIELD_BLOCKS as u32 * 2 * 2;
+const HAS_COMMENT: u32 = GUIDELINE_COLORS * 2;
+
+fn block_number(block: FixedBlock) -> u8 {
+    match block {
+        FixedBlock::Piece(tetromino) => PIECES
+            .iter()
+            .position(|&c| c == tetromino.symbol())
+            .map_or(GARBAGE, |i| i as u8 + 1),
+        FixedBlock::Garbage => GARBAGE,
+    }
+}
+
+fn from_block_number(number: u8) -> Option<FixedBlock> {
+    match number {
+        0 => None,
+        1..=7 => Some(
+            Tetromino::from_symbol(PIECES[number as usize - 1])
+                .map_or(FixedBlock::Garbage, FixedBlock::Piece),
+        ),
+        _ => Some(FixedBlock::Garbage),
+    }
+}
+
+/// The encoded data, read a base-64 number at a time.
+struct Reader {
+    values: Vec<u32>,
+    at: usize,
+}
+
+impl Reader {
+    fn poll(&mut self, digits: usize) -> Result<u32, String> {
+        let values = self
+            .values
+            .get(self.at..self.at + digits)
+            .ok_or("the fumen ends early")?;
+        self.at += digits;
+        Ok(values
+            .iter()
+            .rev()
+            .fold(0, |value, &digit| value * 64 + digit))
+    }
+
+    fn is_done(&self) -> bool {
+        self.at >= self.values.len()
+    }
+}
+
+fn push_number(out: &mut String, mut value: u32, digits: usize) {
+    for _ in 0..digits {
+        out.push(TABLE[(value % 64) as usize] as char);
+        value /= 64;
+    }
+}
+
+/// JavaScript's `escape`, which fumen applies to comments before encoding.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || "@*_+-./".contains(c) {
+            escaped.push(c);
+        } else if (c as u32) < 0x100 {
+            escaped.push_str(&format!("%{:02X}", c as u32));
+        } else {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                escaped.push_str(&format!("%u{:04X}", unit));
+            }
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut units = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let hex = |digits: &str| u16::from_str_radix(digits, 16).ok();
+        let (unit, len) = match (rest.get(1..2), rest.get(2..6), rest.get(1..3)) {
+            _ if c != '%' => (None, c.len_utf8()),
+            (Some("u"), Some(digits), _) if hex(digits).is_some() => (hex(digits), 6),
+            (_, _, Some(digits)) if hex(digits).is_some() => (hex(digits), 3),
+            _ => (None, 1),
+        };
+        match unit {
+            Some(unit) => units.push(unit),
+            None => units.extend(c.encode_utf16(&mut [0; 2]).iter()),
+        }
+        rest = &rest[len..];
+    }
+    String::from_utf16_lossy(&units)
+}
+
+fn read_comment(reader: &mut Reader) -> Result<String, String> {
+    let len = reader.poll(2)? as usize;
+    let mut escaped = String::new();
+    for _ in 0..len.div_ceil(4) {
+        let mut value = reader.poll(5)?;
+        for _ in 0..4 {
+            let c = COMMENT_TABLE
+                .get((value % COMMENT_BASE) as usize)
+                .ok_or("the fumen has a bad comment")?;
+            escaped.push(*c as char);
+            value /= COMMENT_BASE;
+        }
+    }
+    escaped.truncate(len);
+    Ok(unescape(&escaped))
+}
+
+fn write_comment(out: &mut String, comment: &str) -> Result<(), String> {
+    let escaped = escape(comment);
+    if escaped.len() >= 64 * 64 {
+        return Err("the comment is too long".to_string());
+    }
+    push_number(out, escaped.len() as u32, 2);
+    for chunk in escaped.as_bytes().chunks(4) {
+        let value = chunk.iter().rev().fold(0, |value, c| {
+            let index = COMMENT_TABLE.iter().position(|t| t == c).unwrap_or(0);
+            value * COMMENT_BASE + index as u32
+        });
+        push_number(out, value, 5);
+    }
+    Ok(())
+}
+
+/// The pieces of a quiz comment, `#Q=[hold](current)next...`, hold first.
+fn quiz_pieces(comment: &str) -> Option<Vec<Tetromino>> {
+    let quiz = comment.strip_prefix("#Q=[")?;
+    let (hold, rest) = quiz.split_once("](")?;
+    let (current, next) = rest.split_once(')')?;
+    let next: String = next
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+    parse_pieces(&format!("{}{}{}", hold, current, next))
+}
+
+/// Reads a fumen, given as the data or a URL ending in it, as a practice
+/// drill on a `width` by `height` board. Boards are 10 wide in fumen; one
+/// shorter than the diagram's 23 rows is filled from the bottom.
+pub fn decode(code: &str, width: usize, height: usize) -> Result<Practice, String> {
+    let data = match code.trim().find(VERSION) {
+        Some(at) => &code.trim()[at + VERSION.len()..],
+        None => return Err("not a v115 fumen".to_string()),
+    };
+    if width != FIELD_WIDTH {
+        return Err(format!("fumen boards are {} wide", FIELD_WIDTH));
+    }
+    let values = data
+        .chars()
+        .filter(|&c| c != '?')
+        .map(|c| TABLE.iter().position(|&t| t as char == c).map(|v| v as u32))
+        .collect::<Option<Vec<u32>>>()
+        .ok_or("the fumen has a character it can't have")?;
+    let mut reader = Reader { values, at: 0 };
+
+    let mut field = [0u8; FIELD_BLOCKS];
+    let mut placed = Vec::new();
+    let mut quiz = None;
+    let mut repeats = 0;
+    let mut page = 0;
+    while !reader.is_done() {
+        if repeats > 0 {
+            repeats -= 1;
+        } else {
+            let mut cells = 0;
+            while cells < FIELD_BLOCKS {
+                let run = reader.poll(2)?;
+                let (diff, len) = (
+                    (run / FIELD_BLOCKS as u32) as i32 - 8,
+                    run as usize % FIELD_BLOCKS + 1,
+                );
+                if cells + len > FIELD_BLOCKS {
+                    return Err("the fumen's field runs over".to_string());
+                }
+                // Only the first page's field is wanted, and later ones
+                // build on pieces locked in place.
+                if page == 0 {
+                    for cell in &mut field[cells..cells + len] {
+                        let value = *cell as i32 + diff;
+                        if !(0..=GARBAGE as i32).contains(&value) {
+                            return Err("the fumen has a block it can't have".to_string());
+                        }
+                        *cell = value as u8;
+                    }
+                }
+                cells += len;
+                if run == UNCHANGED {
+                    repeats = reader.poll(1)?;
+                }
+            }
+        }
+        let action = reader.poll(3)?;
+        let piece = action % 8;
+        let has_comment = action / HAS_COMMENT % 2 == 1;
+        if piece != 0 {
+            placed.push(PIECES[piece as usize - 1]);
+        }
+        if has_comment {
+            let comment = read_comment(&mut reader)?;
+            if page == 0 {
+                quiz = quiz_pieces(&comment);
+            }
+        }
+        page += 1;
+    }
+
+    let mut board = Board::new(width, height);
+    for (i, &number) in field[..FIELD_WIDTH * FIELD_HEIGHT].iter().enumerate() {
+        if let Some(block) = from_block_number(number) {
+            let x = (i % FIELD_WIDTH) as i32;
+            let y = (i / FIELD_WIDTH) as i32 + height as i32 - FIELD_HEIGHT as i32;
+            if !board.set(Point2::new(x, y), block) {
+                return Err(format!("the board doesn't fit {} by {}", width, height));
+            }
+        }
+    }
+    let queue = match quiz {
+        Some(queue) => queue,
+        None => parse_pieces(&placed.into_iter().collect::<String>())
+            .ok_or("the fumen has pieces this game doesn't")?,
+    };
+    Ok(Practice {
+        board,
+        queue,
+        repeat: false,
+    })
+}
+
+/// Writes `board` as a one-page fumen, with the hold piece and the pieces
+/// to come, the current one first, in a quiz comment if there are any.
+pub fn encode(
+    board: &Board,
+    hold: Option<Tetromino>,
+    queue: &[Tetromino],
+) -> Result<String, String> {
+    if board.width() != FIELD_WIDTH {
+        return Err(format!("fumen boards are {} wide", FIELD_WIDTH));
+    }
+    let mut field = [0u8; FIELD_BLOCKS];
+    for (block, fixed) in board.blocks() {
+        let y = block[1] + FIELD_HEIGHT as i32 - board.height() as i32;
+        if y < 0 {
+            return Err(format!("fumen boards are {} tall", FIELD_HEIGHT));
+        }
+        field[y as usize * FIELD_WIDTH + block[0] as usize] = block_number(fixed);
+    }
+
+    let mut data = String::new();
+    let mut cells = 0;
+    while cells < FIELD_BLOCKS {
+        let len = field[cells..]
+            .iter()
+            .take_while(|&&n| n == field[cells])
+            .count();
+        let run = (field[cells] as u32 + 8) * FIELD_BLOCKS as u32 + len as u32 - 1;
+        push_number(&mut data, run, 2);
+        if run == UNCHANGED {
+            push_number(&mut data, 0, 1);
+        }
+        cells += len;
+    }
+
+    let comment = match queue.split_first() {
+        Some((current, next)) => {
+            let symbols = |pieces: &[Tetromino]| -> Result<String, String> {
+                pieces
+                    .iter()
+                    .map(|t| match PIECES.contains(&t.symbol()) {
+                        true => Ok(t.symbol()),
+                        false => Err(format!("fumen has no {} piece", t.symbol())),
+                    })
+                    .collect()
+            };
+            Some(format!(
+                "#Q=[{}]({}){}",
+                symbols(hold.as_slice())?,
+                symbols(&[*current])?,
+                symbols(next)?
+            ))
+        }
+        None => None,
+    };
+    // No piece placed.
+    let action = GUIDELINE_COLORS + comment.is_some() as u32 * HAS_COMMENT;
+    push_number(&mut data, action, 3);
+    if let Some(ref comment) = comment {
+        write_comment(&mut data, comment)?;
+    }
+
+    // Broken up with '?' the way fumen does, for old links.
+    let mut code = VERSION.to_string();
+    if data.len() < 41 {
+        code.push_str(&data);
+    } else {
+        let (head, tail) = data.split_at(42);
+        code.push_str(head);
+        for chunk in tail.as_bytes().chunks(47) {
+            code.push('?');
+            code.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+        }
+    }
+    Ok(code)
+}
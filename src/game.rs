@@ -0,0 +1,1944 @@
+use crate::board::{Board, FixedBlock, HIDDEN_ROWS};
+use crate::clock::Instant;
+use crate::handicap::Handicap;
+use crate::master::{self, Grade, Grading};
+use crate::mission::Mission;
+use crate::mode::Mode;
+use crate::puzzle::{Goal, Practice, Puzzle};
+use crate::randomizer::Dealer;
+use crate::rules::{
+    LockReset, Randomizer, RotationSystem, Rules, Ruleset, MAX_PREVIEWS, MOVE_RESET_LIMIT,
+};
+use crate::tetromino::{PieceSet, Rotation, Tetromino, Turn};
+use nalgebra::{Point2, Vector2};
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+use std::time::Duration;
+
+pub const DEFAULT_ENTRY_DELAY_MILLIS: u64 = 100;
+/// How long garbage waits after it's sent before it can come in.
+pub const GARBAGE_DELAY_MILLIS: u64 = 500;
+
+const LINES_PER_LEVEL: u32 = 10;
+/// Placements that can be taken back in modes that allow undo.
+const UNDO_LIMIT: usize = 50;
+
+/// Spawn pieces at a random column and facing instead of centered at the top
+/// in their standard orientation.
+const RANDOM_SPAWN: bool = false;
+
+const DEFAULT_GRAVITY_MILLIS: u64 = 500;
+const DEFAULT_DAS_MILLIS: u64 = 170;
+const DEFAULT_ARR_MILLIS: u64 = 50;
+
+/// Timings for held movement keys. `das` is how long a key must be held
+/// before it starts repeating, `arr` is the interval between repeats once it
+/// does. An `arr` of zero moves the piece straight to the wall.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Handling {
+    pub das: Duration,
+    pub arr: Duration,
+    /// The interval between rows while soft drop is held, zero to go
+    /// straight down. With none, each press drops one row.
+    #[serde(default)]
+    pub soft_drop: Option<Duration>,
+    /// How long a held move waits after a turn or a new piece before it
+    /// carries on repeating (DAS cut delay).
+    #[serde(default)]
+    pub dcd: Duration,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            das: Duration::from_millis(DEFAULT_DAS_MILLIS),
+            arr: Duration::from_millis(DEFAULT_ARR_MILLIS),
+            soft_drop: None,
+            dcd: Duration::from_millis(0),
+        }
+    }
+}
+
+/// How a round is set up.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Settings {
+    pub handling: Handling,
+    /// Time between gravity steps.
+    pub gravity: Duration,
+    pub start_level: u32,
+    /// Seed for the piece sequence; the same seed deals the same pieces.
+    pub seed: u64,
+    #[serde(default)]
+    pub mode: Mode,
+    #[serde(default)]
+    pub pieces: PieceSet,
+    #[serde(default)]
+    pub rules: Rules,
+    /// How pieces turn, if not the way the rules have them.
+    #[serde(default)]
+    pub rotation: Option<RotationSystem>,
+    /// How pieces are dealt, if not the way the rules have them.
+    #[serde(default = "legacy_randomizer")]
+    pub randomizer: Option<Randomizer>,
+    /// The player's pick of what puts off a lock, for whichever rounds
+    /// have a lock delay.
+    #[serde(default)]
+    pub lock_reset: LockReset,
+    /// How many of the pieces to come are shown, if not as many as the
+    /// rules show.
+    #[serde(default)]
+    pub previews: Option<usize>,
+    /// Time from a piece locking to the next one spawning (ARE).
+    #[serde(default = "default_entry_delay")]
+    pub entry_delay: Duration,
+    /// How long full rows stay on the board before the stack collapses
+    /// onto them. The entry delay starts once they're gone.
+    #[serde(default)]
+    pub line_clear_delay: Duration,
+    /// How long garbage waits before a lock can take it in, giving time
+    /// to clear lines and send it back. Replays from before it waited
+    /// have none.
+    #[serde(default)]
+    pub garbage_delay: Duration,
+    /// What this side is given to even out a versus round.
+    #[serde(default)]
+    pub handicap: Handicap,
+}
+
+fn default_entry_delay() -> Duration {
+    Duration::from_millis(DEFAULT_ENTRY_DELAY_MILLIS)
+}
+
+/// Rounds from before the randomizer could be picked dealt every piece at
+/// random, whatever the rules.
+fn legacy_randomizer() -> Option<Randomizer> {
+    Some(Randomizer::Uniform)
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            handling: Handling::default(),
+            gravity: Duration::from_millis(DEFAULT_GRAVITY_MILLIS),
+            start_level: 1,
+            seed: 0,
+            mode: Mode::Endless,
+            pieces: PieceSet::Tetrominoes,
+            rules: Rules::Modern,
+            rotation: None,
+            randomizer: None,
+            lock_reset: LockReset::Move,
+            previews: None,
+            entry_delay: default_entry_delay(),
+            line_clear_delay: Duration::from_millis(0),
+            garbage_delay: Duration::from_millis(GARBAGE_DELAY_MILLIS),
+            handicap: Handicap::default(),
+        }
+    }
+}
+
+/// A player action, independent of whichever key or device produced it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Input {
+    Left,
+    Right,
+    RotateCw,
+    RotateCcw,
+    SoftDrop,
+    HardDrop,
+    Hold,
+    /// Takes back the last placement, where the mode allows it.
+    Undo,
+}
+
+impl Input {
+    pub const ALL: [Input; 8] = [
+        Input::Left,
+        Input::Right,
+        Input::RotateCw,
+        Input::RotateCcw,
+        Input::SoftDrop,
+        Input::HardDrop,
+        Input::Hold,
+        Input::Undo,
+    ];
+
+    /// The name the action goes by in config files and scripts.
+    pub fn name(self) -> &'static str {
+        match self {
+            Input::Left => "left",
+            Input::Right => "right",
+            Input::RotateCw => "rotate_cw",
+            Input::RotateCcw => "rotate_ccw",
+            Input::SoftDrop => "soft_drop",
+            Input::HardDrop => "hard_drop",
+            Input::Hold => "hold",
+            Input::Undo => "undo",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Input> {
+        Input::ALL
+            .iter()
+            .cloned()
+            .find(|input| input.name() == name)
+    }
+}
+
+/// Something that happened during a round that the frontend may want to
+/// react to, such as by playing a sound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Move,
+    Rotate,
+    /// The piece came to rest at `pos`, facing `facing`, and was fixed to
+    /// the board.
+    Lock {
+        tetromino: Tetromino,
+        pos: Point2<i32>,
+        facing: Rotation,
+        /// Presses of the move and turn keys it took, a held key counting
+        /// once.
+        presses: u32,
+    },
+    /// One to four rows were cleared by a single piece.
+    LineClear(usize),
+    /// The row at `y` was full, one for each row of a `LineClear` and just
+    /// before it.
+    RowCleared(i32),
+    /// The piece was hard dropped from row `from` to `pos`, facing
+    /// `facing`.
+    HardDrop {
+        tetromino: Tetromino,
+        pos: Point2<i32>,
+        facing: Rotation,
+        from: i32,
+    },
+    GameOver,
+    /// The piece just locked was a T-spin, ahead of its `LineClear` if it
+    /// cleared any rows.
+    TSpin,
+    /// A new piece came into play, from the sequence or the hold slot.
+    Spawn(Tetromino),
+    /// Garbage rows sent to the opponent, after cancelling any that were
+    /// on their way in.
+    Attack(u32),
+}
+
+/// The round as it stood before a lock, for undo: a snapshot, and the
+/// queued pieces it leaves out, so rewinding deals them again.
+#[derive(Clone, Debug)]
+struct Rewind {
+    snapshot: Snapshot,
+    queue: VecDeque<Tetromino>,
+}
+
+/// The falling piece as saved in a `Snapshot`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ActivePiece {
+    pub tetromino: Tetromino,
+    pub x: i32,
+    pub y: i32,
+    pub facing: Rotation,
+}
+
+/// Everything needed to carry a round over to another session. Timers that
+/// only matter from moment to moment, such as auto-repeat and the gravity
+/// step in progress, start over.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub board: Board,
+    pub piece: ActivePiece,
+    /// Whether the piece had locked and the next one was waiting to spawn.
+    pub spawning: bool,
+    pub hold: Option<Tetromino>,
+    pub hold_used: bool,
+    /// The piece after the current one. Saves from before it was tracked
+    /// have none, and one is dealt on restore.
+    #[serde(default)]
+    pub next: Option<Tetromino>,
+    /// The pieces already dealt after `next` to be shown, which saves from
+    /// before they were don't have.
+    #[serde(default)]
+    pub upcoming: Vec<Tetromino>,
+    pub score: u32,
+    pub lines: u32,
+    pub level: u32,
+    pub start_level: u32,
+    pub gravity: Duration,
+    pub seed: u64,
+    #[serde(default)]
+    pub mode: Mode,
+    /// Time played so far.
+    #[serde(default)]
+    pub played: Duration,
+    #[serde(default)]
+    pub pieces: PieceSet,
+    #[serde(default)]
+    pub rules: Rules,
+    /// Saves from before rotation could be picked turn the rules' way.
+    #[serde(default)]
+    pub rotation: Option<RotationSystem>,
+    #[serde(default = "legacy_randomizer")]
+    pub randomizer: Option<Randomizer>,
+    /// What the randomizer remembers, such as the pieces left in its bag.
+    #[serde(default)]
+    pub dealer: Vec<Tetromino>,
+    #[serde(default)]
+    pub lock_reset: LockReset,
+    #[serde(default)]
+    pub previews: Option<usize>,
+    #[serde(default = "default_entry_delay")]
+    pub entry_delay: Duration,
+    #[serde(default)]
+    pub line_clear_delay: Duration,
+    #[serde(default)]
+    pub garbage_delay: Duration,
+    #[serde(default)]
+    pub stats: Stats,
+    #[serde(default)]
+    pub dealt: Dealt,
+    #[serde(default)]
+    pub heatmap: Heatmap,
+    #[serde(default)]
+    pub grading: Grading,
+    /// The random number generator mid-sequence, so a restored game deals
+    /// the pieces it would have.
+    pub rng: XorShiftRng,
+}
+
+/// Running totals kept over a round, for the stats panel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stats {
+    /// Pieces locked.
+    pub pieces: u32,
+    /// Inputs pressed while the round was in play.
+    pub inputs: u32,
+    /// Garbage rows sent, after cancelling what was on its way in.
+    pub attack: u32,
+    /// Clears of one, two, three and four or more rows.
+    #[serde(default)]
+    pub clears: [u32; 4],
+    #[serde(default)]
+    pub t_spins: u32,
+    #[serde(default)]
+    pub perfect_clears: u32,
+    /// Hard clears, four rows or a T-spin, in a row after the first, with
+    /// no easier clear between.
+    #[serde(default)]
+    pub back_to_back: u32,
+    #[serde(default)]
+    pub best_back_to_back: u32,
+    /// Whether the last clear was a hard one, so the next can follow it.
+    #[serde(default)]
+    hard_clear_last: bool,
+}
+
+impl Stats {
+    fn cleared(&mut self, hard: bool) {
+        if hard && self.hard_clear_last {
+            self.back_to_back += 1;
+            self.best_back_to_back = self.best_back_to_back.max(self.back_to_back);
+        } else {
+            self.back_to_back = 0;
+        }
+        self.hard_clear_last = hard;
+    }
+
+    /// Pieces locked a second over `played`.
+    pub fn pieces_per_second(&self, played: Duration) -> f32 {
+        per(self.pieces, played.as_secs_f32())
+    }
+
+    /// Garbage sent a minute over `played`.
+    pub fn attack_per_minute(&self, played: Duration) -> f32 {
+        per(self.attack, played.as_secs_f32() / 60.0)
+    }
+
+    /// Inputs pressed for each piece locked.
+    pub fn inputs_per_piece(&self) -> f32 {
+        per(self.inputs, self.pieces as f32)
+    }
+}
+
+/// How often each piece has been dealt over a round, for the piece
+/// distribution panel.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dealt {
+    /// Pieces dealt so far.
+    total: u32,
+    counts: HashMap<Tetromino, u32>,
+    /// How many pieces had been dealt before each piece was last dealt.
+    last: HashMap<Tetromino, u32>,
+}
+
+impl Dealt {
+    fn deal(&mut self, tetromino: Tetromino) {
+        *self.counts.entry(tetromino).or_insert(0) += 1;
+        self.last.insert(tetromino, self.total);
+        self.total += 1;
+    }
+
+    pub fn count(&self, tetromino: Tetromino) -> u32 {
+        self.counts.get(&tetromino).cloned().unwrap_or(0)
+    }
+
+    /// Pieces dealt since `tetromino` last was, or since the round began if
+    /// it hasn't been yet.
+    pub fn drought(&self, tetromino: Tetromino) -> u32 {
+        match self.last.get(&tetromino) {
+            Some(&at) => self.total - at - 1,
+            None => self.total,
+        }
+    }
+}
+
+/// How many minos have been locked into each board cell.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Heatmap {
+    width: usize,
+    height: usize,
+    /// Row by row from the top.
+    counts: Vec<u32>,
+}
+
+impl Heatmap {
+    pub fn new(width: usize, height: usize) -> Heatmap {
+        Heatmap {
+            width,
+            height,
+            counts: vec![0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn add(&mut self, block: Point2<i32>) {
+        let (x, y) = (block[0] as usize, block[1] as usize);
+        if block[0] >= 0 && block[1] >= 0 && x < self.width && y < self.height {
+            self.counts[y * self.width + x] += 1;
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u32 {
+        self.counts[y * self.width + x]
+    }
+
+    /// The count of the busiest cell.
+    pub fn max(&self) -> u32 {
+        self.counts.iter().cloned().max().unwrap_or(0)
+    }
+
+    /// The share of the minos locked that went into each column.
+    pub fn columns(&self) -> Vec<f32> {
+        let total: u32 = self.counts.iter().sum();
+        (0..self.width)
+            .map(|x| {
+                let column: u32 = (0..self.height).map(|y| self.get(x, y)).sum();
+                per(column, total as f32)
+            })
+            .collect()
+    }
+
+    /// Adds in `other`'s counts, starting over from them if it is for a
+    /// board of another size.
+    pub fn merge(&mut self, other: &Heatmap) {
+        if (self.width, self.height) != (other.width, other.height) {
+            *self = other.clone();
+            return;
+        }
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+    }
+}
+
+fn per(count: u32, over: f32) -> f32 {
+    if over > 0.0 {
+        count as f32 / over
+    } else {
+        0.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Shift {
+    Left,
+    Right,
+}
+
+impl Shift {
+    fn offset(self) -> Vector2<i32> {
+        match self {
+            Shift::Left => Vector2::new(-1, 0),
+            Shift::Right => Vector2::new(1, 0),
+        }
+    }
+}
+
+/// The horizontal key currently held down, if any.
+struct HeldShift {
+    shift: Shift,
+    pressed_at: Instant,
+    last_repeat: Option<Instant>,
+    /// When the repeat may go on after the DAS cut delay.
+    resume_at: Option<Instant>,
+}
+
+/// Rotate and hold presses made while no piece is in play, applied to the
+/// next piece as it spawns (initial rotation / initial hold).
+#[derive(Clone, Copy, Debug, Default)]
+struct InputBuffer {
+    /// Quarter turns clockwise, 0 to 3.
+    rotations: u8,
+    hold: bool,
+}
+
+/// A batch of garbage on its way in.
+#[derive(Clone, Copy, Debug)]
+struct Incoming {
+    rows: u32,
+    sent_at: Instant,
+}
+
+/// A piece that has locked, for modes that fade the stack out.
+struct Placed {
+    tetromino: Tetromino,
+    /// Where its cells are now, following the stack as rows clear and
+    /// garbage comes in. Cells that have been cleared are dropped.
+    cells: Vec<Point2<i32>>,
+    locked_at: Instant,
+}
+
+/// A single round: the board, the falling piece and everything that
+/// advances them.
+pub struct Game {
+    pos: Point2<i32>,
+    facing: Rotation,
+    tetromino: Tetromino,
+    /// The piece that comes after `tetromino`, dealt one ahead so it can be
+    /// shown.
+    next: Tetromino,
+    started_at: Instant,
+    /// When `update` last ran, to measure how much time it has to account
+    /// for.
+    last_update: Instant,
+    /// Time since the piece last fell, carried across updates.
+    fall_elapsed: Duration,
+    gravity: Duration,
+    pub board: Board,
+    pub game_over: bool,
+    pub handling: Handling,
+    held: Option<HeldShift>,
+    /// When the held soft drop last took the piece down a row, if soft drop
+    /// is held and repeats.
+    soft_dropped_at: Option<Instant>,
+    pub hold: Option<Tetromino>,
+    hold_used: bool,
+    spawn_at: Option<Instant>,
+    buffer: InputBuffer,
+    pub score: u32,
+    pub lines: u32,
+    pub level: u32,
+    start_level: u32,
+    seed: u64,
+    mode: Mode,
+    pieces: PieceSet,
+    /// The preset the round was set up with, kept for snapshots.
+    rules: Rules,
+    /// `rules` as the mode has changed them.
+    ruleset: Ruleset,
+    /// When the piece came to rest on the stack, or was last let off
+    /// locking, under a lock delay.
+    grounded_at: Option<Instant>,
+    /// The lowest row the piece has reached, and the move resets it has
+    /// used since.
+    lowest: i32,
+    lock_resets: u32,
+    /// Presses of the move and turn keys since the piece came in.
+    presses: u32,
+    entry_delay: Duration,
+    line_clear_delay: Duration,
+    garbage_delay: Duration,
+    handicap: Handicap,
+    /// When the full rows left by the last lock are taken out.
+    collapse_at: Option<Instant>,
+    /// Time spent in play, pauses aside, up to the end of the round.
+    played: Duration,
+    stats: Stats,
+    dealt: Dealt,
+    heatmap: Heatmap,
+    /// The one source of randomness in a round, seeded from `seed`: the
+    /// piece sequence and, with `RANDOM_SPAWN`, spawn positions.
+    rng: XorShiftRng,
+    /// Picks the pieces from `rng` as the round's randomizer does.
+    dealer: Box<dyn Dealer>,
+    pub perfect_clear_at: Option<Instant>,
+    paused_at: Option<Instant>,
+    events: Vec<Event>,
+    /// Whether the piece's last move was a rotation, for spotting T-spins.
+    rotated_last: bool,
+    /// Pieces in a row that have cleared lines.
+    combo: usize,
+    /// Points the last lock scored for its clear, bonuses included.
+    clear_points: u32,
+    /// Garbage on its way in, a batch per attack, oldest first.
+    garbage: VecDeque<Incoming>,
+    /// Picks the gaps in garbage rows. Kept apart from `rng` so taking
+    /// garbage doesn't change the pieces dealt.
+    garbage_rng: XorShiftRng,
+    /// The round as it stood before each recent lock, newest last, for
+    /// modes that allow undo.
+    history: VecDeque<Rewind>,
+    /// Pieces to deal before falling back on `rng`, after `next`.
+    queue: VecDeque<Tetromino>,
+    /// Whether each piece dealt from `queue` goes back on the end, so the
+    /// round never gets to `rng`.
+    repeat_queue: bool,
+    /// For a puzzle, how many of its pieces are still to come after the
+    /// current one, `next` included.
+    remaining: Option<usize>,
+    /// The puzzle or mission's goal, which ends the round once met.
+    goal: Option<Goal>,
+    /// Pieces locked recently enough to still be fading out, oldest first.
+    fading: VecDeque<Placed>,
+    /// How a Master round is doing for its grade.
+    grading: Grading,
+    /// The columns pieces come in over, on a player's side of a shared
+    /// board, rather than the whole width.
+    lane: Option<Range<i32>>,
+    /// Cells off the stack the piece can't go into either: where the other
+    /// player's piece is, on a shared board.
+    blocked: Vec<Point2<i32>>,
+}
+
+impl Game {
+    pub fn new(board: Board, settings: Settings) -> Game {
+        let mut game = Game::build(board, settings);
+        let rows = game.handicap.garbage_rows as usize;
+        if rows > 0 {
+            let hole = game.garbage_rng.gen_range(0, game.board.width());
+            game.board.push_garbage(rows.min(game.board.height()), hole);
+        }
+        game.deal_first();
+        game
+    }
+
+    /// A round of `puzzle`, which ends when its goal is met or its pieces
+    /// run out.
+    pub fn puzzle(puzzle: &Puzzle, settings: Settings) -> Game {
+        let settings = Settings {
+            mode: Mode::Puzzle,
+            ..settings
+        };
+        let mut game = Game::build(puzzle.board.clone(), settings);
+        game.queue = puzzle.pieces.iter().cloned().collect();
+        game.remaining = Some(puzzle.pieces.len().saturating_sub(1));
+        game.goal = Some(puzzle.goal);
+        game.deal_first();
+        game
+    }
+
+    /// A round of `mission`, from its level, which ends when its goal is met
+    /// or, if it has pieces of its own, they run out.
+    pub fn mission(mission: &Mission, settings: Settings) -> Game {
+        let settings = Settings {
+            mode: Mode::Mission,
+            start_level: mission.level,
+            ..settings
+        };
+        let puzzle = &mission.puzzle;
+        let mut game = Game::build(puzzle.board.clone(), settings);
+        if !puzzle.pieces.is_empty() {
+            game.queue = puzzle.pieces.iter().cloned().collect();
+            game.remaining = Some(puzzle.pieces.len() - 1);
+        }
+        game.goal = Some(puzzle.goal);
+        game.deal_first();
+        game
+    }
+
+    /// A round of `practice`, dealing its pieces in order.
+    pub fn practice(practice: &Practice, settings: Settings) -> Game {
+        let settings = Settings {
+            mode: Mode::Practice,
+            ..settings
+        };
+        let mut game = Game::build(practice.board.clone(), settings);
+        game.queue = practice.queue.iter().cloned().collect();
+        game.repeat_queue = practice.repeat;
+        game.deal_first();
+        game
+    }
+
+    /// A round on one side of a board shared with another player, the
+    /// pieces coming in over the columns in `lane`.
+    pub fn coop(board: Board, settings: Settings, lane: Range<i32>) -> Game {
+        let settings = Settings {
+            mode: Mode::Coop,
+            ..settings
+        };
+        let mut game = Game::build(board, settings);
+        game.lane = Some(lane);
+        game.deal_first();
+        game
+    }
+
+    /// A round with nothing dealt yet.
+    fn build(board: Board, settings: Settings) -> Game {
+        let now = Instant::now();
+        let ruleset = settings.mode.rules(settings.rules.ruleset());
+        let ruleset = Ruleset {
+            rotation: settings.rotation.unwrap_or(ruleset.rotation),
+            randomizer: settings.randomizer.unwrap_or(ruleset.randomizer),
+            lock_reset: settings.lock_reset,
+            previews: settings
+                .previews
+                .unwrap_or(ruleset.previews)
+                .min(MAX_PREVIEWS),
+            ..ruleset
+        };
+        let heatmap = Heatmap::new(board.width(), board.height());
+        Game {
+            pos: Point2::new(0, 0),
+            facing: Rotation::North,
+            started_at: now,
+            last_update: now,
+            tetromino: Tetromino::IBlock,
+            next: Tetromino::IBlock,
+            fall_elapsed: Duration::from_millis(0),
+            gravity: settings.handicap.fall_time(
+                ruleset
+                    .gravity
+                    .at(settings.start_level)
+                    .unwrap_or(settings.gravity),
+            ),
+            board,
+            game_over: false,
+            handling: settings.handling,
+            held: None,
+            soft_dropped_at: None,
+            hold: None,
+            hold_used: false,
+            spawn_at: None,
+            buffer: InputBuffer::default(),
+            score: 0,
+            lines: 0,
+            level: settings.start_level,
+            start_level: settings.start_level,
+            seed: settings.seed,
+            mode: settings.mode,
+            pieces: settings.pieces,
+            rules: settings.rules,
+            ruleset,
+            grounded_at: None,
+            lowest: 0,
+            lock_resets: 0,
+            presses: 0,
+            entry_delay: settings.entry_delay,
+            line_clear_delay: settings.line_clear_delay,
+            garbage_delay: settings.garbage_delay,
+            handicap: settings.handicap,
+            collapse_at: None,
+            played: Duration::from_millis(0),
+            stats: Stats::default(),
+            dealt: Dealt::default(),
+            heatmap,
+            rng: XorShiftRng::seed_from_u64(settings.seed),
+            dealer: ruleset.randomizer.dealer(),
+            perfect_clear_at: None,
+            paused_at: None,
+            events: Vec::new(),
+            rotated_last: false,
+            combo: 0,
+            clear_points: 0,
+            garbage: VecDeque::new(),
+            garbage_rng: XorShiftRng::seed_from_u64(settings.seed.wrapping_add(1)),
+            history: VecDeque::new(),
+            queue: VecDeque::new(),
+            repeat_queue: false,
+            remaining: None,
+            goal: None,
+            fading: VecDeque::new(),
+            grading: Grading::default(),
+            lane: None,
+            blocked: Vec::new(),
+        }
+    }
+
+    fn deal_first(&mut self) {
+        let first = self.deal();
+        self.next = self.deal();
+        self.place(first);
+        self.settle();
+    }
+
+    /// Captures the round so it can be written out and picked up later with
+    /// `restore`.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            board: self.board.clone(),
+            piece: ActivePiece {
+                tetromino: self.tetromino,
+                x: self.pos[0],
+                y: self.pos[1],
+                facing: self.facing,
+            },
+            spawning: self.spawn_at.is_some(),
+            hold: self.hold,
+            hold_used: self.hold_used,
+            next: Some(self.next),
+            upcoming: self.queue.iter().cloned().collect(),
+            score: self.score,
+            lines: self.lines,
+            level: self.level,
+            start_level: self.start_level,
+            gravity: self.gravity,
+            seed: self.seed,
+            mode: self.mode,
+            played: self.played,
+            pieces: self.pieces,
+            rules: self.rules,
+            rotation: Some(self.ruleset.rotation),
+            randomizer: Some(self.ruleset.randomizer),
+            dealer: self.dealer.remembered(),
+            lock_reset: self.ruleset.lock_reset,
+            previews: Some(self.ruleset.previews),
+            entry_delay: self.entry_delay,
+            line_clear_delay: self.line_clear_delay,
+            garbage_delay: self.garbage_delay,
+            stats: self.stats,
+            dealt: self.dealt.clone(),
+            heatmap: self.heatmap.clone(),
+            grading: self.grading,
+            rng: self.rng.clone(),
+        }
+    }
+
+    /// Rebuilds a round from `snapshot`, paused, with the piece sequence
+    /// continuing where it was.
+    pub fn restore(snapshot: Snapshot, handling: Handling, now: Instant) -> Game {
+        let settings = Settings {
+            handling,
+            gravity: snapshot.gravity,
+            start_level: snapshot.start_level,
+            seed: snapshot.seed,
+            mode: snapshot.mode,
+            pieces: snapshot.pieces,
+            rules: snapshot.rules,
+            rotation: snapshot.rotation,
+            randomizer: snapshot.randomizer,
+            lock_reset: snapshot.lock_reset,
+            previews: snapshot.previews,
+            entry_delay: snapshot.entry_delay,
+            line_clear_delay: snapshot.line_clear_delay,
+            garbage_delay: snapshot.garbage_delay,
+            handicap: Handicap::default(),
+        };
+        // Saved mid-clear, the full rows are still there; the next piece
+        // comes in straight away on resume, so clear them now.
+        let mut board = snapshot.board;
+        board.clear_lines();
+        let mut game = Game::new(board, settings);
+        game.played = snapshot.played;
+        game.stats = snapshot.stats;
+        game.dealt = snapshot.dealt;
+        // Saves from before the heatmap was kept have an empty one.
+        if snapshot.heatmap.width() == game.board.width()
+            && snapshot.heatmap.height() == game.board.height()
+        {
+            game.heatmap = snapshot.heatmap;
+        }
+        game.grading = snapshot.grading;
+        game.rng = snapshot.rng;
+        game.dealer.recall(snapshot.dealer);
+        game.next = match snapshot.next {
+            Some(next) => next,
+            None => game.dealer.deal(game.pieces, &mut game.rng),
+        };
+        game.queue = snapshot.upcoming.into_iter().collect();
+        game.look_ahead();
+        let piece = snapshot.piece;
+        game.tetromino = piece.tetromino;
+        game.pos = Point2::new(piece.x, piece.y);
+        game.facing = piece.facing;
+        // The piece had already locked; bring in the next one on resume.
+        if snapshot.spawning {
+            game.spawn_at = Some(now);
+        }
+        game.hold = snapshot.hold;
+        game.hold_used = snapshot.hold_used;
+        game.score = snapshot.score;
+        game.lines = snapshot.lines;
+        game.level = snapshot.level;
+        game.gravity = snapshot.gravity;
+        game.last_update = now;
+        game.paused_at = Some(now);
+        game
+    }
+
+    /// When the round was set up. Frame-stepped play measures its clock
+    /// from here.
+    pub fn started_at(&self) -> Instant {
+        self.started_at
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// How long the round has been played, not counting pauses.
+    pub fn elapsed(&self) -> Duration {
+        self.played
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    pub fn dealt(&self) -> &Dealt {
+        &self.dealt
+    }
+
+    /// Where the round's pieces have locked.
+    pub fn heatmap(&self) -> &Heatmap {
+        &self.heatmap
+    }
+
+    /// The pieces the round deals from.
+    pub fn pieces(&self) -> PieceSet {
+        self.pieces
+    }
+
+    /// Ends the round where it stands, if it hasn't already, with an
+    /// `Event::GameOver` for whatever is watching.
+    pub fn end_round(&mut self) {
+        if !self.game_over {
+            self.game_over = true;
+            self.events.push(Event::GameOver);
+        }
+    }
+
+    /// Whether the round ended by reaching its mode's goal rather than
+    /// topping out.
+    pub fn cleared_goal(&self) -> bool {
+        match self.goal {
+            Some(goal) => goal.is_met(self),
+            None => {
+                self.mode
+                    .is_cleared(self.lines, self.level, self.start_level)
+                    || self.time_left() == Some(Duration::from_millis(0))
+            }
+        }
+    }
+
+    /// For a round against the clock, the time it has to go.
+    pub fn time_left(&self) -> Option<Duration> {
+        self.time_limit()
+            .map(|limit| limit.saturating_sub(self.played))
+    }
+
+    /// How long the round lasts, if its mode or goal sets a time.
+    fn time_limit(&self) -> Option<Duration> {
+        match self.goal {
+            Some(Goal::Survive(secs)) => Some(Duration::from_secs(secs.into())),
+            _ => self.mode.time_limit(),
+        }
+    }
+
+    /// For a puzzle, the pieces still to be played: the one falling, any in
+    /// hold and those to come.
+    pub fn pieces_left(&self) -> Option<usize> {
+        let in_play = self.spawn_at.is_none() as usize + self.hold.is_some() as usize;
+        self.remaining.map(|remaining| remaining + in_play)
+    }
+
+    pub fn goal(&self) -> Option<Goal> {
+        self.goal
+    }
+
+    /// For a Master round, the grade it has earned so far.
+    pub fn grade(&self) -> Option<Grade> {
+        if self.mode == Mode::Master {
+            Some(self.grading.grade(self.score, self.level))
+        } else {
+            None
+        }
+    }
+
+    pub fn tetromino(&self) -> Tetromino {
+        self.tetromino
+    }
+
+    /// For modes that hide the stack, the pieces that are still fading out,
+    /// with how much of the fade each has left from 1 down to 0.
+    pub fn fading(&self) -> impl Iterator<Item = (Tetromino, &[Point2<i32>], f32)> + '_ {
+        let fade = self.mode.fade(self.level).unwrap_or_default().as_secs_f32();
+        let now = self.last_update;
+        self.fading.iter().filter_map(move |placed| {
+            let since = now
+                .saturating_duration_since(placed.locked_at)
+                .as_secs_f32();
+            if since < fade {
+                Some((placed.tetromino, &placed.cells[..], 1.0 - since / fade))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The next piece in the sequence.
+    pub fn next(&self) -> Tetromino {
+        self.next
+    }
+
+    /// The pieces lined up after the next one: those dealt ahead to be
+    /// shown, or a puzzle's or practice's.
+    pub fn queued(&self) -> impl Iterator<Item = Tetromino> + '_ {
+        self.queue.iter().cloned()
+    }
+
+    /// Where the falling piece is and which way it faces.
+    pub fn piece(&self) -> (Point2<i32>, Rotation) {
+        (self.pos, self.facing)
+    }
+
+    /// How long the falling piece has been resting on something at `now`,
+    /// counting from its last lock reset, or nothing while it's in the air.
+    pub fn grounded_for(&self, now: Instant) -> Option<Duration> {
+        self.grounded_at.map(|at| now.saturating_duration_since(at))
+    }
+
+    /// How many times moving the falling piece has put off its lock.
+    pub fn lock_resets(&self) -> u32 {
+        self.lock_resets
+    }
+
+    /// The cells of the falling piece, or nothing during the entry delay.
+    pub fn active_blocks(&self) -> Vec<Point2<i32>> {
+        if self.spawn_at.is_none() {
+            self.tetromino.blocks(self.pos, self.facing)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Where the falling piece would land if dropped straight down, or
+    /// nothing during the entry delay.
+    pub fn ghost_blocks(&self) -> Vec<Point2<i32>> {
+        if self.spawn_at.is_some() {
+            return Vec::new();
+        }
+        let mut pos = self.pos;
+        let fits = |pos: Point2<i32>| {
+            self.tetromino
+                .blocks(pos + Vector2::new(0, 1), self.facing)
+                .into_iter()
+                .all(|block| self.is_open(block))
+        };
+        while fits(pos) {
+            pos[1] += 1;
+        }
+        self.tetromino.blocks(pos, self.facing)
+    }
+
+    pub fn ruleset(&self) -> &Ruleset {
+        &self.ruleset
+    }
+
+    pub fn handicap(&self) -> Handicap {
+        self.handicap
+    }
+
+    /// Queues `rows` of garbage sent by the opponent. Once it has waited
+    /// out the garbage delay it comes in when a piece locks without
+    /// clearing anything, unless clears send it back first.
+    pub fn receive_garbage(&mut self, rows: u32) {
+        if rows > 0 {
+            self.garbage.push_back(Incoming {
+                rows,
+                sent_at: self.last_update,
+            });
+        }
+    }
+
+    /// Whether the hold has been used on the piece in play.
+    pub fn hold_used(&self) -> bool {
+        self.hold_used
+    }
+
+    /// Garbage rows waiting to come in.
+    pub fn pending_garbage(&self) -> u32 {
+        self.garbage
+            .iter()
+            .fold(0, |rows, incoming| rows.saturating_add(incoming.rows))
+    }
+
+    /// Each batch of garbage waiting to come in, oldest first, with how far
+    /// through the garbage delay it is: 1 once the next lock that doesn't
+    /// clear will take it in.
+    pub fn incoming_garbage(&self) -> impl Iterator<Item = (u32, f32)> + '_ {
+        let delay = self.garbage_delay.as_secs_f32();
+        let now = self.last_update;
+        self.garbage.iter().map(move |incoming| {
+            let waited = now
+                .saturating_duration_since(incoming.sent_at)
+                .as_secs_f32();
+            let ready = if delay > 0.0 {
+                (waited / delay).min(1.0)
+            } else {
+                1.0
+            };
+            (incoming.rows, ready)
+        })
+    }
+
+    /// Everything that happened since the last call, oldest first.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// How far the falling piece is through its wait for the next gravity
+    /// step, from 0 to 1, or 0 while it rests on something or there is no
+    /// piece. Only for drawing it part way down; as far as the rules go it
+    /// is always in one cell.
+    pub fn fall_progress(&self) -> f32 {
+        if self.game_over || self.spawn_at.is_some() || !self.not_overlapping_down() {
+            return 0.0;
+        }
+        let gravity = self.gravity.as_secs_f32();
+        if gravity <= 0.0 {
+            return 0.0;
+        }
+        (self.fall_elapsed.as_secs_f32() / gravity).min(1.0)
+    }
+
+    /// Points the last piece to lock scored for the rows it cleared,
+    /// bonuses included.
+    pub fn clear_points(&self) -> u32 {
+        self.clear_points
+    }
+
+    /// Pieces in a row that have cleared lines, up to the last to lock.
+    pub fn combo(&self) -> usize {
+        self.combo
+    }
+
+    /// Time between gravity steps.
+    pub fn gravity(&self) -> Duration {
+        self.gravity
+    }
+
+    /// Keeps the piece out of `cells` as well as the stack, until they're
+    /// set again.
+    pub fn set_blocked(&mut self, cells: Vec<Point2<i32>>) {
+        self.blocked = cells;
+    }
+
+    /// Takes on `board` as another player on a shared board left it,
+    /// lifting the piece clear of any blocks that came down into it.
+    pub fn share_board(&mut self, board: &Board) {
+        self.board = board.clone();
+        if self.spawn_at.is_some() {
+            return;
+        }
+        let top = -(HIDDEN_ROWS as i32);
+        while self.pos[1] > top
+            && !self
+                .tetromino
+                .blocks(self.pos, self.facing)
+                .into_iter()
+                .all(|block| self.board.is_free(block))
+        {
+            self.pos[1] -= 1;
+        }
+    }
+
+    /// Changes the fall speed from here on; time the piece has already
+    /// spent falling counts towards the next step.
+    pub fn set_gravity(&mut self, gravity: Duration) {
+        self.gravity = gravity;
+    }
+
+    /// Advances auto-repeat, spawning and gravity up to `now`.
+    pub fn update(&mut self, now: Instant) {
+        if self.game_over || self.paused_at.is_some() {
+            return;
+        }
+        let delta = now.saturating_duration_since(self.last_update);
+        self.last_update = now;
+        self.played += delta;
+        if let Some(limit) = self.time_limit() {
+            if self.played >= limit {
+                self.played = limit;
+                self.end_round();
+                return;
+            }
+        }
+        if let Some(collapse_at) = self.collapse_at {
+            if now >= collapse_at {
+                self.collapse_at = None;
+                self.board.clear_lines();
+            }
+        }
+        match self.spawn_at {
+            Some(spawn_at) if now >= spawn_at => {
+                // A puzzle's last piece can be the one held.
+                match self.next_piece().or_else(|| self.hold.take()) {
+                    Some(next) => {
+                        self.spawn_at = None;
+                        self.spawn(next);
+                        self.cut_das(now);
+                    }
+                    // Out of puzzle pieces without having solved it.
+                    None => {
+                        self.end_round();
+                        return;
+                    }
+                }
+            }
+            Some(_) => (),
+            None => {
+                self.auto_shift(now);
+                self.auto_soft_drop(now);
+            }
+        }
+        // Gravity only runs while there is a piece, and each new piece
+        // starts its fall from scratch.
+        if self.spawn_at.is_some() {
+            self.fall_elapsed = Duration::from_millis(0);
+            return;
+        }
+        self.fall_elapsed += delta;
+        // A long frame can owe several steps; stop at the lock so the next
+        // piece doesn't inherit them.
+        while self.fall_elapsed >= self.gravity {
+            self.fall_elapsed -= self.gravity;
+            if self.not_overlapping_down() {
+                self.dropped();
+            } else {
+                // Under a lock delay, resting doesn't lock by itself.
+                if self.ruleset.lock_delay.is_none() {
+                    self.lock(now);
+                }
+                self.fall_elapsed = Duration::from_millis(0);
+                break;
+            }
+        }
+        if let Some(lock_delay) = self.ruleset.lock_delay {
+            if !self.not_overlapping_down() {
+                let grounded_at = *self.grounded_at.get_or_insert(now);
+                if now.saturating_duration_since(grounded_at) >= lock_delay {
+                    self.lock(now);
+                }
+            }
+        }
+    }
+
+    pub fn press(&mut self, input: Input, now: Instant) {
+        if self.game_over || self.paused_at.is_some() {
+            return;
+        }
+        self.stats.inputs += 1;
+        if input == Input::Undo {
+            self.undo();
+            return;
+        }
+        // Between lock and spawn there is no piece to move; buffer what
+        // should carry over to the next one.
+        if self.spawn_at.is_some() {
+            match input {
+                Input::Left | Input::Right => (),
+                Input::RotateCw | Input::RotateCcw => {
+                    let turns = if input == Input::RotateCw { 1 } else { 3 };
+                    self.buffer.rotations = (self.buffer.rotations + turns) % 4;
+                    return;
+                }
+                Input::Hold if self.ruleset.hold => {
+                    self.buffer.hold = true;
+                    return;
+                }
+                _ => return,
+            }
+        }
+        let moves = matches!(
+            input,
+            Input::Left | Input::Right | Input::RotateCw | Input::RotateCcw
+        );
+        if moves && self.spawn_at.is_none() {
+            self.presses += 1;
+        }
+        match input {
+            Input::Left | Input::Right => {
+                let shift = if input == Input::Left {
+                    Shift::Left
+                } else {
+                    Shift::Right
+                };
+                if self.spawn_at.is_none() {
+                    self.shift(shift, now);
+                }
+                self.held = Some(HeldShift {
+                    shift,
+                    pressed_at: now,
+                    last_repeat: None,
+                    resume_at: None,
+                });
+            }
+            Input::RotateCw if self.rotate(Turn::Cw) => self.turned(now),
+            Input::RotateCcw if self.rotate(Turn::Ccw) => self.turned(now),
+            Input::SoftDrop => {
+                if self.handling.soft_drop.is_some() {
+                    self.soft_dropped_at = Some(now);
+                }
+                if self.handling.soft_drop == Some(Duration::from_millis(0)) {
+                    let rows = self.fall();
+                    self.drop_scored(rows, false);
+                } else if self.not_overlapping_down() {
+                    self.dropped();
+                    self.drop_scored(1, false);
+                }
+            }
+            Input::Hold if self.ruleset.hold => self.swap_hold(),
+            Input::HardDrop if self.ruleset.hard_drop => {
+                let from = self.pos[1];
+                let rows = self.fall();
+                self.drop_scored(rows, true);
+                self.events.push(Event::HardDrop {
+                    tetromino: self.tetromino,
+                    pos: self.pos,
+                    facing: self.facing,
+                    from,
+                });
+                // Without a lock delay the piece locks on the next gravity
+                // step, as it always has.
+                if self.ruleset.lock_delay.is_some() {
+                    self.lock(now);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn release(&mut self, input: Input) {
+        let released = match input {
+            Input::Left => Shift::Left,
+            Input::Right => Shift::Right,
+            Input::SoftDrop => {
+                self.soft_dropped_at = None;
+                return;
+            }
+            _ => return,
+        };
+        if self.held.as_ref().map(|held| held.shift) == Some(released) {
+            self.held = None;
+        }
+    }
+
+    pub fn pause(&mut self, now: Instant) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(now);
+        }
+    }
+
+    /// Resumes after `pause`. Time spent paused doesn't count towards
+    /// gravity, and every other timer is pushed forward by it so nothing
+    /// catches up in a burst.
+    pub fn resume(&mut self, now: Instant) {
+        if let Some(paused_at) = self.paused_at.take() {
+            let paused_for = now - paused_at;
+            self.last_update = now;
+            if let Some(ref mut spawn_at) = self.spawn_at {
+                *spawn_at += paused_for;
+            }
+            if let Some(ref mut at) = self.perfect_clear_at {
+                *at += paused_for;
+            }
+            for placed in self.fading.iter_mut() {
+                placed.locked_at += paused_for;
+            }
+            if let Some(ref mut at) = self.grounded_at {
+                *at += paused_for;
+            }
+            if let Some(ref mut at) = self.collapse_at {
+                *at += paused_for;
+            }
+            // Keys may have been released while paused; make the player
+            // press again rather than resuming an auto-repeat.
+            self.held = None;
+            self.soft_dropped_at = None;
+        }
+    }
+
+    /// Whether the piece can go into `block`: free on the board and not
+    /// blocked.
+    fn is_open(&self, block: Point2<i32>) -> bool {
+        self.board.is_free(block) && !self.blocked.contains(&block)
+    }
+    fn not_overlapping_down(&self) -> bool {
+        self.tetromino
+            .blocks(self.pos + Vector2::new(0, 1), self.facing)
+            .into_iter()
+            .all(|block| self.is_open(block))
+    }
+    fn not_overlapping_shift(&self, shift: Shift) -> bool {
+        self.tetromino
+            .blocks(self.pos + shift.offset(), self.facing)
+            .into_iter()
+            .all(|block| self.is_open(block))
+    }
+    /// The facing the piece would turn to.
+    fn rotated(&self, turn: Turn) -> Rotation {
+        self.ruleset
+            .rotation
+            .rotate(self.tetromino, self.facing, turn)
+    }
+    /// Turns the piece `turn`'s way, in place or kicked to the first spot
+    /// the rotation system's kick table allows, returning false if it
+    /// couldn't.
+    fn rotate(&mut self, turn: Turn) -> bool {
+        let facing = self.rotated(turn);
+        let fits = |offset: Vector2<i32>| {
+            self.tetromino
+                .blocks(self.pos + offset, facing)
+                .into_iter()
+                .all(|block| self.is_open(block))
+        };
+        let kick = self
+            .ruleset
+            .rotation
+            .kicks()
+            .kicks(self.tetromino, self.facing, turn)
+            .find(|&offset| fits(offset));
+        match kick {
+            Some(offset) => {
+                self.pos += offset;
+                self.facing = facing;
+                self.settle();
+                true
+            }
+            None => false,
+        }
+    }
+    /// Follows up a turn the piece made.
+    fn turned(&mut self, now: Instant) {
+        self.rotated_last = true;
+        self.events.push(Event::Rotate);
+        self.moved(now);
+        self.cut_das(now);
+    }
+    /// Moves the piece one column, returning false if it was blocked.
+    fn shift(&mut self, shift: Shift, now: Instant) -> bool {
+        if self.not_overlapping_shift(shift) {
+            self.pos += shift.offset();
+            self.rotated_last = false;
+            self.events.push(Event::Move);
+            self.moved(now);
+            self.settle();
+            true
+        } else {
+            false
+        }
+    }
+    /// Moves the piece down a row. Under a lock delay the wait starts over
+    /// once it comes to rest again, and a row lower than it has been gives
+    /// back its move resets.
+    fn dropped(&mut self) {
+        self.pos[1] += 1;
+        self.rotated_last = false;
+        self.grounded_at = None;
+        if self.pos[1] > self.lowest {
+            self.lowest = self.pos[1];
+            self.lock_resets = 0;
+        }
+    }
+    /// Under instant gravity, takes the piece straight down onto the stack,
+    /// so it never hangs in the air between updates.
+    fn settle(&mut self) {
+        if self.gravity > Duration::from_millis(0) {
+            return;
+        }
+        self.fall();
+    }
+    /// Takes the piece down as far as it goes, returning how many rows it
+    /// fell.
+    fn fall(&mut self) -> u32 {
+        let mut rows = 0;
+        while self.not_overlapping_down() {
+            self.dropped();
+            rows += 1;
+        }
+        rows
+    }
+    /// Scores the piece being dropped `rows` rows by hand. Master's points
+    /// come from clears alone.
+    fn drop_scored(&mut self, rows: u32, hard: bool) {
+        if self.mode != Mode::Master {
+            self.score += self.ruleset.scoring.drop(rows, hard);
+        }
+    }
+    /// Puts off the lock of a resting piece that has just moved or turned,
+    /// as far as the lock reset policy allows.
+    fn moved(&mut self, now: Instant) {
+        if self.grounded_at.is_none() {
+            return;
+        }
+        match self.ruleset.lock_reset {
+            LockReset::Infinite => self.grounded_at = Some(now),
+            LockReset::Move if self.lock_resets < MOVE_RESET_LIMIT => {
+                self.grounded_at = Some(now);
+                self.lock_resets += 1;
+            }
+            LockReset::Move | LockReset::Step => (),
+        }
+    }
+    /// Holds up the repeat of a held move for the DAS cut delay.
+    fn cut_das(&mut self, now: Instant) {
+        if self.handling.dcd == Duration::from_millis(0) {
+            return;
+        }
+        if let Some(ref mut held) = self.held {
+            held.resume_at = Some(now + self.handling.dcd);
+        }
+    }
+
+    /// Applies DAS/ARR repeats for the held horizontal key.
+    fn auto_shift(&mut self, now: Instant) {
+        let (shift, pressed_at, last_repeat, resume_at) = match self.held {
+            Some(ref held) => (
+                held.shift,
+                held.pressed_at,
+                held.last_repeat,
+                held.resume_at,
+            ),
+            None => return,
+        };
+        let first_repeat = pressed_at + self.handling.das;
+        if now < first_repeat || resume_at.is_some_and(|resume_at| now < resume_at) {
+            return;
+        }
+        if self.handling.arr == Duration::from_millis(0) {
+            while self.shift(shift, now) {}
+            return;
+        }
+        let mut next = match last_repeat {
+            Some(last) => last + self.handling.arr,
+            None => first_repeat,
+        };
+        // The repeats held up by a cut are skipped, not made up.
+        if let Some(resume_at) = resume_at {
+            next = next.max(resume_at);
+        }
+        while next <= now {
+            self.shift(shift, now);
+            if let Some(ref mut held) = self.held {
+                held.last_repeat = Some(next);
+            }
+            next += self.handling.arr;
+        }
+    }
+    /// Takes the piece down at the held soft drop's rate.
+    fn auto_soft_drop(&mut self, now: Instant) {
+        let (last, interval) = match (self.soft_dropped_at, self.handling.soft_drop) {
+            (Some(last), Some(interval)) => (last, interval),
+            _ => return,
+        };
+        if interval == Duration::from_millis(0) {
+            let rows = self.fall();
+            self.drop_scored(rows, false);
+            return;
+        }
+        let mut next = last + interval;
+        while next <= now {
+            if self.not_overlapping_down() {
+                self.dropped();
+                self.drop_scored(1, false);
+            }
+            self.soft_dropped_at = Some(next);
+            next += interval;
+        }
+    }
+
+    /// Takes the next piece, dealing a new one behind it, or `None` if a
+    /// puzzle has run out.
+    fn next_piece(&mut self) -> Option<Tetromino> {
+        match self.remaining {
+            Some(0) => return None,
+            Some(ref mut remaining) => *remaining -= 1,
+            None => (),
+        }
+        let dealt = self.deal();
+        Some(std::mem::replace(&mut self.next, dealt))
+    }
+    fn deal(&mut self) -> Tetromino {
+        let dealt = match self.queue.pop_front() {
+            Some(queued) => {
+                if self.repeat_queue {
+                    self.queue.push_back(queued);
+                }
+                queued
+            }
+            None => self.dealer.deal(self.pieces, &mut self.rng),
+        };
+        self.dealt.deal(dealt);
+        self.look_ahead();
+        dealt
+    }
+    /// Deals enough pieces ahead of time to show those to come after
+    /// `next`. Puzzles and repeated practice queues have them all already.
+    fn look_ahead(&mut self) {
+        if self.remaining.is_some() || self.repeat_queue {
+            return;
+        }
+        while self.queue.len() + 1 < self.ruleset.previews {
+            let dealt = self.dealer.deal(self.pieces, &mut self.rng);
+            self.queue.push_back(dealt);
+        }
+    }
+    /// Puts `tetromino` at the top of the board as the active piece.
+    fn place(&mut self, tetromino: Tetromino) {
+        self.tetromino = tetromino;
+        self.rotated_last = false;
+        self.grounded_at = None;
+        self.events.push(Event::Spawn(tetromino));
+        self.facing = if RANDOM_SPAWN {
+            Rotation::from(self.rng.gen_range(0, 4))
+        } else {
+            self.ruleset.rotation.spawn_facing(self.tetromino)
+        };
+
+        let min_x = self.tetromino.min_x(self.facing);
+        let max_x = self.tetromino.max_x(self.facing);
+        let lane = match self.lane {
+            Some(ref lane) => lane.clone(),
+            None => 0..self.board.width() as i32,
+        };
+
+        self.pos[0] = if RANDOM_SPAWN {
+            self.rng.gen_range(lane.start - min_x, lane.end - max_x)
+        } else {
+            // Center the piece's bounding box, rounding to the left.
+            lane.start + (lane.end - lane.start - (max_x - min_x + 1)) / 2 - min_x
+        };
+
+        let min_y = self.tetromino.min_y(self.facing);
+        self.pos[1] = -min_y;
+        self.lowest = self.pos[1];
+        self.lock_resets = 0;
+        self.presses = 0;
+    }
+    /// Places the next piece, applying any rotation or hold buffered while
+    /// waiting for it.
+    fn spawn(&mut self, tetromino: Tetromino) {
+        if self.mode == Mode::Master {
+            self.level = master::spawned(self.level);
+        }
+        self.place(tetromino);
+        let buffer = std::mem::take(&mut self.buffer);
+        if buffer.hold {
+            self.swap_hold();
+        }
+        match buffer.rotations {
+            // Three turns one way are one the other, kicked that way.
+            3 => {
+                self.rotate(Turn::Ccw);
+            }
+            turns => {
+                for _ in 0..turns {
+                    self.rotate(Turn::Cw);
+                }
+            }
+        }
+        self.settle();
+    }
+    /// Swaps the active piece with the hold slot, once per piece.
+    fn swap_hold(&mut self) {
+        if self.hold_used {
+            return;
+        }
+        let next = match self.hold {
+            Some(held) => held,
+            None => match self.next_piece() {
+                Some(next) => next,
+                None => return,
+            },
+        };
+        self.hold = Some(self.tetromino);
+        self.place(next);
+        self.settle();
+        self.hold_used = true;
+    }
+    /// Whether the T piece just locked is wedged in by three of the four
+    /// cells diagonal to its center, walls included, having turned into
+    /// place.
+    fn t_spin(&self) -> bool {
+        if self.tetromino != Tetromino::TBlock || !self.rotated_last {
+            return false;
+        }
+        let blocks = self.tetromino.blocks(self.pos, self.facing);
+        let touching =
+            |a: Point2<i32>, b: Point2<i32>| (a[0] - b[0]).abs() + (a[1] - b[1]).abs() == 1;
+        let center = match blocks.iter().find(|&&block| {
+            blocks
+                .iter()
+                .filter(|&&other| touching(block, other))
+                .count()
+                == 3
+        }) {
+            Some(&center) => center,
+            None => return false,
+        };
+        let corners = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+        corners
+            .iter()
+            .filter(|&&(x, y)| !self.board.is_free(center + Vector2::new(x, y)))
+            .count()
+            >= 3
+    }
+
+    /// Puts the round back the way it was before the last lock, with the
+    /// piece that locked back at the top.
+    fn undo(&mut self) {
+        if !self.mode.allows_undo() {
+            return;
+        }
+        let Rewind { snapshot, queue } = match self.history.pop_back() {
+            Some(placement) => placement,
+            None => return,
+        };
+        self.board = snapshot.board;
+        self.queue = queue;
+        self.stats = snapshot.stats;
+        self.dealt = snapshot.dealt;
+        self.hold = snapshot.hold;
+        self.hold_used = snapshot.hold_used;
+        if let Some(next) = snapshot.next {
+            self.next = next;
+        }
+        self.score = snapshot.score;
+        self.lines = snapshot.lines;
+        self.level = snapshot.level;
+        self.gravity = snapshot.gravity;
+        self.grading = snapshot.grading;
+        self.rng = snapshot.rng;
+        self.dealer.recall(snapshot.dealer);
+        self.spawn_at = None;
+        self.collapse_at = None;
+        self.buffer = InputBuffer::default();
+        self.fall_elapsed = Duration::from_millis(0);
+        self.perfect_clear_at = None;
+        self.combo = 0;
+        self.clear_points = 0;
+        self.fading.clear();
+        self.place(snapshot.piece.tetromino);
+        self.settle();
+    }
+
+    /// Sends `rows` to the opponent, first cancelling garbage on its way
+    /// in.
+    fn attack(&mut self, mut rows: u32) {
+        while rows > 0 {
+            let incoming = match self.garbage.front_mut() {
+                Some(incoming) => incoming,
+                None => break,
+            };
+            let cancelled = rows.min(incoming.rows);
+            rows -= cancelled;
+            incoming.rows -= cancelled;
+            if incoming.rows == 0 {
+                self.garbage.pop_front();
+            }
+        }
+        if rows > 0 {
+            self.stats.attack += rows;
+            self.events.push(Event::Attack(rows));
+        }
+    }
+
+    /// Pushes the garbage that has waited out the delay by `now` into the
+    /// bottom of the board, a gap in the same column for each batch, or
+    /// in a column of its own for each row if the rules make it messy.
+    fn take_garbage(&mut self, now: Instant) {
+        while let Some(&Incoming { rows, sent_at }) = self.garbage.front() {
+            if now.saturating_duration_since(sent_at) < self.garbage_delay {
+                break;
+            }
+            self.garbage.pop_front();
+            // No more than the board's height comes in at once.
+            let rows = (rows as usize).min(self.board.height());
+            let (batches, batch) = if self.ruleset.attack.table().messy {
+                (rows, 1)
+            } else {
+                (1, rows)
+            };
+            for _ in 0..batches {
+                let hole = self.garbage_rng.gen_range(0, self.board.width());
+                if self.board.push_garbage(batch, hole) {
+                    self.game_over = true;
+                }
+            }
+            for placed in self.fading.iter_mut() {
+                for cell in placed.cells.iter_mut() {
+                    cell[1] -= rows as i32;
+                }
+            }
+        }
+    }
+    /// Starts the piece just set on the board fading, and moves the pieces
+    /// already fading to where they will land once full rows clear. Those
+    /// that have faded out are let go.
+    fn fade_in(&mut self, now: Instant, fade: Duration) {
+        while let Some(oldest) = self.fading.front() {
+            if now.saturating_duration_since(oldest.locked_at) < fade {
+                break;
+            }
+            self.fading.pop_front();
+        }
+        self.fading.push_back(Placed {
+            tetromino: self.tetromino,
+            cells: self.tetromino.blocks(self.pos, self.facing),
+            locked_at: now,
+        });
+        let full: Vec<i32> = self.board.full_rows().collect();
+        if full.is_empty() {
+            return;
+        }
+        for placed in self.fading.iter_mut() {
+            placed.cells.retain(|cell| !full.contains(&cell[1]));
+            for cell in placed.cells.iter_mut() {
+                cell[1] += full.iter().filter(|&&y| y > cell[1]).count() as i32;
+            }
+        }
+    }
+
+    /// Fixes the active piece to the board, clears rows, straight away or
+    /// after the line clear delay, and starts the entry delay before the
+    /// next piece.
+    fn lock(&mut self, now: Instant) {
+        if self.mode.allows_undo() {
+            if self.history.len() == UNDO_LIMIT {
+                self.history.pop_front();
+            }
+            self.history.push_back(Rewind {
+                snapshot: self.snapshot(),
+                queue: self.queue.clone(),
+            });
+        }
+        let fixed_block = FixedBlock::Piece(self.tetromino);
+        let blocks = self.tetromino.blocks(self.pos, self.facing);
+        for &block in &blocks {
+            self.heatmap.add(block);
+            if !self.board.set(block, fixed_block) {
+                self.game_over = true;
+            }
+        }
+        // Locking out: nothing of the piece made it into view.
+        if !blocks.iter().any(|&block| self.board.is_visible(block)) {
+            self.game_over = true;
+        }
+        self.events.push(Event::Lock {
+            tetromino: self.tetromino,
+            pos: self.pos,
+            facing: self.facing,
+            presses: self.presses,
+        });
+        self.stats.pieces += 1;
+        let score_before = self.score;
+        if let Some(fade) = self.mode.fade(self.level) {
+            self.fade_in(now, fade);
+        }
+        let t_spin = self.t_spin();
+        let full: Vec<i32> = self.board.full_rows().collect();
+        let cleared = full.len();
+        // Whether the board will be empty once the full rows go.
+        let perfect_clear =
+            cleared > 0 && self.board.blocks().count() == cleared * self.board.width();
+        let clear_delay = if cleared > 0 {
+            self.line_clear_delay
+        } else {
+            Duration::from_millis(0)
+        };
+        if clear_delay > Duration::from_millis(0) {
+            self.collapse_at = Some(now + clear_delay);
+        } else {
+            self.board.clear_lines();
+        }
+        if t_spin {
+            self.stats.t_spins += 1;
+            self.events.push(Event::TSpin);
+        }
+        if perfect_clear {
+            self.stats.perfect_clears += 1;
+        }
+        if cleared > 0 {
+            self.stats.clears[cleared.min(4) - 1] += 1;
+            self.stats.cleared(cleared >= 4 || t_spin);
+            for &y in &full {
+                self.events.push(Event::RowCleared(y));
+            }
+            self.events.push(Event::LineClear(cleared));
+            self.combo += 1;
+            let table = self.ruleset.attack.table();
+            let rows = table.lines_sent(cleared, t_spin, perfect_clear, self.combo - 1);
+            self.attack(self.handicap.attack(rows));
+        } else {
+            self.combo = 0;
+            self.take_garbage(now);
+        }
+        if self.game_over && !self.mode.can_top_out() {
+            // Start over on an empty board; the placement that topped out
+            // can still be undone.
+            self.game_over = false;
+            self.board = Board::new(self.board.width(), self.board.height());
+            self.collapse_at = None;
+            self.fading.clear();
+        }
+        if self.game_over {
+            self.events.push(Event::GameOver);
+        }
+        // Lines are counted in playfield rows, so a big mino clears more
+        // than one.
+        self.lines += (cleared * self.mode.scale()) as u32;
+        if self.mode == Mode::Master {
+            let from = self.level;
+            let (points, level) = self.grading.cleared(from, cleared as u32, perfect_clear);
+            self.score += points;
+            self.level = level;
+            self.grading.reached(from, level, self.score, self.played);
+        } else {
+            self.score += self.ruleset.scoring.line_clear(cleared) * self.level;
+            self.level = self.start_level + self.lines / LINES_PER_LEVEL;
+        }
+        if let Some(gravity) = self.ruleset.gravity.at(self.level) {
+            self.gravity = self.handicap.fall_time(gravity);
+        }
+        if self.cleared_goal() {
+            self.end_round();
+        }
+        if let Some(bonus) = self.ruleset.scoring.perfect_clear_bonus() {
+            if perfect_clear {
+                // Master's own points already count emptying the board.
+                if self.mode != Mode::Master {
+                    self.score += bonus * self.level;
+                }
+                self.perfect_clear_at = Some(now);
+            }
+        }
+        self.clear_points = self.score - score_before;
+        self.hold_used = false;
+        self.spawn_at = Some(now + clear_delay + self.entry_delay);
+    }
+}
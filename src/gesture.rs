@@ -0,0 +1,78 @@
+//! Touch and mouse gestures on the board, turned into the inputs the keys
+//! would give: tap to rotate, drag sideways to move a column per cell
+//! dragged, swipe down to hard drop and up to hold. Positions are in board
+//! cells, so they go with the board however big it's drawn.
+
+use crate::game::Input;
+
+/// How far a touch has to go up or down, in cells, to be a swipe.
+const SWIPE_CELLS: f32 = 2.0;
+/// How far a touch can wander, in cells, and still be a tap.
+const TAP_CELLS: f32 = 0.5;
+
+struct Touch {
+    start: (f32, f32),
+    /// Where the sideways moves made so far are counted from.
+    column: f32,
+    /// Whether the piece has been moved, which makes it a drag rather than
+    /// a tap or a swipe.
+    moved: bool,
+}
+
+/// Follows one touch, or the mouse with a button down, from start to end.
+#[derive(Default)]
+pub struct Gestures {
+    touch: Option<Touch>,
+}
+
+impl Gestures {
+    pub fn start(&mut self, x: f32, y: f32) {
+        self.touch = Some(Touch {
+            start: (x, y),
+            column: x,
+            moved: false,
+        });
+    }
+
+    /// Follows the touch to `x`, `y`, giving a move for each whole cell it
+    /// has gone sideways since the last. Once it has gone far enough up or
+    /// down to be a swipe it doesn't move the piece any more, so a swipe
+    /// doesn't drift.
+    pub fn drag(&mut self, x: f32, y: f32) -> Vec<Input> {
+        let mut inputs = Vec::new();
+        let touch = match self.touch {
+            Some(ref mut touch) => touch,
+            None => return inputs,
+        };
+        if !touch.moved && (y - touch.start.1).abs() >= SWIPE_CELLS {
+            return inputs;
+        }
+        while x - touch.column >= 1.0 {
+            touch.column += 1.0;
+            inputs.push(Input::Right);
+        }
+        while touch.column - x >= 1.0 {
+            touch.column -= 1.0;
+            inputs.push(Input::Left);
+        }
+        touch.moved |= !inputs.is_empty();
+        inputs
+    }
+
+    /// Ends the touch at `x`, `y`, giving what it was if it wasn't a drag.
+    pub fn end(&mut self, x: f32, y: f32) -> Option<Input> {
+        let touch = self.touch.take()?;
+        let (dx, dy) = (x - touch.start.0, y - touch.start.1);
+        if touch.moved {
+            None
+        } else if dy >= SWIPE_CELLS {
+            Some(Input::HardDrop)
+        } else if dy <= -SWIPE_CELLS {
+            Some(Input::Hold)
+        } else if dx.abs() < TAP_CELLS && dy.abs() < TAP_CELLS {
+            Some(Input::RotateCw)
+        } else {
+            None
+        }
+    }
+}
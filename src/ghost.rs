@@ -0,0 +1,76 @@
+//! Ghost races: a recorded run of the player's own played back faintly
+//! beside the one in play, frame for frame on the same pieces, so how the
+//! new run is doing against the old can be seen as it goes.
+
+use crate::config::Config;
+use crate::layout::MINI_LABEL_HEIGHT;
+use crate::locale::Locale;
+use crate::render::BoardRenderer;
+use crate::text;
+use ggez::graphics::Color;
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+use tetris_rs::game::Game;
+use tetris_rs::replay::{self, Playback, Replay};
+
+/// How see-through the ghost's board is drawn.
+const GHOST_ALPHA: f32 = 0.4;
+
+pub struct Ghost {
+    playback: Playback,
+    game: Game,
+    /// Logic steps run on the ghost's game so far.
+    frame: u64,
+    renderer: BoardRenderer,
+}
+
+impl Ghost {
+    /// A ghost of `replay`, drawn with cells `cell_size` across.
+    pub fn new(
+        ctx: &mut Context,
+        config: &Config,
+        replay: Replay,
+        cell_size: i32,
+    ) -> GameResult<Ghost> {
+        let mut faded = config.clone();
+        faded.colors = config.colors.faded(GHOST_ALPHA);
+        let playback = Playback::new(replay);
+        Ok(Ghost {
+            game: playback.new_game(),
+            playback,
+            frame: 0,
+            renderer: BoardRenderer::sized(ctx, &faded, cell_size)?,
+        })
+    }
+
+    /// Plays the recorded run up to frame `until`, where the run in play
+    /// has got to, or to where it ended.
+    pub fn advance(&mut self, until: u64) {
+        while self.frame < until && !self.game.game_over {
+            let now = self.game.started_at() + replay::frame_time(self.frame);
+            for action in self.playback.actions_at(self.frame) {
+                action.apply(&mut self.game, now);
+            }
+            self.frame += 1;
+            self.game
+                .update(self.game.started_at() + replay::frame_time(self.frame));
+            // Only the run in play makes sounds.
+            self.game.take_events();
+        }
+    }
+
+    /// Draws the ghost's board at `origin`, with the lines it had cleared
+    /// by now over it.
+    pub fn draw(
+        &mut self,
+        ctx: &mut Context,
+        origin: Point2<f32>,
+        locale: &Locale,
+        color: Color,
+    ) -> GameResult {
+        self.renderer.draw(ctx, &self.game, origin)?;
+        let label = locale.format("ghost.label", &[&self.game.lines]);
+        let at = Point2::new(origin[0], origin[1] - MINI_LABEL_HEIGHT + 4.0);
+        text::draw(ctx, &label, 14.0, color, at)
+    }
+}
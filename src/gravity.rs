@@ -0,0 +1,117 @@
+//! Gravity curves: how fast pieces fall at each level. Like the kick
+//! tables they're data, built in from `gravity.toml` and replaceable by the
+//! player's own, so a mode's speeds can be matched to the game it follows.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const BUILT_IN: &str = include_str!("gravity.toml");
+
+/// What `frames_per_row` and `cells_per_frame` count in when a curve
+/// doesn't say.
+pub const DEFAULT_FRAMES_PER_SECOND: f64 = 60.0;
+/// Gravity from which pieces fall to the stack the moment they're free
+/// to, in cells per frame; taller than any board a piece can fall in one.
+pub const INSTANT_G: f64 = 20.0;
+
+/// The time between gravity steps for pieces falling `g` cells a frame at
+/// `frames_per_second`, so 1/60G at 60 is a cell a second. Speeds of more
+/// than a cell a frame come out shorter than a frame, and the game takes
+/// as many steps as have come due each update; from `INSTANT_G` on there
+/// is no time at all between them.
+pub fn from_g(g: f64, frames_per_second: f64) -> Duration {
+    if g >= INSTANT_G {
+        Duration::from_millis(0)
+    } else {
+        Duration::from_secs_f64(1.0 / (g * frames_per_second))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GravityTable {
+    #[serde(default)]
+    cells_per_second: Vec<f64>,
+    #[serde(default)]
+    frames_per_row: Vec<f64>,
+    /// In G, cells fallen each frame, the unit of the arcade games' curves.
+    #[serde(default)]
+    cells_per_frame: Vec<f64>,
+    #[serde(default = "default_frames_per_second")]
+    frames_per_second: f64,
+}
+
+fn default_frames_per_second() -> f64 {
+    DEFAULT_FRAMES_PER_SECOND
+}
+
+impl GravityTable {
+    /// The time between gravity steps at `level`, counted from 1. Past the
+    /// end of the table the last entry holds.
+    pub fn at(&self, level: u32) -> Duration {
+        let index = level.max(1) as usize - 1;
+        let entry = |list: &[f64]| list[index.min(list.len() - 1)];
+        if !self.frames_per_row.is_empty() {
+            Duration::from_secs_f64(entry(&self.frames_per_row) / self.frames_per_second)
+        } else if !self.cells_per_frame.is_empty() {
+            from_g(entry(&self.cells_per_frame), self.frames_per_second)
+        } else {
+            Duration::from_secs_f64(1.0 / entry(&self.cells_per_second))
+        }
+    }
+
+    fn check(&self, name: &str) -> Result<(), String> {
+        let lists = [
+            &self.cells_per_second,
+            &self.frames_per_row,
+            &self.cells_per_frame,
+        ];
+        if lists.iter().filter(|list| !list.is_empty()).count() != 1 {
+            return Err(format!(
+                "{:?} needs one of cells_per_second, frames_per_row and cells_per_frame",
+                name
+            ));
+        }
+        let mut speeds = lists
+            .iter()
+            .copied()
+            .flatten()
+            .chain([&self.frames_per_second]);
+        if speeds.any(|&v| !(v > 0.0 && v.is_finite())) {
+            return Err(format!("{:?} has a speed that isn't above 0", name));
+        }
+        Ok(())
+    }
+}
+
+static TABLES: OnceLock<BTreeMap<String, GravityTable>> = OnceLock::new();
+
+fn parse(text: &str) -> Result<BTreeMap<String, GravityTable>, String> {
+    let tables: BTreeMap<String, GravityTable> = toml::from_str(text).map_err(|e| e.to_string())?;
+    for (name, table) in &tables {
+        table.check(name)?;
+    }
+    Ok(tables)
+}
+
+fn built_in() -> BTreeMap<String, GravityTable> {
+    parse(BUILT_IN).expect("built-in gravity curves are valid")
+}
+
+/// Replaces built-in curves with those in `text`, in the layout of
+/// `gravity.toml`, by name. Call it the way
+/// [`kicks::define`](crate::kicks::define) is.
+pub fn define(text: &str) -> Result<(), String> {
+    let mut tables = built_in();
+    tables.extend(parse(text)?);
+    TABLES
+        .set(tables)
+        .map_err(|_| "gravity curves are already in use".to_string())
+}
+
+/// The curve called `name`, if there is one.
+pub fn table(name: &str) -> Option<&'static GravityTable> {
+    TABLES.get_or_init(built_in).get(name)
+}
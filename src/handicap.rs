@@ -0,0 +1,48 @@
+//! Handicaps: changes to one side of a versus round so players of
+//! different skill can give each other a game.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// The most garbage rows a side can start with.
+pub const MAX_GARBAGE_ROWS: u32 = 10;
+/// How far the speed and attack handicaps go, in percent.
+pub const MIN_SPEED_PERCENT: u32 = 50;
+pub const MAX_HANDICAP_PERCENT: u32 = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Handicap {
+    /// Rows of garbage on the board from the start.
+    pub garbage_rows: u32,
+    /// The most upcoming pieces shown, if fewer than usual.
+    pub preview: Option<usize>,
+    /// How fast pieces fall, as a percentage of the usual speed.
+    pub speed_percent: u32,
+    /// Garbage sent, as a percentage of what the clears are worth.
+    pub attack_percent: u32,
+}
+
+impl Default for Handicap {
+    fn default() -> Self {
+        Handicap {
+            garbage_rows: 0,
+            preview: None,
+            speed_percent: 100,
+            attack_percent: 100,
+        }
+    }
+}
+
+impl Handicap {
+    /// The time between gravity steps for a side whose pieces would
+    /// otherwise take `gravity`.
+    pub fn fall_time(self, gravity: Duration) -> Duration {
+        gravity.saturating_mul(100) / self.speed_percent.max(1)
+    }
+
+    /// The garbage a side sends for clears worth `rows`.
+    pub fn attack(self, rows: u32) -> u32 {
+        rows.saturating_mul(self.attack_percent) / 100
+    }
+}
@@ -0,0 +1,139 @@
+//! Running rounds without a window: an input script played through, or
+//! the computer playing versus against itself to compare weights.
+
+use crate::cli::Cli;
+use crate::replays;
+use ggez::GameResult;
+use std::path::Path;
+use tetris_rs::ai::{Ai, Difficulty, Weights};
+use tetris_rs::board::Board;
+use tetris_rs::bot::BotDriver;
+use tetris_rs::game::{Event, Game, Settings};
+use tetris_rs::replay;
+
+/// Plays the input script at `path` without opening a window and prints
+/// the final board and score.
+pub fn run(cli: &Cli, path: &Path) -> GameResult {
+    let script = replays::load_script(path, cli.script_settings())?;
+    let mut game = script.new_game();
+    let frames = replay::simulate(&mut game, &script.actions);
+
+    print!("{}", game.board);
+    println!("score: {}", game.score);
+    println!("lines: {}", game.lines);
+    println!("level: {}", game.level);
+    println!("frames: {}", frames);
+    println!("seed: {}", game.seed());
+    println!("game over: {}", game.game_over);
+    Ok(())
+}
+
+/// The longest a self-play round runs before it's called off.
+const SELFPLAY_LIMIT_SECS: u64 = 300;
+
+/// One side of a self-play round.
+struct Side {
+    game: Game,
+    bot: BotDriver,
+    sent: u32,
+}
+
+impl Side {
+    fn new(weights: Weights, settings: Settings) -> Side {
+        let difficulty = Difficulty::Inhuman;
+        let ai = Ai::with_difficulty(weights, difficulty, settings.seed);
+        Side {
+            game: Game::new(Board::default(), settings),
+            bot: BotDriver::new(Box::new(ai), difficulty.frames_per_input()),
+            sent: 0,
+        }
+    }
+
+    /// Plays one frame, returning the garbage sent.
+    fn step(&mut self, frame: u64) -> u32 {
+        let started = self.game.started_at();
+        self.bot
+            .step(&mut self.game, started + replay::frame_time(frame));
+        self.game.update(started + replay::frame_time(frame + 1));
+        let mut rows = 0;
+        for event in self.game.take_events() {
+            if let Event::Attack(sent) = event {
+                rows += sent;
+            }
+        }
+        self.sent += rows;
+        rows
+    }
+}
+
+/// Plays `games` versus rounds of the computer against itself, the
+/// --ai-weights on one side and the built-in weights on the other, each
+/// round dealing both the same pieces, and prints how they did.
+pub fn selfplay(cli: &Cli, games: u32) -> GameResult {
+    let challenger = cli.ai_weights()?;
+    let seed = cli.seed.unwrap_or_else(rand::random);
+    let limit = SELFPLAY_LIMIT_SECS * u64::from(replay::FRAMES_PER_SECOND);
+    // Wins for the challenger and the built-in weights, then draws and
+    // rounds called off.
+    let mut results = [0; 4];
+    let mut lines = [0; 2];
+    let mut sent = [0; 2];
+    for round in 0..games {
+        let settings = Settings {
+            start_level: cli.level.unwrap_or(1),
+            seed: seed.wrapping_add(u64::from(round)),
+            ..Settings::default()
+        };
+        let mut sides = [
+            Side::new(challenger, settings),
+            Side::new(Weights::default(), settings),
+        ];
+        let mut frame = 0;
+        while frame < limit && !sides.iter().any(|side| side.game.game_over) {
+            let attacks = [sides[0].step(frame), sides[1].step(frame)];
+            sides[1].game.receive_garbage(attacks[0]);
+            sides[0].game.receive_garbage(attacks[1]);
+            frame += 1;
+        }
+        let result = match (sides[0].game.game_over, sides[1].game.game_over) {
+            (false, true) => 0,
+            (true, false) => 1,
+            (true, true) => 2,
+            (false, false) => 3,
+        };
+        results[result] += 1;
+        for (at, side) in sides.iter().enumerate() {
+            lines[at] += side.game.lines;
+            sent[at] += side.sent;
+        }
+        println!(
+            "round {}: {} (seed {}, {} frames)",
+            round + 1,
+            ["won", "lost", "drawn", "called off"][result],
+            settings.seed,
+            frame
+        );
+    }
+
+    let name = match cli.ai_weights {
+        Some(ref path) => path.display().to_string(),
+        None => "built-in".to_string(),
+    };
+    println!("rounds: {}", games);
+    println!("{} wins: {}", name, results[0]);
+    println!("built-in wins: {}", results[1]);
+    println!("draws: {}", results[2]);
+    println!("called off: {}", results[3]);
+    let average = |total: u32| total as f32 / games as f32;
+    println!(
+        "lines per round: {:.1} vs {:.1}",
+        average(lines[0]),
+        average(lines[1])
+    );
+    println!(
+        "garbage sent per round: {:.1} vs {:.1}",
+        average(sent[0]),
+        average(sent[1])
+    );
+    Ok(())
+}
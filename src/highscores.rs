@@ -0,0 +1,196 @@
+use ggez::filesystem;
+use ggez::{Context, GameError, GameResult};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tetris_rs::game::Game;
+
+const HIGH_SCORES_FILE: &str = "highscores.toml";
+const MARATHON_SCORES_FILE: &str = "marathon_scores.toml";
+const SPRINT_TIMES_FILE: &str = "sprint_times.toml";
+
+/// How many scores the table keeps.
+pub const MAX_ENTRIES: usize = 10;
+
+/// Longest name that can be entered for a score.
+pub const MAX_NAME_LEN: usize = 8;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    #[serde(default)]
+    pub name: String,
+    pub score: u32,
+    pub lines: u32,
+    pub level: u32,
+    /// Day the run finished, as `YYYY-MM-DD`.
+    pub date: String,
+}
+
+/// The best scores so far, highest first, stored in the user data directory.
+/// Endless play and marathons keep a table each.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    #[serde(default)]
+    pub entries: Vec<Entry>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl HighScores {
+    /// Reads the endless table, starting an empty one if there is no file
+    /// yet.
+    pub fn load(ctx: &Context) -> GameResult<HighScores> {
+        HighScores::load_from(ctx, HIGH_SCORES_FILE)
+    }
+
+    pub fn load_marathon(ctx: &Context) -> GameResult<HighScores> {
+        HighScores::load_from(ctx, MARATHON_SCORES_FILE)
+    }
+
+    fn load_from(ctx: &Context, file: &str) -> GameResult<HighScores> {
+        let path = filesystem::user_data_dir(ctx).join(file);
+        let mut high_scores: HighScores = load_table(&path)?;
+        high_scores.path = path;
+        Ok(high_scores)
+    }
+
+    pub fn save(&self) -> GameResult {
+        save_table(&self.path, self)
+    }
+
+    /// Adds the result of a finished game if it makes the table, returning
+    /// its rank from zero.
+    pub fn record(&mut self, game: &Game) -> Option<usize> {
+        let rank = self
+            .entries
+            .iter()
+            .position(|entry| game.score > entry.score)
+            .unwrap_or(self.entries.len());
+        if rank >= MAX_ENTRIES || game.score == 0 {
+            return None;
+        }
+        let entry = Entry {
+            name: String::new(),
+            score: game.score,
+            lines: game.lines,
+            level: game.level,
+            date: today(),
+        };
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SprintEntry {
+    #[serde(default)]
+    pub name: String,
+    /// Time taken to clear the lines, in milliseconds.
+    pub millis: u64,
+    pub date: String,
+}
+
+/// The fastest finished sprints so far, quickest first.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SprintTimes {
+    #[serde(default)]
+    pub entries: Vec<SprintEntry>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl SprintTimes {
+    pub fn load(ctx: &Context) -> GameResult<SprintTimes> {
+        let path = filesystem::user_data_dir(ctx).join(SPRINT_TIMES_FILE);
+        let mut times: SprintTimes = load_table(&path)?;
+        times.path = path;
+        Ok(times)
+    }
+
+    pub fn save(&self) -> GameResult {
+        save_table(&self.path, self)
+    }
+
+    /// Adds the time of a finished sprint if it makes the table, returning
+    /// its rank from zero. Sprints that topped out don't count.
+    pub fn record(&mut self, game: &Game) -> Option<usize> {
+        if !game.cleared_goal() {
+            return None;
+        }
+        let millis = game.elapsed().as_millis() as u64;
+        let rank = self
+            .entries
+            .iter()
+            .position(|entry| millis < entry.millis)
+            .unwrap_or(self.entries.len());
+        if rank >= MAX_ENTRIES {
+            return None;
+        }
+        let entry = SprintEntry {
+            name: String::new(),
+            millis,
+            date: today(),
+        };
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+}
+
+/// A time as `m:ss.mmm`, the way sprint times are shown.
+pub fn format_time(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Reads a table from `path`, or an empty one if there is no file yet.
+pub fn load_table<T: DeserializeOwned + Default>(path: &Path) -> GameResult<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    toml::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| GameError::ResourceLoadError(format!("Invalid {}: {}", path.display(), e)))
+}
+
+pub fn save_table<T: Serialize>(path: &Path, table: &T) -> GameResult {
+    let contents = toml::to_string(table).map_err(|e| {
+        GameError::ResourceLoadError(format!("Could not write {}: {}", path.display(), e))
+    })?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Whole days from the Unix epoch to now, in UTC.
+pub fn epoch_days() -> u64 {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    secs / 86_400
+}
+
+/// Today's date in UTC as `YYYY-MM-DD`.
+pub fn today() -> String {
+    // Days since the epoch to a civil date, after Howard Hinnant's
+    // `civil_from_days`.
+    let z = epoch_days() as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -0,0 +1,199 @@
+use ggez::event::KeyCode;
+use std::collections::{BTreeMap, HashMap};
+use tetris_rs::game::Input;
+
+/// Keys that may be bound to an action. Escape and P are left out because
+/// they always pause.
+const BINDABLE_KEYS: &[KeyCode] = &[
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Space,
+    KeyCode::Return,
+    KeyCode::Tab,
+    KeyCode::LShift,
+    KeyCode::RShift,
+    KeyCode::LControl,
+    KeyCode::RControl,
+    KeyCode::LAlt,
+    KeyCode::RAlt,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::Numpad0,
+    KeyCode::Numpad1,
+    KeyCode::Numpad2,
+    KeyCode::Numpad3,
+    KeyCode::Numpad4,
+    KeyCode::Numpad5,
+    KeyCode::Numpad6,
+    KeyCode::Numpad7,
+    KeyCode::Numpad8,
+    KeyCode::Numpad9,
+];
+
+/// Keys that may restart the round besides those above. F3 is the stats
+/// panel's, F6 and F7 the overlay's and F11 fullscreen's.
+const RESTART_ONLY_KEYS: &[KeyCode] = &[
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F12,
+];
+
+pub fn is_bindable(key: KeyCode) -> bool {
+    BINDABLE_KEYS.contains(&key)
+}
+
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .iter()
+        .cloned()
+        .find(|&key| key_name(key) == name)
+}
+
+/// The key called `name` that restarts the round, if it may.
+pub fn restart_key(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .iter()
+        .chain(RESTART_ONLY_KEYS)
+        .cloned()
+        .find(|&key| key_name(key) == name)
+}
+
+/// Which keys trigger each game action.
+pub struct KeyMap {
+    bindings: HashMap<Input, Vec<KeyCode>>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let mut bindings = HashMap::new();
+        bindings.insert(Input::Left, vec![KeyCode::Left]);
+        bindings.insert(Input::Right, vec![KeyCode::Right]);
+        bindings.insert(Input::RotateCw, vec![KeyCode::Up]);
+        bindings.insert(Input::RotateCcw, vec![KeyCode::X]);
+        bindings.insert(Input::SoftDrop, vec![KeyCode::Down]);
+        bindings.insert(Input::HardDrop, vec![KeyCode::Space]);
+        bindings.insert(Input::Hold, vec![KeyCode::C, KeyCode::LShift]);
+        bindings.insert(Input::Undo, vec![KeyCode::Z]);
+        KeyMap { bindings }
+    }
+}
+
+impl KeyMap {
+    /// The second player's keys in co-op, on the left of the keyboard
+    /// clear of the defaults. There's no undo in co-op.
+    pub fn partner() -> KeyMap {
+        let mut bindings = HashMap::new();
+        bindings.insert(Input::Left, vec![KeyCode::A]);
+        bindings.insert(Input::Right, vec![KeyCode::D]);
+        bindings.insert(Input::RotateCw, vec![KeyCode::W]);
+        bindings.insert(Input::SoftDrop, vec![KeyCode::S]);
+        bindings.insert(Input::HardDrop, vec![KeyCode::Q]);
+        bindings.insert(Input::Hold, vec![KeyCode::E]);
+        KeyMap { bindings }
+    }
+
+    pub fn input_for(&self, key: KeyCode) -> Option<Input> {
+        Input::ALL
+            .iter()
+            .cloned()
+            .find(|input| self.keys(*input).contains(&key))
+    }
+
+    pub fn keys(&self, input: Input) -> &[KeyCode] {
+        self.bindings.get(&input).map_or(&[], Vec::as_slice)
+    }
+
+    /// Makes `key` the only key for `input`, taking it away from any other
+    /// action it was bound to.
+    pub fn bind(&mut self, input: Input, key: KeyCode) {
+        for keys in self.bindings.values_mut() {
+            keys.retain(|&bound| bound != key);
+        }
+        self.bindings.insert(input, vec![key]);
+    }
+
+    /// Builds a keymap from the `[keys]` table of the config. Actions that
+    /// are missing or have no recognised keys keep their default bindings.
+    pub fn from_config(keys: &BTreeMap<String, Vec<String>>) -> KeyMap {
+        KeyMap::default().with_config(keys)
+    }
+
+    /// Builds the second player's keymap from the `[partner_keys]` table
+    /// of the config, as `from_config` does.
+    pub fn partner_from_config(keys: &BTreeMap<String, Vec<String>>) -> KeyMap {
+        KeyMap::partner().with_config(keys)
+    }
+
+    fn with_config(self, keys: &BTreeMap<String, Vec<String>>) -> KeyMap {
+        let mut keymap = self;
+        for &input in Input::ALL.iter() {
+            let names = match keys.get(input.name()) {
+                Some(names) => names,
+                None => continue,
+            };
+            let keys: Vec<KeyCode> = names.iter().filter_map(|name| parse_key(name)).collect();
+            if !keys.is_empty() {
+                keymap.bindings.insert(input, keys);
+            }
+        }
+        keymap
+    }
+
+    pub fn to_config(&self) -> BTreeMap<String, Vec<String>> {
+        Input::ALL
+            .iter()
+            .map(|&input| {
+                let names = self.keys(input).iter().map(|&key| key_name(key)).collect();
+                (input.name().to_string(), names)
+            })
+            .collect()
+    }
+}
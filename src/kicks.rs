@@ -0,0 +1,142 @@
+//! Kick tables: where a turning piece that doesn't fit in place may be
+//! nudged to instead. The tables are data, built in from `kicks.toml` and
+//! replaceable by the player's own, so a kick set can be tried out without
+//! rebuilding.
+
+use crate::tetromino::{Rotation, Tetromino, Turn};
+use nalgebra::Vector2;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+const BUILT_IN: &str = include_str!("kicks.toml");
+
+/// The furthest a kick may move a piece along either axis.
+const MAX_KICK: i32 = 3;
+
+/// The offsets tried for each turn one way out of each facing, in the
+/// order of `Rotation::ALL`.
+type Turns = Vec<Vec<[i32; 2]>>;
+
+/// Lists for counter-clockwise turns, laid out as the clockwise ones are.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CcwTurns {
+    #[serde(default)]
+    turns: Turns,
+    #[serde(default)]
+    pieces: BTreeMap<char, Turns>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KickTable {
+    /// Whether only the listed offsets are tried, without trying the turn
+    /// in place first, for systems whose pieces turn about another point
+    /// than the one their shapes here do.
+    #[serde(default)]
+    listed_only: bool,
+    #[serde(default)]
+    turns: Turns,
+    /// Pieces, by board letter, with lists of their own.
+    #[serde(default)]
+    pieces: BTreeMap<char, Turns>,
+    /// Lists of their own for counter-clockwise turns. Without them a
+    /// counter-clockwise turn tries the clockwise turn back the other way,
+    /// each offset reversed, which is how SRS's tables go.
+    #[serde(default)]
+    ccw: Option<CcwTurns>,
+}
+
+impl KickTable {
+    /// The offsets to try, in order, when `tetromino` turns `turn` out of
+    /// `facing`: after trying it in place, unless the table says not to.
+    pub fn kicks(
+        &self,
+        tetromino: Tetromino,
+        facing: Rotation,
+        turn: Turn,
+    ) -> impl Iterator<Item = Vector2<i32>> {
+        let listed = |turns: &Turns, pieces: &BTreeMap<char, Turns>, facing: Rotation| {
+            let turns = pieces.get(&tetromino.symbol()).unwrap_or(turns);
+            turns
+                .get(facing.index())
+                .into_iter()
+                .flatten()
+                .map(|&[x, y]| Vector2::new(x, y))
+                .collect::<Vec<_>>()
+        };
+        let listed = match (turn, &self.ccw) {
+            (Turn::Cw, _) => listed(&self.turns, &self.pieces, facing),
+            (Turn::Ccw, Some(ccw)) => listed(&ccw.turns, &ccw.pieces, facing),
+            (Turn::Ccw, None) => listed(&self.turns, &self.pieces, facing.ccw())
+                .into_iter()
+                .map(|offset| -offset)
+                .collect(),
+        };
+        let in_place = if self.listed_only {
+            None
+        } else {
+            Some(Vector2::new(0, 0))
+        };
+        in_place.into_iter().chain(listed)
+    }
+
+    fn check(&self, name: &str) -> Result<(), String> {
+        let ccw = self
+            .ccw
+            .iter()
+            .flat_map(|ccw| std::iter::once(&ccw.turns).chain(ccw.pieces.values()));
+        let all = std::iter::once(&self.turns)
+            .chain(self.pieces.values())
+            .chain(ccw);
+        for turns in all {
+            if !turns.is_empty() && turns.len() != 4 {
+                return Err(format!("{:?} needs a list for each of the 4 turns", name));
+            }
+            if turns.iter().flatten().flatten().any(|v| v.abs() > MAX_KICK) {
+                return Err(format!(
+                    "{:?} kicks a piece more than {} cells",
+                    name, MAX_KICK
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+static TABLES: OnceLock<BTreeMap<String, KickTable>> = OnceLock::new();
+
+fn parse(text: &str) -> Result<BTreeMap<String, KickTable>, String> {
+    let tables: BTreeMap<String, KickTable> = toml::from_str(text).map_err(|e| e.to_string())?;
+    for (name, table) in &tables {
+        table.check(name)?;
+    }
+    Ok(tables)
+}
+
+fn built_in() -> BTreeMap<String, KickTable> {
+    parse(BUILT_IN).expect("built-in kick tables are valid")
+}
+
+/// Replaces built-in tables with those in `text`, in the layout of
+/// `kicks.toml`, by name. The tables are fixed once any piece has turned,
+/// so this has to be called first thing, before any round starts; the
+/// gravity curves and attack tables are defined the same way, at the same
+/// time. Replays only play back the same with the same tables.
+pub fn define(text: &str) -> Result<(), String> {
+    let mut tables = built_in();
+    tables.extend(parse(text)?);
+    TABLES
+        .set(tables)
+        .map_err(|_| "kick tables are already in use".to_string())
+}
+
+/// The table called `name`, or one without kicks if there isn't one.
+pub fn table(name: &str) -> &'static KickTable {
+    static NONE: OnceLock<KickTable> = OnceLock::new();
+    TABLES
+        .get_or_init(built_in)
+        .get(name)
+        .unwrap_or_else(|| NONE.get_or_init(KickTable::default))
+}
@@ -0,0 +1,132 @@
+use ggez::graphics::Rect;
+use ggez::nalgebra::Point2;
+use tetris_rs::board::Board;
+
+/// Width of the panels either side of a board.
+pub const PANEL_WIDTH: f32 = 160.0;
+/// Size of a cell in the hold and next previews.
+pub const PREVIEW_CELL_SIZE: f32 = 24.0;
+/// Height given to each piece preview, room for two rows of cells.
+pub const PREVIEW_HEIGHT: f32 = 56.0;
+/// Where a panel's first heading goes.
+pub const PANEL_TOP: f32 = 8.0;
+/// Where the left panel's mode HUD goes, under the hold box.
+pub const HUD_TOP: f32 = PANEL_TOP + 24.0 + PREVIEW_HEIGHT + 16.0;
+/// How many times smaller a versus opponent's board is drawn.
+pub const MINI_SCALE: i32 = 2;
+/// Space either side of the opponent's mini board.
+pub const MINI_MARGIN: f32 = 16.0;
+/// Room over the mini board for the opponent's name.
+pub const MINI_LABEL_HEIGHT: f32 = 24.0;
+/// Height of the second player's hold and next previews in co-op, each
+/// under a heading.
+const PARTNER_PANEL_HEIGHT: f32 = 2.0 * (24.0 + PREVIEW_HEIGHT);
+/// Room at the foot of the left panel for the seed.
+const SEED_HEIGHT: f32 = 24.0;
+
+/// Size of a cell on a versus opponent's mini board, for a player's board
+/// with cells `cell_size` across.
+pub fn mini_cell_size(cell_size: i32) -> i32 {
+    (cell_size / MINI_SCALE).max(1)
+}
+
+/// Where one player's board and the panels beside it go on screen: the
+/// hold box and the mode's own HUD on the left, the next queue and the
+/// numbers on the right. Players sit side by side, each with a layout of
+/// their own.
+#[derive(Clone, Copy, Debug)]
+pub struct Layout {
+    /// The left edge of the left panel.
+    left: f32,
+    board_width: f32,
+    height: f32,
+}
+
+impl Layout {
+    pub fn new(board: &Board, cell_size: i32) -> Layout {
+        Layout::sized(board.width(), board.height(), cell_size)
+    }
+
+    /// The layout for a board of `width` by `height` cells.
+    pub fn sized(width: usize, height: usize, cell_size: i32) -> Layout {
+        Layout {
+            left: 0.0,
+            board_width: (width as i32 * cell_size) as f32,
+            height: (height as i32 * cell_size) as f32,
+        }
+    }
+
+    /// The next player's layout, to the right of this one.
+    pub fn beside(self) -> Layout {
+        Layout {
+            left: self.left + self.width(),
+            ..self
+        }
+    }
+
+    pub fn width(self) -> f32 {
+        self.board_width + 2.0 * PANEL_WIDTH
+    }
+
+    /// The width with a versus opponent's mini board to the right.
+    pub fn width_with_mini(self) -> f32 {
+        self.width_with_minis(1)
+    }
+
+    /// The width with `count` mini boards in a row to the right.
+    pub fn width_with_minis(self, count: usize) -> f32 {
+        self.width() + count as f32 * self.mini_width()
+    }
+
+    /// The room a mini board takes, margins and all.
+    fn mini_width(self) -> f32 {
+        self.board_width / MINI_SCALE as f32 + 2.0 * MINI_MARGIN
+    }
+
+    pub fn height(self) -> f32 {
+        self.height
+    }
+
+    /// The board's top-left corner.
+    pub fn board(self) -> Point2<f32> {
+        Point2::new(self.left + PANEL_WIDTH, 0.0)
+    }
+
+    /// The top-left corner of a versus opponent's mini board, to the right
+    /// of this layout's panels.
+    pub fn mini_board(self) -> Point2<f32> {
+        self.mini_board_at(0)
+    }
+
+    /// The top-left corner of the mini board `at` places along the row.
+    pub fn mini_board_at(self, at: usize) -> Point2<f32> {
+        Point2::new(
+            self.left + self.width() + at as f32 * self.mini_width() + MINI_MARGIN,
+            MINI_LABEL_HEIGHT,
+        )
+    }
+
+    pub fn left_panel(self) -> Rect {
+        Rect::new(self.left, 0.0, PANEL_WIDTH, self.height)
+    }
+
+    /// Where the second player's hold and next previews go in co-op, at
+    /// the foot of the left panel over the seed.
+    pub fn partner_panel(self) -> Rect {
+        Rect::new(
+            self.left,
+            self.height - SEED_HEIGHT - PARTNER_PANEL_HEIGHT,
+            PANEL_WIDTH,
+            PARTNER_PANEL_HEIGHT,
+        )
+    }
+
+    pub fn right_panel(self) -> Rect {
+        Rect::new(
+            self.left + PANEL_WIDTH + self.board_width,
+            0.0,
+            PANEL_WIDTH,
+            self.height,
+        )
+    }
+}
@@ -0,0 +1,26 @@
+pub mod ai;
+pub mod attack;
+pub mod board;
+pub mod bot;
+pub mod clock;
+pub mod events;
+pub mod finesse;
+pub mod fumen;
+pub mod game;
+pub mod gesture;
+pub mod gravity;
+pub mod handicap;
+pub mod kicks;
+pub mod master;
+pub mod mission;
+pub mod mode;
+pub mod puzzle;
+pub mod randomizer;
+pub mod replay;
+pub mod rules;
+pub mod script;
+pub mod targeting;
+pub mod tetromino;
+pub mod tutor;
+#[cfg(feature = "web")]
+pub mod web;
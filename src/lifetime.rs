@@ -0,0 +1,191 @@
+//! Lifetime stats: totals over every round each profile has finished, and
+//! how the latest went, for the stats screen's trends.
+
+use crate::highscores;
+use crate::locale::Locale;
+use crate::text;
+use ggez::filesystem;
+use ggez::graphics::{self, Color, DrawMode, MeshBuilder, Rect};
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tetris_rs::game::Game;
+
+const LIFETIME_FILE: &str = "lifetime.toml";
+/// How many of the latest rounds the trends are drawn over.
+const HISTORY_ROUNDS: usize = 50;
+/// The shortest round whose pace counts towards the best, so a quick
+/// top out can't set one.
+const MIN_PACE_SECS: u64 = 10;
+/// Space kept clear either side of the trends, and between them.
+const GRAPH_MARGIN: f32 = 32.0;
+const LABEL_SIZE: f32 = 16.0;
+const LINE_WIDTH: f32 = 2.0;
+
+/// How one round went, for the trends.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Round {
+    pub pps: f32,
+    pub lines: u32,
+}
+
+/// One profile's totals.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Totals {
+    pub games: u32,
+    pub lines: u32,
+    /// Clears of four rows or more.
+    pub tetrises: u32,
+    pub millis: u64,
+    pub best_pps: f32,
+    /// The latest rounds, oldest first.
+    pub history: Vec<Round>,
+}
+
+impl Totals {
+    /// The share of lines cleared four or more at a time.
+    pub fn tetris_rate(&self) -> f32 {
+        if self.lines == 0 {
+            0.0
+        } else {
+            (self.tetrises * 4) as f32 / self.lines as f32
+        }
+    }
+
+    pub fn time(&self) -> Duration {
+        Duration::from_millis(self.millis)
+    }
+
+    /// Draws the totals from `top` down and the trends of the latest
+    /// rounds under them, side by side to the bottom of `view`.
+    pub fn draw(
+        &self,
+        ctx: &mut Context,
+        view: Rect,
+        top: f32,
+        locale: &Locale,
+        accent: Color,
+    ) -> GameResult {
+        let minutes = self.time().as_secs() / 60;
+        let rows = [
+            locale.format("stats.games", &[&self.games]),
+            locale.format("stats.time", &[&(minutes / 60), &(minutes % 60)]),
+            locale.format("stats.lines", &[&self.lines]),
+            locale.format(
+                "stats.tetris_rate",
+                &[&format!("{:.0}", self.tetris_rate() * 100.0)],
+            ),
+            locale.format("stats.best_pps", &[&format!("{:.2}", self.best_pps)]),
+        ];
+        let mut y = top;
+        for row in &rows {
+            text::draw_centered(ctx, row, 20.0, graphics::WHITE, y)?;
+            y += 28.0;
+        }
+        if self.history.len() < 2 {
+            let none = locale.get("stats.no_trends");
+            return text::draw_centered(ctx, none, 18.0, graphics::WHITE, y + 24.0);
+        }
+        let graph_width = (view.w - 3.0 * GRAPH_MARGIN) / 2.0;
+        let left = view.x + GRAPH_MARGIN;
+        let graph_top = y + 32.0;
+        let height = (view.y + view.h - 64.0 - graph_top).max(40.0);
+        let pps: Vec<f32> = self.history.iter().map(|round| round.pps).collect();
+        let lines: Vec<f32> = self
+            .history
+            .iter()
+            .map(|round| round.lines as f32)
+            .collect();
+        let graphs = [
+            (locale.get("stats.pps_trend"), pps, left),
+            (
+                locale.get("stats.lines_trend"),
+                lines,
+                left + graph_width + GRAPH_MARGIN,
+            ),
+        ];
+        for (label, values, x) in &graphs {
+            let area = Rect::new(*x, graph_top, graph_width, height);
+            draw_trend(ctx, area, values, accent)?;
+            let at = Point2::new(*x, graph_top - LABEL_SIZE - 4.0);
+            text::draw(ctx, label, LABEL_SIZE, graphics::WHITE, at)?;
+        }
+        Ok(())
+    }
+}
+
+/// A line through `values` across `area`, from zero at the bottom to the
+/// most of them at the top, in a frame.
+fn draw_trend(ctx: &mut Context, area: Rect, values: &[f32], color: Color) -> GameResult {
+    let most = values.iter().copied().fold(0.0, f32::max).max(f32::EPSILON);
+    let step = area.w / (values.len() - 1) as f32;
+    let points: Vec<Point2<f32>> = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            Point2::new(
+                area.x + step * i as f32,
+                area.y + area.h * (1.0 - value / most),
+            )
+        })
+        .collect();
+    let mut builder = MeshBuilder::new();
+    builder.rectangle(DrawMode::stroke(1.0), area, Color::new(1.0, 1.0, 1.0, 0.3));
+    builder.line(&points, LINE_WIDTH, color)?;
+    let mesh = builder.build(ctx)?;
+    graphics::draw(ctx, &mesh, (Point2::new(0.0, 0.0),))?;
+    let most = format!("{:.1}", most);
+    let at = Point2::new(area.x + 4.0, area.y + 4.0);
+    text::draw(ctx, &most, 12.0, Color::new(1.0, 1.0, 1.0, 0.6), at)
+}
+
+/// Every profile's totals, by the profile's name, stored in the user data
+/// directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Lifetime {
+    #[serde(default)]
+    profiles: BTreeMap<String, Totals>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Lifetime {
+    pub fn load(ctx: &Context) -> GameResult<Lifetime> {
+        let path = filesystem::user_data_dir(ctx).join(LIFETIME_FILE);
+        let mut lifetime: Lifetime = highscores::load_table(&path)?;
+        lifetime.path = path;
+        Ok(lifetime)
+    }
+
+    pub fn totals(&self, profile: &str) -> Totals {
+        self.profiles.get(profile).cloned().unwrap_or_default()
+    }
+
+    /// Adds the round `game` just finished to `profile`'s totals.
+    pub fn record(&mut self, profile: &str, game: &Game) {
+        let stats = game.stats();
+        let played = game.elapsed();
+        let pps = stats.pieces_per_second(played);
+        let totals = self.profiles.entry(profile.to_string()).or_default();
+        totals.games += 1;
+        totals.lines += game.lines;
+        totals.tetrises += stats.clears[3];
+        totals.millis += played.as_millis() as u64;
+        if played >= Duration::from_secs(MIN_PACE_SECS) {
+            totals.best_pps = totals.best_pps.max(pps);
+        }
+        totals.history.push(Round {
+            pps,
+            lines: game.lines,
+        });
+        let over = totals.history.len().saturating_sub(HISTORY_ROUNDS);
+        totals.history.drain(..over);
+        if let Err(e) = highscores::save_table(&self.path, self) {
+            eprintln!("Could not save the lifetime stats: {}", e);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! The text the game shows, in the player's language. Each language is a
+//! `.toml` file in `resources/locales` named by its code, with a table per
+//! screen or part of one:
+//!
+//! ```toml
+//! language = "English"
+//!
+//! [menu]
+//! start = "Start"
+//!
+//! [hud]
+//! pieces_left = "Pieces left: {}"
+//! ```
+//!
+//! Text is looked up by its place in the file, as `menu.start`. Each `{}`
+//! is filled in with a value, in order. `en.toml` has every key, and
+//! anything another language leaves out is shown in English. Packs can add
+//! languages of their own, in their `locales` folder.
+
+use ggez::filesystem;
+use ggez::{Context, GameError, GameResult};
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::io::Read;
+use std::path::PathBuf;
+use toml::Value;
+
+const LOCALE_DIR: &str = "locales";
+pub const DEFAULT_LANGUAGE: &str = "en";
+/// The English text, built in for the terminal game, which has no
+/// resources to read the rest from.
+const ENGLISH: &str = include_str!("../resources/locales/en.toml");
+
+pub struct Locale {
+    texts: HashMap<String, String>,
+}
+
+/// Adds the strings in `value` to `texts`, each under its path from the
+/// top of the file.
+fn flatten(prefix: &str, value: Value, texts: &mut HashMap<String, String>) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&key, value, texts);
+            }
+        }
+        Value::String(text) => {
+            texts.insert(prefix.to_string(), text);
+        }
+        _ => eprintln!("Text for {} in a locale isn't a string", prefix),
+    }
+}
+
+fn read(ctx: &mut Context, language: &str) -> GameResult<HashMap<String, String>> {
+    let path = format!("/{}/{}.toml", LOCALE_DIR, language);
+    let mut text = String::new();
+    filesystem::open(ctx, &path)?.read_to_string(&mut text)?;
+    parse(&path, &text)
+}
+
+fn parse(path: &str, text: &str) -> GameResult<HashMap<String, String>> {
+    let value: Value = toml::from_str(text)
+        .map_err(|e| GameError::ResourceLoadError(format!("Invalid {}: {}", path, e)))?;
+    let mut texts = HashMap::new();
+    flatten("", value, &mut texts);
+    Ok(texts)
+}
+
+impl Locale {
+    /// The text for `language`, over the English. A language that can't be
+    /// read leaves the English as it is.
+    pub fn load(ctx: &mut Context, language: &str) -> Locale {
+        let mut texts = read(ctx, DEFAULT_LANGUAGE).unwrap_or_else(|e| {
+            eprintln!("Could not read the English text: {}", e);
+            HashMap::new()
+        });
+        if language != DEFAULT_LANGUAGE {
+            match read(ctx, language) {
+                Ok(translated) => texts.extend(translated),
+                Err(e) => eprintln!("Could not read the {} text: {}", language, e),
+            }
+        }
+        Locale { texts }
+    }
+
+    /// The English text as built in, without reading any files.
+    pub fn english() -> Locale {
+        let texts = parse(DEFAULT_LANGUAGE, ENGLISH).unwrap_or_else(|e| {
+            eprintln!("Could not read the English text: {}", e);
+            HashMap::new()
+        });
+        Locale { texts }
+    }
+
+    /// The codes of the languages there are text for, in order.
+    pub fn languages(ctx: &mut Context) -> Vec<String> {
+        let mut languages: Vec<String> = filesystem::read_dir(ctx, format!("/{}", LOCALE_DIR))
+            .map(|paths| {
+                paths
+                    .filter(|path: &PathBuf| path.extension().is_some_and(|ext| ext == "toml"))
+                    .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        languages.sort();
+        languages.dedup();
+        languages
+    }
+
+    /// The text for `key`, or the key itself if there's none, so anything
+    /// missing shows where it goes.
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.texts.get(key).map_or(key, String::as_str)
+    }
+
+    /// The text for `value`, one of the kind of thing `kind` names, keyed
+    /// by how it's written in the code, as `rules.Modern`.
+    pub fn name(&self, kind: &str, value: impl Debug) -> String {
+        self.get(&format!("{}.{:?}", kind, value)).to_string()
+    }
+
+    /// The text for `key` with its `{}`s filled in with `values`.
+    pub fn format(&self, key: &str, values: &[&dyn Display]) -> String {
+        let mut values = values.iter();
+        let mut parts = self.get(key).split("{}");
+        let mut text = parts.next().unwrap_or_default().to_string();
+        for part in parts {
+            if let Some(value) = values.next() {
+                text.push_str(&value.to_string());
+            }
+            text.push_str(part);
+        }
+        text
+    }
+}
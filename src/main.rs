@@ -1,382 +1,4636 @@
 use ggez::conf;
 use ggez::event;
-use ggez::event::{KeyCode, KeyMods};
+use ggez::event::{KeyCode, KeyMods, MouseButton};
+use ggez::filesystem;
 use ggez::graphics;
 use ggez::nalgebra as na;
+use ggez::timer;
 use ggez::{Context, GameResult};
-use na::{Point2, Vector2};
-use rand::{
-    distributions::{Distribution, Standard},
-    Rng,
-};
-use std::ops::Neg;
+use std::collections::VecDeque;
+use std::env;
+use std::path::Path;
 use std::time::{Duration, Instant};
+use tetris_rs::ai::{Ai, Difficulty, Weights};
+use tetris_rs::board::Board;
+use tetris_rs::bot::BotDriver;
+use tetris_rs::events::{self, Observer};
+use tetris_rs::finesse::{Fault, Finesse};
+use tetris_rs::fumen;
+use tetris_rs::game::{Event, Game, Handling, Heatmap, Input, Settings, GARBAGE_DELAY_MILLIS};
+use tetris_rs::gesture::Gestures;
+use tetris_rs::handicap::{Handicap, MAX_GARBAGE_ROWS, MAX_HANDICAP_PERCENT, MIN_SPEED_PERCENT};
+use tetris_rs::mission::{self, Mission};
+use tetris_rs::mode::{Mode, MARATHON_LEVELS};
+use tetris_rs::puzzle::{Goal, Practice, Puzzle};
+use tetris_rs::replay::{self, Action, Playback, Replay, TimedAction};
+use tetris_rs::rules::{LockReset, Randomizer, RotationSystem, Rules, MAX_PREVIEWS};
+use tetris_rs::targeting::Targeting;
+use tetris_rs::tetromino::{PieceSet, Tetromino};
+use tetris_rs::tutor::Tutor;
 
-const GRID_SIZE: (i32, i32) = (16, 32);
-const GRID_CELL_SIZE: (i32, i32) = (32, 32);
+mod achievements;
+mod announcer;
+mod backdrop;
+mod bugreport;
+mod campaign;
+mod chat;
+mod cli;
+mod clip;
+mod config;
+mod coop;
+mod daily;
+mod discovery;
+mod editor;
+mod ghost;
+mod headless;
+mod highscores;
+mod keymap;
+mod layout;
+mod lifetime;
+mod locale;
+mod mods;
+mod net;
+mod packs;
+mod particles;
+mod pieces;
+mod popups;
+mod puzzles;
+mod races;
+mod render;
+mod replays;
+mod room;
+mod savegame;
+mod sessions;
+mod shake;
+mod sound;
+mod soundpacks;
+mod spectate;
+mod splits;
+mod text;
+mod themes;
+mod trail;
+mod tui;
+mod versus;
 
-const SCREEN_SIZE: (i32, i32) = (
-    GRID_SIZE.0 * GRID_CELL_SIZE.0,
-    GRID_SIZE.1 * GRID_CELL_SIZE.1,
-);
+use achievements::{Achievement, Achievements};
+use announcer::Announcer;
+use backdrop::Backdrop;
+use bugreport::InputLog;
+use campaign::Campaign;
+use chat::Chat;
+use clap::Parser;
+use cli::Cli;
+use config::{Config, Marks};
+use coop::Partner;
+use daily::Daily;
+use discovery::Browser;
+use editor::Editor;
+use ghost::Ghost;
+use highscores::{HighScores, SprintTimes};
+use keymap::KeyMap;
+use layout::{mini_cell_size, Layout, HUD_TOP};
+use lifetime::Lifetime;
+use locale::Locale;
+use mods::Mods;
+use net::{Hello, Lobby, Match, Message, Spectators};
+use packs::Packs;
+use particles::Particles;
+use popups::Popups;
+use puzzles::Progress;
+use races::Races;
+use render::BoardRenderer;
+use savegame::Slot;
+use shake::Shake;
+use sound::Audio;
+use soundpacks::SoundPack;
+use spectate::Spectator;
+use splits::{SplitTimer, Splits};
+use themes::Theme;
+use trail::Trails;
+use versus::{Controller, Opponent, Outcome, Remote, Versus, MAX_CPUS};
 
-const UPDATES_PER_SECOND: f32 = 2.0;
-const MILLIS_PER_UPDATE: u64 = (1.0 / UPDATES_PER_SECOND * 1000.0) as u64;
+const BANNER_MILLIS: u64 = 2000;
+/// How long a finesse hint stays up.
+const FINESSE_HINT_MILLIS: u64 = 1500;
+/// How long the 3-2-1 before a round takes, and how long GO stays up once
+/// it starts, in logic steps.
+const COUNTDOWN_FRAMES: u64 = 3 * replay::FRAMES_PER_SECOND as u64;
+const GO_FRAMES: u64 = replay::FRAMES_PER_SECOND as u64 / 2;
+/// How hard the screen shakes for a hard drop and a tetris, in pixels.
+const HARD_DROP_SHAKE: f32 = 4.0;
+const TETRIS_SHAKE: f32 = 10.0;
+/// How long each achievement toast stays up.
+const TOAST_MILLIS: u64 = 3000;
+/// How long the title screen sits untouched before the demo starts.
+const DEMO_IDLE_SECS: u64 = 30;
+/// The frame rate while nothing on screen is moving or the window is in
+/// the background, to spare the processor.
+const IDLE_FPS: u32 = 4;
+/// How long a still screen goes untouched before dropping to `IDLE_FPS`.
+const IDLE_AFTER_SECS: u64 = 2;
+/// How often a round in play is written out in case the game doesn't get
+/// to close properly.
+const AUTOSAVE_SECS: u64 = 15;
 
-struct MainState {
-    pos: na::Point2<i32>,
-    facing: u8,
-    tetromino: Tetromino,
-    start_time: Instant,
-    updates_so_far: i32,
-    board: Board,
-    game_over: bool,
-}
+/// The modes that keep a table of their best rounds, in the order the high
+/// score screen pages through them.
+const RANKED_MODES: [Mode; 3] = [Mode::Endless, Mode::Marathon, Mode::Sprint];
 
-struct Board {
-    data: [[Option<FixedBlock>; 16]; 32],
-}
+const OPTION_ITEMS: usize = 30;
+const CONTROL_ITEMS: usize = Input::ALL.len() + 1;
+const HANDLING_ITEMS: usize = 6;
+const HANDLING_STEP_MILLIS: u64 = 10;
+/// The slowest soft drop that repeats, below which each press drops a row.
+const SLOWEST_SOFT_DROP_MILLIS: u64 = 100;
+const VOLUME_STEP: u32 = 10;
+/// A handicap line for each side on the versus setup screen with the
+/// computer's difficulty between them, how many CPUs and how to target
+/// them, then start and back.
+const VERSUS_ITEMS: usize = 13;
+const HANDICAP_LINES: usize = 4;
+const DIFFICULTY_ITEM: usize = HANDICAP_LINES;
+const CPUS_ITEM: usize = 2 * HANDICAP_LINES + 1;
+const TARGETING_ITEM: usize = CPUS_ITEM + 1;
+/// How far the speed and attack handicaps go a step, in percent.
+const HANDICAP_STEP_PERCENT: u32 = 10;
+/// How far the practice speed goes a step, and how far it goes, in
+/// percent.
+const PRACTICE_SPEED_STEP: u32 = 25;
+const MIN_PRACTICE_SPEED: u32 = 25;
+const MAX_PRACTICE_SPEED: u32 = 200;
+/// How far under the top of the HUD special actions are called out.
+const ANNOUNCEMENTS_BELOW: f32 = 80.0;
+/// And the splits, under as many of those as are shown at once.
+const SPLITS_BELOW: f32 = 160.0;
+/// The frame rate limits offered, stepped through from none.
+const FPS_CAPS: [u32; 4] = [30, 60, 120, 144];
+/// How much of the high score table fits under the game over text.
+const GAME_OVER_SCORE_ROWS: usize = 5;
+/// The longest room code or address that can be typed to join a game.
+const MAX_JOIN_LEN: usize = 48;
+/// Where chat goes, up from the bottom left corner, and how big.
+const CHAT_MARGIN: f32 = 8.0;
+const CHAT_LINE: f32 = 20.0;
+const CHAT_SIZE: f32 = 16.0;
 
-impl Board {
-    fn get(&self, block: na::Point2<i32>) -> Option<&Option<FixedBlock>> {
-        match self.data.get(block[1] as usize) {
-            None => None,
-            Some(row) => row.get(block[0] as usize),
-        }
-    }
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MenuItem {
+    Continue,
+    Start,
+    Daily,
+    Race,
+    Marathon,
+    Sprint,
+    Zen,
+    Invisible,
+    Big,
+    TwentyG,
+    Master,
+    Campaign,
+    Puzzle,
+    Practice,
+    Editor,
+    Versus,
+    Coop,
+    Host,
+    Join,
+    Local,
+    HighScores,
+    Stats,
+    Packs,
+    Options,
+    Quit,
+}
 
-    fn get_mut(&mut self, block: na::Point2<i32>) -> Option<&mut Option<FixedBlock>> {
-        match self.data.get_mut(block[1] as usize) {
-            None => None,
-            Some(row) => row.get_mut(block[0] as usize),
+impl MenuItem {
+    /// The key of its text in the locale.
+    fn key(self) -> &'static str {
+        match self {
+            MenuItem::Continue => "menu.continue",
+            MenuItem::Start => "menu.start",
+            MenuItem::Daily => "menu.daily",
+            MenuItem::Race => "menu.race",
+            MenuItem::Marathon => "menu.marathon",
+            MenuItem::Zen => "menu.zen",
+            MenuItem::Invisible => "menu.invisible",
+            MenuItem::Big => "menu.big",
+            MenuItem::TwentyG => "menu.twenty_g",
+            MenuItem::Master => "menu.master",
+            MenuItem::Campaign => "menu.campaign",
+            MenuItem::Puzzle => "menu.puzzle",
+            MenuItem::Practice => "menu.practice",
+            MenuItem::Editor => "menu.editor",
+            MenuItem::Sprint => "menu.sprint",
+            MenuItem::Versus => "menu.versus",
+            MenuItem::Coop => "menu.coop",
+            MenuItem::Host => "menu.host",
+            MenuItem::Join => "menu.join",
+            MenuItem::Local => "menu.local",
+            MenuItem::HighScores => "menu.high_scores",
+            MenuItem::Stats => "menu.stats",
+            MenuItem::Packs => "menu.mods",
+            MenuItem::Options => "menu.options",
+            MenuItem::Quit => "menu.quit",
         }
     }
 }
 
+/// Which screen the game is on.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-struct FixedBlock {
-    tetromino: Tetromino,
+enum Scene {
+    Menu {
+        selected: usize,
+    },
+    Options {
+        selected: usize,
+    },
+    /// Rebinding keys; `waiting` is set while the next key press will be
+    /// bound to the selected action.
+    Controls {
+        selected: usize,
+        waiting: bool,
+    },
+    /// Tuning the handling of the profile in use.
+    Handling {
+        selected: usize,
+    },
+    /// Offered on launch when the last run ended mid-round without putting
+    /// the game away: resume it from the autosave, or throw it out.
+    Recover {
+        selected: usize,
+    },
+    /// The high score table for `mode`, or for `Mode::Sprint` the best
+    /// times.
+    HighScores {
+        mode: Mode,
+    },
+    /// Typing a name for the entry at `rank` in the table for the mode just
+    /// played.
+    NameEntry {
+        rank: usize,
+    },
+    Playing,
+    Paused,
+    GameOver,
+    /// Where pieces have locked over the rounds played since the game
+    /// started, from the game over screen.
+    Heatmap,
+    /// The profile in use's lifetime stats, with trends over the latest
+    /// rounds.
+    Stats,
+    /// Watching a recorded game.
+    Replay,
+    /// The computer playing on its own while nobody is at the title screen.
+    Demo,
+    /// Typing the room code or address of a network game to join.
+    Join,
+    /// Typing the seed of a race.
+    RaceSetup,
+    /// How run number `run` on the race seed compares with the others.
+    RaceResults {
+        run: usize,
+    },
+    /// Picking from the games announced on the local network.
+    LocalGames {
+        selected: usize,
+    },
+    /// Turning content packs on and off.
+    Packs {
+        selected: usize,
+    },
+    /// Picking a puzzle to play, with those solved marked.
+    Puzzles {
+        selected: usize,
+    },
+    /// Picking a campaign mission to play, with the stars earned on each
+    /// and those not yet unlocked marked.
+    Missions {
+        selected: usize,
+    },
+    /// Setting up a board to play on or save as a puzzle.
+    Editor,
+    /// Setting the handicaps for a round against the computer.
+    VersusSetup {
+        selected: usize,
+    },
+    /// Setting up a network game.
+    Lobby,
+    /// Looking on at someone else's network game.
+    Spectating,
+}
+
+struct MainState {
+    config: Config,
+    scene: Scene,
+    game: Game,
+    handling: Handling,
+    keymap: KeyMap,
+    restart_key: Option<KeyCode>,
+    /// When the restart key went down, while it's held for long enough.
+    restart_held: Option<Instant>,
+    /// Fixed seed from the command line; each game gets a fresh one if unset.
+    seed: Option<u64>,
+    /// How the computer player scores boards, from the command line.
+    ai_weights: Weights,
+    /// Suggests placements and grades the player's, with the option on.
+    tutor: Tutor,
+    finesse: Finesse,
+    /// The last piece placed in more presses than it needed, and when, to
+    /// show the shortest way for a moment.
+    finesse_hint: Option<(Fault, Instant)>,
+    start_level: u32,
+    /// The level marathons start at, picked on the menu and kept in the
+    /// config.
+    marathon_level: u32,
+    /// A fumen from the command line to practice on instead of the
+    /// configured drill.
+    fumen: Option<String>,
+    /// The puzzles there are to play on this board size, and the one picked
+    /// on the menu.
+    puzzles: Vec<Puzzle>,
+    progress: Progress,
+    /// The campaign's missions, the one last picked, and the stars earned.
+    missions: Vec<Mission>,
+    mission: usize,
+    campaign: Campaign,
+    /// The board editor's setup, kept from one visit to the next.
+    editor: Option<Editor>,
+    puzzle: usize,
+    high_scores: HighScores,
+    marathon_scores: HighScores,
+    sprint_times: SprintTimes,
+    /// Where the last game placed in its mode's table, if it made it.
+    last_rank: Option<usize>,
+    /// The name being typed on the name entry screen, kept between games so
+    /// the same player doesn't have to type it again.
+    name: String,
+    /// The room code or address being typed to join a network game.
+    joining: String,
+    audio: Audio,
+    renderer: BoardRenderer,
+    /// What's drawn around the board, as the config picks.
+    backdrop: Backdrop,
+    particles: Particles,
+    shake: Shake,
+    trails: Trails,
+    popups: Popups,
+    announcer: Announcer,
+    /// Follows the mouse while it's held down on the board, for playing by
+    /// touch.
+    gestures: Gestures,
+    /// Where the pointer last was across the board, in cells, and the
+    /// piece last moved under it, by the pieces locked before it and its
+    /// shape, so each new one is moved over as it comes in. For mouse
+    /// placement.
+    pointer: Option<f32>,
+    aimed: Option<(u32, Tetromino)>,
+    /// The themes there are to pick from on the options screen.
+    themes: Vec<Theme>,
+    /// The sound packs there are to pick from on the options screen.
+    sound_packs: Vec<SoundPack>,
+    /// The text shown, in the language picked.
+    locale: Locale,
+    /// Whether there is a saved game to continue.
+    saved: bool,
+    /// Logic steps since the round started; the game's clock.
+    frame: u64,
+    /// Whether the developer overlay is showing over the board. Unlike the
+    /// stats panel it isn't saved.
+    debug: bool,
+    /// Whether the round only advances when stepped, from the overlay, and
+    /// the steps asked for that haven't been taken yet.
+    stepping: bool,
+    steps: u32,
+    /// Logic steps left in the countdown before the round starts, which
+    /// holds the clock, and so gravity, and ignores input until then.
+    countdown: u64,
+    /// The round being played, as it is recorded. Rounds picked up from a
+    /// saved game aren't recorded since they don't start from an empty
+    /// board.
+    recording: Option<Replay>,
+    /// The last minute or so of the round in play, for a bug report.
+    input_log: InputLog,
+    /// The last finished round, to watch from the game over screen.
+    last_replay: Option<Replay>,
+    /// Where pieces have locked in the rounds finished since the game
+    /// started, on the last board size played.
+    heatmap: Heatmap,
+    playback: Option<Playback>,
+    /// Set for a round against the computer or another player.
+    versus: Option<Versus>,
+    /// The second player in a co-op round.
+    partner: Option<Partner>,
+    /// A run of the player's own being raced, if there is one.
+    ghost: Option<Ghost>,
+    lobby: Option<Lobby>,
+    /// Listening for local games while they're being picked from.
+    browser: Option<Browser>,
+    /// What the players of a network game have said to each other.
+    chat: Chat,
+    /// Anyone looking on at a network game this side is hosting.
+    spectators: Option<Spectators>,
+    spectator: Option<Spectator>,
+    /// Logic steps the title screen has gone without a key press.
+    idle_frames: u64,
+    /// Percent of a game step owed, so practice can run faster or slower
+    /// than the fixed rate.
+    speed_credit: u32,
+    /// Plays the demo game.
+    demo: Option<BotDriver>,
+    achievements: Achievements,
+    /// Scripts from the user's mods folder, run in single player rounds.
+    mods: Mods,
+    packs: Packs,
+    daily: Daily,
+    /// The date of the daily challenge being played, if it is one.
+    daily_date: Option<String>,
+    races: Races,
+    /// Every profile's totals over the rounds they've finished.
+    lifetime: Lifetime,
+    /// The best run's splits in each mode, and the round in play's.
+    splits: Splits,
+    split_timer: SplitTimer,
+    /// The race seed as typed, kept for the next race.
+    race_seed: String,
+    /// The seed of the race in play, if the round is one.
+    race: Option<String>,
+    /// Achievements just unlocked and waiting to be shown, oldest first,
+    /// with when the first went up. Timed by the wall clock, as they stay
+    /// up past the end of the round.
+    toasts: VecDeque<Achievement>,
+    toast_at: Option<Instant>,
+    /// What the window's title was last set to, so it's only set again
+    /// when it changes.
+    window_title: String,
+    /// When the last frame was shown, for the frame rate cap.
+    last_frame: Instant,
+    /// Whether the window has focus, and when the player last pressed a
+    /// key or moved the mouse, for dropping to `IDLE_FPS`.
+    focused: bool,
+    last_input: Instant,
 }
 
 impl MainState {
-    fn new(_ctx: &mut Context) -> GameResult<MainState> {
-        Ok(MainState {
-            pos: na::Point2::new(rand::thread_rng().gen_range(0, 15), 0),
-            facing: 0,
-            start_time: Instant::now(),
-            tetromino: rand::random(),
-            updates_so_far: 0,
-            board: Board {
-                data: [[None; 16]; 32],
-            },
-            game_over: false,
-        })
+    fn new(ctx: &mut Context, config: Config, packs: Packs, cli: &Cli) -> GameResult<MainState> {
+        let ai_weights = cli.ai_weights()?;
+        let sound_packs = soundpacks::load(ctx);
+        let mut state = MainState {
+            scene: Scene::Menu { selected: 0 },
+            game: Game::new(Board::default(), Settings::default()),
+            handling: config.handling(),
+            keymap: KeyMap::from_config(&config.keys),
+            restart_key: keymap::restart_key(&config.restart_key),
+            restart_held: None,
+            audio: Audio::load(
+                ctx,
+                &config,
+                &packs,
+                sound_packs.iter().find(|pack| pack.id == config.sound_pack),
+            ),
+            renderer: BoardRenderer::new(ctx, &config)?,
+            backdrop: Backdrop::load(ctx, &config.backdrop),
+            particles: Particles::default(),
+            shake: Shake::default(),
+            trails: Trails::default(),
+            popups: Popups::default(),
+            announcer: Announcer::default(),
+            gestures: Gestures::default(),
+            pointer: None,
+            aimed: None,
+            themes: themes::load(ctx),
+            sound_packs,
+            locale: Locale::load(ctx, &config.language),
+            saved: savegame::exists(ctx, Slot::Kept),
+            // The command line only picks for this run.
+            marathon_level: cli
+                .level
+                .unwrap_or(config.marathon_level)
+                .clamp(1, MARATHON_LEVELS),
+            puzzles: puzzles::load(ctx, config.board_width, config.board_height),
+            progress: Progress::load(ctx)?,
+            missions: campaign::load(ctx, config.board_width, config.board_height),
+            mission: 0,
+            campaign: Campaign::load(ctx)?,
+            editor: None,
+            config,
+            seed: cli.seed,
+            ai_weights,
+            tutor: Tutor::new(ai_weights),
+            finesse: Finesse::default(),
+            finesse_hint: None,
+            start_level: cli.level.unwrap_or(1),
+            fumen: cli.fumen.clone(),
+            puzzle: 0,
+            high_scores: HighScores::load(ctx)?,
+            marathon_scores: HighScores::load_marathon(ctx)?,
+            sprint_times: SprintTimes::load(ctx)?,
+            last_rank: None,
+            name: String::new(),
+            joining: String::new(),
+            frame: 0,
+            debug: false,
+            stepping: false,
+            steps: 0,
+            countdown: 0,
+            recording: None,
+            input_log: InputLog::default(),
+            last_replay: None,
+            heatmap: Heatmap::default(),
+            achievements: Achievements::load(ctx)?,
+            mods: Mods::load(ctx, &packs)?,
+            packs,
+            daily: Daily::load(ctx)?,
+            daily_date: None,
+            races: Races::load(ctx)?,
+            lifetime: Lifetime::load(ctx)?,
+            splits: Splits::load(ctx)?,
+            split_timer: SplitTimer::default(),
+            race_seed: String::new(),
+            race: None,
+            toasts: VecDeque::new(),
+            toast_at: None,
+            window_title: String::new(),
+            last_frame: Instant::now(),
+            focused: true,
+            last_input: Instant::now(),
+            playback: None,
+            versus: None,
+            partner: None,
+            ghost: None,
+            lobby: None,
+            browser: None,
+            chat: Chat::default(),
+            spectators: None,
+            spectator: None,
+            idle_frames: 0,
+            speed_credit: 0,
+            demo: None,
+        };
+        state.puzzle = state
+            .puzzles
+            .iter()
+            .position(|puzzle| puzzle.name == state.config.puzzle)
+            .unwrap_or(0);
+        if savegame::exists(ctx, Slot::Autosave) {
+            state.scene = Scene::Recover { selected: 0 };
+        }
+        if let Some(ref path) = cli.replay {
+            state.watch(ctx, replays::load(path)?);
+        }
+        if let Some(ref path) = cli.script {
+            state.watch(ctx, replays::load_script(path, cli.script_settings())?);
+        }
+        if state.fumen.is_some() {
+            state.start_practice(ctx);
+        }
+        if let Some(mode) = cli.mode {
+            state.start(ctx, mode, false, None);
+        }
+        if let Some(ref addr) = cli.host {
+            state.host(addr)?;
+        } else if let Some(ref addr) = cli.join {
+            state.join(addr)?;
+        } else if let Some(ref addr) = cli.watch {
+            state.spectator = Some(Spectator::new(net::watch(addr)?));
+            state.scene = Scene::Spectating;
+        }
+        Ok(state)
     }
-    fn not_overlapping_down(&self) -> bool {
-        self.tetromino
-            .blocks(self.pos + na::Vector2::new(0, 1), self.facing)
-            .into_iter()
-            .all(|block| self.board.get(block) == Some(&None))
+
+    /// How a new round is set up, from the config and the command line.
+    fn settings(&self) -> Settings {
+        Settings {
+            handling: self.handling,
+            gravity: self.config.gravity(),
+            start_level: self.start_level,
+            seed: self.seed.unwrap_or_else(rand::random),
+            mode: Mode::Endless,
+            pieces: PieceSet::Tetrominoes,
+            rules: Rules::Modern,
+            rotation: None,
+            randomizer: None,
+            lock_reset: self.config.lock_reset,
+            previews: self.config.previews,
+            entry_delay: self.config.entry_delay(),
+            line_clear_delay: self.config.line_clear_delay(),
+            garbage_delay: Duration::from_millis(GARBAGE_DELAY_MILLIS),
+            handicap: Handicap::default(),
+        }
     }
-    fn not_overlapping_left(&self) -> bool {
-        self.tetromino
-            .blocks(self.pos + na::Vector2::new(-1, 0), self.facing)
-            .into_iter()
-            .all(|block| self.board.get(block) == Some(&None))
+
+    /// Starts a new round of `mode`, against the computer if `versus` is
+    /// set, and dealt from `seed` if given.
+    fn start(&mut self, ctx: &mut Context, mode: Mode, versus: bool, seed: Option<u64>) {
+        if mode == Mode::Coop {
+            return self.start_coop(ctx, seed);
+        }
+        self.discard_save(ctx);
+        let scale = mode.scale();
+        let board = Board::new(
+            (self.config.board_width / scale).max(1),
+            (self.config.board_height / scale).max(1),
+        );
+        let mut settings = Settings {
+            mode,
+            pieces: self.config.pieces,
+            rules: self.config.rules,
+            rotation: self.config.rotation,
+            randomizer: self.config.randomizer,
+            ..self.settings()
+        };
+        match mode {
+            Mode::Marathon => settings.start_level = self.marathon_level,
+            Mode::Master => settings.start_level = 0,
+            _ => (),
+        }
+        if let Some(seed) = seed {
+            settings.seed = seed;
+        }
+        if versus {
+            settings.handicap = self.config.handicap;
+        }
+        let cpus = if versus {
+            self.config.cpus.clamp(1, MAX_CPUS)
+        } else {
+            0
+        };
+        let opponents = (0..cpus as u64)
+            .map(|at| {
+                let difficulty = self.config.cpu_difficulty;
+                // Each CPU thinks for itself, on the same pieces as the rest.
+                let seed = settings.seed.wrapping_add(at);
+                let ai = Ai::with_difficulty(self.ai_weights, difficulty, seed);
+                let bot = BotDriver::new(Box::new(ai), difficulty.frames_per_input());
+                // The bot only knows how to play by modern rules.
+                let theirs = Settings {
+                    rules: Rules::Modern,
+                    rotation: None,
+                    handicap: self.config.cpu_handicap,
+                    ..settings
+                };
+                (Controller::Bot(bot), theirs)
+            })
+            .collect();
+        self.start_round(ctx, board, settings, opponents);
     }
-    fn not_overlapping_right(&self) -> bool {
-        self.tetromino
-            .blocks(self.pos + na::Vector2::new(1, 0), self.facing)
-            .into_iter()
-            .all(|block| self.board.get(block) == Some(&None))
+
+    /// Starts a co-op round on a board twice the usual width, dealt from
+    /// `seed` if given. Like versus, co-op isn't recorded; a replay has
+    /// one player in it.
+    fn start_coop(&mut self, ctx: &mut Context, seed: Option<u64>) {
+        self.discard_save(ctx);
+        let board = Board::new(
+            coop::board_width(self.config.board_width),
+            self.config.board_height,
+        );
+        let mut settings = Settings {
+            pieces: self.config.pieces,
+            rules: self.config.rules,
+            rotation: self.config.rotation,
+            randomizer: self.config.randomizer,
+            ..self.settings()
+        };
+        if let Some(seed) = seed {
+            settings.seed = seed;
+        }
+        let keymap = KeyMap::partner_from_config(&self.config.partner_keys);
+        let (game, partner) = coop::start(board, settings, keymap);
+        self.game = game;
+        self.partner = Some(partner);
+        self.daily_date = None;
+        self.race = None;
+        self.frame = 0;
+        self.countdown = COUNTDOWN_FRAMES;
+        self.recording = None;
+        // The last round played alone can't be raced or watched from
+        // this one's game over screen.
+        self.last_replay = None;
+        self.versus = None;
+        self.ghost = None;
+        self.spectators = None;
+        self.aimed = None;
+        savegame::delete(ctx, Slot::Autosave);
+        self.fit_window(ctx);
+        self.scene = Scene::Playing;
     }
-    fn not_overlapping_rotate(&self) -> bool {
-        self.tetromino
-            .blocks(self.pos, self.facing + 1)
-            .into_iter()
-            .all(|block| self.board.get(block) == Some(&None))
+
+    /// Starts today's challenge, a two minute ultra from the day's seed on
+    /// the standard board and rules so everyone plays the same pieces. One
+    /// go a day.
+    fn start_daily(&mut self, ctx: &mut Context) {
+        let date = match self.daily.start() {
+            Some(date) => date,
+            None => return,
+        };
+        self.discard_save(ctx);
+        let settings = Settings {
+            handling: self.handling,
+            seed: daily::seed(),
+            mode: Mode::Ultra,
+            ..Settings::default()
+        };
+        self.start_round(ctx, Board::default(), settings, Vec::new());
+        self.daily_date = Some(date);
     }
-}
 
-impl event::EventHandler for MainState {
-    fn update(&mut self, _ctx: &mut Context) -> GameResult {
-        if Instant::now() - self.start_time
-            >= Duration::from_millis(MILLIS_PER_UPDATE * self.updates_so_far as u64)
-        {
-            if !self.game_over {
-                if self.not_overlapping_down() {
-                    self.pos[1] += 1;
-                } else {
-                    let fixed_block = FixedBlock {
-                        tetromino: self.tetromino,
-                    };
-                    for block in self.tetromino.blocks(self.pos, self.facing) {
-                        match self.board.get_mut(block) {
-                            Some(ref mut a) if a.is_none() => **a = Some(fixed_block),
-                            _ => {
-                                self.game_over = true;
-                            }
-                        }
-                    }
-                    for y in 0..self.board.data.len() {
-                        if self.board.data[y].iter().all(Option::is_some) {
-                            for higher in (0..y).rev() {
-                                let lower = higher + 1;
-                                for x in 0..GRID_SIZE.0 {
-                                    *self
-                                        .board
-                                        .get_mut(na::Point2::new(x, lower as i32))
-                                        .unwrap() =
-                                        *self.board.get(na::Point2::new(x, higher as i32)).unwrap();
-                                }
-                            }
-                        }
-                    }
+    /// Starts a race on the seed typed in: a 40 line sprint by the standard
+    /// rules, so it deals the same pieces and plays the same for everyone.
+    fn start_race(&mut self, ctx: &mut Context) {
+        let seed = self.race_seed.trim().to_string();
+        if seed.is_empty() {
+            return;
+        }
+        self.discard_save(ctx);
+        let settings = Settings {
+            handling: self.handling,
+            seed: races::seed(&seed),
+            mode: Mode::Sprint,
+            ..Settings::default()
+        };
+        self.start_round(ctx, Board::default(), settings, Vec::new());
+        self.race = Some(seed);
+    }
+
+    /// Starts the puzzle picked on the menu, or over again after a miss.
+    /// Puzzles aren't recorded; a replay can't set one up.
+    fn start_puzzle(&mut self, ctx: &mut Context) {
+        let puzzle = match self.puzzles.get(self.puzzle) {
+            Some(puzzle) => puzzle,
+            None => return,
+        };
+        self.game = Game::puzzle(puzzle, self.settings());
+        self.frame = 0;
+        self.countdown = COUNTDOWN_FRAMES;
+        self.recording = None;
+        self.versus = None;
+        self.partner = None;
+        self.ghost = None;
+        self.spectators = None;
+        self.fit_window(ctx);
+        self.scene = Scene::Playing;
+    }
+
+    /// Starts the mission picked on the campaign screen, or over again after
+    /// a miss. Like puzzles, missions aren't recorded.
+    fn start_mission(&mut self, ctx: &mut Context) {
+        let mission = match self.missions.get(self.mission) {
+            Some(mission) => mission,
+            None => return,
+        };
+        self.game = Game::mission(mission, self.settings());
+        self.frame = 0;
+        self.countdown = COUNTDOWN_FRAMES;
+        self.recording = None;
+        self.versus = None;
+        self.partner = None;
+        self.ghost = None;
+        self.spectators = None;
+        self.fit_window(ctx);
+        self.scene = Scene::Playing;
+    }
 
-                    self.tetromino = rand::random();
-                    self.facing = rand::thread_rng().gen_range(0, 4);
+    /// Starts a practice round on the piece order and board in the config,
+    /// or the fumen there or on the command line, or over again. Like
+    /// puzzles, practice isn't recorded.
+    fn start_practice(&mut self, ctx: &mut Context) {
+        let (width, height) = (self.config.board_width, self.config.board_height);
+        let fumen = match self.fumen {
+            Some(ref code) => code.as_str(),
+            None => self.config.practice_fumen.as_str(),
+        };
+        let practice = if fumen.is_empty() {
+            Practice::parse(
+                &self.config.practice_queue,
+                &self.config.practice_board,
+                width,
+                height,
+            )
+        } else {
+            fumen::decode(fumen, width, height)
+        };
+        let practice = match practice {
+            Ok(practice) => practice,
+            Err(e) => {
+                eprintln!("Could not start practice: {}", e);
+                return;
+            }
+        };
+        self.game = Game::practice(&practice, self.settings());
+        self.frame = 0;
+        self.countdown = COUNTDOWN_FRAMES;
+        self.recording = None;
+        self.versus = None;
+        self.partner = None;
+        self.ghost = None;
+        self.spectators = None;
+        self.fit_window(ctx);
+        self.scene = Scene::Playing;
+    }
 
-                    let min_x = self.tetromino.min_x(self.facing);
-                    let max_x = self.tetromino.max_x(self.facing);
+    /// Opens the board editor on the setup left there last time, or an
+    /// empty board.
+    fn open_editor(&mut self, ctx: &mut Context) {
+        let (width, height) = (self.config.board_width, self.config.board_height);
+        self.editor
+            .get_or_insert_with(|| Editor::new(width, height));
+        self.preview_editor(ctx);
+        self.scene = Scene::Editor;
+    }
 
-                    self.pos[0] = rand::thread_rng().gen_range(-min_x, 16 - max_x);
+    /// Shows the editor's setup as the round it would start.
+    fn preview_editor(&mut self, ctx: &mut Context) {
+        let practice = match self.editor {
+            Some(ref editor) => editor.practice(),
+            None => return,
+        };
+        self.game = Game::practice(&practice, self.settings());
+        self.frame = 0;
+        self.countdown = 0;
+        self.recording = None;
+        self.versus = None;
+        self.partner = None;
+        self.ghost = None;
+        self.spectators = None;
+        self.fit_window(ctx);
+    }
 
-                    let min_y = self.tetromino.min_y(self.facing);
-                    self.pos[1] = -min_y;
-                }
-                self.updates_so_far += 1;
+    fn editor_key(&mut self, ctx: &mut Context, keycode: KeyCode) {
+        let editor = match self.editor {
+            Some(ref mut editor) => editor,
+            None => return,
+        };
+        editor.note = None;
+        match keycode {
+            KeyCode::Left => editor.move_cursor(-1, 0),
+            KeyCode::Right => editor.move_cursor(1, 0),
+            KeyCode::Up => editor.move_cursor(0, -1),
+            KeyCode::Down => editor.move_cursor(0, 1),
+            KeyCode::Space => editor.toggle(),
+            KeyCode::Delete => editor.erase(),
+            KeyCode::Tab => editor.next_brush(self.config.pieces.pieces()),
+            KeyCode::Back => editor.pop_piece(),
+            KeyCode::PageUp => editor.change_goal(true),
+            KeyCode::PageDown => editor.change_goal(false),
+            KeyCode::Return => return self.play_editor(ctx),
+            KeyCode::F2 => self.save_editor(ctx),
+            KeyCode::Escape => {
+                self.scene = self.menu(MenuItem::Editor);
+                return;
             }
+            _ => return,
         }
-        Ok(())
+        self.preview_editor(ctx);
     }
 
-    fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        graphics::clear(ctx, [0.1, 0.2, 0.3, 1.0].into());
-
-        let blocks = self.tetromino.blocks(self.pos, self.facing);
+    /// Plays the editor's setup as the practice drill, which it stays as
+    /// for retries and the Practice menu item.
+    fn play_editor(&mut self, ctx: &mut Context) {
+        let editor = match self.editor {
+            Some(ref editor) => editor,
+            None => return,
+        };
+        self.config.practice_board = editor.rows();
+        self.config.practice_queue = editor.pieces().iter().map(|piece| piece.symbol()).collect();
+        self.config.practice_fumen.clear();
+        self.fumen = None;
+        self.save_config(ctx);
+        self.start_practice(ctx);
+    }
 
-        for block in blocks {
-            let rectangle = graphics::Mesh::new_rectangle(
-                ctx,
-                graphics::DrawMode::fill(),
-                block_rect(block),
-                self.tetromino.color(),
-            )?;
-            graphics::draw(ctx, &rectangle, (na::Point2::new(0.0, 0.0),))?;
-        }
-
-        // Draw fixed blocks.
-        for (y, row) in self.board.data.iter().enumerate() {
-            for (x, square) in row.iter().enumerate() {
-                let block = na::Point2::new(x as i32, y as i32);
-                if let Some(b) = square {
-                    let rectangle = graphics::Mesh::new_rectangle(
-                        ctx,
-                        graphics::DrawMode::fill(),
-                        block_rect(block),
-                        b.tetromino.color(),
-                    )?;
-                    graphics::draw(ctx, &rectangle, (na::Point2::new(0.0, 0.0),))?;
-                };
+    /// Saves the editor's setup as a puzzle file, and adds it to the
+    /// puzzles to play.
+    fn save_editor(&mut self, ctx: &mut Context) {
+        let name = (self.puzzles.len() + 1..)
+            .map(|n| self.locale.format("editor.name", &[&n]))
+            .find(|name| self.puzzles.iter().all(|puzzle| &puzzle.name != name))
+            .unwrap_or_default();
+        let editor = match self.editor {
+            Some(ref mut editor) => editor,
+            None => return,
+        };
+        let puzzle = match editor.puzzle(name) {
+            Some(puzzle) => puzzle,
+            None => {
+                editor.note = Some(self.locale.get("editor.no_pieces").to_string());
+                return;
             }
+        };
+        match puzzles::save(ctx, &puzzle) {
+            Ok(path) => {
+                let saved = self
+                    .locale
+                    .format("editor.saved", &[&puzzle.name, &path.display()]);
+                editor.note = Some(saved);
+                self.puzzles.push(puzzle);
+            }
+            Err(e) => editor.note = Some(self.locale.format("editor.not_saved", &[&e])),
         }
+    }
 
-        graphics::present(ctx)?;
-        Ok(())
+    /// Prints the board in play and the pieces known to be coming as a
+    /// fumen, to share.
+    fn export_fumen(&self) {
+        let mut queue = Vec::new();
+        // Between pieces, the one that just locked is still the current one.
+        if !self.game.active_blocks().is_empty() {
+            queue.push(self.game.tetromino());
+        }
+        queue.push(self.game.next());
+        queue.extend(self.game.queued());
+        match fumen::encode(&self.game.board, self.game.hold, &queue) {
+            Ok(code) => println!("fumen: {}", code),
+            Err(e) => eprintln!("Could not write a fumen: {}", e),
+        }
     }
 
-    fn key_down_event(
+    /// Whether the round in play is one that R starts over, a puzzle, a
+    /// mission or practice.
+    fn can_retry(&self) -> bool {
+        matches!(
+            self.game.mode(),
+            Mode::Puzzle | Mode::Mission | Mode::Practice
+        )
+    }
+
+    /// Whether the round just over can be played again from the game over
+    /// screen, with the same pieces or new ones. Puzzles and practice are
+    /// retried instead, and the daily challenge is one go.
+    fn can_restart(&self) -> bool {
+        self.versus.is_none() && !self.can_retry() && self.game.mode() != Mode::Ultra
+    }
+
+    /// Plays the round just over again on the same pieces, against a ghost
+    /// of it.
+    fn race_ghost(&mut self, ctx: &mut Context) {
+        let replay = match self.last_replay.clone() {
+            Some(replay) => replay,
+            None => return,
+        };
+        self.start(ctx, replay.settings.mode, false, Some(replay.settings.seed));
+        let cell_size = mini_cell_size(self.config.cell_size);
+        self.ghost = Ghost::new(ctx, &self.config, replay, cell_size)
+            .map_err(|e| eprintln!("Could not set up the ghost: {}", e))
+            .ok();
+        self.fit_window(ctx);
+    }
+
+    fn retry(&mut self, ctx: &mut Context) {
+        match self.game.mode() {
+            Mode::Puzzle => self.start_puzzle(ctx),
+            Mode::Mission => self.start_mission(ctx),
+            _ => self.start_practice(ctx),
+        }
+    }
+
+    /// Whether the restart key can drop the round in play for a fresh one.
+    /// Not against someone, or in the daily challenge, which is one go.
+    fn can_restart_now(&self) -> bool {
+        self.versus.is_none()
+            && self.playback.is_none()
+            && self.demo.is_none()
+            && self.game.mode() != Mode::Ultra
+    }
+
+    /// Drops the round in play for a fresh one in the same mode: the same
+    /// race, puzzle, mission, practice or ghost race, or new pieces
+    /// otherwise.
+    fn restart_now(&mut self, ctx: &mut Context) {
+        self.restart_held = None;
+        if self.race.is_some() {
+            self.start_race(ctx);
+        } else if self.ghost.is_some() {
+            self.race_ghost(ctx);
+        } else if self.can_retry() {
+            self.retry(ctx);
+        } else {
+            self.start(ctx, self.game.mode(), false, None);
+        }
+    }
+
+    /// Whether the round in play can be saved to continue later. There's no
+    /// saving an opponent or a second player, or the pieces a puzzle or
+    /// practice has left, and the daily challenge is played in one go.
+    fn can_save(&self) -> bool {
+        self.versus.is_none()
+            && self.partner.is_none()
+            && !self.can_retry()
+            && self.game.mode() != Mode::Ultra
+    }
+
+    /// Starts a network game once both sides have said hello.
+    fn start_online(&mut self, ctx: &mut Context, found: Match) {
+        self.chat.clear();
+        let board = Board::new(found.local.board_width, found.local.board_height);
+        let remote = Controller::Remote(Remote::new(found.connection));
+        self.start_round(
+            ctx,
+            board,
+            found.local.settings,
+            vec![(remote, found.remote.settings)],
+        );
+        self.spectators = found.spectators;
+        if let Some(ref mut spectators) = self.spectators {
+            spectators.send(1, &Message::Hello(found.local));
+            spectators.send(2, &Message::Hello(found.remote));
+        }
+    }
+
+    /// Starts a round on `board`, with any opponents playing under their
+    /// own settings. Every side deals from the same seed, so gets the same
+    /// pieces.
+    fn start_round(
         &mut self,
-        _ctx: &mut Context,
-        keycode: KeyCode,
-        _keymods: KeyMods,
-        _repeat: bool,
+        ctx: &mut Context,
+        board: Board,
+        settings: Settings,
+        opponents: Vec<(Controller, Settings)>,
     ) {
-        match keycode {
-            KeyCode::Left => {
-                if self.not_overlapping_left() {
-                    self.pos[0] -= 1
-                }
+        self.daily_date = None;
+        self.race = None;
+        let cell_size = mini_cell_size(self.config.cell_size);
+        let opponents: Vec<Opponent> = opponents
+            .into_iter()
+            .filter_map(|(controller, theirs)| {
+                Opponent::new(
+                    ctx,
+                    &self.config,
+                    board.clone(),
+                    theirs,
+                    controller,
+                    cell_size,
+                )
+                .map_err(|e| eprintln!("Could not set up the opponent: {}", e))
+                .ok()
+            })
+            .collect();
+        self.versus = if opponents.is_empty() {
+            None
+        } else {
+            Some(Versus::new(opponents, self.config.targeting, settings.seed))
+        };
+        self.partner = None;
+        self.ghost = None;
+        self.spectators = None;
+        // A replay has no opponent in it, so only single player rounds are
+        // recorded.
+        self.recording = match self.versus {
+            Some(_) => None,
+            None => Some(Replay::new(&board, settings)),
+        };
+        self.game = Game::new(board, settings);
+        self.frame = 0;
+        self.countdown = COUNTDOWN_FRAMES;
+        self.aimed = None;
+        savegame::delete(ctx, Slot::Autosave);
+        self.fit_window(ctx);
+        self.scene = Scene::Playing;
+    }
+
+    fn watch(&mut self, ctx: &mut Context, replay: Replay) {
+        let playback = Playback::new(replay);
+        self.game = playback.new_game();
+        self.frame = 0;
+        self.playback = Some(playback);
+        self.versus = None;
+        self.partner = None;
+        self.ghost = None;
+        self.fit_window(ctx);
+        self.scene = Scene::Replay;
+    }
+
+    /// Starts a game for the computer to play by itself.
+    fn start_demo(&mut self, ctx: &mut Context) {
+        let board = Board::new(self.config.board_width, self.config.board_height);
+        let settings = Settings {
+            handling: self.handling,
+            gravity: self.config.gravity(),
+            start_level: 1,
+            seed: rand::random(),
+            mode: Mode::Endless,
+            pieces: PieceSet::Tetrominoes,
+            rules: Rules::Modern,
+            rotation: None,
+            randomizer: None,
+            lock_reset: LockReset::Move,
+            previews: None,
+            entry_delay: self.config.entry_delay(),
+            line_clear_delay: self.config.line_clear_delay(),
+            garbage_delay: Duration::from_millis(GARBAGE_DELAY_MILLIS),
+            handicap: Handicap::default(),
+        };
+        self.game = Game::new(board, settings);
+        self.frame = 0;
+        self.recording = None;
+        self.versus = None;
+        self.partner = None;
+        self.demo = Some(BotDriver::new(
+            Box::new(Ai::new(self.ai_weights)),
+            Difficulty::default().frames_per_input(),
+        ));
+        self.fit_window(ctx);
+        self.scene = Scene::Demo;
+    }
+
+    /// Back to the title screen, where the idle countdown starts over.
+    fn end_demo(&mut self) {
+        self.demo = None;
+        self.idle_frames = 0;
+        self.scene = Scene::Menu { selected: 0 };
+    }
+
+    /// Switches between a window and borderless fullscreen, and keeps to
+    /// it next time.
+    fn toggle_fullscreen(&mut self, ctx: &mut Context) {
+        // From what the window is, which the command line may have changed.
+        self.config.fullscreen = !is_fullscreen(ctx);
+        let fullscreen_type = if self.config.fullscreen {
+            conf::FullscreenType::Desktop
+        } else {
+            conf::FullscreenType::Windowed
+        };
+        let view = view(ctx);
+        let switched = graphics::set_fullscreen(ctx, fullscreen_type).and_then(|()| {
+            // Back to the size the window was before, in a window. The
+            // resize that follows either way scales the screen to fit.
+            if fullscreen_type == conf::FullscreenType::Windowed {
+                graphics::set_drawable_size(ctx, view.w, view.h)?;
             }
-            KeyCode::Right => {
-                if self.not_overlapping_right() {
-                    self.pos[0] += 1
-                }
+            let (window_width, window_height) = graphics::drawable_size(ctx);
+            letterbox(ctx, view.w, view.h, window_width, window_height)
+        });
+        if let Err(e) = switched {
+            eprintln!("Could not switch to fullscreen: {}", e);
+        }
+        self.save_config(ctx);
+    }
+
+    /// Lays the screen out for the board in play, and the opponents' or
+    /// ghost's mini boards beside it if there are any.
+    fn fit_window(&self, ctx: &mut Context) {
+        let layout = self.layout();
+        let width = match self.versus {
+            Some(ref versus) => layout.width_with_minis(versus.opponents()),
+            None if self.ghost.is_some() => layout.width_with_mini(),
+            None => layout.width(),
+        };
+        fit_size(ctx, width, layout.height());
+    }
+
+    /// Size of a board cell on screen, bigger in modes with big minos.
+    fn cell_size(&self) -> i32 {
+        self.config.cell_size * self.game.mode().scale() as i32
+    }
+
+    /// Where this side's board and panels go.
+    fn layout(&self) -> Layout {
+        Layout::new(&self.game.board, self.cell_size())
+    }
+
+    /// The current time on the game's clock, which only moves forward with
+    /// logic steps.
+    /// How fast the round runs, as a percentage of the fixed rate. Only
+    /// practice can be sped up or slowed down.
+    fn speed_percent(&self) -> u32 {
+        if self.game.mode() == Mode::Practice && self.versus.is_none() {
+            self.config.practice_speed_percent
+        } else {
+            100
+        }
+    }
+
+    fn game_time(&self) -> Instant {
+        self.game.started_at() + replay::frame_time(self.frame)
+    }
+
+    /// Where a point in the window falls on this side's board, in cells.
+    fn board_cell(&self, ctx: &Context, x: f32, y: f32) -> (f32, f32) {
+        let at = screen_point(ctx, x, y);
+        let board = self.layout().board();
+        let cell_size = self.cell_size() as f32;
+        (
+            (at[0] - board[0]) / cell_size,
+            (at[1] - board[1]) / cell_size,
+        )
+    }
+
+    /// The cell of the board a point in the window is over, for the
+    /// editor.
+    fn editor_cell(&self, ctx: &Context, x: f32, y: f32) -> na::Point2<i32> {
+        let (x, y) = self.board_cell(ctx, x, y);
+        na::Point2::new(x.floor() as i32, y.floor() as i32)
+    }
+
+    /// Presses and lets go of `input` straight away, for gestures, whose
+    /// moves shouldn't start auto-repeat.
+    fn tap(&mut self, input: Input) {
+        self.act(Action::Press(input));
+        self.act(Action::Release(input));
+    }
+
+    /// Moves the falling piece to be centred under the pointer, as near as
+    /// whole columns allow, for mouse placement. There's nothing to move
+    /// during the entry delay.
+    fn aim(&mut self) {
+        let x = match self.pointer {
+            Some(x) => x,
+            None => return,
+        };
+        let blocks = self.game.active_blocks();
+        let columns = blocks.iter().map(|block| block[0]);
+        let (left, right) = match (columns.clone().min(), columns.max()) {
+            (Some(left), Some(right)) => (left, right),
+            _ => return,
+        };
+        let width = (right - left + 1) as f32;
+        let shift = (x - width / 2.0).round() as i32 - left;
+        let input = if shift < 0 { Input::Left } else { Input::Right };
+        for _ in 0..shift.abs() {
+            self.tap(input);
+        }
+        self.aimed = Some((self.game.stats().pieces, self.game.tetromino()));
+    }
+
+    /// Applies a player action to the game, recording it for the replay.
+    fn act(&mut self, action: Action) {
+        let now = self.game_time();
+        action.apply(&mut self.game, now);
+        if let Some(ref mut partner) = self.partner {
+            partner.follow(&mut self.game, action, now);
+        }
+        self.input_log.record(self.frame, action);
+        if let Some(ref mut recording) = self.recording {
+            recording.record(self.frame, action);
+        }
+        if let Some(ref mut versus) = self.versus {
+            let message = Message::Act(TimedAction {
+                frame: self.frame,
+                action,
+            });
+            versus.send(&message);
+            if let Some(ref mut spectators) = self.spectators {
+                spectators.send(1, &message);
             }
-            KeyCode::Up => {
-                if self.not_overlapping_rotate() {
-                    self.facing += 1
-                }
+        }
+    }
+
+    /// Applies an action of the second player's to their game in co-op.
+    fn act_partner(&mut self, action: Action) {
+        let now = self.game_time();
+        if let Some(ref mut partner) = self.partner {
+            partner.act(&mut self.game, action, now);
+        }
+    }
+
+    /// Advances the round being played by one logic step.
+    fn tick(&mut self, ctx: &mut Context) {
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            return;
+        }
+        self.frame += 1;
+        self.game.update(self.game_time());
+        self.handle_events();
+        let now = self.game_time();
+        if let Some(ref mut partner) = self.partner {
+            partner.advance(&mut self.game, now);
+        }
+        self.handle_partner_events();
+        if let Some(ref mut recording) = self.recording {
+            recording.record_checksum(self.frame, &self.game);
+        }
+        self.input_log.update(self.frame, &self.game);
+        let piece = (self.game.stats().pieces, self.game.tetromino());
+        if self.config.mouse_placement && self.aimed != Some(piece) {
+            self.aim();
+        }
+        if self
+            .frame
+            .is_multiple_of(AUTOSAVE_SECS * u64::from(replay::FRAMES_PER_SECOND))
+            && self.can_save()
+            && !self.game.game_over
+        {
+            if let Err(e) = savegame::save(ctx, Slot::Autosave, &self.game.snapshot()) {
+                eprintln!("Could not autosave: {}", e);
             }
-            KeyCode::Down => {
-                if self.not_overlapping_down() {
-                    self.pos[1] += 1
+        }
+        self.toasts.extend(self.achievements.take_earned());
+        let attacks = match self.versus {
+            Some(ref mut versus) => {
+                versus.send(&Message::Frame(self.frame));
+                if let Some(ref mut spectators) = self.spectators {
+                    spectators.send(1, &Message::Frame(self.frame));
                 }
+                versus.advance(self.frame)
+            }
+            None => Vec::new(),
+        };
+        if let Some(ref mut ghost) = self.ghost {
+            ghost.advance(self.frame);
+        }
+        self.relay();
+        // Taken as actions so a remote opponent's copy of this side gets
+        // them on the same frame.
+        for rows in attacks {
+            self.act(Action::Garbage(rows));
+        }
+        if self.game.game_over || self.outcome() != Outcome::Undecided {
+            self.finish(ctx);
+        }
+    }
+
+    /// Passes on what the other player sent to anyone looking on, and lets
+    /// in new spectators.
+    fn relay(&mut self) {
+        let received = match self.versus {
+            Some(ref mut versus) => versus.take_received(),
+            None => return,
+        };
+        for message in &received {
+            if let (Message::Say(text), true) = (message, self.config.chat) {
+                self.chat.add(text, false);
+            }
+        }
+        if let Some(ref mut spectators) = self.spectators {
+            for message in received {
+                spectators.send(2, &message);
             }
-            KeyCode::Space => {
-                while self.not_overlapping_down() {
-                    self.pos[1] += 1
+            spectators.poll();
+        }
+    }
+
+    /// Sends a line of chat to the other player, and to anyone looking on.
+    fn say(&mut self, text: &str) {
+        let message = Message::Say(text.to_string());
+        if let Some(ref mut versus) = self.versus {
+            versus.send(&message);
+        }
+        if let Some(ref mut spectators) = self.spectators {
+            spectators.send(1, &message);
+        }
+        self.chat.add(text, true);
+    }
+
+    /// Whether the OS key repeat of a held `keycode` is passed on: only for
+    /// inputs in play the game doesn't repeat itself. Moves have the
+    /// game's auto-repeat instead, and so does soft drop where it has a
+    /// rate of its own; without one, holding it drops a row each repeat.
+    fn takes_os_repeat(&self, keycode: KeyCode) -> bool {
+        if self.scene != Scene::Playing {
+            return false;
+        }
+        let partner = self.partner.as_ref().and_then(|p| p.input_for(keycode));
+        match partner.or_else(|| self.keymap.input_for(keycode)) {
+            Some(Input::Left) | Some(Input::Right) => false,
+            Some(Input::SoftDrop) => self.game.handling.soft_drop.is_none(),
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    /// Handles `keycode` for the chat of a network game, returning whether
+    /// it was the chat's: F2 starts a line, which takes every key until
+    /// it's sent or dropped, and F5 to F8 send the stock ones.
+    fn chat_key(&mut self, keycode: KeyCode) -> bool {
+        if self.chat.typing().is_some() {
+            match keycode {
+                KeyCode::Return => {
+                    if let Some(text) = self.chat.finish() {
+                        self.say(&text);
+                    }
                 }
+                KeyCode::Escape => self.chat.stop_typing(),
+                KeyCode::Back => self.chat.backspace(),
+                _ => (),
             }
-            _ => (),
+            return true;
         }
+        let emote = match keycode {
+            KeyCode::F2 => {
+                self.chat.start_typing();
+                return true;
+            }
+            KeyCode::F5 => chat::EMOTES[0],
+            KeyCode::F6 => chat::EMOTES[1],
+            KeyCode::F7 => chat::EMOTES[2],
+            KeyCode::F8 => chat::EMOTES[3],
+            _ => return false,
+        };
+        self.say(emote);
+        true
     }
-}
 
-pub trait Rotate90 {
-    fn rotate_90(self, facing: u8) -> Self;
-}
+    /// Runs the game being looked on, sizing the window to both boards once
+    /// they're known.
+    fn spectate_tick(&mut self, ctx: &mut Context) {
+        if let Some(ref mut spectator) = self.spectator {
+            let ready = spectator.is_ready();
+            spectator.update(ctx, &self.config);
+            if let (false, true, Some(board)) = (ready, spectator.is_ready(), spectator.board()) {
+                fit_boards(ctx, board, 2, self.config.cell_size);
+            }
+        }
+    }
+
+    /// Waits on `addr` for another player to join a network game set up
+    /// from the config.
+    fn host(&mut self, addr: &str) -> std::io::Result<()> {
+        let hello = Hello {
+            board_width: self.config.board_width,
+            board_height: self.config.board_height,
+            settings: Settings {
+                handicap: self.config.handicap,
+                ..self.settings()
+            },
+        };
+        self.lobby = Some(Lobby::host(addr, hello)?);
+        self.scene = Scene::Lobby;
+        Ok(())
+    }
 
-impl<T: na::Scalar + Neg<Output = T>> Rotate90 for Vector2<T> {
-    fn rotate_90(self, facing: u8) -> Self {
-        match facing % 4 {
-            0 => self,
-            1 => Vector2::new(-self[1], self[0]),
-            2 => Vector2::new(-self[0], -self[1]),
-            3 => Vector2::new(self[1], -self[0]),
-            _ => unreachable!(),
+    /// Joins the network game at `addr`, an address or a room code.
+    fn join(&mut self, addr: &str) -> std::io::Result<()> {
+        self.lobby = Some(Lobby::join(addr, self.handling, self.config.handicap)?);
+        self.scene = Scene::Lobby;
+        Ok(())
+    }
+
+    /// Moves the network handshake along, starting the round once it's
+    /// done.
+    fn lobby_tick(&mut self, ctx: &mut Context) {
+        let polled = match self.lobby {
+            Some(ref mut lobby) => lobby.poll(),
+            None => return,
+        };
+        match polled {
+            Ok(Some(found)) => {
+                self.lobby = None;
+                self.start_online(ctx, found);
+            }
+            Ok(None) => (),
+            Err(e) => {
+                eprintln!("Could not start the network game: {}", e);
+                self.lobby = None;
+                self.scene = Scene::Menu { selected: 0 };
+            }
         }
     }
-}
 
-fn block_rect(block: Point2<i32>) -> graphics::Rect {
-    graphics::Rect::new(
-        GRID_CELL_SIZE.0 as f32 * block[0] as f32,
-        GRID_CELL_SIZE.1 as f32 * block[1] as f32,
-        GRID_CELL_SIZE.0 as f32,
-        GRID_CELL_SIZE.1 as f32,
-    )
-}
+    /// How the versus round stands; always undecided without an opponent.
+    fn outcome(&self) -> Outcome {
+        let over_at = if self.game.game_over {
+            Some(self.frame)
+        } else {
+            None
+        };
+        self.versus
+            .as_ref()
+            .map_or(Outcome::Undecided, |versus| versus.outcome(over_at))
+    }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-enum Tetromino {
-    IBlock,
-    OBlock,
-    TBlock,
-    SBlock,
-    ZBlock,
-    JBlock,
-    LBlock,
-}
+    /// Advances the replay being watched by one logic step, feeding in the
+    /// actions recorded for it.
+    fn replay_tick(&mut self) {
+        if self.game.game_over {
+            return;
+        }
+        let now = self.game_time();
+        if let Some(ref mut playback) = self.playback {
+            for action in playback.actions_at(self.frame) {
+                action.apply(&mut self.game, now);
+            }
+        }
+        self.frame += 1;
+        self.game.update(self.game_time());
+        self.handle_events();
+        if let Some(ref mut playback) = self.playback {
+            playback.check_checksum(self.frame, &self.game);
+        }
+        if self.game.game_over {
+            self.audio.stop_music();
+        }
+    }
 
-impl Tetromino {
-    fn block_offsets(self) -> Vec<Vector2<i32>> {
-        match self {
-            Tetromino::IBlock => vec![
-                Vector2::new(0, 0),
-                Vector2::new(1, 0),
-                Vector2::new(2, 0),
-                Vector2::new(-1, 0),
-            ],
-            Tetromino::OBlock => vec![
-                Vector2::new(0, 0),
-                Vector2::new(1, 0),
-                Vector2::new(0, 1),
-                Vector2::new(1, 1),
-            ],
-            Tetromino::TBlock => vec![
-                Vector2::new(0, 0),
-                Vector2::new(0, 1),
-                Vector2::new(-1, 0),
-                Vector2::new(1, 0),
-            ],
-            Tetromino::SBlock => vec![
-                Vector2::new(0, 0),
-                Vector2::new(0, 1),
-                Vector2::new(1, 0),
-                Vector2::new(-1, 1),
-            ],
-            Tetromino::ZBlock => vec![
-                Vector2::new(0, 0),
-                Vector2::new(0, 1),
-                Vector2::new(-1, 0),
-                Vector2::new(1, 1),
-            ],
-            Tetromino::LBlock => vec![
-                Vector2::new(0, 0),
-                Vector2::new(0, 1),
-                Vector2::new(0, -1),
-                Vector2::new(-1, -1),
-            ],
-            Tetromino::JBlock => vec![
-                Vector2::new(0, 0),
-                Vector2::new(0, 1),
-                Vector2::new(0, -1),
-                Vector2::new(1, -1),
-            ],
-        }
-    }
-
-    fn blocks(self, pos: Point2<i32>, facing: u8) -> Vec<Point2<i32>> {
-        self.block_offsets()
-            .iter()
-            .map(|block_vector| pos + block_vector.rotate_90(facing))
-            .collect()
+    /// Advances the demo by one logic step. It plays silently and goes back
+    /// to the title screen when it tops out.
+    fn demo_tick(&mut self) {
+        self.frame += 1;
+        let now = self.game_time();
+        if let Some(ref mut demo) = self.demo {
+            demo.step(&mut self.game, now);
+        }
+        self.game.update(now);
+        self.game.take_events();
+        if self.game.game_over {
+            self.end_demo();
+        }
     }
-    fn color(self) -> graphics::Color {
-        match self {
-            Tetromino::IBlock => graphics::Color::from_rgb(66, 241, 244),
-            Tetromino::OBlock => graphics::Color::from_rgb(233, 237, 42),
-            Tetromino::TBlock => graphics::Color::from_rgb(182, 42, 237),
-            Tetromino::SBlock => graphics::Color::from_rgb(88, 237, 42),
-            Tetromino::ZBlock => graphics::Color::from_rgb(226, 50, 27),
-            Tetromino::JBlock => graphics::Color::from_rgb(22, 75, 221),
-            Tetromino::LBlock => graphics::Color::from_rgb(219, 108, 17),
+
+    /// Whether the tutor is watching: with the option on, in a round the
+    /// player is playing alone.
+    fn tutoring(&self) -> bool {
+        self.config.tutor && self.versus.is_none() && self.playback.is_none() && self.demo.is_none()
+    }
+
+    /// Whether the round in play is timed to each split, played alone in a
+    /// mode run for time or lines.
+    fn timing_splits(&self) -> bool {
+        splits::is_split(self.game.mode())
+            && self.versus.is_none()
+            && self.playback.is_none()
+            && self.demo.is_none()
+    }
+
+    /// Passes what happened this step on to everything that reacts to it.
+    fn handle_events(&mut self) {
+        // Mods stay out of rounds against or with someone, who wouldn't be
+        // playing by them, and out of the demo.
+        let modded = self.versus.is_none() && self.partner.is_none() && self.demo.is_none();
+        let tutoring = self.tutoring();
+        let splits = self.timing_splits();
+        let hints = self.config.finesse_hints && self.playback.is_none() && self.demo.is_none();
+        let mut effects = Effects {
+            particles: &mut self.particles,
+            trails: &mut self.trails,
+            shake: &mut self.shake,
+            config: &self.config,
+        };
+        let mut observers: Vec<&mut dyn Observer> =
+            vec![&mut self.audio, &mut self.popups, &mut self.announcer];
+        if !self.config.reduced_motion {
+            observers.push(&mut effects);
+        }
+        if let Some(ref mut versus) = self.versus {
+            observers.push(versus);
+        }
+        if let Some(ref mut recording) = self.recording {
+            observers.push(recording);
+        }
+        match self.playback {
+            Some(ref mut playback) => observers.push(playback),
+            // Watching a replay earns nothing.
+            None => observers.push(&mut self.achievements),
+        }
+        if modded {
+            observers.push(&mut self.mods);
+        }
+        if tutoring {
+            observers.push(&mut self.tutor);
         }
+        if splits {
+            observers.push(&mut self.split_timer);
+        }
+        if hints {
+            observers.push(&mut self.finesse);
+        }
+        events::dispatch(&mut self.game, self.frame, &mut observers);
+        if let Some(fault) = self.finesse.take_fault() {
+            self.finesse_hint = Some((fault, self.game_time()));
+        }
+        if modded {
+            self.mods.apply(&mut self.game);
+        }
+        self.audio.update_music(&self.game);
     }
-    fn min_x(self, facing: u8) -> i32 {
-        self.blocks(na::Point2::new(0, 0), facing)
-            .into_iter()
-            .map(|block| block[0])
-            .min()
-            .unwrap()
+
+    /// Passes what the second player's game turned up in co-op on to the
+    /// sound and effects; the rest is the player's own.
+    fn handle_partner_events(&mut self) {
+        let partner = match self.partner {
+            Some(ref mut partner) => partner,
+            None => return,
+        };
+        let mut effects = Effects {
+            particles: &mut self.particles,
+            trails: &mut self.trails,
+            shake: &mut self.shake,
+            config: &self.config,
+        };
+        let mut observers: Vec<&mut dyn Observer> = vec![&mut self.audio, &mut self.popups];
+        if !self.config.reduced_motion {
+            observers.push(&mut effects);
+        }
+        events::dispatch(&mut partner.game, self.frame, &mut observers);
     }
-    fn max_x(self, facing: u8) -> i32 {
-        self.blocks(na::Point2::new(0, 0), facing)
-            .into_iter()
-            .map(|block| block[0])
-            .max()
-            .unwrap()
+
+    /// Picks up the game in `slot` where it was left. The kept game goes
+    /// once it's picked up, but the autosave stays until the next one
+    /// replaces it, in case the game goes down again straight away.
+    fn continue_game(&mut self, ctx: &mut Context, slot: Slot) {
+        match savegame::load(ctx, slot) {
+            Ok(snapshot) => {
+                let now = Instant::now();
+                self.game = Game::restore(snapshot, self.handling, now);
+                self.game.resume(now);
+                self.frame = 0;
+                self.countdown = COUNTDOWN_FRAMES;
+                self.recording = None;
+                self.versus = None;
+                self.partner = None;
+                self.ghost = None;
+                self.fit_window(ctx);
+                self.scene = Scene::Playing;
+            }
+            Err(e) => eprintln!("Could not load saved game: {}", e),
+        }
+        if slot == Slot::Kept {
+            self.discard_save(ctx);
+        }
     }
-    fn min_y(self, facing: u8) -> i32 {
-        self.blocks(na::Point2::new(0, 0), facing)
-            .into_iter()
-            .map(|block| block[1])
-            .min()
-            .unwrap()
+
+    /// Saves the game in progress so it can be continued from the menu,
+    /// which takes over from the autosave.
+    fn save_game(&mut self, ctx: &mut Context) {
+        match savegame::save(ctx, Slot::Kept, &self.game.snapshot()) {
+            Ok(()) => {
+                self.saved = true;
+                savegame::delete(ctx, Slot::Autosave);
+            }
+            Err(e) => eprintln!("Could not save game: {}", e),
+        }
     }
-    pub fn max_y(self, facing: u8) -> i32 {
-        self.blocks(na::Point2::new(0, 0), facing)
-            .into_iter()
-            .map(|block| block[1])
-            .max()
-            .unwrap()
+
+    fn discard_save(&mut self, ctx: &mut Context) {
+        if self.saved {
+            savegame::delete(ctx, Slot::Kept);
+            self.saved = false;
+        }
     }
-}
 
-impl Distribution<Tetromino> for Standard {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Tetromino {
-        match rng.gen_range(0, 7) {
-            0 => Tetromino::IBlock,
-            1 => Tetromino::OBlock,
-            2 => Tetromino::TBlock,
-            3 => Tetromino::SBlock,
-            4 => Tetromino::ZBlock,
-            5 => Tetromino::JBlock,
-            6 => Tetromino::LBlock,
-            _ => unreachable!(),
+    fn menu_items(&self) -> Vec<MenuItem> {
+        let mut items = vec![
+            MenuItem::Start,
+            MenuItem::Daily,
+            MenuItem::Race,
+            MenuItem::Marathon,
+            MenuItem::Sprint,
+            MenuItem::Zen,
+            MenuItem::Invisible,
+            MenuItem::Big,
+            MenuItem::TwentyG,
+            MenuItem::Master,
+            MenuItem::Campaign,
+            MenuItem::Puzzle,
+            MenuItem::Practice,
+            MenuItem::Editor,
+            MenuItem::Versus,
+            MenuItem::Coop,
+            MenuItem::Host,
+            MenuItem::Join,
+            MenuItem::Local,
+            MenuItem::HighScores,
+            MenuItem::Stats,
+            MenuItem::Packs,
+            MenuItem::Options,
+            MenuItem::Quit,
+        ];
+        if self.saved {
+            items.insert(0, MenuItem::Continue);
         }
+        if self.missions.is_empty() {
+            items.retain(|&item| item != MenuItem::Campaign);
+        }
+        if self.puzzles.is_empty() {
+            items.retain(|&item| item != MenuItem::Puzzle);
+        }
+        items
     }
-}
 
-pub fn main() -> GameResult {
-    let (ctx, events_loop) = &mut ggez::ContextBuilder::new("Tetris?", "Sam")
-        .window_setup(conf::WindowSetup::default().title("Tetris?"))
-        .window_mode(
-            conf::WindowMode::default().dimensions(SCREEN_SIZE.0 as f32, SCREEN_SIZE.1 as f32),
-        )
-        .build()?;
+    /// The menu with `item` selected.
+    fn menu(&self, item: MenuItem) -> Scene {
+        let selected = self
+            .menu_items()
+            .iter()
+            .position(|&i| i == item)
+            .unwrap_or(0);
+        Scene::Menu { selected }
+    }
+
+    fn menu_key(&mut self, ctx: &mut Context, keycode: KeyCode, selected: usize) {
+        let items = self.menu_items();
+        match keycode {
+            KeyCode::Up => {
+                let selected = (selected + items.len() - 1) % items.len();
+                self.scene = Scene::Menu { selected };
+            }
+            KeyCode::Down => {
+                let selected = (selected + 1) % items.len();
+                self.scene = Scene::Menu { selected };
+            }
+            KeyCode::Left if items[selected] == MenuItem::Marathon => {
+                self.pick_marathon_level(ctx, (self.marathon_level - 1).max(1));
+            }
+            KeyCode::Right if items[selected] == MenuItem::Marathon => {
+                self.pick_marathon_level(ctx, (self.marathon_level + 1).min(MARATHON_LEVELS));
+            }
+            KeyCode::Return | KeyCode::Space => match items[selected] {
+                MenuItem::Continue => self.continue_game(ctx, Slot::Kept),
+                MenuItem::Start => self.start(ctx, Mode::Endless, false, None),
+                MenuItem::Daily => self.start_daily(ctx),
+                MenuItem::Race => self.scene = Scene::RaceSetup,
+                MenuItem::Marathon => self.start(ctx, Mode::Marathon, false, None),
+                MenuItem::Sprint => self.start(ctx, Mode::Sprint, false, None),
+                MenuItem::Zen => self.start(ctx, Mode::Zen, false, None),
+                MenuItem::Invisible => self.start(ctx, Mode::Invisible, false, None),
+                MenuItem::Big => self.start(ctx, Mode::Big, false, None),
+                MenuItem::TwentyG => self.start(ctx, Mode::TwentyG, false, None),
+                MenuItem::Master => self.start(ctx, Mode::Master, false, None),
+                MenuItem::Campaign => {
+                    self.scene = Scene::Missions {
+                        selected: self.mission,
+                    }
+                }
+                MenuItem::Puzzle => {
+                    self.scene = Scene::Puzzles {
+                        selected: self.puzzle,
+                    }
+                }
+                MenuItem::Practice => self.start_practice(ctx),
+                MenuItem::Editor => self.open_editor(ctx),
+                MenuItem::Versus => {
+                    self.scene = Scene::VersusSetup {
+                        selected: VERSUS_ITEMS - 2,
+                    }
+                }
+                MenuItem::Coop => self.start(ctx, Mode::Coop, false, None),
+                MenuItem::Host => {
+                    let addr = format!("0.0.0.0:{}", net::DEFAULT_PORT);
+                    if let Err(e) = self.host(&addr) {
+                        eprintln!("Could not host a network game: {}", e);
+                    }
+                }
+                MenuItem::Join => self.scene = Scene::Join,
+                MenuItem::Local => match Browser::new() {
+                    Ok(browser) => {
+                        self.browser = Some(browser);
+                        self.scene = Scene::LocalGames { selected: 0 };
+                    }
+                    Err(e) => eprintln!("Could not look for local games: {}", e),
+                },
+                MenuItem::HighScores => {
+                    self.scene = Scene::HighScores {
+                        mode: Mode::Endless,
+                    }
+                }
+                MenuItem::Stats => self.scene = Scene::Stats,
+                MenuItem::Packs => self.scene = Scene::Packs { selected: 0 },
+                MenuItem::Options => self.scene = Scene::Options { selected: 0 },
+                MenuItem::Quit => ggez::quit(ctx),
+            },
+            KeyCode::Escape => ggez::quit(ctx),
+            _ => (),
+        }
+    }
+
+    /// Picks the level marathons start at, and keeps it for next time.
+    fn pick_marathon_level(&mut self, ctx: &mut Context, level: u32) {
+        self.marathon_level = level;
+        self.config.marathon_level = level;
+        self.save_config(ctx);
+    }
+
+    /// Picks the puzzle at `at` to play, and keeps it for next time.
+    fn pick_puzzle(&mut self, ctx: &mut Context, at: usize) {
+        self.puzzle = at;
+        self.config.puzzle = self.puzzles[at].name.clone();
+        self.save_config(ctx);
+    }
+
+    fn options_key(&mut self, ctx: &mut Context, keycode: KeyCode, selected: usize) {
+        match keycode {
+            KeyCode::Up => {
+                let selected = (selected + OPTION_ITEMS - 1) % OPTION_ITEMS;
+                self.scene = Scene::Options { selected };
+            }
+            KeyCode::Down => {
+                let selected = (selected + 1) % OPTION_ITEMS;
+                self.scene = Scene::Options { selected };
+            }
+            KeyCode::Left if selected == 0 => self.switch_profile(ctx, false),
+            KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 0 => {
+                self.switch_profile(ctx, true)
+            }
+            KeyCode::Return | KeyCode::Space if selected == 1 => {
+                self.scene = Scene::Handling { selected: 0 }
+            }
+            KeyCode::Left if selected == 3 || selected == 4 => {
+                self.adjust_volume(ctx, selected, false)
+            }
+            KeyCode::Right if selected == 3 || selected == 4 => {
+                self.adjust_volume(ctx, selected, true)
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 5 => {
+                self.config.pieces = match self.config.pieces {
+                    PieceSet::Tetrominoes => PieceSet::Pentominoes,
+                    // Only offered once `pieces.toml` has added some.
+                    PieceSet::Pentominoes if !PieceSet::Custom.pieces().is_empty() => {
+                        PieceSet::Custom
+                    }
+                    PieceSet::Pentominoes | PieceSet::Custom => PieceSet::Tetrominoes,
+                };
+                self.save_config(ctx);
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 6 => {
+                self.config.rules = match self.config.rules {
+                    Rules::Modern => Rules::Classic,
+                    Rules::Classic => Rules::Modern,
+                };
+                self.save_config(ctx);
+            }
+            KeyCode::Left if selected == 7 => self.switch_rotation(ctx, false),
+            KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 7 => {
+                self.switch_rotation(ctx, true)
+            }
+            KeyCode::Left if selected == 8 => self.switch_randomizer(ctx, false),
+            KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 8 => {
+                self.switch_randomizer(ctx, true)
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 9 => {
+                self.config.lock_reset = match self.config.lock_reset {
+                    LockReset::Move => LockReset::Infinite,
+                    LockReset::Infinite => LockReset::Step,
+                    LockReset::Step => LockReset::Move,
+                };
+                self.save_config(ctx);
+            }
+            KeyCode::Left if selected == 10 || selected == 11 => {
+                self.adjust_delay(ctx, selected, false)
+            }
+            KeyCode::Right if selected == 10 || selected == 11 => {
+                self.adjust_delay(ctx, selected, true)
+            }
+            KeyCode::Left if selected == 12 => self.switch_theme(ctx, false),
+            KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 12 => {
+                self.switch_theme(ctx, true)
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 13 => {
+                self.config.marks = match self.config.marks {
+                    Marks::Off => Marks::Letters,
+                    Marks::Letters => Marks::Patterns,
+                    Marks::Patterns => Marks::Off,
+                };
+                self.rebuild_renderer(ctx);
+                self.save_config(ctx);
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 14 => {
+                self.config.high_contrast = !self.config.high_contrast;
+                self.rebuild_renderer(ctx);
+                self.save_config(ctx);
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 15 => {
+                self.config.screen_shake = !self.config.screen_shake;
+                self.save_config(ctx);
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 16 => {
+                self.config.reduced_motion = !self.config.reduced_motion;
+                self.rebuild_renderer(ctx);
+                self.save_config(ctx);
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 17 => {
+                self.toggle_fullscreen(ctx)
+            }
+            KeyCode::Escape => self.scene = self.menu(MenuItem::Options),
+            KeyCode::Return | KeyCode::Space if selected == 2 => {
+                let muted = !self.audio.muted();
+                self.audio.set_muted(muted);
+                self.config.muted = muted;
+                self.save_config(ctx);
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 18 => {
+                self.config.mouse_placement = !self.config.mouse_placement;
+                self.save_config(ctx);
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 19 => {
+                self.config.chat = !self.config.chat;
+                self.save_config(ctx);
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 20 => {
+                self.config.tutor = !self.config.tutor;
+                self.save_config(ctx);
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 21 => {
+                self.config.finesse_hints = !self.config.finesse_hints;
+                self.save_config(ctx);
+            }
+            KeyCode::Left if selected == 22 => self.switch_language(ctx, false),
+            KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 22 => {
+                self.switch_language(ctx, true)
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 23 => {
+                self.config.vsync = !self.config.vsync;
+                if let Err(e) = save_window_conf(ctx, self.config.vsync) {
+                    eprintln!("Could not save the window settings: {}", e);
+                }
+                self.save_config(ctx);
+            }
+            KeyCode::Left if selected == 24 => self.switch_fps_cap(ctx, false),
+            KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 24 => {
+                self.switch_fps_cap(ctx, true)
+            }
+            KeyCode::Left if selected == 25 => self.switch_sound_pack(ctx, false),
+            KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 25 => {
+                self.switch_sound_pack(ctx, true)
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 26 => {
+                self.config.pan_sounds = !self.config.pan_sounds;
+                self.audio.set_panned(self.config.pan_sounds);
+                self.save_config(ctx);
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::Space if selected == 27 => {
+                self.config.pause_on_focus_loss = !self.config.pause_on_focus_loss;
+                self.save_config(ctx);
+            }
+            KeyCode::Return | KeyCode::Space if selected == 28 => {
+                self.scene = Scene::Controls {
+                    selected: 0,
+                    waiting: false,
+                }
+            }
+            KeyCode::Return | KeyCode::Space if selected == 29 => {
+                self.scene = self.menu(MenuItem::Options)
+            }
+            _ => (),
+        }
+    }
+
+    /// Steps through the rotation systems, and back round to the rules'
+    /// own.
+    fn switch_rotation(&mut self, ctx: &mut Context, forward: bool) {
+        let systems = RotationSystem::ALL;
+        let at = self
+            .config
+            .rotation
+            .and_then(|system| systems.iter().position(|&s| s == system));
+        self.config.rotation = match (at, forward) {
+            (None, true) => Some(systems[0]),
+            (None, false) => systems.last().copied(),
+            (Some(at), true) => systems.get(at + 1).copied(),
+            (Some(0), false) => None,
+            (Some(at), false) => Some(systems[at - 1]),
+        };
+        self.save_config(ctx);
+    }
+
+    /// Steps through the frame rate limits, and back round to none.
+    fn switch_fps_cap(&mut self, ctx: &mut Context, forward: bool) {
+        let at = self
+            .config
+            .fps_cap
+            .and_then(|fps| FPS_CAPS.iter().position(|&cap| cap == fps));
+        self.config.fps_cap = match (at, forward) {
+            (None, true) => Some(FPS_CAPS[0]),
+            (None, false) => FPS_CAPS.last().copied(),
+            (Some(at), true) => FPS_CAPS.get(at + 1).copied(),
+            (Some(0), false) => None,
+            (Some(at), false) => Some(FPS_CAPS[at - 1]),
+        };
+        self.save_config(ctx);
+    }
+
+    /// Steps through the randomizers, and back round to the rules' own.
+    fn switch_randomizer(&mut self, ctx: &mut Context, forward: bool) {
+        let randomizers = Randomizer::ALL;
+        let at = self
+            .config
+            .randomizer
+            .and_then(|randomizer| randomizers.iter().position(|&r| r == randomizer));
+        self.config.randomizer = match (at, forward) {
+            (None, true) => Some(randomizers[0]),
+            (None, false) => randomizers.last().copied(),
+            (Some(at), true) => randomizers.get(at + 1).copied(),
+            (Some(0), false) => None,
+            (Some(at), false) => Some(randomizers[at - 1]),
+        };
+        self.save_config(ctx);
+    }
+
+    fn switch_profile(&mut self, ctx: &mut Context, forward: bool) {
+        self.config.switch_profile(forward);
+        self.handling = self.config.handling();
+        self.save_config(ctx);
+    }
+
+    fn handling_key(&mut self, ctx: &mut Context, keycode: KeyCode, selected: usize) {
+        let up = keycode == KeyCode::Right;
+        let step = |millis: u64| {
+            if up {
+                millis + HANDLING_STEP_MILLIS
+            } else {
+                millis.saturating_sub(HANDLING_STEP_MILLIS)
+            }
+        };
+        let profile = self.config.profile_mut();
+        match keycode {
+            KeyCode::Up => {
+                let selected = (selected + HANDLING_ITEMS - 1) % HANDLING_ITEMS;
+                self.scene = Scene::Handling { selected };
+                return;
+            }
+            KeyCode::Down => {
+                let selected = (selected + 1) % HANDLING_ITEMS;
+                self.scene = Scene::Handling { selected };
+                return;
+            }
+            KeyCode::Left | KeyCode::Right if selected == 0 => {
+                profile.das_millis = step(profile.das_millis)
+            }
+            KeyCode::Left | KeyCode::Right if selected == 1 => {
+                profile.arr_millis = step(profile.arr_millis)
+            }
+            // Right goes faster, from a row a press to straight down.
+            KeyCode::Left | KeyCode::Right if selected == 2 => {
+                profile.soft_drop_millis = match profile.soft_drop_millis {
+                    None if up => Some(SLOWEST_SOFT_DROP_MILLIS),
+                    Some(millis) if up => Some(millis.saturating_sub(HANDLING_STEP_MILLIS)),
+                    Some(millis) if millis < SLOWEST_SOFT_DROP_MILLIS => {
+                        Some(millis + HANDLING_STEP_MILLIS)
+                    }
+                    _ => None,
+                }
+            }
+            KeyCode::Left | KeyCode::Right if selected == 3 => {
+                profile.dcd_millis = step(profile.dcd_millis)
+            }
+            KeyCode::Return | KeyCode::Space if selected == 4 => self.config.add_profile(),
+            KeyCode::Escape => {
+                self.scene = Scene::Options { selected: 1 };
+                return;
+            }
+            KeyCode::Return | KeyCode::Space if selected == 5 => {
+                self.scene = Scene::Options { selected: 1 };
+                return;
+            }
+            _ => return,
+        }
+        self.handling = self.config.handling();
+        self.save_config(ctx);
+    }
+
+    fn versus_setup_key(&mut self, ctx: &mut Context, keycode: KeyCode, selected: usize) {
+        let up = keycode == KeyCode::Right;
+        match keycode {
+            KeyCode::Up => {
+                let selected = (selected + VERSUS_ITEMS - 1) % VERSUS_ITEMS;
+                self.scene = Scene::VersusSetup { selected };
+            }
+            KeyCode::Down => {
+                let selected = (selected + 1) % VERSUS_ITEMS;
+                self.scene = Scene::VersusSetup { selected };
+            }
+            KeyCode::Left | KeyCode::Right if selected < HANDICAP_LINES => {
+                step_handicap(&mut self.config.handicap, selected, up);
+                self.save_config(ctx);
+            }
+            KeyCode::Left | KeyCode::Right if selected == DIFFICULTY_ITEM => {
+                let all = Difficulty::ALL;
+                let at = all.iter().position(|&d| d == self.config.cpu_difficulty);
+                let at = match (at, up) {
+                    (Some(at), true) => (at + 1).min(all.len() - 1),
+                    (Some(at), false) => at.saturating_sub(1),
+                    (None, _) => 0,
+                };
+                self.config.cpu_difficulty = all[at];
+                self.save_config(ctx);
+            }
+            KeyCode::Left | KeyCode::Right if selected <= 2 * HANDICAP_LINES => {
+                let line = selected - DIFFICULTY_ITEM - 1;
+                step_handicap(&mut self.config.cpu_handicap, line, up);
+                self.save_config(ctx);
+            }
+            KeyCode::Left | KeyCode::Right if selected == CPUS_ITEM => {
+                let cpus = self.config.cpus.clamp(1, MAX_CPUS);
+                self.config.cpus = if up {
+                    (cpus + 1).min(MAX_CPUS)
+                } else {
+                    (cpus - 1).max(1)
+                };
+                self.save_config(ctx);
+            }
+            KeyCode::Left | KeyCode::Right if selected == TARGETING_ITEM => {
+                let all = Targeting::ALL;
+                let at = all.iter().position(|&t| t == self.config.targeting);
+                let at = match (at, up) {
+                    (Some(at), true) => (at + 1) % all.len(),
+                    (Some(at), false) => (at + all.len() - 1) % all.len(),
+                    (None, _) => 0,
+                };
+                self.config.targeting = all[at];
+                self.save_config(ctx);
+            }
+            KeyCode::Return | KeyCode::Space if selected == VERSUS_ITEMS - 2 => {
+                self.start(ctx, Mode::Endless, true, None);
+            }
+            KeyCode::Return | KeyCode::Space if selected == VERSUS_ITEMS - 1 => {
+                self.scene = self.menu(MenuItem::Versus);
+            }
+            KeyCode::Escape => self.scene = self.menu(MenuItem::Versus),
+            _ => (),
+        }
+    }
+
+    /// The lines of the versus setup screen for `handicap`, played by
+    /// `who`.
+    fn handicap_lines(&self, who: &str, handicap: Handicap) -> [String; HANDICAP_LINES] {
+        let locale = &self.locale;
+        let preview = match handicap.preview {
+            Some(shown) => shown.to_string(),
+            None => locale.get("versus_setup.preview_all").to_string(),
+        };
+        [
+            locale.format("versus_setup.garbage", &[&who, &handicap.garbage_rows]),
+            locale.format("versus_setup.preview", &[&who, &preview]),
+            locale.format("versus_setup.speed", &[&who, &handicap.speed_percent]),
+            locale.format("versus_setup.attack", &[&who, &handicap.attack_percent]),
+        ]
+    }
+
+    /// The theme the colors in use come from, if they haven't been changed
+    /// by hand.
+    fn theme(&self) -> Option<usize> {
+        self.themes
+            .iter()
+            .position(|theme| theme.colors == self.config.colors)
+    }
+
+    /// Swaps in the next theme, or the one before, and keeps it.
+    fn switch_theme(&mut self, ctx: &mut Context, forward: bool) {
+        let count = self.themes.len();
+        if count == 0 {
+            return;
+        }
+        let at = match (self.theme(), forward) {
+            (Some(at), true) => (at + 1) % count,
+            (Some(at), false) => (at + count - 1) % count,
+            (None, true) => 0,
+            (None, false) => count - 1,
+        };
+        self.config.colors = self.themes[at].colors.clone();
+        self.config.backdrop = self.themes[at].backdrop.clone();
+        self.backdrop = Backdrop::load(ctx, &self.config.backdrop);
+        self.rebuild_renderer(ctx);
+        self.save_config(ctx);
+    }
+
+    /// The sound pack in use, if there is one and it's still there.
+    fn sound_pack(&self) -> Option<&SoundPack> {
+        self.sound_packs
+            .iter()
+            .find(|pack| pack.id == self.config.sound_pack)
+    }
+
+    /// Steps through the sound packs, and back round to the game's own
+    /// sounds, and swaps in the one picked.
+    fn switch_sound_pack(&mut self, ctx: &mut Context, forward: bool) {
+        let ids: Vec<&str> = self
+            .sound_packs
+            .iter()
+            .map(|pack| pack.id.as_str())
+            .collect();
+        let at = ids.iter().position(|&id| id == self.config.sound_pack);
+        let id = match (at, forward) {
+            (None, true) => ids.first().copied(),
+            (None, false) => ids.last().copied(),
+            (Some(at), true) => ids.get(at + 1).copied(),
+            (Some(0), false) => None,
+            (Some(at), false) => Some(ids[at - 1]),
+        };
+        self.config.sound_pack = id.unwrap_or_default().to_string();
+        self.audio = Audio::load(ctx, &self.config, &self.packs, self.sound_pack());
+        self.save_config(ctx);
+    }
+
+    /// Steps through the languages there's text for, and shows the game in
+    /// the one picked.
+    fn switch_language(&mut self, ctx: &mut Context, forward: bool) {
+        let languages = Locale::languages(ctx);
+        let count = languages.len();
+        if count == 0 {
+            return;
+        }
+        let current = languages
+            .iter()
+            .position(|language| *language == self.config.language);
+        let at = match (current, forward) {
+            (Some(at), true) => (at + 1) % count,
+            (Some(at), false) => (at + count - 1) % count,
+            (None, true) => 0,
+            (None, false) => count - 1,
+        };
+        self.config.language = languages[at].clone();
+        self.locale = Locale::load(ctx, &self.config.language);
+        self.save_config(ctx);
+    }
+
+    /// Picks up changes to the config that the board is drawn with.
+    fn rebuild_renderer(&mut self, ctx: &mut Context) {
+        match BoardRenderer::new(ctx, &self.config) {
+            Ok(renderer) => self.renderer = renderer,
+            Err(e) => eprintln!("Could not redraw the board: {}", e),
+        }
+    }
+
+    /// Moves the music (`selected` 3) or effects (4) volume one step.
+    fn adjust_volume(&mut self, ctx: &mut Context, selected: usize, up: bool) {
+        let step = |volume: u32| {
+            if up {
+                (volume + VOLUME_STEP).min(100)
+            } else {
+                volume.saturating_sub(VOLUME_STEP)
+            }
+        };
+        if selected == 3 {
+            self.audio.set_music_volume(step(self.audio.music_volume()));
+            self.config.music_volume = self.audio.music_volume();
+        } else {
+            self.audio.set_sfx_volume(step(self.audio.sfx_volume()));
+            self.config.sfx_volume = self.audio.sfx_volume();
+        }
+        self.save_config(ctx);
+    }
+
+    /// Moves the entry delay (`selected` 9) or line clear delay (10) one
+    /// step.
+    fn adjust_delay(&mut self, ctx: &mut Context, selected: usize, up: bool) {
+        let millis = if selected == 10 {
+            &mut self.config.entry_delay_millis
+        } else {
+            &mut self.config.line_clear_delay_millis
+        };
+        *millis = if up {
+            *millis + HANDLING_STEP_MILLIS
+        } else {
+            millis.saturating_sub(HANDLING_STEP_MILLIS)
+        };
+        self.save_config(ctx);
+    }
+
+    fn save_config(&self, ctx: &mut Context) {
+        if let Err(e) = self.config.save(ctx) {
+            eprintln!("Could not save settings: {}", e);
+        }
+    }
+
+    fn controls_key(&mut self, keycode: KeyCode, selected: usize) {
+        match keycode {
+            KeyCode::Up => {
+                let selected = (selected + CONTROL_ITEMS - 1) % CONTROL_ITEMS;
+                self.scene = Scene::Controls {
+                    selected,
+                    waiting: false,
+                };
+            }
+            KeyCode::Down => {
+                let selected = (selected + 1) % CONTROL_ITEMS;
+                self.scene = Scene::Controls {
+                    selected,
+                    waiting: false,
+                };
+            }
+            KeyCode::Escape => self.scene = Scene::Options { selected: 28 },
+            KeyCode::Return | KeyCode::Space => {
+                if selected < Input::ALL.len() {
+                    self.scene = Scene::Controls {
+                        selected,
+                        waiting: true,
+                    };
+                } else {
+                    self.scene = Scene::Options { selected: 28 };
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Binds the key pressed while waiting on the controls screen and saves
+    /// the new bindings.
+    fn rebind_key(&mut self, ctx: &mut Context, keycode: KeyCode, selected: usize) {
+        if keycode != KeyCode::Escape {
+            if !keymap::is_bindable(keycode) {
+                return;
+            }
+            self.keymap.bind(Input::ALL[selected], keycode);
+            self.config.keys = self.keymap.to_config();
+            if let Err(e) = self.config.save(ctx) {
+                eprintln!("Could not save key bindings: {}", e);
+            }
+        }
+        self.scene = Scene::Controls {
+            selected,
+            waiting: false,
+        };
+    }
+
+    /// Records the finished game in the high score table, asking for a name
+    /// if it made it in, and in the session log. Versus rounds end when
+    /// either side tops out and don't count towards the table.
+    fn finish(&mut self, ctx: &mut Context) {
+        self.audio.stop_music();
+        savegame::delete(ctx, Slot::Autosave);
+        if let Err(e) = sessions::record(ctx, &self.game) {
+            eprintln!("Could not record session: {}", e);
+        }
+        self.lifetime
+            .record(&self.config.profile().name, &self.game);
+        self.heatmap.merge(self.game.heatmap());
+        if self.game.mode() == Mode::Ultra {
+            if let Some(date) = self.daily_date.take() {
+                self.daily.record(date, &self.game);
+            }
+        }
+        if let Some(recording) = self.recording.take() {
+            match replays::save(ctx, &recording) {
+                Ok(path) => println!("Replay saved to {}", path.display()),
+                Err(e) => eprintln!("Could not save replay: {}", e),
+            }
+            self.last_replay = Some(recording);
+        }
+        if self.game.mode() == Mode::Puzzle && self.game.cleared_goal() {
+            let name = &self.puzzles[self.puzzle].name;
+            self.progress.record(name, self.game.stats().pieces);
+        }
+        if self.game.mode() == Mode::Mission {
+            let mission = &self.missions[self.mission];
+            let stars = mission.rating.stars(&self.game);
+            self.campaign.record(&mission.puzzle.name, stars);
+        }
+        if self.timing_splits() {
+            self.splits.record(self.game.mode(), &self.split_timer);
+        }
+        if let Some(seed) = self.race.take() {
+            let run = self.races.record(&seed, &self.game);
+            self.last_rank = None;
+            self.scene = Scene::RaceResults { run };
+            return;
+        }
+        self.last_rank = match self.game.mode() {
+            _ if self.versus.is_some() => None,
+            Mode::Endless => self.high_scores.record(&self.game),
+            Mode::Marathon => self.marathon_scores.record(&self.game),
+            Mode::Sprint => self.sprint_times.record(&self.game),
+            Mode::Zen
+            | Mode::Puzzle
+            | Mode::Mission
+            | Mode::Practice
+            | Mode::Invisible
+            | Mode::Big
+            | Mode::Ultra
+            | Mode::TwentyG
+            | Mode::Master
+            | Mode::Coop => None,
+        };
+        self.scene = match self.last_rank {
+            Some(rank) => Scene::NameEntry { rank },
+            None => Scene::GameOver,
+        };
+    }
+
+    fn name_entry_key(&mut self, keycode: KeyCode, rank: usize) {
+        match keycode {
+            KeyCode::Back => {
+                self.name.pop();
+            }
+            KeyCode::Return => {
+                let name = self.name.trim();
+                let name = if name.is_empty() {
+                    "???".to_string()
+                } else {
+                    name.to_string()
+                };
+                let saved = match self.game.mode() {
+                    Mode::Endless => {
+                        self.high_scores.entries[rank].name = name;
+                        self.high_scores.save()
+                    }
+                    Mode::Marathon => {
+                        self.marathon_scores.entries[rank].name = name;
+                        self.marathon_scores.save()
+                    }
+                    Mode::Sprint => {
+                        self.sprint_times.entries[rank].name = name;
+                        self.sprint_times.save()
+                    }
+                    Mode::Zen
+                    | Mode::Puzzle
+                    | Mode::Mission
+                    | Mode::Practice
+                    | Mode::Invisible
+                    | Mode::Big
+                    | Mode::Ultra
+                    | Mode::TwentyG
+                    | Mode::Master
+                    | Mode::Coop => Ok(()),
+                };
+                if let Err(e) = saved {
+                    eprintln!("Could not save high scores: {}", e);
+                }
+                self.scene = Scene::GameOver;
+            }
+            _ => (),
+        }
+    }
+
+    /// Draws the table for `mode` as rows starting at `top`, highlighting
+    /// the last game's entry.
+    fn draw_high_scores(&self, ctx: &mut Context, mode: Mode, top: f32, rows: usize) -> GameResult {
+        let width = highscores::MAX_NAME_LEN;
+        let table: Vec<String> = match mode {
+            Mode::Endless | Mode::Marathon => {
+                let scores = if mode == Mode::Endless {
+                    &self.high_scores
+                } else {
+                    &self.marathon_scores
+                };
+                scores
+                    .entries
+                    .iter()
+                    .map(|entry| {
+                        format!(
+                            "{:<width$} {:>7} {:>4}  L{:<2} {}",
+                            entry.name, entry.score, entry.lines, entry.level, entry.date
+                        )
+                    })
+                    .collect()
+            }
+            Mode::Sprint => self
+                .sprint_times
+                .entries
+                .iter()
+                .map(|entry| {
+                    let time = highscores::format_time(Duration::from_millis(entry.millis));
+                    format!("{:<width$} {:>9}  {}", entry.name, time, entry.date)
+                })
+                .collect(),
+            Mode::Zen
+            | Mode::Puzzle
+            | Mode::Mission
+            | Mode::Practice
+            | Mode::Invisible
+            | Mode::Big
+            | Mode::Ultra
+            | Mode::TwentyG
+            | Mode::Master
+            | Mode::Coop => Vec::new(),
+        };
+        if table.is_empty() {
+            let none = self.locale.get("high_scores.none");
+            return text::draw_centered(ctx, none, 16.0, graphics::WHITE, top);
+        }
+        // Only the last game's own table has its entry in it.
+        let last_rank = self.last_rank.filter(|_| self.game.mode() == mode);
+        for (i, entry) in table.iter().take(rows).enumerate() {
+            let row = format!("{:>2}. {}", i + 1, entry);
+            let color = if last_rank == Some(i) {
+                self.config.colors.accent()
+            } else {
+                graphics::WHITE
+            };
+            text::draw_centered(ctx, &row, 14.0, color, top + 24.0 * i as f32)?;
+        }
+        Ok(())
+    }
+
+    /// The oldest achievement still to be announced, over whatever screen
+    /// is up.
+    /// Draws the chat up from the bottom left corner, the line being typed
+    /// under those already said.
+    fn draw_chat(&self, ctx: &mut Context) -> GameResult {
+        let mut y = view(ctx).h - CHAT_MARGIN - CHAT_LINE;
+        if let Some(typing) = self.chat.typing() {
+            let typing = self.locale.format("chat.typing", &[&typing]);
+            let at = na::Point2::new(CHAT_MARGIN, y);
+            text::draw(ctx, &typing, CHAT_SIZE, self.config.colors.accent(), at)?;
+            y -= CHAT_LINE;
+        }
+        let shown: Vec<_> = self.chat.shown(Instant::now()).collect();
+        for (text, mine, alpha) in shown.into_iter().rev() {
+            let line = if mine { "chat.mine" } else { "chat.theirs" };
+            let line = self.locale.format(line, &[&text]);
+            let mut color = graphics::WHITE;
+            color.a = alpha;
+            let at = na::Point2::new(CHAT_MARGIN, y);
+            text::draw(ctx, &line, CHAT_SIZE, color, at)?;
+            y -= CHAT_LINE;
+        }
+        Ok(())
+    }
+
+    fn draw_toast(&mut self, ctx: &mut Context) -> GameResult {
+        let now = Instant::now();
+        let at = *self.toast_at.get_or_insert(now);
+        if now.saturating_duration_since(at) >= Duration::from_millis(TOAST_MILLIS) {
+            self.toasts.pop_front();
+            self.toast_at = None;
+        }
+        let achievement = match self.toasts.front() {
+            Some(&achievement) => achievement,
+            None => {
+                self.toast_at = None;
+                return Ok(());
+            }
+        };
+        let achievement = self.locale.name("achievement", achievement);
+        let text = self.locale.format("achievement.unlocked", &[&achievement]);
+        let accent = self.config.colors.accent();
+        text::draw_outlined(ctx, &text, 18.0, accent, graphics::BLACK, 56.0)
+    }
+
+    /// How the round just over went, a line at a time, for the game over
+    /// screen.
+    fn summary(&self) -> Vec<String> {
+        let stats = self.game.stats();
+        let played = self.game.elapsed();
+        let [singles, doubles, triples, tetrises] = stats.clears;
+        let locale = &self.locale;
+        vec![
+            locale.format("summary.lines", &[&self.game.lines, &self.game.level]),
+            locale.format(
+                "summary.pace",
+                &[
+                    &highscores::format_time(played),
+                    &stats.pieces,
+                    &format!("{:.2}", stats.pieces_per_second(played)),
+                ],
+            ),
+            locale.format("summary.clears", &[&singles, &doubles, &triples, &tetrises]),
+            locale.format(
+                "summary.specials",
+                &[
+                    &stats.t_spins,
+                    &stats.perfect_clears,
+                    &stats.best_back_to_back,
+                ],
+            ),
+        ]
+    }
+
+    /// Writes out the last minute or so of the round on screen, to attach
+    /// to a bug report.
+    fn save_bug_report(&self, ctx: &mut Context) {
+        match self.input_log.save(ctx, self.frame, &self.game) {
+            Ok(path) => println!("Bug report saved to {}", path.display()),
+            Err(e) => eprintln!("Could not save bug report: {}", e),
+        }
+    }
+
+    /// Whether the round in play can be paused; not against a player over
+    /// the network, who can't be paused too.
+    fn can_pause(&self) -> bool {
+        self.scene == Scene::Playing && !self.versus.as_ref().is_some_and(Versus::is_remote)
+    }
+
+    /// Stops the clock on the round in play and shows the pause screen.
+    fn pause(&mut self) {
+        self.game.pause(self.game_time());
+        self.audio.pause_music();
+        self.restart_held = None;
+        self.scene = Scene::Paused;
+    }
+
+    /// Whether to draw at `IDLE_FPS`: with the window in the background,
+    /// or on a screen where nothing moves once the player has left it be.
+    /// Network games keep to full speed so the other side isn't kept
+    /// waiting.
+    fn idle(&self) -> bool {
+        let online = self.versus.as_ref().is_some_and(Versus::is_remote)
+            || self.lobby.is_some()
+            || self.spectator.is_some()
+            || self.spectators.is_some();
+        if online {
+            return false;
+        }
+        if !self.focused {
+            return true;
+        }
+        let still = match self.scene {
+            Scene::Menu { .. }
+            | Scene::Options { .. }
+            | Scene::Controls { .. }
+            | Scene::Handling { .. }
+            | Scene::Recover { .. }
+            | Scene::HighScores { .. }
+            | Scene::NameEntry { .. }
+            | Scene::Paused
+            | Scene::Heatmap
+            | Scene::Stats
+            | Scene::Join
+            | Scene::RaceSetup
+            | Scene::RaceResults { .. }
+            | Scene::LocalGames { .. }
+            | Scene::Packs { .. }
+            | Scene::Puzzles { .. }
+            | Scene::Missions { .. }
+            | Scene::VersusSetup { .. }
+            | Scene::Editor => true,
+            Scene::Playing
+            | Scene::GameOver
+            | Scene::Replay
+            | Scene::Demo
+            | Scene::Lobby
+            | Scene::Spectating => false,
+        };
+        still && self.last_input.elapsed() >= Duration::from_secs(IDLE_AFTER_SECS)
+    }
+
+    /// The window's title: the mode and score while a round is on screen,
+    /// and whether it's paused, or just the game's name anywhere else.
+    fn title(&self) -> String {
+        let locale = &self.locale;
+        match self.scene {
+            Scene::Playing | Scene::Paused | Scene::GameOver | Scene::Heatmap => {
+                let mode = locale.name("mode", self.game.mode());
+                let score = group_digits(self.game.score);
+                let title = locale.format("window.round", &[&mode, &score]);
+                if self.scene == Scene::Paused {
+                    locale.format("window.paused", &[&title])
+                } else {
+                    title
+                }
+            }
+            _ => locale.get("window.title").to_string(),
+        }
+    }
+
+    /// What `goal` asks for, in words.
+    fn goal_text(&self, goal: Goal) -> String {
+        match goal {
+            Goal::PerfectClear => self.locale.get("goal.perfect_clear").to_string(),
+            Goal::Lines(1) => self.locale.get("goal.line").to_string(),
+            Goal::Lines(lines) => self.locale.format("goal.lines", &[&lines]),
+            Goal::TSpins(1) => self.locale.get("goal.t_spin").to_string(),
+            Goal::TSpins(t_spins) => self.locale.format("goal.t_spins", &[&t_spins]),
+            Goal::Survive(secs) => {
+                let time = format!("{}:{:02}", secs / 60, secs % 60);
+                self.locale.format("goal.survive", &[&time])
+            }
+        }
+    }
+
+    /// How far the round in play is towards `goal`, for a round without
+    /// set pieces to count down instead.
+    fn goal_progress(&self, goal: Goal) -> Option<String> {
+        match goal {
+            Goal::PerfectClear => None,
+            Goal::Lines(lines) => Some(format!("{}/{}", self.game.lines.min(lines), lines)),
+            Goal::TSpins(t_spins) => {
+                let landed = self.game.stats().t_spins.min(t_spins);
+                Some(format!("{}/{}", landed, t_spins))
+            }
+            Goal::Survive(_) => self.game.time_left().map(highscores::format_time),
+        }
+    }
+
+    /// Draws the board being set up with the cursor on it, what the brush,
+    /// pieces and goal are, and the keys.
+    fn draw_editor(&mut self, ctx: &mut Context) -> GameResult {
+        self.draw_board(ctx)?;
+        let editor = match self.editor {
+            Some(ref editor) => editor,
+            None => return Ok(()),
+        };
+        let layout = self.layout();
+        let cell_size = self.cell_size() as f32;
+        let accent = self.config.colors.accent();
+        let cursor = editor.cursor();
+        let cell = graphics::Rect::new(
+            layout.board()[0] + cursor[0] as f32 * cell_size,
+            layout.board()[1] + cursor[1] as f32 * cell_size,
+            cell_size,
+            cell_size,
+        );
+        let outline =
+            graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::stroke(2.0), cell, accent)?;
+        graphics::draw(ctx, &outline, (na::Point2::new(0.0, 0.0),))?;
+
+        let locale = &self.locale;
+        let pieces: String = editor.pieces().iter().map(|piece| piece.symbol()).collect();
+        let lines = [
+            locale.format("editor.brush", &[&editor.brush().to_char()]),
+            locale.format("editor.pieces", &[&pieces]),
+            locale.format("editor.goal", &[&self.goal_text(editor.goal())]),
+        ];
+        let hud = layout.left_panel().x + 8.0;
+        for (i, line) in lines.iter().enumerate() {
+            let at = na::Point2::new(hud, HUD_TOP + 24.0 * i as f32);
+            text::draw(ctx, line, 14.0, graphics::WHITE, at)?;
+        }
+        let title = locale.get("editor.title");
+        text::draw_centered(ctx, title, 20.0, accent, 24.0)?;
+        if let Some(ref note) = editor.note {
+            text::draw_outlined(ctx, note, 18.0, accent, graphics::BLACK, 52.0)?;
+        }
+        let bottom = view(ctx).h - 48.0;
+        let paint = locale.get("editor.keys_paint");
+        text::draw_centered(ctx, paint, 16.0, graphics::WHITE, bottom - 24.0)?;
+        let keys = locale.get("editor.keys");
+        text::draw_centered(ctx, keys, 16.0, graphics::WHITE, bottom)
+    }
+
+    /// Draws the 3-2-1 while the round waits to start, and GO as it does.
+    fn draw_countdown(&self, ctx: &mut Context) -> GameResult {
+        let fps = u64::from(replay::FRAMES_PER_SECOND);
+        let text = if self.countdown > 0 {
+            self.countdown.div_ceil(fps).to_string()
+        } else if self.frame < GO_FRAMES {
+            self.locale.get("hud.go").to_string()
+        } else {
+            return Ok(());
+        };
+        let center = view(ctx).h / 2.0;
+        let accent = self.config.colors.accent();
+        text::draw_outlined(ctx, &text, 64.0, accent, graphics::BLACK, center - 32.0)
+    }
+
+    /// The live stats panel, the first press of F3, stacked up from
+    /// `bottom` of the left panel.
+    fn draw_stats(&self, ctx: &mut Context, bottom: f32) -> GameResult {
+        let left = self.layout().left_panel().x + 8.0;
+        let stats = self.game.stats();
+        let played = self.game.elapsed();
+        let locale = &self.locale;
+        let rows = [
+            locale.format("stats.time", &[&highscores::format_time(played)]),
+            locale.format("stats.pieces", &[&stats.pieces]),
+            locale.format(
+                "stats.pps",
+                &[&format!("{:.2}", stats.pieces_per_second(played))],
+            ),
+            locale.format("stats.attack", &[&stats.attack]),
+            locale.format(
+                "stats.apm",
+                &[&format!("{:.1}", stats.attack_per_minute(played))],
+            ),
+            locale.format("stats.inputs", &[&stats.inputs]),
+            locale.format("stats.kpp", &[&format!("{:.2}", stats.inputs_per_piece())]),
+        ];
+        for (i, row) in rows.iter().rev().enumerate() {
+            let y = bottom - 20.0 * (i + 1) as f32;
+            text::draw(ctx, row, 14.0, graphics::WHITE, na::Point2::new(left, y))?;
+        }
+        Ok(())
+    }
+
+    /// Every run on the race seed, best first, with run number `run`, the
+    /// one just played, picked out.
+    fn draw_race_results(&self, ctx: &mut Context, run: usize) -> GameResult {
+        let seed = self.race_seed.trim();
+        let accent = self.config.colors.accent();
+        let top = 80.0;
+        let title = self.locale.format("race.results", &[&seed]);
+        text::draw_centered(ctx, &title, 40.0, accent, top)?;
+        let bottom = view(ctx).h - 48.0;
+        let standings = self.races.standings(seed);
+        let rows = (((bottom - 40.0 - (top + 64.0)) / 28.0) as usize).max(1);
+        // The run just played stays on screen however far down it came.
+        let at = standings.iter().position(|&(number, _)| number == run);
+        let skip = at.map_or(0, |at| (at + 1).saturating_sub(rows));
+        let shown = standings.iter().enumerate().skip(skip).take(rows);
+        for (i, (at, &(number, entry))) in shown.enumerate() {
+            let place = at + 1;
+            let result = if entry.finished {
+                highscores::format_time(entry.time())
+            } else {
+                self.locale.format("race.topped_out", &[&entry.lines])
+            };
+            let line = self
+                .locale
+                .format("race.run", &[&place, &number, &result, &entry.pieces]);
+            let color = if number == run {
+                accent
+            } else {
+                graphics::WHITE
+            };
+            text::draw_centered(ctx, &line, 20.0, color, top + 64.0 + 28.0 * i as f32)?;
+        }
+        let share = self.locale.format("race.share", &[&seed]);
+        text::draw_centered(ctx, &share, 16.0, graphics::WHITE, bottom - 32.0)?;
+        let keys = self.locale.get("race.results_keys");
+        text::draw_centered(ctx, keys, 20.0, graphics::WHITE, bottom)
+    }
+
+    /// The state behind what's on the board, for chasing down logic bugs,
+    /// over the top left of the board with the second press of F3.
+    fn draw_debug(&self, ctx: &mut Context) -> GameResult {
+        let (pos, facing) = self.game.piece();
+        let lock = match (
+            self.game.ruleset().lock_delay,
+            self.game.grounded_for(self.game_time()),
+        ) {
+            (None, _) => "none".to_string(),
+            (Some(_), None) => "airborne".to_string(),
+            (Some(delay), Some(grounded)) => format!(
+                "{}/{}ms",
+                grounded.as_millis().min(delay.as_millis()),
+                delay.as_millis()
+            ),
+        };
+        let bag: String = std::iter::once(self.game.next())
+            .chain(self.game.queued())
+            .map(|tetromino| tetromino.symbol())
+            .collect();
+        let rows = [
+            format!("FPS: {:.0}", timer::fps(ctx)),
+            format!("Tick: {} ({} drawn)", self.frame, timer::ticks(ctx)),
+            format!(
+                "Piece: {:?} ({}, {}) facing {:?}",
+                self.game.tetromino(),
+                pos[0],
+                pos[1],
+                facing
+            ),
+            format!("Lock: {} resets {}", lock, self.game.lock_resets()),
+            format!("Bag: {:?} {}", self.game.ruleset().randomizer, bag),
+            if self.stepping {
+                "Stepping: F7 one tick, F6 to run".to_string()
+            } else {
+                "Running: F6 to step".to_string()
+            },
+        ];
+        let layout = self.layout();
+        let board = layout.board();
+        let width = layout.right_panel().x - board[0];
+        let backdrop = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(board[0], board[1], width, 20.0 * rows.len() as f32 + 8.0),
+            graphics::Color::new(0.0, 0.0, 0.0, 0.7),
+        )?;
+        graphics::draw(ctx, &backdrop, (na::Point2::new(0.0, 0.0),))?;
+        for (i, row) in rows.iter().enumerate() {
+            let at = na::Point2::new(board[0] + 4.0, board[1] + 4.0 + 20.0 * i as f32);
+            text::draw(ctx, row, 14.0, graphics::WHITE, at)?;
+        }
+        Ok(())
+    }
+
+    /// How many of each piece have been dealt, and how long each has been
+    /// gone, down the right panel from `top` with the stats panel.
+    fn draw_distribution(&self, ctx: &mut Context, top: f32) -> GameResult {
+        let dealt = self.game.dealt();
+        let pieces = match self.game.pieces().pieces() {
+            [] => &Tetromino::ALL[..],
+            pieces => pieces,
+        };
+        let left = self.layout().right_panel().x + 8.0;
+        for (i, &tetromino) in pieces.iter().enumerate() {
+            let row = format!(
+                "{} {:>3} {:>3}",
+                tetromino.symbol(),
+                dealt.count(tetromino),
+                dealt.drought(tetromino)
+            );
+            let color = self.config.colors.piece(tetromino);
+            let at = na::Point2::new(left, top + 16.0 * i as f32);
+            text::draw(ctx, &row, 14.0, color, at)?;
+        }
+        Ok(())
+    }
+
+    fn draw_board(&mut self, ctx: &mut Context) -> GameResult {
+        let layout = self.layout();
+        let cell_size = self.cell_size() as f32;
+        let board = graphics::Rect::new(
+            layout.board()[0],
+            layout.board()[1],
+            self.game.board.width() as f32 * cell_size,
+            self.game.board.height() as f32 * cell_size,
+        );
+        if !self.config.high_contrast {
+            let elapsed = if self.config.reduced_motion {
+                Duration::from_secs(0)
+            } else {
+                timer::time_since_start(ctx)
+            };
+            let background = self.config.colors.background();
+            self.backdrop
+                .draw(ctx, view(ctx), board, background, self.game.level, elapsed)?;
+        }
+        self.renderer.draw(ctx, &self.game, layout.board())?;
+        if let Some(ref partner) = self.partner {
+            self.renderer
+                .draw_piece(ctx, &partner.game, layout.board())?;
+        }
+        if let (true, Some(cells)) = (self.tutoring(), self.tutor.suggestion()) {
+            self.renderer
+                .draw_suggestion(ctx, &self.game, cells, layout.board())?;
+        }
+        self.trails
+            .draw(ctx, Instant::now(), layout.board(), cell_size)?;
+        self.particles.draw(ctx, layout.board(), cell_size)?;
+        self.popups.draw(
+            ctx,
+            Instant::now(),
+            board,
+            cell_size,
+            &self.locale,
+            &self.config,
+        )?;
+        let numbers_end = self
+            .renderer
+            .draw_panels(ctx, &self.game, &self.locale, layout)?;
+        if let Some(ref partner) = self.partner {
+            self.renderer.draw_partner_panel(
+                ctx,
+                &partner.game,
+                &self.locale,
+                layout.partner_panel(),
+            )?;
+        }
+        if let Some(ref mut versus) = self.versus {
+            versus.draw(
+                ctx,
+                layout,
+                &self.locale,
+                self.config.colors.subdued(),
+                self.config.colors.accent(),
+            )?;
+        }
+        if let Some(ref mut ghost) = self.ghost {
+            ghost.draw(
+                ctx,
+                layout.mini_board(),
+                &self.locale,
+                self.config.colors.subdued(),
+            )?;
+        }
+        // Shown so a run can be repeated with `--seed`.
+        let seed = self.locale.format("hud.seed", &[&self.game.seed()]);
+        let bottom = layout.height() - 20.0;
+        let hud = layout.left_panel().x + 8.0;
+        text::draw(
+            ctx,
+            &seed,
+            14.0,
+            self.config.colors.subdued(),
+            na::Point2::new(hud, bottom),
+        )?;
+        // Under the mode's own lines.
+        let announcements = HUD_TOP + ANNOUNCEMENTS_BELOW;
+        self.announcer
+            .draw(ctx, Instant::now(), hud, announcements, &self.locale)?;
+        if self.timing_splits() {
+            let mut top = HUD_TOP + SPLITS_BELOW;
+            // Sprints have the time up top already.
+            if self.game.mode().line_goal().is_none() {
+                let time = highscores::format_time(self.game.elapsed());
+                let time = self.locale.format("splits.time", &[&time]);
+                text::draw(ctx, &time, 14.0, graphics::WHITE, na::Point2::new(hud, top))?;
+                top += 20.0;
+            }
+            let panel = layout.left_panel();
+            let best = self.splits.best(self.game.mode());
+            let across = (hud, panel.x + panel.w - 8.0);
+            self.split_timer
+                .draw(ctx, across, top, best, &self.locale)?;
+        }
+        if self.config.show_stats {
+            self.draw_stats(ctx, bottom)?;
+            self.draw_distribution(ctx, numbers_end)?;
+        }
+        if self.debug {
+            self.draw_debug(ctx)?;
+        }
+        if let Some(goal) = self.game.mode().line_goal() {
+            let time = highscores::format_time(self.game.elapsed());
+            text::draw(
+                ctx,
+                &time,
+                20.0,
+                graphics::WHITE,
+                na::Point2::new(hud, HUD_TOP),
+            )?;
+            let lines = format!("{}/{}", self.game.lines.min(goal), goal);
+            text::draw(
+                ctx,
+                &lines,
+                14.0,
+                self.config.colors.subdued(),
+                na::Point2::new(hud, HUD_TOP + 24.0),
+            )?;
+        }
+        // A goal's own time is shown under it.
+        if let (None, Some(left)) = (self.game.goal(), self.game.time_left()) {
+            let time = highscores::format_time(left);
+            text::draw(
+                ctx,
+                &time,
+                20.0,
+                graphics::WHITE,
+                na::Point2::new(hud, HUD_TOP),
+            )?;
+        }
+        if let Some(goal) = self.game.goal() {
+            let line = self.goal_text(goal);
+            text::draw(
+                ctx,
+                &line,
+                20.0,
+                graphics::WHITE,
+                na::Point2::new(hud, HUD_TOP),
+            )?;
+            let progress = match self.game.pieces_left() {
+                Some(left) => Some(self.locale.format("hud.pieces_left", &[&left])),
+                None => self.goal_progress(goal),
+            };
+            if let Some(progress) = progress {
+                text::draw(
+                    ctx,
+                    &progress,
+                    14.0,
+                    self.config.colors.subdued(),
+                    na::Point2::new(hud, HUD_TOP + 24.0),
+                )?;
+            }
+        }
+        if let Some(grade) = self.game.grade() {
+            let grade = self.locale.format("hud.grade", &[&grade.label()]);
+            text::draw(
+                ctx,
+                &grade,
+                20.0,
+                graphics::WHITE,
+                na::Point2::new(hud, HUD_TOP),
+            )?;
+        }
+        if let (true, Some(grade)) = (self.tutoring(), self.tutor.grade()) {
+            let grade = self.locale.name("placement", grade);
+            let placement = self.locale.format("hud.placement", &[&grade]);
+            text::draw(
+                ctx,
+                &placement,
+                14.0,
+                self.config.colors.subdued(),
+                na::Point2::new(hud, HUD_TOP + 48.0),
+            )?;
+        }
+        if self.game.mode() == Mode::Marathon {
+            // Finishing moves on to the level after the last.
+            let level = self.game.level.min(MARATHON_LEVELS);
+            let level = self
+                .locale
+                .format("hud.marathon_level", &[&level, &MARATHON_LEVELS]);
+            text::draw(
+                ctx,
+                &level,
+                20.0,
+                graphics::WHITE,
+                na::Point2::new(hud, HUD_TOP),
+            )?;
+        }
+
+        if let Some(at) = self.game.perfect_clear_at {
+            let shown_for = self.game_time().saturating_duration_since(at);
+            if shown_for < Duration::from_millis(BANNER_MILLIS) {
+                let banner = self.locale.get("hud.perfect_clear");
+                let center = view(ctx).h / 2.0;
+                let accent = self.config.colors.accent();
+                text::draw_outlined(ctx, banner, 32.0, accent, graphics::BLACK, center)?;
+            }
+        }
+        if let Some((ref fault, at)) = self.finesse_hint {
+            let shown_for = self.game_time().saturating_duration_since(at);
+            if shown_for < Duration::from_millis(FINESSE_HINT_MILLIS) {
+                let keys = match fault.keys.len() {
+                    0 => self.locale.get("hud.finesse_drop").to_string(),
+                    _ => fault
+                        .keys
+                        .iter()
+                        .map(|key| key.symbol())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                };
+                let hint = self
+                    .locale
+                    .format("hud.finesse", &[&keys, &fault.presses, &fault.keys.len()]);
+                let below = view(ctx).h * 3.0 / 4.0;
+                let color = graphics::WHITE;
+                text::draw_outlined(ctx, &hint, 20.0, color, graphics::BLACK, below)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl event::EventHandler for MainState {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        // Step at a fixed rate whatever the frame rate. The steps are taken
+        // on every screen so time spent in menus doesn't pile up.
+        while timer::check_update_time(ctx, replay::FRAMES_PER_SECOND) {
+            match self.scene {
+                // Held long enough to restart.
+                Scene::Playing
+                    if self.restart_held.is_some_and(|at| {
+                        at.elapsed() >= Duration::from_millis(self.config.restart_hold_millis)
+                    }) =>
+                {
+                    self.restart_now(ctx)
+                }
+                Scene::Playing if self.stepping && self.versus.is_none() && self.steps > 0 => {
+                    self.steps -= 1;
+                    self.tick(ctx);
+                }
+                Scene::Playing if self.stepping && self.versus.is_none() => (),
+                Scene::Playing => {
+                    self.speed_credit += self.speed_percent();
+                    while self.speed_credit >= 100 && self.scene == Scene::Playing {
+                        self.speed_credit -= 100;
+                        self.tick(ctx);
+                    }
+                    if self.scene != Scene::Playing {
+                        self.speed_credit = 0;
+                    }
+                }
+                Scene::Replay => self.replay_tick(),
+                Scene::Menu { .. } => {
+                    self.idle_frames += 1;
+                    if self.idle_frames >= DEMO_IDLE_SECS * u64::from(replay::FRAMES_PER_SECOND) {
+                        self.start_demo(ctx);
+                    }
+                }
+                Scene::Demo => self.demo_tick(),
+                // The other side may still be catching up to where this one
+                // topped out.
+                Scene::GameOver => {
+                    if let Some(ref mut versus) = self.versus {
+                        versus.advance(self.frame);
+                    }
+                    self.relay();
+                }
+                Scene::Lobby => self.lobby_tick(ctx),
+                Scene::LocalGames { .. } => {
+                    if let Some(ref mut browser) = self.browser {
+                        browser.poll();
+                    }
+                }
+                Scene::Spectating => self.spectate_tick(ctx),
+                _ => (),
+            }
+            self.particles.step(1.0 / replay::FRAMES_PER_SECOND as f32);
+        }
+        let title = self.title();
+        if title != self.window_title {
+            graphics::set_window_title(ctx, &title);
+            self.window_title = title;
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let background = if self.config.high_contrast {
+            graphics::BLACK
+        } else {
+            self.config.colors.background()
+        };
+        graphics::clear(ctx, background);
+        let shake = self.shake.offset(Instant::now());
+        let shaken = graphics::DrawParam::new().dest(na::Point2::from(shake));
+        graphics::push_transform(ctx, Some(shaken.to_matrix()));
+        graphics::apply_transformations(ctx)?;
+
+        match self.scene {
+            Scene::Menu { selected } => {
+                let items: Vec<String> = self
+                    .menu_items()
+                    .iter()
+                    .map(|&item| {
+                        let label = self.locale.get(item.key());
+                        match item {
+                            MenuItem::Marathon => self
+                                .locale
+                                .format("menu.with_level", &[&label, &self.marathon_level]),
+                            MenuItem::Daily => match self.daily.today() {
+                                Some(attempt) => self
+                                    .locale
+                                    .format("menu.with_played", &[&label, &attempt.score]),
+                                None => self.locale.format("menu.with_ultra", &[&label]),
+                            },
+                            _ => label.to_string(),
+                        }
+                    })
+                    .collect();
+                let title = self.locale.get("menu.title");
+                draw_menu(ctx, title, &items, selected, self.config.colors.accent())?;
+            }
+            Scene::Options { selected } => {
+                let locale = &self.locale;
+                let on_off = |on: bool| locale.get(if on { "options.on" } else { "options.off" });
+                let by_rules = locale.get("options.by_the_rules").to_string();
+                let theme = self
+                    .theme()
+                    .map_or(locale.get("options.custom_theme"), |at| {
+                        self.themes[at].name.as_str()
+                    });
+                let mouse = if self.config.mouse_placement {
+                    "options.mouse_placement"
+                } else {
+                    "options.mouse_gestures"
+                };
+                let chat = if self.config.chat {
+                    "options.chat_shown"
+                } else {
+                    "options.chat_muted"
+                };
+                let mut vsync = on_off(self.config.vsync).to_string();
+                if self.config.vsync != ctx.conf.window_setup.vsync {
+                    vsync = locale.format("options.after_restart", &[&vsync]);
+                }
+                let sound_pack = self
+                    .sound_pack()
+                    .map_or(locale.get("options.default_sounds"), |pack| {
+                        pack.name.as_str()
+                    });
+                let fps_cap = self.config.fps_cap.map_or_else(
+                    || locale.get("options.off").to_string(),
+                    |fps| locale.format("options.fps", &[&fps]),
+                );
+                let items = vec![
+                    locale.format("options.profile", &[&self.config.profile().name]),
+                    locale.get("options.handling").to_string(),
+                    locale.format("options.sound", &[&on_off(!self.audio.muted())]),
+                    locale.format("options.music_volume", &[&self.audio.music_volume()]),
+                    locale.format("options.effects_volume", &[&self.audio.sfx_volume()]),
+                    locale.format(
+                        "options.pieces",
+                        &[&locale.name("pieces", self.config.pieces)],
+                    ),
+                    locale.format("options.rules", &[&locale.name("rules", self.config.rules)]),
+                    locale.format(
+                        "options.rotation",
+                        &[&self.config.rotation.map_or(by_rules.clone(), |rotation| {
+                            locale.name("rotation", rotation)
+                        })],
+                    ),
+                    locale.format(
+                        "options.randomizer",
+                        &[&self
+                            .config
+                            .randomizer
+                            .map_or(by_rules, |randomizer| locale.name("randomizer", randomizer))],
+                    ),
+                    locale.format(
+                        "options.lock_reset",
+                        &[&locale.name("lock_reset", self.config.lock_reset)],
+                    ),
+                    locale.format("options.entry_delay", &[&self.config.entry_delay_millis]),
+                    locale.format(
+                        "options.line_clear_delay",
+                        &[&self.config.line_clear_delay_millis],
+                    ),
+                    locale.format("options.theme", &[&theme]),
+                    locale.format("options.marks", &[&locale.name("marks", self.config.marks)]),
+                    locale.format(
+                        "options.high_contrast",
+                        &[&on_off(self.config.high_contrast)],
+                    ),
+                    locale.format("options.screen_shake", &[&on_off(self.config.screen_shake)]),
+                    locale.format(
+                        "options.reduced_motion",
+                        &[&on_off(self.config.reduced_motion)],
+                    ),
+                    locale.format("options.fullscreen", &[&on_off(is_fullscreen(ctx))]),
+                    locale.format("options.mouse", &[&locale.get(mouse)]),
+                    locale.format("options.chat", &[&locale.get(chat)]),
+                    locale.format("options.tutor", &[&on_off(self.config.tutor)]),
+                    locale.format(
+                        "options.finesse_hints",
+                        &[&on_off(self.config.finesse_hints)],
+                    ),
+                    locale.format("options.language", &[&locale.get("language")]),
+                    locale.format("options.vsync", &[&vsync]),
+                    locale.format("options.fps_cap", &[&fps_cap]),
+                    locale.format("options.sound_pack", &[&sound_pack]),
+                    locale.format("options.pan_sounds", &[&on_off(self.config.pan_sounds)]),
+                    locale.format(
+                        "options.pause_on_focus_loss",
+                        &[&on_off(self.config.pause_on_focus_loss)],
+                    ),
+                    locale.get("options.controls").to_string(),
+                    locale.get("options.back").to_string(),
+                ];
+                let title = locale.get("options.title");
+                draw_menu(ctx, title, &items, selected, self.config.colors.accent())?;
+            }
+            Scene::Handling { selected } => {
+                let profile = self.config.profile();
+                let locale = &self.locale;
+                let soft_drop = match profile.soft_drop_millis {
+                    None => locale.get("handling.soft_drop_press").to_string(),
+                    Some(0) => locale.get("handling.soft_drop_instant").to_string(),
+                    Some(millis) => locale.format("handling.soft_drop_every", &[&millis]),
+                };
+                let items = [
+                    locale.format("handling.das", &[&profile.das_millis]),
+                    locale.format("handling.arr", &[&profile.arr_millis]),
+                    locale.format("handling.soft_drop", &[&soft_drop]),
+                    locale.format("handling.dcd", &[&profile.dcd_millis]),
+                    locale.get("handling.new_profile").to_string(),
+                    locale.get("handling.back").to_string(),
+                ];
+                let name = profile.name.to_uppercase();
+                let title = locale.format("handling.title", &[&name]);
+                draw_menu(ctx, &title, &items, selected, self.config.colors.accent())?;
+            }
+            Scene::VersusSetup { selected } => {
+                let mut items = Vec::with_capacity(VERSUS_ITEMS);
+                items.extend(
+                    self.handicap_lines(self.locale.get("versus_setup.you"), self.config.handicap),
+                );
+                let difficulty = self.locale.name("difficulty", self.config.cpu_difficulty);
+                items.push(
+                    self.locale
+                        .format("versus_setup.difficulty", &[&difficulty]),
+                );
+                items.extend(
+                    self.handicap_lines(self.locale.get("versus.cpu"), self.config.cpu_handicap),
+                );
+                let cpus = self.config.cpus.clamp(1, MAX_CPUS);
+                items.push(self.locale.format("versus_setup.cpus", &[&cpus]));
+                let targeting = self.locale.name("targeting", self.config.targeting);
+                items.push(self.locale.format("versus_setup.targeting", &[&targeting]));
+                items.push(self.locale.get("versus_setup.start").to_string());
+                items.push(self.locale.get("versus_setup.back").to_string());
+                let title = self.locale.get("versus_setup.title");
+                draw_menu(ctx, title, &items, selected, self.config.colors.accent())?;
+                let bottom = view(ctx).h - 48.0;
+                let note = self.locale.get("versus_setup.online");
+                text::draw_centered(ctx, note, 20.0, graphics::WHITE, bottom)?;
+            }
+            Scene::Recover { selected } => {
+                let items = [
+                    self.locale.get("recover.resume").to_string(),
+                    self.locale.get("recover.discard").to_string(),
+                ];
+                let title = self.locale.get("recover.title");
+                draw_menu(ctx, title, &items, selected, self.config.colors.accent())?;
+            }
+            Scene::Controls { selected, waiting } => {
+                let mut items: Vec<String> = Input::ALL
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &input)| {
+                        let keys = if waiting && i == selected {
+                            self.locale.get("controls.waiting").to_string()
+                        } else {
+                            let names: Vec<String> = self
+                                .keymap
+                                .keys(input)
+                                .iter()
+                                .map(|&key| keymap::key_name(key))
+                                .collect();
+                            names.join(", ")
+                        };
+                        let action = self.locale.name("input", input);
+                        self.locale.format("controls.binding", &[&action, &keys])
+                    })
+                    .collect();
+                items.push(self.locale.get("controls.back").to_string());
+                let title = self.locale.get("controls.title");
+                draw_menu(ctx, title, &items, selected, self.config.colors.accent())?;
+            }
+            Scene::HighScores { mode } => {
+                let top = view(ctx).h / 3.0;
+                let title = match mode {
+                    Mode::Endless
+                    | Mode::Zen
+                    | Mode::Puzzle
+                    | Mode::Mission
+                    | Mode::Practice
+                    | Mode::Invisible
+                    | Mode::Big
+                    | Mode::Ultra
+                    | Mode::TwentyG
+                    | Mode::Master
+                    | Mode::Coop => "high_scores.title",
+                    Mode::Marathon => "high_scores.marathon_title",
+                    Mode::Sprint => "high_scores.sprint_title",
+                };
+                let title = self.locale.get(title);
+                text::draw_centered(ctx, title, 40.0, self.config.colors.accent(), top)?;
+                self.draw_high_scores(ctx, mode, top + 64.0, highscores::MAX_ENTRIES)?;
+                let bottom = top + 88.0 + 24.0 * highscores::MAX_ENTRIES as f32;
+                let prompt = self.locale.get("high_scores.keys");
+                text::draw_centered(ctx, prompt, 20.0, graphics::WHITE, bottom)?;
+            }
+            Scene::NameEntry { .. } => {
+                let center = view(ctx).h / 2.0;
+                let locale = &self.locale;
+                let (title, result) = match self.game.mode() {
+                    Mode::Endless
+                    | Mode::Marathon
+                    | Mode::Zen
+                    | Mode::Puzzle
+                    | Mode::Mission
+                    | Mode::Practice
+                    | Mode::Invisible
+                    | Mode::Big
+                    | Mode::Ultra
+                    | Mode::TwentyG
+                    | Mode::Master
+                    | Mode::Coop => (
+                        locale.get("name_entry.high_score"),
+                        locale.format("result.score", &[&self.game.score]),
+                    ),
+                    Mode::Sprint => (
+                        locale.get("name_entry.best_time"),
+                        locale.format(
+                            "result.time",
+                            &[&highscores::format_time(self.game.elapsed())],
+                        ),
+                    ),
+                };
+                text::draw_centered(ctx, title, 40.0, self.config.colors.accent(), center - 96.0)?;
+                text::draw_centered(ctx, &result, 24.0, graphics::WHITE, center - 48.0)?;
+                let prompt = locale.get("name_entry.prompt");
+                text::draw_centered(ctx, prompt, 20.0, graphics::WHITE, center)?;
+                // Pad with underscores to show how many letters are left.
+                let name = format!("{:_<width$}", self.name, width = highscores::MAX_NAME_LEN);
+                text::draw_centered(ctx, &name, 32.0, self.config.colors.accent(), center + 48.0)?;
+                let keys = self.locale.get("name_entry.keys");
+                text::draw_centered(ctx, keys, 20.0, graphics::WHITE, center + 104.0)?;
+            }
+            Scene::Playing => {
+                self.draw_board(ctx)?;
+                self.draw_countdown(ctx)?;
+            }
+            // Hide the board while paused so the pause can't be used to plan.
+            Scene::Paused => {
+                let center = view(ctx).h / 2.0;
+                let title = self.locale.get("paused.title");
+                text::draw_centered(ctx, title, 48.0, graphics::WHITE, center)?;
+                let prompt = if self.can_retry() {
+                    "paused.keys_retry"
+                } else if self.can_save() {
+                    "paused.keys_save"
+                } else {
+                    "paused.keys"
+                };
+                let prompt = self.locale.get(prompt);
+                text::draw_centered(ctx, prompt, 20.0, graphics::WHITE, center + 48.0)?;
+                let fumen = self.locale.get("paused.fumen");
+                text::draw_centered(ctx, fumen, 20.0, graphics::WHITE, center + 76.0)?;
+                if self.game.mode() == Mode::Practice && self.versus.is_none() {
+                    let speed = self
+                        .locale
+                        .format("paused.speed", &[&self.config.practice_speed_percent]);
+                    text::draw_centered(ctx, &speed, 20.0, graphics::WHITE, center + 104.0)?;
+                }
+            }
+            Scene::GameOver => {
+                self.draw_board(ctx)?;
+                let top = view(ctx).h / 5.0;
+                let title = match self.outcome() {
+                    _ if self.game.mode() == Mode::Puzzle => {
+                        if self.game.cleared_goal() {
+                            "game_over.solved"
+                        } else {
+                            "game_over.failed"
+                        }
+                    }
+                    _ if self.game.mode() == Mode::Mission => {
+                        if self.game.cleared_goal() {
+                            "game_over.complete"
+                        } else {
+                            "game_over.failed"
+                        }
+                    }
+                    _ if self.game.cleared_goal() => "game_over.finished",
+                    _ if self.versus.is_none() => "game_over.title",
+                    Outcome::Win => "game_over.win",
+                    Outcome::Lose => "game_over.lose",
+                    Outcome::Draw => "game_over.draw",
+                    Outcome::Left => "game_over.left",
+                    Outcome::Undecided => "game_over.waiting",
+                };
+                let title = self.locale.get(title);
+                text::draw_centered(ctx, title, 48.0, self.config.colors.accent(), top)?;
+                let result = match self.game.mode() {
+                    Mode::Endless
+                    | Mode::Marathon
+                    | Mode::Zen
+                    | Mode::Practice
+                    | Mode::Invisible
+                    | Mode::Big
+                    | Mode::Ultra
+                    | Mode::TwentyG
+                    | Mode::Coop => self.locale.format("result.score", &[&self.game.score]),
+                    Mode::Master => match self.game.grade() {
+                        Some(grade) => self.locale.format("result.grade", &[&grade.label()]),
+                        None => self.locale.format("result.score", &[&self.game.score]),
+                    },
+                    Mode::Sprint => {
+                        let time = highscores::format_time(self.game.elapsed());
+                        self.locale.format("result.time", &[&time])
+                    }
+                    Mode::Puzzle => self.puzzles[self.puzzle].name.clone(),
+                    Mode::Mission => {
+                        let mission = &self.missions[self.mission];
+                        let stars = star_marks(mission.rating.stars(&self.game));
+                        self.locale
+                            .format("campaign.result", &[&mission.puzzle.name, &stars])
+                    }
+                };
+                text::draw_centered(ctx, &result, 32.0, graphics::WHITE, top + 48.0)?;
+                let mut y = top + 88.0;
+                for row in self.summary() {
+                    text::draw_centered(ctx, &row, 18.0, graphics::WHITE, y)?;
+                    y += 24.0;
+                }
+                y += 12.0;
+                let prompt = if self.can_retry() {
+                    "game_over.keys_retry"
+                } else if self.last_replay.is_some() && self.versus.is_none() {
+                    "game_over.keys_replay"
+                } else if self.versus.is_none() {
+                    "game_over.keys_heatmap"
+                } else {
+                    "game_over.keys"
+                };
+                let prompt = self.locale.get(prompt);
+                text::draw_centered(ctx, prompt, 20.0, graphics::WHITE, y)?;
+                if self.can_restart() {
+                    y += 28.0;
+                    let prompt = if self.last_replay.is_some() {
+                        self.locale.get("game_over.keys_restart_ghost")
+                    } else {
+                        self.locale.get("game_over.keys_restart")
+                    };
+                    text::draw_centered(ctx, prompt, 20.0, graphics::WHITE, y)?;
+                }
+                if self.versus.is_none() && RANKED_MODES.contains(&self.game.mode()) {
+                    let mode = self.game.mode();
+                    self.draw_high_scores(ctx, mode, y + 40.0, GAME_OVER_SCORE_ROWS)?;
+                }
+            }
+            Scene::Heatmap => {
+                let cell_size = self.cell_size();
+                let origin = self.layout().board();
+                render::draw_heatmap(ctx, &self.heatmap, cell_size, origin)?;
+                let title = self.locale.get("heatmap.title");
+                text::draw_centered(ctx, title, 20.0, self.config.colors.accent(), 24.0)?;
+                // Each column's share of the minos locked, along the bottom.
+                let bottom = view(ctx).h - 20.0;
+                for (x, share) in self.heatmap.columns().iter().enumerate() {
+                    let share = format!("{:.0}%", share * 100.0);
+                    let left = origin[0] + (x as i32 * cell_size) as f32;
+                    let at = na::Point2::new(left + 2.0, bottom);
+                    text::draw(ctx, &share, 12.0, graphics::WHITE, at)?;
+                }
+                let center = view(ctx).h / 2.0;
+                let keys = self.locale.get("heatmap.keys");
+                text::draw_centered(ctx, keys, 20.0, graphics::WHITE, center)?;
+            }
+            Scene::Stats => {
+                let top = 80.0;
+                let name = self.config.profile().name.to_uppercase();
+                let title = self.locale.format("stats.title", &[&name]);
+                text::draw_centered(ctx, &title, 40.0, self.config.colors.accent(), top)?;
+                let totals = self.lifetime.totals(&self.config.profile().name);
+                let accent = self.config.colors.accent();
+                totals.draw(ctx, view(ctx), top + 64.0, &self.locale, accent)?;
+                let bottom = view(ctx).h - 48.0;
+                let keys = self.locale.get("stats.keys");
+                text::draw_centered(ctx, keys, 20.0, graphics::WHITE, bottom)?;
+            }
+            Scene::Replay => {
+                self.draw_board(ctx)?;
+                let title = self.locale.get("replay.title");
+                text::draw_centered(ctx, title, 20.0, self.config.colors.accent(), 24.0)?;
+                let desynced_at = self.playback.as_ref().and_then(|p| p.desynced_at);
+                if let Some(frame) = desynced_at {
+                    let warning = self.locale.format("replay.desynced", &[&frame]);
+                    text::draw_centered(ctx, &warning, 16.0, self.config.colors.accent(), 52.0)?;
+                }
+                if self.game.game_over {
+                    let center = view(ctx).h / 2.0;
+                    let end = self.locale.get("replay.end");
+                    text::draw_centered(ctx, end, 32.0, self.config.colors.accent(), center)?;
+                    let score = self.locale.format("result.score", &[&self.game.score]);
+                    text::draw_centered(ctx, &score, 24.0, graphics::WHITE, center + 40.0)?;
+                    let keys = self.locale.get("replay.keys");
+                    text::draw_centered(ctx, keys, 20.0, graphics::WHITE, center + 76.0)?;
+                }
+            }
+            Scene::RaceSetup => {
+                let center = view(ctx).h / 2.0;
+                let accent = self.config.colors.accent();
+                let title = self.locale.get("race.title");
+                text::draw_centered(ctx, title, 48.0, accent, center - 160.0)?;
+                let about = self.locale.get("race.about");
+                text::draw_centered(ctx, about, 20.0, graphics::WHITE, center - 96.0)?;
+                let prompt = self.locale.get("race.prompt");
+                text::draw_centered(ctx, prompt, 20.0, graphics::WHITE, center - 48.0)?;
+                let typed = format!("{}_", self.race_seed);
+                text::draw_centered(ctx, &typed, 32.0, accent, center)?;
+                let keys = self.locale.get("race.keys");
+                text::draw_centered(ctx, keys, 20.0, graphics::WHITE, center + 56.0)?;
+            }
+            Scene::RaceResults { run } => self.draw_race_results(ctx, run)?,
+            Scene::Join => {
+                let center = view(ctx).h / 2.0;
+                let prompt = self.locale.get("join.prompt");
+                text::draw_centered(ctx, prompt, 20.0, graphics::WHITE, center - 48.0)?;
+                let typed = format!("{}_", self.joining);
+                text::draw_centered(ctx, &typed, 32.0, self.config.colors.accent(), center)?;
+                let keys = self.locale.get("join.keys");
+                text::draw_centered(ctx, keys, 20.0, graphics::WHITE, center + 56.0)?;
+            }
+            Scene::LocalGames { selected } => {
+                let top = 80.0;
+                let title = self.locale.get("local_games.title");
+                text::draw_centered(ctx, title, 40.0, self.config.colors.accent(), top)?;
+                let games = self.browser.as_ref().map_or(&[][..], Browser::games);
+                if games.is_empty() {
+                    let waiting = self.locale.get("local_games.waiting");
+                    text::draw_centered(ctx, waiting, 20.0, graphics::WHITE, top + 64.0)?;
+                }
+                for (i, game) in games.iter().enumerate() {
+                    let color = if i == selected {
+                        self.config.colors.accent()
+                    } else {
+                        graphics::WHITE
+                    };
+                    let y = top + 64.0 + 32.0 * i as f32;
+                    text::draw_centered(ctx, &game.label(), 24.0, color, y)?;
+                }
+                let bottom = view(ctx).h - 48.0;
+                let prompt = self.locale.get("local_games.keys");
+                text::draw_centered(ctx, prompt, 20.0, graphics::WHITE, bottom)?;
+            }
+            Scene::Packs { selected } => {
+                let top = 80.0;
+                let title = self.locale.get("mods.title");
+                text::draw_centered(ctx, title, 40.0, self.config.colors.accent(), top)?;
+                let packs = self.packs.all();
+                if packs.is_empty() {
+                    let none = self.locale.get("mods.none");
+                    text::draw_centered(ctx, none, 20.0, graphics::WHITE, top + 64.0)?;
+                }
+                for (i, pack) in packs.iter().enumerate() {
+                    let color = if i == selected {
+                        self.config.colors.accent()
+                    } else {
+                        graphics::WHITE
+                    };
+                    let on = self
+                        .locale
+                        .get(if pack.enabled { "mods.on" } else { "mods.off" });
+                    let line = if pack.author.is_empty() {
+                        self.locale.format("mods.pack", &[&pack.name, &on])
+                    } else {
+                        self.locale
+                            .format("mods.pack_by", &[&pack.name, &pack.author, &on])
+                    };
+                    let y = top + 64.0 + 32.0 * i as f32;
+                    text::draw_centered(ctx, &line, 24.0, color, y)?;
+                }
+                let bottom = view(ctx).h - 48.0;
+                if let Some(pack) = packs.get(selected) {
+                    let y = bottom - 72.0;
+                    text::draw_centered(ctx, &pack.description, 20.0, graphics::WHITE, y)?;
+                }
+                let note = self.locale.get("mods.next_start");
+                text::draw_centered(ctx, note, 20.0, graphics::WHITE, bottom - 36.0)?;
+                let prompt = self.locale.get("mods.keys");
+                text::draw_centered(ctx, prompt, 20.0, graphics::WHITE, bottom)?;
+            }
+            Scene::Lobby => {
+                let status = match self.lobby.as_ref().map(Lobby::status) {
+                    Some((key, Some(value))) => self.locale.format(key, &[&value]),
+                    Some((key, None)) => self.locale.get(key).to_string(),
+                    None => String::new(),
+                };
+                let center = view(ctx).h / 2.0;
+                text::draw_centered(ctx, &status, 20.0, graphics::WHITE, center)?;
+                let keys = self.locale.get("lobby.keys");
+                text::draw_centered(ctx, keys, 20.0, graphics::WHITE, center + 40.0)?;
+            }
+            Scene::Puzzles { selected } => {
+                let locale = &self.locale;
+                let items: Vec<String> = self
+                    .puzzles
+                    .iter()
+                    .map(|puzzle| {
+                        let mut line = puzzle.name.clone();
+                        if let Some(par) = puzzle.par {
+                            line = locale.format("puzzles.with_par", &[&line, &par]);
+                        }
+                        if let Some(best) = self.progress.best(&puzzle.name) {
+                            line = locale.format("puzzles.solved", &[&line, &best]);
+                        }
+                        line
+                    })
+                    .collect();
+                let title = locale.get("puzzles.title");
+                draw_menu(ctx, title, &items, selected, self.config.colors.accent())?;
+                let bottom = view(ctx).h - 48.0;
+                let solved = self.progress.count(&self.puzzles);
+                let count = locale.format("puzzles.count", &[&solved, &self.puzzles.len()]);
+                text::draw_centered(ctx, &count, 20.0, graphics::WHITE, bottom - 36.0)?;
+                let keys = locale.get("puzzles.keys");
+                text::draw_centered(ctx, keys, 20.0, graphics::WHITE, bottom)?;
+            }
+            Scene::Missions { selected } => {
+                let locale = &self.locale;
+                let items: Vec<String> = self
+                    .missions
+                    .iter()
+                    .enumerate()
+                    .map(|(i, mission)| {
+                        let name = &mission.puzzle.name;
+                        if self.campaign.is_unlocked(&self.missions, i) {
+                            let goal = self.goal_text(mission.puzzle.goal);
+                            let stars = star_marks(self.campaign.stars(name));
+                            locale.format("campaign.mission", &[name, &goal, &stars])
+                        } else {
+                            locale.format("campaign.locked", &[name])
+                        }
+                    })
+                    .collect();
+                let title = locale.get("campaign.title");
+                draw_menu(ctx, title, &items, selected, self.config.colors.accent())?;
+                let bottom = view(ctx).h - 48.0;
+                let earned = self.campaign.total(&self.missions);
+                let most = self.missions.len() as u32 * mission::MAX_STARS;
+                let total = locale.format("campaign.total", &[&earned, &most]);
+                text::draw_centered(ctx, &total, 20.0, graphics::WHITE, bottom - 36.0)?;
+                let keys = locale.get("campaign.keys");
+                text::draw_centered(ctx, keys, 20.0, graphics::WHITE, bottom)?;
+            }
+            Scene::Editor => self.draw_editor(ctx)?,
+            Scene::Demo => {
+                self.draw_board(ctx)?;
+                let title = self.locale.get("demo.title");
+                text::draw_centered(ctx, title, 20.0, self.config.colors.accent(), 24.0)?;
+                let bottom = view(ctx).h - 48.0;
+                let keys = self.locale.get("demo.keys");
+                text::draw_centered(ctx, keys, 20.0, graphics::WHITE, bottom)?;
+            }
+            Scene::Spectating => {
+                let center = view(ctx).h / 2.0;
+                if let Some(ref mut spectator) = self.spectator {
+                    let (cell_size, color) = (self.config.cell_size, self.config.colors.subdued());
+                    spectator.draw(ctx, cell_size, &self.locale, color)?;
+                    let status = match spectator.outcome() {
+                        _ if !spectator.is_ready() && !spectator.is_closed() => {
+                            Some("spectating.waiting")
+                        }
+                        Outcome::Win => Some("spectating.first_wins"),
+                        Outcome::Lose => Some("spectating.second_wins"),
+                        Outcome::Draw => Some("spectating.draw"),
+                        _ if spectator.is_closed() => Some("spectating.closed"),
+                        _ => None,
+                    };
+                    if let Some(status) = status {
+                        let status = self.locale.get(status);
+                        text::draw_centered(
+                            ctx,
+                            status,
+                            32.0,
+                            self.config.colors.accent(),
+                            center,
+                        )?;
+                    }
+                }
+                let bottom = view(ctx).h - 48.0;
+                let keys = self.locale.get("spectating.keys");
+                text::draw_centered(ctx, keys, 20.0, graphics::WHITE, bottom)?;
+            }
+        }
+
+        let online = self.versus.as_ref().is_some_and(Versus::is_remote);
+        if let (Scene::Playing | Scene::GameOver, true) = (self.scene, online) {
+            self.draw_chat(ctx)?;
+        }
+        self.draw_toast(ctx)?;
+        graphics::pop_transform(ctx);
+        graphics::apply_transformations(ctx)?;
+        graphics::present(ctx)?;
+        // Holds the next frame back until its turn under the cap, or the
+        // idle rate.
+        let fps = if self.idle() {
+            Some(IDLE_FPS)
+        } else {
+            self.config.fps_cap
+        };
+        if let Some(fps) = fps {
+            let frame = Duration::from_secs(1) / fps.max(1);
+            let since = self.last_frame.elapsed();
+            if since < frame {
+                timer::sleep(frame - since);
+            }
+        }
+        self.last_frame = Instant::now();
+        Ok(())
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        keymods: KeyMods,
+        repeat: bool,
+    ) {
+        if repeat && !self.takes_os_repeat(keycode) {
+            return;
+        }
+        self.idle_frames = 0;
+        self.last_input = Instant::now();
+        if keycode == KeyCode::F11 || keycode == KeyCode::Return && keymods.contains(KeyMods::ALT) {
+            self.toggle_fullscreen(ctx);
+            return;
+        }
+        if keycode == KeyCode::F9
+            && matches!(self.scene, Scene::Playing | Scene::Paused | Scene::GameOver)
+        {
+            self.save_bug_report(ctx);
+            return;
+        }
+        let online = self.versus.as_ref().is_some_and(Versus::is_remote);
+        if let (Scene::Playing | Scene::GameOver, true) = (self.scene, online) {
+            if self.chat_key(keycode) {
+                return;
+            }
+        }
+        match self.scene {
+            Scene::Menu { selected } => self.menu_key(ctx, keycode, selected),
+            Scene::Options { selected } => self.options_key(ctx, keycode, selected),
+            Scene::Handling { selected } => self.handling_key(ctx, keycode, selected),
+            Scene::VersusSetup { selected } => self.versus_setup_key(ctx, keycode, selected),
+            Scene::Recover { selected } => match keycode {
+                KeyCode::Up | KeyCode::Down => {
+                    self.scene = Scene::Recover {
+                        selected: 1 - selected,
+                    };
+                }
+                KeyCode::Return | KeyCode::Space if selected == 0 => {
+                    self.continue_game(ctx, Slot::Autosave);
+                }
+                KeyCode::Return | KeyCode::Space | KeyCode::Escape => {
+                    savegame::delete(ctx, Slot::Autosave);
+                    self.scene = Scene::Menu { selected: 0 };
+                }
+                _ => (),
+            },
+            Scene::Controls {
+                selected,
+                waiting: false,
+            } => self.controls_key(keycode, selected),
+            Scene::Controls {
+                selected,
+                waiting: true,
+            } => self.rebind_key(ctx, keycode, selected),
+            Scene::Playing => match keycode {
+                _ if Some(keycode) == self.restart_key && self.can_restart_now() => {
+                    if self.config.restart_hold_millis == 0 {
+                        self.restart_now(ctx);
+                    } else {
+                        self.restart_held = Some(Instant::now());
+                    }
+                }
+                // The other player can't be paused too.
+                KeyCode::Escape | KeyCode::P
+                    if self.versus.as_ref().is_some_and(Versus::is_remote) => {}
+                KeyCode::Escape | KeyCode::P => self.pause(),
+                // Stats, then stats with the overlay, then neither.
+                KeyCode::F3 if self.config.show_stats && !self.debug => self.debug = true,
+                KeyCode::F3 => {
+                    self.config.show_stats = !self.config.show_stats;
+                    self.debug = false;
+                    self.stepping = false;
+                    self.save_config(ctx);
+                }
+                // Freezing the round to step through it a tick at a time,
+                // with input still taken in between, for rotation and lock
+                // edge cases. Not with an opponent, who won't wait.
+                KeyCode::F6 if self.debug && self.versus.is_none() => {
+                    self.stepping = !self.stepping;
+                    self.steps = 0;
+                }
+                KeyCode::F7 if self.stepping => self.steps += 1,
+                // Picking who to attack by hand, unless Tab is bound to play.
+                KeyCode::Tab
+                    if self.versus.as_ref().is_some_and(Versus::is_crowded)
+                        && self.keymap.input_for(keycode).is_none() =>
+                {
+                    if let Some(ref mut versus) = self.versus {
+                        versus.cycle_target();
+                    }
+                }
+                _ if self.countdown > 0 => (),
+                _ => {
+                    let partner = self.partner.as_ref().and_then(|p| p.input_for(keycode));
+                    if let Some(input) = partner {
+                        self.act_partner(Action::Press(input));
+                    } else if let Some(input) = self.keymap.input_for(keycode) {
+                        self.act(Action::Press(input));
+                    }
+                }
+            },
+            Scene::Paused => match keycode {
+                KeyCode::Escape | KeyCode::P => {
+                    self.act(Action::Resume);
+                    self.audio.resume_music();
+                    self.scene = Scene::Playing;
+                }
+                KeyCode::R if self.can_retry() => self.retry(ctx),
+                KeyCode::F => self.export_fumen(),
+                KeyCode::Left | KeyCode::Right
+                    if self.game.mode() == Mode::Practice && self.versus.is_none() =>
+                {
+                    let speed = &mut self.config.practice_speed_percent;
+                    *speed = if keycode == KeyCode::Right {
+                        (*speed + PRACTICE_SPEED_STEP).min(MAX_PRACTICE_SPEED)
+                    } else {
+                        speed
+                            .saturating_sub(PRACTICE_SPEED_STEP)
+                            .max(MIN_PRACTICE_SPEED)
+                    };
+                    self.save_config(ctx);
+                }
+                KeyCode::Q if self.can_retry() => {
+                    self.audio.stop_music();
+                    self.scene = match self.game.mode() {
+                        Mode::Puzzle => Scene::Puzzles {
+                            selected: self.puzzle,
+                        },
+                        Mode::Mission => Scene::Missions {
+                            selected: self.mission,
+                        },
+                        _ => self.menu(MenuItem::Practice),
+                    };
+                }
+                KeyCode::Q if self.versus.is_some() => {
+                    self.audio.stop_music();
+                    self.scene = self.menu(MenuItem::Versus);
+                }
+                // Co-op can't be saved to go on with later.
+                KeyCode::Q if self.partner.is_some() => {
+                    self.audio.stop_music();
+                    self.scene = self.menu(MenuItem::Coop);
+                }
+                // A race can't be picked up again later.
+                KeyCode::Q if self.race.is_some() => {
+                    self.audio.stop_music();
+                    self.race = None;
+                    self.scene = Scene::RaceSetup;
+                }
+                KeyCode::Q => {
+                    self.audio.stop_music();
+                    self.save_game(ctx);
+                    self.scene = self.menu(MenuItem::Continue);
+                }
+                _ => (),
+            },
+            Scene::NameEntry { rank } => self.name_entry_key(keycode, rank),
+            Scene::HighScores { mode } => match keycode {
+                KeyCode::Return | KeyCode::Space | KeyCode::Escape => {
+                    self.scene = self.menu(MenuItem::HighScores);
+                }
+                KeyCode::Left | KeyCode::Right => {
+                    let tables = RANKED_MODES.len();
+                    let at = RANKED_MODES.iter().position(|&m| m == mode).unwrap_or(0);
+                    let at = if keycode == KeyCode::Left {
+                        (at + tables - 1) % tables
+                    } else {
+                        (at + 1) % tables
+                    };
+                    self.scene = Scene::HighScores {
+                        mode: RANKED_MODES[at],
+                    };
+                }
+                _ => (),
+            },
+            Scene::GameOver => match keycode {
+                KeyCode::Return | KeyCode::Escape => {
+                    // Hangs up on a network opponent and any spectators.
+                    self.versus = None;
+                    self.partner = None;
+                    self.ghost = None;
+                    self.spectators = None;
+                    self.scene = match self.game.mode() {
+                        Mode::Puzzle => Scene::Puzzles {
+                            selected: self.puzzle,
+                        },
+                        Mode::Mission => Scene::Missions {
+                            selected: self.mission,
+                        },
+                        Mode::Coop => self.menu(MenuItem::Coop),
+                        _ => self.menu(MenuItem::Start),
+                    };
+                }
+                KeyCode::R if self.can_retry() => self.retry(ctx),
+                KeyCode::S if self.can_restart() => {
+                    let seed = self.game.seed();
+                    self.start(ctx, self.game.mode(), false, Some(seed));
+                }
+                KeyCode::N if self.can_restart() => self.start(ctx, self.game.mode(), false, None),
+                KeyCode::G if self.can_restart() => self.race_ghost(ctx),
+                KeyCode::R if self.versus.is_none() => {
+                    if let Some(replay) = self.last_replay.clone() {
+                        self.watch(ctx, replay);
+                    }
+                }
+                // Versus rounds stay on the game over screen, which keeps
+                // the other side in step.
+                KeyCode::H if self.versus.is_none() => self.scene = Scene::Heatmap,
+                _ => (),
+            },
+            Scene::Heatmap => match keycode {
+                KeyCode::Return | KeyCode::Space | KeyCode::Escape | KeyCode::H => {
+                    self.scene = Scene::GameOver
+                }
+                _ => (),
+            },
+            Scene::Stats => match keycode {
+                KeyCode::Return | KeyCode::Space | KeyCode::Escape => {
+                    self.scene = self.menu(MenuItem::Stats)
+                }
+                _ => (),
+            },
+            Scene::Demo => self.end_demo(),
+            Scene::RaceSetup => match keycode {
+                KeyCode::Back => {
+                    self.race_seed.pop();
+                }
+                KeyCode::Return => self.start_race(ctx),
+                KeyCode::Escape => self.scene = self.menu(MenuItem::Race),
+                _ => (),
+            },
+            Scene::RaceResults { .. } => match keycode {
+                KeyCode::Return | KeyCode::R => self.start_race(ctx),
+                KeyCode::Escape => self.scene = Scene::RaceSetup,
+                _ => (),
+            },
+            Scene::Join => match keycode {
+                KeyCode::Back => {
+                    self.joining.pop();
+                }
+                KeyCode::Return => {
+                    let addr = self.joining.trim().to_string();
+                    if addr.is_empty() {
+                        return;
+                    }
+                    if let Err(e) = self.join(&addr) {
+                        eprintln!("Could not join {}: {}", addr, e);
+                    }
+                }
+                KeyCode::Escape => self.scene = self.menu(MenuItem::Join),
+                _ => (),
+            },
+            Scene::LocalGames { selected } => {
+                let games = self.browser.as_ref().map_or(&[][..], Browser::games);
+                let count = games.len().max(1);
+                match keycode {
+                    KeyCode::Up => {
+                        let selected = (selected + count - 1) % count;
+                        self.scene = Scene::LocalGames { selected };
+                    }
+                    KeyCode::Down => {
+                        let selected = (selected + 1) % count;
+                        self.scene = Scene::LocalGames { selected };
+                    }
+                    KeyCode::Return | KeyCode::Space => {
+                        let addr = match games.get(selected) {
+                            Some(game) => game.addr.to_string(),
+                            None => return,
+                        };
+                        self.browser = None;
+                        if let Err(e) = self.join(&addr) {
+                            eprintln!("Could not join {}: {}", addr, e);
+                            self.scene = self.menu(MenuItem::Local);
+                        }
+                    }
+                    KeyCode::Escape => {
+                        self.browser = None;
+                        self.scene = self.menu(MenuItem::Local);
+                    }
+                    _ => (),
+                }
+            }
+            Scene::Packs { selected } => {
+                let count = self.packs.all().len().max(1);
+                match keycode {
+                    KeyCode::Up => {
+                        let selected = (selected + count - 1) % count;
+                        self.scene = Scene::Packs { selected };
+                    }
+                    KeyCode::Down => {
+                        let selected = (selected + 1) % count;
+                        self.scene = Scene::Packs { selected };
+                    }
+                    KeyCode::Return | KeyCode::Space if selected < self.packs.all().len() => {
+                        self.config.disabled_packs = self.packs.toggle(selected);
+                        self.save_config(ctx);
+                    }
+                    KeyCode::Escape => self.scene = self.menu(MenuItem::Packs),
+                    _ => (),
+                }
+            }
+            Scene::Puzzles { selected } => {
+                let count = self.puzzles.len().max(1);
+                match keycode {
+                    KeyCode::Up => {
+                        let selected = (selected + count - 1) % count;
+                        self.scene = Scene::Puzzles { selected };
+                    }
+                    KeyCode::Down => {
+                        let selected = (selected + 1) % count;
+                        self.scene = Scene::Puzzles { selected };
+                    }
+                    KeyCode::Return | KeyCode::Space if selected < self.puzzles.len() => {
+                        self.pick_puzzle(ctx, selected);
+                        self.start_puzzle(ctx);
+                    }
+                    KeyCode::Escape => self.scene = self.menu(MenuItem::Puzzle),
+                    _ => (),
+                }
+            }
+            Scene::Missions { selected } => {
+                let count = self.missions.len().max(1);
+                match keycode {
+                    KeyCode::Up => {
+                        let selected = (selected + count - 1) % count;
+                        self.scene = Scene::Missions { selected };
+                    }
+                    KeyCode::Down => {
+                        let selected = (selected + 1) % count;
+                        self.scene = Scene::Missions { selected };
+                    }
+                    KeyCode::Return | KeyCode::Space
+                        if self.campaign.is_unlocked(&self.missions, selected) =>
+                    {
+                        self.mission = selected;
+                        self.start_mission(ctx);
+                    }
+                    KeyCode::Escape => self.scene = self.menu(MenuItem::Campaign),
+                    _ => (),
+                }
+            }
+            Scene::Editor => self.editor_key(ctx, keycode),
+            Scene::Lobby => match keycode {
+                KeyCode::Return | KeyCode::Space => {
+                    if let Some(ref mut lobby) = self.lobby {
+                        lobby.ready();
+                    }
+                }
+                KeyCode::Escape => {
+                    self.lobby = None;
+                    self.scene = Scene::Menu { selected: 0 };
+                }
+                _ => (),
+            },
+            Scene::Spectating => {
+                if let KeyCode::Return | KeyCode::Escape = keycode {
+                    self.spectator = None;
+                    self.scene = Scene::Menu { selected: 0 };
+                }
+            }
+            Scene::Replay => {
+                if let KeyCode::Return | KeyCode::Escape = keycode {
+                    self.audio.stop_music();
+                    self.playback = None;
+                    self.scene = self.menu(MenuItem::Start);
+                }
+            }
+        }
+    }
+
+    /// Closing the window mid-game keeps the game for next time.
+    fn quit_event(&mut self, ctx: &mut Context) -> bool {
+        if let (Scene::Playing | Scene::Paused, true) = (self.scene, self.can_save()) {
+            self.save_game(ctx);
+        }
+        false
+    }
+
+    fn text_input_event(&mut self, ctx: &mut Context, character: char) {
+        if let Scene::NameEntry { .. } = self.scene {
+            // Letters, digits and inner spaces only, which also keeps out
+            // the control characters sent for Backspace and Enter, and the
+            // hard drop that may have ended the game.
+            let allowed =
+                character.is_ascii_alphanumeric() || (character == ' ' && !self.name.is_empty());
+            if allowed && self.name.len() < highscores::MAX_NAME_LEN {
+                self.name.push(character.to_ascii_uppercase());
+            }
+        }
+        if self.chat.typing().is_some() {
+            self.chat.type_char(character);
+        }
+        if let (Scene::Editor, Some(editor)) = (self.scene, self.editor.as_mut()) {
+            // Typing a piece's letter deals it after the rest.
+            if let Some(piece) = Tetromino::from_symbol(character.to_ascii_uppercase()) {
+                editor.push_piece(piece);
+                self.preview_editor(ctx);
+            }
+        }
+        if let Scene::RaceSetup = self.scene {
+            let allowed = character.is_ascii_alphanumeric() || character == '-';
+            if allowed && self.race_seed.len() < races::MAX_SEED_LEN {
+                self.race_seed.push(character);
+            }
+        }
+        if let Scene::Join = self.scene {
+            // Enough for a room code, a host name or an address and port.
+            let allowed = character.is_ascii_alphanumeric() || "-.:[]".contains(character);
+            if allowed && self.joining.len() < MAX_JOIN_LEN {
+                self.joining.push(character);
+            }
+        }
+    }
+
+    /// Drops to `IDLE_FPS` while the window is in the background, and back
+    /// to full speed once it's in front again. A round in play pauses as it
+    /// goes, if the player wants.
+    fn focus_event(&mut self, ctx: &mut Context, gained: bool) {
+        self.focused = gained;
+        self.last_input = Instant::now();
+        if gained {
+            // Time the window spent minimized is let go of rather than
+            // caught up on in a burst of frames, unless a round is still
+            // running through it.
+            if self.scene != Scene::Playing {
+                while timer::check_update_time(ctx, replay::FRAMES_PER_SECOND) {}
+            }
+        } else if self.config.pause_on_focus_loss && self.can_pause() {
+            self.pause();
+        }
+    }
+
+    /// Scales everything to the new size of the window, keeping its shape.
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        let view = view(ctx);
+        if let Err(e) = letterbox(ctx, view.w, view.h, width, height) {
+            eprintln!("Could not resize the window: {}", e);
+        }
+    }
+
+    /// Mouse gestures on the board play like touches: see `Gestures`. With
+    /// mouse placement on, a click hard drops instead.
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        self.last_input = Instant::now();
+        // In the editor the left button paints cells in and the right
+        // rubs them out.
+        if self.scene == Scene::Editor {
+            let cell = self.editor_cell(ctx, x, y);
+            if let Some(ref mut editor) = self.editor {
+                editor.note = None;
+                match button {
+                    MouseButton::Left => editor.start_stroke(cell, true),
+                    MouseButton::Right => editor.start_stroke(cell, false),
+                    _ => return,
+                }
+            }
+            return self.preview_editor(ctx);
+        }
+        if button != MouseButton::Left || self.scene != Scene::Playing || self.countdown > 0 {
+            return;
+        }
+        if self.config.mouse_placement {
+            self.tap(Input::HardDrop);
+        } else {
+            let (x, y) = self.board_cell(ctx, x, y);
+            self.gestures.start(x, y);
+        }
+    }
+
+    fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
+        self.last_input = Instant::now();
+        if self.scene == Scene::Editor {
+            let cell = self.editor_cell(ctx, x, y);
+            if self
+                .editor
+                .as_mut()
+                .is_some_and(|editor| editor.stroke_to(cell))
+            {
+                self.preview_editor(ctx);
+            }
+            return;
+        }
+        if self.scene != Scene::Playing {
+            return;
+        }
+        let (x, y) = self.board_cell(ctx, x, y);
+        if self.config.mouse_placement {
+            self.pointer = Some(x);
+            if self.countdown == 0 {
+                self.aim();
+            }
+            return;
+        }
+        for input in self.gestures.drag(x, y) {
+            self.tap(input);
+        }
+    }
+
+    fn mouse_button_up_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        if let Some(ref mut editor) = self.editor {
+            editor.end_stroke();
+        }
+        if button != MouseButton::Left {
+            return;
+        }
+        let (x, y) = self.board_cell(ctx, x, y);
+        // Paused since it went down, it's dropped.
+        if let (Some(input), Scene::Playing) = (self.gestures.end(x, y), self.scene) {
+            self.tap(input);
+        }
+    }
+
+    /// With mouse placement on, each notch of the wheel either way turns
+    /// the piece clockwise, the only way it turns.
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) {
+        self.last_input = Instant::now();
+        if self.config.mouse_placement
+            && self.scene == Scene::Playing
+            && self.countdown == 0
+            && y != 0.0
+        {
+            self.tap(Input::RotateCw);
+            // Turning can change how wide it is.
+            self.aim();
+        }
+    }
+
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymods: KeyMods) {
+        if Some(keycode) == self.restart_key {
+            self.restart_held = None;
+        }
+        // Releases while paused don't matter, resuming drops held keys.
+        if self.scene != Scene::Playing || self.countdown > 0 {
+            return;
+        }
+        let partner = self.partner.as_ref().and_then(|p| p.input_for(keycode));
+        if let Some(input) = partner {
+            self.act_partner(Action::Release(input));
+        } else if let Some(input) = self.keymap.input_for(keycode) {
+            self.act(Action::Release(input));
+        }
+    }
+}
+
+/// The particles, trails and screen shake that events set off, with the
+/// config that says how they look.
+struct Effects<'a> {
+    particles: &'a mut Particles,
+    trails: &'a mut Trails,
+    shake: &'a mut Shake,
+    config: &'a Config,
+}
+
+impl Observer for Effects<'_> {
+    fn notify(&mut self, event: Event, _: u64, game: &Game) {
+        match event {
+            Event::RowCleared(y) => {
+                let (board, colors) = (&game.board, &self.config.colors);
+                // The row is still there if it waits out a line clear
+                // delay.
+                let color = |x| match board.get(na::Point2::new(x, y)) {
+                    Some(Some(block)) => colors.block(block),
+                    _ => graphics::WHITE,
+                };
+                self.particles.clear_row(y, board.width(), color);
+            }
+            Event::HardDrop {
+                tetromino,
+                pos,
+                facing,
+                from,
+            } => {
+                let color = self.config.colors.piece(tetromino);
+                self.particles.hard_drop(tetromino, pos, facing, color);
+                let now = Instant::now();
+                self.trails.add(now, tetromino, pos, facing, from, color);
+                if self.config.screen_shake {
+                    self.shake.kick(Instant::now(), HARD_DROP_SHAKE);
+                }
+            }
+            Event::LineClear(4) if self.config.screen_shake => {
+                self.shake.kick(Instant::now(), TETRIS_SHAKE);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Lays the screen out for `boards` copies of `board` side by side, sizing
+/// the window to fit them, or letterboxing them if it's fullscreen.
+fn fit_boards(ctx: &mut Context, board: &Board, boards: usize, cell_size: i32) {
+    let layout = Layout::new(board, cell_size);
+    fit_size(ctx, boards as f32 * layout.width(), layout.height());
+}
+
+/// Sizes the window to `width` by `height`, or letterboxes that in the
+/// screen when fullscreen.
+fn fit_size(ctx: &mut Context, width: f32, height: f32) {
+    let window = if is_fullscreen(ctx) {
+        Ok(graphics::drawable_size(ctx))
+    } else {
+        graphics::set_drawable_size(ctx, width, height).map(|()| (width, height))
+    };
+    let resized = window.and_then(|(window_width, window_height)| {
+        letterbox(ctx, width, height, window_width, window_height)
+    });
+    if let Err(e) = resized {
+        eprintln!("Could not resize the window: {}", e);
+    }
+}
+
+/// How the window is made, with or without vsync.
+fn window_setup(vsync: bool) -> conf::WindowSetup {
+    conf::WindowSetup::default().title("Tetris").vsync(vsync)
+}
+
+/// Writes the `conf.toml` ggez reads from the user config directory as it
+/// makes the window, which is the only point vsync can be set.
+fn save_window_conf(ctx: &mut Context, vsync: bool) -> GameResult {
+    let mut conf = conf::Conf::new();
+    conf.window_setup = window_setup(vsync);
+    filesystem::write_config(ctx, &conf)
+}
+
+fn is_fullscreen(ctx: &Context) -> bool {
+    ctx.conf.window_mode.fullscreen_type != conf::FullscreenType::Windowed
+}
+
+/// Lays a `width` by `height` screen out in a window `window_width` by
+/// `window_height`, as big as it fits, with bars either side or above and
+/// below where their shapes differ.
+fn letterbox(
+    ctx: &mut Context,
+    width: f32,
+    height: f32,
+    window_width: f32,
+    window_height: f32,
+) -> GameResult {
+    // Minimised.
+    if window_width <= 0.0 || window_height <= 0.0 {
+        return Ok(());
+    }
+    let scale = (window_width / width).min(window_height / height);
+    let (view_width, view_height) = (window_width / scale, window_height / scale);
+    let screen = graphics::Rect::new(
+        (width - view_width) / 2.0,
+        (height - view_height) / 2.0,
+        view_width,
+        view_height,
+    );
+    graphics::set_screen_coordinates(ctx, screen)
+}
+
+/// The part of the screen the game is laid out in, leaving out the bars
+/// `letterbox` puts round it.
+fn view(ctx: &Context) -> graphics::Rect {
+    let screen = graphics::screen_coordinates(ctx);
+    graphics::Rect::new(
+        0.0,
+        0.0,
+        screen.w + 2.0 * screen.x,
+        screen.h + 2.0 * screen.y,
+    )
+}
+
+/// Where a point in the window, in pixels as ggez gives mouse positions,
+/// is in screen coordinates.
+fn screen_point(ctx: &Context, x: f32, y: f32) -> na::Point2<f32> {
+    let screen = graphics::screen_coordinates(ctx);
+    let (width, height) = graphics::drawable_size(ctx);
+    na::Point2::new(
+        screen.x + x / width * screen.w,
+        screen.y + y / height * screen.h,
+    )
+}
+
+/// `value` with its digits in groups of three, as 42,300.
+fn group_digits(value: u32) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// `earned` stars out of a mission's most, as filled and hollow ones.
+fn star_marks(earned: u32) -> String {
+    (0..mission::MAX_STARS)
+        .map(|i| if i < earned { '★' } else { '☆' })
+        .collect()
+}
+
+/// Moves line `at` of `handicap`, in the order the versus setup screen
+/// lists them, a step up or down. Down shows fewer pieces, from all of
+/// them to none.
+fn step_handicap(handicap: &mut Handicap, at: usize, up: bool) {
+    let step = |percent: u32, least: u32| {
+        if up {
+            (percent + HANDICAP_STEP_PERCENT).min(MAX_HANDICAP_PERCENT)
+        } else {
+            percent.saturating_sub(HANDICAP_STEP_PERCENT).max(least)
+        }
+    };
+    match at {
+        0 if up => handicap.garbage_rows = (handicap.garbage_rows + 1).min(MAX_GARBAGE_ROWS),
+        0 => handicap.garbage_rows = handicap.garbage_rows.saturating_sub(1),
+        1 => {
+            handicap.preview = match (handicap.preview, up) {
+                (None, true) => None,
+                (None, false) => Some(MAX_PREVIEWS - 1),
+                (Some(shown), true) if shown + 1 >= MAX_PREVIEWS => None,
+                (Some(shown), true) => Some(shown + 1),
+                (Some(shown), false) => Some(shown.saturating_sub(1)),
+            }
+        }
+        2 => handicap.speed_percent = step(handicap.speed_percent, MIN_SPEED_PERCENT),
+        _ => handicap.attack_percent = step(handicap.attack_percent, 0),
+    }
+}
+
+/// Draws a title and a vertical list of entries with `selected` highlighted.
+fn draw_menu(
+    ctx: &mut Context,
+    title: &str,
+    items: &[String],
+    selected: usize,
+    accent: graphics::Color,
+) -> GameResult {
+    let top = view(ctx).h / 3.0;
+    text::draw_centered(ctx, title, 48.0, accent, top)?;
+    let first = top + 80.0;
+    // Lists too long for the screen scroll to keep the selected item on it.
+    let rows = (((view(ctx).h - first) / 32.0) as usize).max(1);
+    let skip = (selected + 1)
+        .saturating_sub(rows)
+        .min(items.len().saturating_sub(rows));
+    for (i, item) in items.iter().enumerate().skip(skip).take(rows) {
+        let (text, color) = if i == selected {
+            (format!("> {} <", item), accent)
+        } else {
+            (item.clone(), graphics::WHITE)
+        };
+        let y = first + 32.0 * (i - skip) as f32;
+        text::draw_centered(ctx, &text, 20.0, color, y)?;
+    }
+    Ok(())
+}
+
+pub fn main() -> GameResult {
+    let cli = Cli::parse();
+    if let (true, Some(script)) = (cli.headless, &cli.script) {
+        return headless::run(&cli, script);
+    }
+    if let Some(games) = cli.ai_selfplay {
+        return headless::selfplay(&cli, games);
+    }
+    if let (Some(replay), Some(gif)) = (&cli.replay, &cli.gif) {
+        return clip::export(replay, gif);
+    }
+    if cli.tui {
+        return tui::run(&cli);
+    }
+    let mut builder =
+        ggez::ContextBuilder::new("tetris-rs", "Sam").window_setup(window_setup(true));
+    // Use the resources next to the sources when run through cargo.
+    if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
+        builder = builder.add_resource_path(Path::new(&manifest_dir).join("resources"));
+    }
+    let (ctx, events_loop) = &mut builder.build()?;
+
+    // The config lives in the user directory, which ggez only gives us once
+    // the context exists, so size the window after the fact.
+    let config = Config::load(ctx, cli.config.clone())?;
+    // The window was made before the config could be read, with the vsync
+    // ggez keeps, so keep it in step for the next start.
+    if config.vsync != ctx.conf.window_setup.vsync {
+        if let Err(e) = save_window_conf(ctx, config.vsync) {
+            eprintln!("Could not save the window settings: {}", e);
+        }
+    }
+    let packs = Packs::load(ctx, &config.disabled_packs);
+    packs.mount(ctx);
+    text::load(ctx);
+    // Before anything deals a piece, which fixes the shape table.
+    pieces::load(ctx, &packs)?;
+    pieces::load_kicks(ctx, &packs)?;
+    pieces::load_gravity(ctx, &packs)?;
+    pieces::load_attack(ctx, &packs)?;
+    let (width, height) = config.screen_size(1);
+    let fullscreen_type = if cli.fullscreen(config.fullscreen) {
+        conf::FullscreenType::Desktop
+    } else {
+        conf::FullscreenType::Windowed
+    };
+    graphics::set_mode(
+        ctx,
+        conf::WindowMode::default()
+            .dimensions(width, height)
+            .resizable(true)
+            .fullscreen_type(fullscreen_type),
+    )?;
+    let (window_width, window_height) = graphics::drawable_size(ctx);
+    letterbox(ctx, width, height, window_width, window_height)?;
 
-    let state = &mut MainState::new(ctx)?;
+    let state = &mut MainState::new(ctx, config, packs, &cli)?;
     event::run(ctx, events_loop, state)
 }
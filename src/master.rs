@@ -0,0 +1,117 @@
+//! Master mode, after TGM: a level from 0 to 999 that every piece and
+//! every cleared line moves on, pieces falling faster until they're at 20G
+//! half way, the stack fading out of sight in the last section, and a grade
+//! for the score, from 9 up to GM.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Reaching this level finishes the round.
+pub const LAST_LEVEL: u32 = 999;
+
+/// Levels to a section. A piece can't take the level into the next
+/// section, or to the last level; only clearing lines can.
+const SECTION_LEVELS: u32 = 100;
+
+/// Levels to each entry of the `master` curve in `gravity.toml`.
+pub const GRAVITY_LEVELS: u32 = 10;
+
+/// From here on pieces fall at 20G.
+pub const TWENTY_G_LEVEL: u32 = 500;
+
+/// From here on, the last section, the stack is out of sight.
+pub const FADE_LEVEL: u32 = 900;
+
+/// The score each grade from 8 up to S9 takes, lowest first.
+const GRADE_SCORES: [u32; 17] = [
+    400, 800, 1400, 2000, 3500, 5500, 8000, 12000, 16000, 22000, 30000, 40000, 52000, 66000, 82000,
+    100_000, 120_000,
+];
+
+/// What it takes to stay in line for GM: each level reached within the
+/// time, in seconds, and with the score.
+const GM_CHECKPOINTS: [(u32, u64, u32); 3] =
+    [(300, 255, 12000), (500, 450, 40000), (999, 810, 126_000)];
+
+/// A grade from 9, the lowest, through 1 and S1 to S9, then GM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Grade(usize);
+
+impl Grade {
+    pub const GM: Grade = Grade(GRADE_SCORES.len() + 1);
+
+    /// The grade `score` has earned, short of GM.
+    pub fn for_score(score: u32) -> Grade {
+        Grade(GRADE_SCORES.iter().filter(|&&needs| score >= needs).count())
+    }
+
+    pub fn label(self) -> String {
+        match self.0 {
+            0..=8 => (9 - self.0).to_string(),
+            _ if self == Grade::GM => "GM".to_string(),
+            _ => format!("S{}", self.0 - 8),
+        }
+    }
+}
+
+/// The level once a new piece comes in at `level`.
+pub fn spawned(level: u32) -> u32 {
+    if level % SECTION_LEVELS == SECTION_LEVELS - 1 || level >= LAST_LEVEL - 1 {
+        level
+    } else {
+        level + 1
+    }
+}
+
+/// Where a Master round stands beyond its score and level: the combo that
+/// multiplies its clears, and whether it's still in line for GM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Grading {
+    combo: u32,
+    in_line: bool,
+}
+
+impl Default for Grading {
+    fn default() -> Self {
+        Grading {
+            combo: 1,
+            in_line: true,
+        }
+    }
+}
+
+impl Grading {
+    /// Scores clearing `lines` rows at `level`, four times over for
+    /// emptying the board, and returns the points with the level after.
+    /// Clears in a row build up the combo; a lock without one ends it.
+    pub fn cleared(&mut self, level: u32, lines: u32, bravo: bool) -> (u32, u32) {
+        if lines == 0 {
+            self.combo = 1;
+            return (0, level);
+        }
+        self.combo += 2 * lines - 2;
+        let bravo = if bravo { 4 } else { 1 };
+        let points = (level + lines).div_ceil(4) * lines * self.combo * bravo;
+        (points, (level + lines).min(LAST_LEVEL))
+    }
+
+    /// Checks the round against the GM checkpoints it passed going from
+    /// level `from` to `to`, standing at `score` after `played`.
+    pub fn reached(&mut self, from: u32, to: u32, score: u32, played: Duration) {
+        for &(level, seconds, needs) in &GM_CHECKPOINTS {
+            let passed = from < level && to >= level;
+            if passed && (played > Duration::from_secs(seconds) || score < needs) {
+                self.in_line = false;
+            }
+        }
+    }
+
+    /// The grade a round at `score` and `level` has earned.
+    pub fn grade(&self, score: u32, level: u32) -> Grade {
+        if self.in_line && level >= LAST_LEVEL {
+            Grade::GM
+        } else {
+            Grade::for_score(score)
+        }
+    }
+}
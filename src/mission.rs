@@ -0,0 +1,105 @@
+use crate::game::Game;
+use crate::puzzle::{self, Goal, Puzzle};
+use serde::{Deserialize, Serialize};
+
+/// The most stars a mission gives.
+pub const MAX_STARS: u32 = 3;
+
+/// What a mission's stars are earned by: what it takes for three and for
+/// two. Completing it at all earns one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rating {
+    /// Using at most this many pieces.
+    Pieces([u32; 2]),
+    /// Taking at most this many seconds.
+    Seconds([u32; 2]),
+    /// Scoring at least this many points.
+    Score([u32; 2]),
+}
+
+impl Rating {
+    /// The stars `game` earned, none if it didn't complete the mission.
+    pub fn stars(self, game: &Game) -> u32 {
+        if !game.cleared_goal() {
+            return 0;
+        }
+        let (three, two) = match self {
+            Rating::Pieces([three, two]) => {
+                let pieces = game.stats().pieces;
+                (pieces <= three, pieces <= two)
+            }
+            Rating::Seconds([three, two]) => {
+                let secs = game.elapsed().as_secs();
+                (secs <= three.into(), secs <= two.into())
+            }
+            Rating::Score([three, two]) => (game.score >= three, game.score >= two),
+        };
+        if three {
+            MAX_STARS
+        } else if two {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+/// One step of the campaign: a puzzle played from a starting level, with
+/// the pieces falling faster as it climbs. A mission with no pieces of its
+/// own deals at random for as long as it lasts.
+#[derive(Clone, Debug)]
+pub struct Mission {
+    pub puzzle: Puzzle,
+    pub level: u32,
+    pub rating: Rating,
+}
+
+/// How a mission is written in a file, as a puzzle is but with the pieces
+/// and board optional, the level to start from, and the stars:
+///
+/// ```toml
+/// name = "Spin cycle"
+/// level = 3
+/// goal = { t_spins = 3 }
+/// stars = { seconds = [90, 180] }
+/// ```
+#[derive(Deserialize)]
+struct SavedMission {
+    name: String,
+    #[serde(default)]
+    pieces: String,
+    #[serde(default)]
+    rows: Vec<String>,
+    #[serde(default = "first_level")]
+    level: u32,
+    goal: Goal,
+    stars: Rating,
+}
+
+fn first_level() -> u32 {
+    1
+}
+
+impl Mission {
+    /// A mission from a mission file, on a board `width` by `height`.
+    pub fn parse(text: &str, width: usize, height: usize) -> Result<Mission, String> {
+        let saved: SavedMission = toml::from_str(text).map_err(|e| e.to_string())?;
+        let pieces = puzzle::parse_pieces(&saved.pieces)
+            .ok_or_else(|| format!("unknown piece in {:?}", saved.pieces))?;
+        let rows: Vec<&str> = saved.rows.iter().map(String::as_str).collect();
+        let board = puzzle::bottom_rows(&rows, width, height)
+            .ok_or_else(|| format!("the board doesn't fit {} by {}", width, height))?;
+        Ok(Mission {
+            puzzle: Puzzle {
+                name: saved.name,
+                board,
+                pieces,
+                goal: saved.goal,
+                par: None,
+            },
+            level: saved.level.max(1),
+            rating: saved.stars,
+        })
+    }
+}
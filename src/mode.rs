@@ -0,0 +1,221 @@
+use crate::master;
+use crate::rules::{GravityCurve, Ruleset};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Lines to clear to finish a sprint.
+pub const SPRINT_LINES: u32 = 40;
+
+/// Levels in a marathon; clearing the last one finishes it.
+pub const MARATHON_LEVELS: u32 = 15;
+
+/// How long an ultra round lasts.
+const ULTRA_MILLIS: u64 = 120_000;
+
+/// How long a locked piece stays in sight in an invisible round.
+const FADE_MILLIS: u64 = 1000;
+
+/// How long a marathon piece can rest before locking, so the last levels'
+/// speeds stay playable.
+const MARATHON_LOCK_DELAY_MILLIS: u64 = 500;
+
+/// How long a piece can rest before locking under 20G, where it rests
+/// from the moment it spawns.
+const TWENTY_G_LOCK_DELAY_MILLIS: u64 = 500;
+
+/// How long a Master piece can rest before locking.
+const MASTER_LOCK_DELAY_MILLIS: u64 = 500;
+
+/// How long a locked piece stays in sight in Master's last section.
+const MASTER_FADE_MILLIS: u64 = 2000;
+
+/// What a round is played for, and so when it ends. Those that need
+/// nothing picked first can be started from the command line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum Mode {
+    /// Play on for points until the stack tops out.
+    #[default]
+    Endless,
+    /// Clear `SPRINT_LINES` lines as fast as possible.
+    Sprint,
+    /// Climb to `MARATHON_LEVELS`, the pieces falling faster each level.
+    Marathon,
+    /// Play on with no way to lose: topping out clears the board, and
+    /// placements can be taken back.
+    Zen,
+    /// Solve a `Puzzle`; its goal decides when the round is won.
+    #[value(skip)]
+    Puzzle,
+    /// Play a `Practice` drill's pieces, in the order given, until the
+    /// stack tops out.
+    #[value(skip)]
+    Practice,
+    /// Play on for points with the stack out of sight: each piece fades
+    /// soon after it locks, and the whole stack is shown at the end.
+    Invisible,
+    /// Play on for points with every mino twice the size, so the pieces
+    /// fill a playfield half as wide and half as tall.
+    Big,
+    /// Score as much as possible before the time runs out.
+    Ultra,
+    /// Play on for points with pieces falling straight to the stack as
+    /// they spawn, so everything is done by sliding and turning them
+    /// before they lock.
+    TwentyG,
+    /// Climb from level 0 to `master::LAST_LEVEL` for a grade, the pieces
+    /// speeding up to 20G and the stack fading out in the last section.
+    Master,
+    /// Complete a campaign `Mission`, its goal decided as a puzzle's is
+    /// with the pieces falling faster each level, as in a marathon.
+    #[value(skip)]
+    Mission,
+    /// Play on for points with a second player on one board twice the
+    /// width, each dropping pieces of their own, for a score they share.
+    Coop,
+}
+
+impl Mode {
+    /// Lines that finish the round once cleared, for modes that have a
+    /// target.
+    pub fn line_goal(self) -> Option<u32> {
+        match self {
+            Mode::Endless
+            | Mode::Marathon
+            | Mode::Zen
+            | Mode::Puzzle
+            | Mode::Mission
+            | Mode::Practice
+            | Mode::Invisible
+            | Mode::Big
+            | Mode::Ultra
+            | Mode::TwentyG
+            | Mode::Master
+            | Mode::Coop => None,
+            Mode::Sprint => Some(SPRINT_LINES),
+        }
+    }
+
+    /// Whether a round standing at `lines` and `level`, from `start_level`,
+    /// has done what the mode asks. A marathon started past the last level
+    /// runs for one level.
+    pub fn is_cleared(self, lines: u32, level: u32, start_level: u32) -> bool {
+        match self {
+            Mode::Endless
+            | Mode::Zen
+            | Mode::Puzzle
+            | Mode::Mission
+            | Mode::Practice
+            | Mode::Invisible
+            | Mode::Big
+            | Mode::Ultra
+            | Mode::TwentyG
+            | Mode::Coop => false,
+            Mode::Sprint => lines >= SPRINT_LINES,
+            Mode::Marathon => level > MARATHON_LEVELS.max(start_level),
+            Mode::Master => level >= master::LAST_LEVEL,
+        }
+    }
+
+    /// `ruleset` as changed for this mode's rounds.
+    pub fn rules(self, ruleset: Ruleset) -> Ruleset {
+        match self {
+            Mode::Marathon | Mode::Mission => Ruleset {
+                gravity: GravityCurve::Guideline,
+                lock_delay: Some(Duration::from_millis(MARATHON_LOCK_DELAY_MILLIS)),
+                ..ruleset
+            },
+            Mode::TwentyG => Ruleset {
+                gravity: GravityCurve::Instant,
+                lock_delay: Some(Duration::from_millis(TWENTY_G_LOCK_DELAY_MILLIS)),
+                ..ruleset
+            },
+            Mode::Master => Ruleset {
+                gravity: GravityCurve::Master,
+                lock_delay: Some(Duration::from_millis(MASTER_LOCK_DELAY_MILLIS)),
+                ..ruleset
+            },
+            Mode::Endless
+            | Mode::Sprint
+            | Mode::Zen
+            | Mode::Puzzle
+            | Mode::Practice
+            | Mode::Invisible
+            | Mode::Big
+            | Mode::Ultra
+            | Mode::Coop => ruleset,
+        }
+    }
+
+    /// Whether the round ends when the stack reaches the top.
+    pub fn can_top_out(self) -> bool {
+        self != Mode::Zen
+    }
+
+    /// Whether `Input::Undo` rewinds the round a placement at a time.
+    pub fn allows_undo(self) -> bool {
+        matches!(self, Mode::Zen | Mode::Practice)
+    }
+
+    /// For modes that hide the stack at `level`, how long a locked piece
+    /// takes to fade out.
+    pub fn fade(self, level: u32) -> Option<Duration> {
+        match self {
+            Mode::Invisible => Some(Duration::from_millis(FADE_MILLIS)),
+            Mode::Master if level >= master::FADE_LEVEL => {
+                Some(Duration::from_millis(MASTER_FADE_MILLIS))
+            }
+            Mode::Endless
+            | Mode::Sprint
+            | Mode::Marathon
+            | Mode::Zen
+            | Mode::Puzzle
+            | Mode::Mission
+            | Mode::Practice
+            | Mode::Big
+            | Mode::Ultra
+            | Mode::TwentyG
+            | Mode::Master
+            | Mode::Coop => None,
+        }
+    }
+
+    /// For modes played against the clock, how long a round lasts.
+    pub fn time_limit(self) -> Option<Duration> {
+        match self {
+            Mode::Ultra => Some(Duration::from_millis(ULTRA_MILLIS)),
+            Mode::Endless
+            | Mode::Sprint
+            | Mode::Marathon
+            | Mode::Zen
+            | Mode::Puzzle
+            | Mode::Mission
+            | Mode::Practice
+            | Mode::Invisible
+            | Mode::Big
+            | Mode::TwentyG
+            | Mode::Master
+            | Mode::Coop => None,
+        }
+    }
+
+    /// How many playfield cells each mino covers across and down. A round
+    /// is played on a board scaled down by this, one board cell to a mino.
+    pub fn scale(self) -> usize {
+        match self {
+            Mode::Big => 2,
+            Mode::Endless
+            | Mode::Sprint
+            | Mode::Marathon
+            | Mode::Zen
+            | Mode::Puzzle
+            | Mode::Mission
+            | Mode::Practice
+            | Mode::Invisible
+            | Mode::Ultra
+            | Mode::TwentyG
+            | Mode::Master
+            | Mode::Coop => 1,
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//! Mods: Rhai scripts in the `mods` folder of the user config directory,
+//! or in the `scripts` folder of a content pack (see `packs`), that change
+//! how single player rounds play without rebuilding the game. A mod reacts
+//! to a round's events by defining any of
+//!
+//! ```text
+//! fn on_spawn(game, piece)       // piece is its letter, as in "T"
+//! fn on_lock(game)
+//! fn on_lines_cleared(game, lines)
+//! fn on_t_spin(game)
+//! fn on_game_over(game)
+//! ```
+//!
+//! `game` can be read, `game.score`, `game.lines`, `game.level`,
+//! `game.pieces` (locked so far), `game.piece`, `game.next`, `game.width`,
+//! `game.height` and `game.cell(x, y)`, with rows counted down from the top
+//! (a piece letter, `"G"` for garbage or `""` for empty), and changed only
+//! so far: `game.add_score(points)`, `game.set_score(points)`,
+//! `game.add_garbage(rows)`, which comes in the way an opponent's does,
+//! and `game.fill(x, y)`, which puts garbage in an empty cell. For
+//! example, every tenth piece bringing in a row:
+//!
+//! ```text
+//! fn on_lock(game) {
+//!     if game.pieces % 10 == 0 { game.add_garbage(1); }
+//! }
+//! ```
+//!
+//! Changes are made once every mod has heard about the event. Mods sit out
+//! network games, whose other side couldn't follow them, and a replay only
+//! plays back right with the mods it was recorded with.
+
+use crate::packs::Packs;
+use ggez::filesystem;
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameError, GameResult};
+use rhai::{Engine, Scope, AST};
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use tetris_rs::board::{Board, FixedBlock};
+use tetris_rs::events::Observer;
+use tetris_rs::game::{Event, Game};
+
+pub const MODS_DIR: &str = "mods";
+const SCRIPTS_DIR: &str = "scripts";
+const MOD_EXTENSION: &str = "rhai";
+
+/// Steps a mod may take handling one event before it's stopped, so one
+/// stuck in a loop can't hang the game.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// A change a mod asked for, made once the event has gone round.
+#[derive(Clone, Copy, Debug)]
+enum Change {
+    AddScore(i64),
+    SetScore(i64),
+    AddGarbage(i64),
+    Fill(i64, i64),
+}
+
+/// The round as a mod sees it: a copy of where it stands, and the changes
+/// asked for so far.
+#[derive(Clone)]
+struct ModGame {
+    board: Rc<Board>,
+    score: i64,
+    lines: i64,
+    level: i64,
+    pieces: i64,
+    piece: String,
+    next: String,
+    changes: Rc<RefCell<Vec<Change>>>,
+}
+
+impl ModGame {
+    fn cell(&mut self, x: i64, y: i64) -> String {
+        match self.board.get(Point2::new(x as i32, y as i32)) {
+            Some(Some(block)) => block.to_char().to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn change(&mut self, change: Change) {
+        self.changes.borrow_mut().push(change);
+    }
+}
+
+struct Mod {
+    name: String,
+    ast: AST,
+    /// Set once it has gone wrong, after which it's left out.
+    failed: bool,
+}
+
+pub struct Mods {
+    engine: Engine,
+    mods: Vec<Mod>,
+    changes: Rc<RefCell<Vec<Change>>>,
+}
+
+impl Mods {
+    /// Compiles every mod in the user config directory, then those of the
+    /// packs that are on. No folder is fine, but a mod that doesn't compile
+    /// stops the game starting, as a bad `pieces.toml` does.
+    pub fn load(ctx: &Context, packs: &Packs) -> GameResult<Mods> {
+        let mut mods = Mods {
+            engine: engine(),
+            mods: Vec::new(),
+            changes: Rc::default(),
+        };
+        let mut paths = scripts(&filesystem::user_config_dir(ctx).join(MODS_DIR))?;
+        for pack in packs.enabled() {
+            if let Some(dir) = pack.file(SCRIPTS_DIR) {
+                paths.extend(scripts(&dir)?);
+            }
+        }
+        for path in paths {
+            let name = path.display().to_string();
+            let ast = mods
+                .engine
+                .compile(fs::read_to_string(&path)?)
+                .map_err(|e| GameError::ResourceLoadError(format!("Invalid {}: {}", name, e)))?;
+            mods.mods.push(Mod {
+                name,
+                ast,
+                failed: false,
+            });
+        }
+        Ok(mods)
+    }
+
+    /// Makes the changes the mods asked for while hearing about the last
+    /// events.
+    pub fn apply(&mut self, game: &mut Game) {
+        for change in self.changes.borrow_mut().drain(..) {
+            match change {
+                Change::AddScore(points) => {
+                    game.score = clamp(i64::from(game.score) + points, u32::MAX)
+                }
+                Change::SetScore(points) => game.score = clamp(points, u32::MAX),
+                Change::AddGarbage(rows) => {
+                    game.receive_garbage(clamp(rows, game.board.height() as u32))
+                }
+                Change::Fill(x, y) => {
+                    game.board
+                        .set(Point2::new(x as i32, y as i32), FixedBlock::Garbage);
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, function: &str, game: &Game, extra: Option<rhai::Dynamic>) {
+        let seen = ModGame {
+            board: Rc::new(game.board.clone()),
+            score: i64::from(game.score),
+            lines: i64::from(game.lines),
+            level: i64::from(game.level),
+            pieces: i64::from(game.stats().pieces),
+            piece: game.tetromino().symbol().to_string(),
+            next: game.next().symbol().to_string(),
+            changes: self.changes.clone(),
+        };
+        for m in self.mods.iter_mut().filter(|m| !m.failed) {
+            if !m.ast.iter_functions().any(|f| f.name == function) {
+                continue;
+            }
+            let mut scope = Scope::new();
+            let result = match extra {
+                Some(ref extra) => self.engine.call_fn::<()>(
+                    &mut scope,
+                    &m.ast,
+                    function,
+                    (seen.clone(), extra.clone()),
+                ),
+                None => self
+                    .engine
+                    .call_fn::<()>(&mut scope, &m.ast, function, (seen.clone(),)),
+            };
+            if let Err(e) = result {
+                eprintln!("Mod {} failed in {} and is off: {}", m.name, function, e);
+                m.failed = true;
+            }
+        }
+    }
+}
+
+impl Observer for Mods {
+    fn notify(&mut self, event: Event, _: u64, game: &Game) {
+        match event {
+            Event::Spawn(piece) => {
+                let piece = piece.symbol().to_string();
+                self.call("on_spawn", game, Some(piece.into()));
+            }
+            Event::Lock { .. } => self.call("on_lock", game, None),
+            Event::LineClear(lines) => {
+                self.call("on_lines_cleared", game, Some((lines as i64).into()))
+            }
+            Event::TSpin => self.call("on_t_spin", game, None),
+            Event::GameOver => self.call("on_game_over", game, None),
+            _ => (),
+        }
+    }
+}
+
+/// The mods in `dir`, in order of their names, which is the order they
+/// hear about each event in.
+fn scripts(dir: &Path) -> GameResult<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == MOD_EXTENSION))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// `value` as a count no less than zero and no more than `max`.
+fn clamp(value: i64, max: u32) -> u32 {
+    value.max(0).min(i64::from(max)) as u32
+}
+
+/// The engine mods run in, knowing only the round as `ModGame` shows it.
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine
+        .register_type_with_name::<ModGame>("Game")
+        .register_get("score", |game: &mut ModGame| game.score)
+        .register_get("lines", |game: &mut ModGame| game.lines)
+        .register_get("level", |game: &mut ModGame| game.level)
+        .register_get("pieces", |game: &mut ModGame| game.pieces)
+        .register_get("piece", |game: &mut ModGame| game.piece.clone())
+        .register_get("next", |game: &mut ModGame| game.next.clone())
+        .register_get("width", |game: &mut ModGame| game.board.width() as i64)
+        .register_get("height", |game: &mut ModGame| game.board.height() as i64)
+        .register_fn("cell", ModGame::cell)
+        .register_fn("add_score", |game: &mut ModGame, points: i64| {
+            game.change(Change::AddScore(points))
+        })
+        .register_fn("set_score", |game: &mut ModGame, points: i64| {
+            game.change(Change::SetScore(points))
+        })
+        .register_fn("add_garbage", |game: &mut ModGame, rows: i64| {
+            game.change(Change::AddGarbage(rows))
+        })
+        .register_fn("fill", |game: &mut ModGame, x: i64, y: i64| {
+            game.change(Change::Fill(x, y))
+        });
+    engine
+}
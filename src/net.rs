@@ -0,0 +1,696 @@
+//! Head-to-head play over TCP. Both sides deal from the same seed and send
+//! each other their actions stamped with the frame they happened on, the
+//! way replays are recorded, so each can run its own copy of the other's
+//! game. Messages are lines of text:
+//!
+//! ```text
+//! hello <seed> <width> <height> <gravity ms> <start level> <das ms> <arr ms>
+//!       [<entry delay ms> [<line clear delay ms> [<garbage delay ms>
+//!       [<soft drop ms>|press [<das cut delay ms> [<garbage rows>
+//!       <preview>|all <speed %> <attack %>]]]]]]
+//! act <frame> press|release <action>
+//! act <frame> resume
+//! act <frame> garbage <rows>
+//! frame <frame>
+//! ready
+//! say <text>
+//! ```
+//!
+//! `frame n` promises that every action before frame `n` has been sent.
+//! Once both hellos are through, each side sends `ready` when its player
+//! is, and the round starts once both have. `say` is a line of chat. The
+//! last four numbers of a hello are the sender's handicap.
+//!
+//! Spectators connect to the host and send `watch`. The host passes on
+//! everything from both players, each line prefixed with `1` for the host
+//! or `2` for the other player, starting from the hellos so a late
+//! spectator can catch up.
+
+use crate::chat;
+use crate::discovery::Announcer;
+use crate::room;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use tetris_rs::board;
+use tetris_rs::game::{Handling, Input, Settings};
+use tetris_rs::handicap::{Handicap, MAX_GARBAGE_ROWS, MAX_HANDICAP_PERCENT, MIN_SPEED_PERCENT};
+use tetris_rs::mode::Mode;
+use tetris_rs::replay::{Action, TimedAction};
+use tetris_rs::rules::{LockReset, Rules};
+use tetris_rs::tetromino::PieceSet;
+
+pub const DEFAULT_PORT: u16 = 7878;
+
+const CONNECT_TIMEOUT_SECS: u64 = 5;
+/// The longest time a hello may give for anything, well past any that's
+/// played with, so none can run the clock past what it holds.
+const MAX_HELLO_MILLIS: u64 = 10_000;
+
+/// How a round is set up, as one side sees it. The host's seed, board and
+/// speed are used by both sides; each keeps its own handling and handicap.
+#[derive(Clone, Copy, Debug)]
+pub struct Hello {
+    pub board_width: usize,
+    pub board_height: usize,
+    pub settings: Settings,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Hello(Hello),
+    Act(TimedAction),
+    /// Everything before this frame has been sent.
+    Frame(u64),
+    /// Asks the host to be sent the game to look on.
+    Watch,
+    /// The player is ready for the round to start.
+    Ready,
+    /// A line of chat.
+    Say(String),
+}
+
+impl Message {
+    fn encode(&self) -> String {
+        match *self {
+            Message::Hello(hello) => {
+                let settings = hello.settings;
+                let handicap = settings.handicap;
+                format!(
+                    "hello {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+                    settings.seed,
+                    hello.board_width,
+                    hello.board_height,
+                    settings.gravity.as_millis(),
+                    settings.start_level,
+                    settings.handling.das.as_millis(),
+                    settings.handling.arr.as_millis(),
+                    settings.entry_delay.as_millis(),
+                    settings.line_clear_delay.as_millis(),
+                    settings.garbage_delay.as_millis(),
+                    settings
+                        .handling
+                        .soft_drop
+                        .map_or("press".to_string(), |rate| rate.as_millis().to_string()),
+                    settings.handling.dcd.as_millis(),
+                    handicap.garbage_rows,
+                    handicap
+                        .preview
+                        .map_or("all".to_string(), |shown| shown.to_string()),
+                    handicap.speed_percent,
+                    handicap.attack_percent,
+                    settings
+                        .previews
+                        .map_or("rules".to_string(), |shown| shown.to_string())
+                )
+            }
+            Message::Act(TimedAction { frame, action }) => match action {
+                Action::Press(input) => format!("act {} press {}", frame, input.name()),
+                Action::Release(input) => format!("act {} release {}", frame, input.name()),
+                Action::Resume => format!("act {} resume", frame),
+                Action::Garbage(rows) => format!("act {} garbage {}", frame, rows),
+            },
+            Message::Frame(frame) => format!("frame {}", frame),
+            Message::Watch => "watch".to_string(),
+            Message::Ready => "ready".to_string(),
+            Message::Say(ref text) => format!("say {}", text),
+        }
+    }
+
+    fn decode(line: &str) -> Result<Message, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |word: &str| {
+            word.parse::<u64>()
+                .map_err(|_| format!("bad number {:?} in {:?}", word, line))
+        };
+        let out_of_range = |word: &str| format!("number {:?} out of range in {:?}", word, line);
+        let count = |word: &str| u32::try_from(number(word)?).map_err(|_| out_of_range(word));
+        let size = |word: &str| usize::try_from(number(word)?).map_err(|_| out_of_range(word));
+        let millis = |word: &str| match number(word)? {
+            ms if ms <= MAX_HELLO_MILLIS => Ok(Duration::from_millis(ms)),
+            _ => Err(out_of_range(word)),
+        };
+        let input =
+            |name: &str| Input::from_name(name).ok_or_else(|| format!("unknown action {:?}", name));
+        match words.as_slice() {
+            ["hello", seed, width, height, gravity, level, das, arr, ref rest @ ..]
+                if rest.len() <= 10 =>
+            {
+                let board_width = size(width)?;
+                let board_height = size(height)?;
+                if board_width == 0
+                    || board_width > board::MAX_WIDTH
+                    || board_height == 0
+                    || board_height > board::MAX_HEIGHT
+                {
+                    return Err(format!("bad board size in {:?}", line));
+                }
+                Ok(Message::Hello(Hello {
+                    board_width,
+                    board_height,
+                    settings: Settings {
+                        handling: Handling {
+                            das: millis(das)?,
+                            arr: millis(arr)?,
+                            soft_drop: match rest.get(3) {
+                                Some(&"press") | None => None,
+                                Some(rate) => Some(millis(rate)?),
+                            },
+                            dcd: match rest.get(4) {
+                                Some(delay) => millis(delay)?,
+                                None => Duration::from_millis(0),
+                            },
+                        },
+                        gravity: millis(gravity)?,
+                        start_level: count(level)?,
+                        seed: number(seed)?,
+                        // Network games are always played for keeps, with
+                        // the usual pieces and rules.
+                        mode: Mode::Endless,
+                        pieces: PieceSet::Tetrominoes,
+                        rules: Rules::Modern,
+                        rotation: None,
+                        randomizer: None,
+                        // These rules have no lock delay to reset.
+                        lock_reset: LockReset::Move,
+                        previews: match rest.get(9) {
+                            Some(&"rules") | None => None,
+                            Some(shown) => Some(size(shown)?),
+                        },
+                        // Hosts from before the delays were settable leave
+                        // them out.
+                        entry_delay: match rest.first() {
+                            Some(delay) => millis(delay)?,
+                            None => Settings::default().entry_delay,
+                        },
+                        line_clear_delay: match rest.get(1) {
+                            Some(delay) => millis(delay)?,
+                            None => Settings::default().line_clear_delay,
+                        },
+                        // Theirs took garbage in straight away.
+                        garbage_delay: match rest.get(2) {
+                            Some(delay) => millis(delay)?,
+                            None => Duration::from_millis(0),
+                        },
+                        handicap: match rest.get(5..9) {
+                            // Kept to what can be picked here, whatever
+                            // they send.
+                            Some(&[rows, preview, speed, attack]) => Handicap {
+                                garbage_rows: count(rows)?.min(MAX_GARBAGE_ROWS),
+                                preview: match preview {
+                                    "all" => None,
+                                    shown => Some(size(shown)?),
+                                },
+                                speed_percent: count(speed)?
+                                    .clamp(MIN_SPEED_PERCENT, MAX_HANDICAP_PERCENT),
+                                attack_percent: count(attack)?.min(MAX_HANDICAP_PERCENT),
+                            },
+                            _ => Handicap::default(),
+                        },
+                    },
+                }))
+            }
+            ["act", frame, kind, name] => {
+                let action = match *kind {
+                    "press" => Action::Press(input(name)?),
+                    "release" => Action::Release(input(name)?),
+                    "garbage" => Action::Garbage(count(name)?),
+                    _ => return Err(format!("unknown message {:?}", line)),
+                };
+                Ok(Message::Act(TimedAction {
+                    frame: number(frame)?,
+                    action,
+                }))
+            }
+            ["act", frame, "resume"] => Ok(Message::Act(TimedAction {
+                frame: number(frame)?,
+                action: Action::Resume,
+            })),
+            ["frame", frame] => Ok(Message::Frame(number(frame)?)),
+            ["watch"] => Ok(Message::Watch),
+            ["ready"] => Ok(Message::Ready),
+            ["say", ..] => {
+                let text = line.trim_start()["say".len()..].trim();
+                Ok(Message::Say(text.chars().take(chat::MAX_LEN).collect()))
+            }
+            _ => Err(format!("unknown message {:?}", line)),
+        }
+    }
+}
+
+/// A link to the other player. It never blocks: sends are queued until the
+/// socket takes them and `receive` returns whatever has arrived.
+pub struct Connection {
+    stream: TcpStream,
+    outgoing: Vec<u8>,
+    /// Bytes of a line that hasn't fully arrived yet.
+    incoming: Vec<u8>,
+    /// Messages with the player they're about on a spectator's link, or 0.
+    received: VecDeque<(usize, Message)>,
+    closed: bool,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Connection> {
+        stream.set_nonblocking(true)?;
+        // Actions are tiny and late ones hold up the other side.
+        stream.set_nodelay(true)?;
+        Ok(Connection {
+            stream,
+            outgoing: Vec::new(),
+            incoming: Vec::new(),
+            received: VecDeque::new(),
+            closed: false,
+        })
+    }
+
+    /// Whether the other side has gone, or the link broke.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn send(&mut self, message: &Message) {
+        self.write_line(&message.encode());
+    }
+
+    /// Sends a message about player `side` to a spectator.
+    fn send_about(&mut self, side: usize, message: &Message) {
+        self.write_line(&format!("{} {}", side, message.encode()));
+    }
+
+    fn write_line(&mut self, line: &str) {
+        self.outgoing.extend_from_slice(line.as_bytes());
+        self.outgoing.push(b'\n');
+        self.flush();
+    }
+
+    fn flush(&mut self) {
+        while !self.closed && !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => self.close("connection closed"),
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => self.close(&e.to_string()),
+            }
+        }
+    }
+
+    /// Every message that has come in since the last call.
+    pub fn receive(&mut self) -> Vec<Message> {
+        self.read();
+        self.received
+            .drain(..)
+            .map(|(_, message)| message)
+            .collect()
+    }
+
+    /// Every message that has come in on a spectator's link since the last
+    /// call, with the player it's about.
+    pub fn receive_about(&mut self) -> Vec<(usize, Message)> {
+        self.read();
+        self.received.drain(..).collect()
+    }
+
+    fn next_message(&mut self) -> Option<Message> {
+        self.read();
+        self.received.pop_front().map(|(_, message)| message)
+    }
+
+    fn read(&mut self) {
+        self.flush();
+        let mut buffer = [0; 4096];
+        while !self.closed {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => self.close("opponent disconnected"),
+                Ok(read) => self.incoming.extend_from_slice(&buffer[..read]),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => self.close(&e.to_string()),
+            }
+        }
+        while let Some(end) = self.incoming.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.incoming.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+            let (side, line) = match line.split_once(' ') {
+                Some((side, rest)) if side.parse::<usize>().is_ok() => {
+                    (side.parse().unwrap_or(0), rest)
+                }
+                _ => (0, line),
+            };
+            match Message::decode(line) {
+                Ok(message) => self.received.push_back((side, message)),
+                Err(e) => {
+                    // A side that sends nonsense can't be kept in sync with.
+                    self.close(&e);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn close(&mut self, reason: &str) {
+        if !self.closed {
+            eprintln!("Network game ended: {}", reason);
+            self.closed = true;
+        }
+    }
+}
+
+/// A connected pair, ready to start: the settings this side plays with and
+/// the ones the other side does, and on the host, anyone looking on.
+pub struct Match {
+    pub connection: Connection,
+    pub local: Hello,
+    pub remote: Hello,
+    pub spectators: Option<Spectators>,
+}
+
+/// The host's spectators, and whoever has connected to become one.
+pub struct Spectators {
+    listener: TcpListener,
+    /// Connected but not yet asked to watch.
+    pending: Vec<Connection>,
+    watchers: Vec<Connection>,
+    /// Everything passed on so far, for spectators who join late.
+    log: Vec<(usize, Message)>,
+}
+
+impl Spectators {
+    fn new(listener: TcpListener, watchers: Vec<Connection>) -> Spectators {
+        Spectators {
+            listener,
+            pending: Vec::new(),
+            watchers,
+            log: Vec::new(),
+        }
+    }
+
+    /// Lets in new spectators, bringing each up to date.
+    pub fn poll(&mut self) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            match Connection::new(stream) {
+                Ok(connection) => self.pending.push(connection),
+                Err(e) => eprintln!("Could not accept a spectator: {}", e),
+            }
+        }
+        let mut pending = Vec::new();
+        for mut connection in self.pending.drain(..) {
+            match connection.next_message() {
+                Some(Message::Watch) => {
+                    for (side, message) in &self.log {
+                        connection.send_about(*side, message);
+                    }
+                    self.watchers.push(connection);
+                }
+                // The game has already started; there's no joining it.
+                Some(_) => (),
+                None if connection.is_closed() => (),
+                None => pending.push(connection),
+            }
+        }
+        self.pending = pending;
+        self.watchers.retain(|watcher| !watcher.is_closed());
+    }
+
+    /// Passes on a message from player `side`.
+    pub fn send(&mut self, side: usize, message: &Message) {
+        for watcher in &mut self.watchers {
+            watcher.send_about(side, message);
+        }
+        self.log.push((side, message.clone()));
+    }
+}
+
+/// Setting up a network game, from either end.
+pub enum Lobby {
+    /// Waiting for someone to connect, then for their hello.
+    Hosting {
+        listener: TcpListener,
+        /// Connected but not yet said what they're here for.
+        pending: Vec<Connection>,
+        /// Spectators in early, waiting for the game to start.
+        watchers: Vec<Connection>,
+        hello: Hello,
+        /// The room code for the other player to join with, if this
+        /// machine has an address one can be made from.
+        code: Option<String>,
+        /// Telling the local network about the game, if it can be told.
+        announcer: Option<Announcer>,
+    },
+    /// Connected to a host and waiting for its hello.
+    Joining {
+        connection: Option<Connection>,
+        handling: Handling,
+        handicap: Handicap,
+    },
+    /// Both sides said hello; waiting for both players to be ready.
+    Ready {
+        found: Option<Box<Match>>,
+        ready: bool,
+        remote_ready: bool,
+    },
+}
+
+impl Lobby {
+    /// Listens on `addr` for a game set up as `hello`.
+    pub fn host(addr: &str, hello: Hello) -> io::Result<Lobby> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local = listener.local_addr()?;
+        let announcer = Announcer::new(local.port())
+            .map_err(|e| eprintln!("Could not announce the game on the local network: {}", e))
+            .ok();
+        Ok(Lobby::Hosting {
+            listener,
+            pending: Vec::new(),
+            watchers: Vec::new(),
+            hello,
+            code: room::reachable(local).map(room::code),
+            announcer,
+        })
+    }
+
+    /// Connects to a host at `addr`, an address or a room code. The host
+    /// decides everything but `handling` and `handicap`.
+    pub fn join(addr: &str, handling: Handling, handicap: Handicap) -> io::Result<Lobby> {
+        Ok(Lobby::Joining {
+            connection: Some(connect(addr)?),
+            handling,
+            handicap,
+        })
+    }
+
+    /// What the lobby is waiting for, to show the player: the key of its
+    /// text in the locale, and the value to fill in, if it takes one.
+    pub fn status(&self) -> (&'static str, Option<String>) {
+        match self {
+            Lobby::Hosting {
+                code: Some(code), ..
+            } => ("lobby.room_code", Some(code.clone())),
+            Lobby::Hosting { listener, .. } => match listener.local_addr() {
+                Ok(addr) => ("lobby.waiting_on_port", Some(addr.port().to_string())),
+                Err(_) => ("lobby.waiting", None),
+            },
+            Lobby::Joining { .. } => ("lobby.joining", None),
+            Lobby::Ready { ready: true, .. } => ("lobby.waiting_for_ready", None),
+            Lobby::Ready {
+                remote_ready: true, ..
+            } => ("lobby.opponent_ready", None),
+            Lobby::Ready { .. } => ("lobby.opponent_found", None),
+        }
+    }
+
+    /// Tells the other side this player is ready, once both have said
+    /// hello.
+    pub fn ready(&mut self) {
+        if let Lobby::Ready {
+            found: Some(found),
+            ready: ready @ false,
+            ..
+        } = self
+        {
+            found.connection.send(&Message::Ready);
+            *ready = true;
+        }
+    }
+
+    /// Moves the handshake along, returning the match once both sides have
+    /// said hello and both players are ready.
+    pub fn poll(&mut self) -> io::Result<Option<Match>> {
+        if let Lobby::Ready {
+            found,
+            ready,
+            remote_ready,
+        } = self
+        {
+            if let Some(ref mut found) = found {
+                // Only up to their ready: what comes after is the round.
+                while !*remote_ready {
+                    match found.connection.next_message() {
+                        Some(Message::Ready) => *remote_ready = true,
+                        Some(_) => {
+                            return Err(io::Error::new(
+                                ErrorKind::InvalidData,
+                                "expected the other side to be ready",
+                            ))
+                        }
+                        None if found.connection.is_closed() => {
+                            return Err(io::Error::new(
+                                ErrorKind::ConnectionAborted,
+                                "the other side left",
+                            ))
+                        }
+                        None => break,
+                    }
+                }
+            }
+            return Ok(if *ready && *remote_ready {
+                found.take().map(|found| *found)
+            } else {
+                None
+            });
+        }
+        if let Some(found) = self.handshake()? {
+            *self = Lobby::Ready {
+                found: Some(Box::new(found)),
+                ready: false,
+                remote_ready: false,
+            };
+        }
+        Ok(None)
+    }
+
+    /// Moves the exchange of hellos along, returning the match once both
+    /// sides have said theirs.
+    fn handshake(&mut self) -> io::Result<Option<Match>> {
+        match self {
+            Lobby::Hosting {
+                listener,
+                pending,
+                watchers,
+                hello,
+                announcer,
+                ..
+            } => {
+                if let Some(announcer) = announcer {
+                    announcer.poll();
+                }
+                loop {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            // Spectators ignore this, it isn't about a player.
+                            let mut accepted = Connection::new(stream)?;
+                            accepted.send(&Message::Hello(*hello));
+                            pending.push(accepted);
+                        }
+                        Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                        Err(e) => return Err(e),
+                    }
+                }
+                let mut found = None;
+                let mut waiting = Vec::new();
+                for mut connection in pending.drain(..) {
+                    match connection.next_message() {
+                        Some(Message::Hello(remote)) if found.is_none() => {
+                            found = Some((connection, remote))
+                        }
+                        Some(Message::Watch) => watchers.push(connection),
+                        // Anyone else gets turned away.
+                        Some(_) => (),
+                        None if connection.is_closed() => (),
+                        None => waiting.push(connection),
+                    }
+                }
+                *pending = waiting;
+                let (connection, remote) = match found {
+                    Some(found) => found,
+                    None => return Ok(None),
+                };
+                let spectators = Spectators::new(listener.try_clone()?, std::mem::take(watchers));
+                Ok(Some(Match {
+                    connection,
+                    local: *hello,
+                    remote,
+                    spectators: Some(spectators),
+                }))
+            }
+            Lobby::Joining {
+                connection,
+                handling,
+                handicap,
+            } => {
+                let remote = match connection {
+                    Some(joined) => first_hello(joined)?,
+                    None => None,
+                };
+                let remote = match remote {
+                    Some(remote) => remote,
+                    None => return Ok(None),
+                };
+                let local = Hello {
+                    settings: Settings {
+                        handling: *handling,
+                        handicap: *handicap,
+                        ..remote.settings
+                    },
+                    ..remote
+                };
+                Ok(connection.take().map(|mut connection| {
+                    connection.send(&Message::Hello(local));
+                    Match {
+                        connection,
+                        local,
+                        remote,
+                        spectators: None,
+                    }
+                }))
+            }
+            Lobby::Ready { .. } => Ok(None),
+        }
+    }
+}
+
+/// Connects to `addr`, a room code or an address, adding the default port
+/// to an address without one.
+fn connect(addr: &str) -> io::Result<Connection> {
+    let addr = if let Some(addr) = room::addr(addr) {
+        addr.to_string()
+    } else if addr.contains(':') {
+        addr.to_string()
+    } else {
+        format!("{}:{}", addr, DEFAULT_PORT)
+    };
+    let resolved = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(ErrorKind::NotFound, format!("no address for {}", addr)))?;
+    let stream = TcpStream::connect_timeout(&resolved, Duration::from_secs(CONNECT_TIMEOUT_SECS))?;
+    Connection::new(stream)
+}
+
+/// Connects to the host of a game at `addr` to look on.
+pub fn watch(addr: &str) -> io::Result<Connection> {
+    let mut connection = connect(addr)?;
+    connection.send(&Message::Watch);
+    Ok(connection)
+}
+
+/// Waits for the other side's hello, which is the first thing either sends.
+fn first_hello(connection: &mut Connection) -> io::Result<Option<Hello>> {
+    match connection.next_message() {
+        Some(Message::Hello(hello)) => Ok(Some(hello)),
+        Some(_) => Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "expected a hello first",
+        )),
+        None if connection.is_closed() => Err(io::Error::new(
+            ErrorKind::ConnectionAborted,
+            "the other side left",
+        )),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,138 @@
+//! Content packs: folders in the `mods` folder of the user config directory
+//! that each bring a set of things to the game, with a `pack.toml` saying
+//! what the pack is:
+//!
+//! ```toml
+//! name = "Neon"
+//! author = "Someone"
+//! description = "Glowing minos and synth sounds"
+//! ```
+//!
+//! Inside, a pack is laid out like the game's resources and the player's
+//! config: `themes`, `skins`, `locales`, `puzzles` and `missions` add to
+//! those, files in `sounds` and `music` stand in for the game's own of the
+//! same names, `pieces.toml`, `kicks.toml`, `gravity.toml` and
+//! `attack.toml` are read as the player's would be if they have none, and
+//! the Rhai scripts in `scripts` are run as mods. Packs are read in order
+//! of their folder names, a later one's file winning over an earlier
+//! one's. Every pack is on until turned off, which takes effect the next
+//! time the game starts.
+
+use crate::mods::MODS_DIR;
+use ggez::filesystem;
+use ggez::Context;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MANIFEST_FILE: &str = "pack.toml";
+
+/// The layout of `pack.toml`.
+#[derive(Deserialize)]
+struct Manifest {
+    name: String,
+    #[serde(default)]
+    author: String,
+    #[serde(default)]
+    description: String,
+}
+
+pub struct Pack {
+    /// The pack's folder name, which it's turned off by.
+    pub id: String,
+    pub name: String,
+    pub author: String,
+    pub description: String,
+    /// Whether it's on. Turning it on or off takes effect the next time
+    /// the game starts.
+    pub enabled: bool,
+    dir: PathBuf,
+}
+
+impl Pack {
+    /// `path` in the pack, if it has it.
+    pub fn file(&self, path: &str) -> Option<PathBuf> {
+        Some(self.dir.join(path)).filter(|path| path.exists())
+    }
+}
+
+fn read(dir: &Path, disabled: &[String]) -> Option<Pack> {
+    let path = dir.join(MANIFEST_FILE);
+    let text = fs::read_to_string(&path).ok()?;
+    let manifest: Manifest = match toml::from_str(&text) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("Invalid pack {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    let id = dir.file_name()?.to_string_lossy().into_owned();
+    Some(Pack {
+        enabled: !disabled.contains(&id),
+        id,
+        name: manifest.name,
+        author: manifest.author,
+        description: manifest.description,
+        dir: dir.to_path_buf(),
+    })
+}
+
+/// The packs in the user config directory, whether on or off. Folders
+/// without a readable `pack.toml` are left out.
+pub struct Packs {
+    packs: Vec<Pack>,
+}
+
+impl Packs {
+    /// Finds the packs, leaving off those in `disabled`.
+    pub fn load(ctx: &Context, disabled: &[String]) -> Packs {
+        let dir = filesystem::user_config_dir(ctx).join(MODS_DIR);
+        let mut dirs: Vec<PathBuf> = fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.is_dir())
+                    .collect()
+            })
+            .unwrap_or_default();
+        dirs.sort();
+        Packs {
+            packs: dirs.iter().filter_map(|dir| read(dir, disabled)).collect(),
+        }
+    }
+
+    pub fn all(&self) -> &[Pack] {
+        &self.packs
+    }
+
+    pub fn enabled(&self) -> impl Iterator<Item = &Pack> + '_ {
+        self.packs.iter().filter(|pack| pack.enabled)
+    }
+
+    /// `path` from the last pack that is on and has it.
+    pub fn file(&self, path: &str) -> Option<PathBuf> {
+        self.enabled().filter_map(|pack| pack.file(path)).last()
+    }
+
+    /// Adds the packs that are on to where resources are looked for, after
+    /// the game's own, so their themes, skins and languages are found with
+    /// the rest.
+    pub fn mount(&self, ctx: &mut Context) {
+        for pack in self.enabled() {
+            filesystem::mount(ctx, &pack.dir, true);
+        }
+    }
+
+    /// Turns the pack at `at` on or off for the next time the game starts,
+    /// returning the ids of those now off.
+    pub fn toggle(&mut self, at: usize) -> Vec<String> {
+        if let Some(pack) = self.packs.get_mut(at) {
+            pack.enabled = !pack.enabled;
+        }
+        self.packs
+            .iter()
+            .filter(|pack| !pack.enabled)
+            .map(|pack| pack.id.clone())
+            .collect()
+    }
+}
@@ -0,0 +1,129 @@
+use ggez::graphics::{self, Color, DrawMode, MeshBuilder, Rect};
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+use rand::Rng;
+use tetris_rs::tetromino::{Rotation, Tetromino};
+
+/// Sparks thrown from each cell of a cleared row.
+const SPARKS_PER_CELL: usize = 3;
+/// Fragments knocked off the underside of a hard-dropped piece.
+const FRAGMENTS_PER_CELL: usize = 4;
+/// Cells per second squared.
+const GRAVITY: f32 = 30.0;
+/// Size of a particle, as a fraction of a cell.
+const SIZE: f32 = 0.15;
+/// Past this many the oldest make way for new ones.
+const MAX_PARTICLES: usize = 2000;
+
+struct Particle {
+    /// Where it is, and how fast it's going, in board cells.
+    pos: Point2<f32>,
+    velocity: (f32, f32),
+    color: Color,
+    /// Seconds left, out of `life`.
+    left: f32,
+    life: f32,
+}
+
+/// Bits thrown off the board by line clears and hard drops, in board
+/// cells so they go with the board wherever it's drawn. They're only for
+/// show, so they come from the thread's random numbers rather than the
+/// round's seed.
+#[derive(Default)]
+pub struct Particles {
+    particles: Vec<Particle>,
+}
+
+impl Particles {
+    /// Throws sparks up out of the row at `y`, `width` cells across, in
+    /// the colors `color` gives each cell.
+    pub fn clear_row(&mut self, y: i32, width: usize, color: impl Fn(i32) -> Color) {
+        let mut rng = rand::thread_rng();
+        for x in 0..width as i32 {
+            for _ in 0..SPARKS_PER_CELL {
+                let pos = Point2::new(x as f32 + rng.gen::<f32>(), y as f32 + rng.gen::<f32>());
+                let velocity = (rng.gen_range(-4.0, 4.0), rng.gen_range(-10.0, -2.0));
+                let life = rng.gen_range(0.4, 0.9);
+                self.add(Particle {
+                    pos,
+                    velocity,
+                    color: color(x),
+                    left: life,
+                    life,
+                });
+            }
+        }
+    }
+
+    /// Knocks fragments off the bottom of `tetromino` where it landed at
+    /// `pos`, facing `facing`.
+    pub fn hard_drop(
+        &mut self,
+        tetromino: Tetromino,
+        pos: Point2<i32>,
+        facing: Rotation,
+        color: Color,
+    ) {
+        let mut rng = rand::thread_rng();
+        let blocks = tetromino.blocks(pos, facing);
+        let undersides = blocks
+            .iter()
+            .filter(|block| !blocks.contains(&Point2::new(block[0], block[1] + 1)));
+        for block in undersides {
+            for _ in 0..FRAGMENTS_PER_CELL {
+                let pos = Point2::new(block[0] as f32 + rng.gen::<f32>(), block[1] as f32 + 1.0);
+                let velocity = (rng.gen_range(-3.0, 3.0), rng.gen_range(-5.0, -1.0));
+                let life = rng.gen_range(0.2, 0.4);
+                self.add(Particle {
+                    pos,
+                    velocity,
+                    color,
+                    left: life,
+                    life,
+                });
+            }
+        }
+    }
+
+    fn add(&mut self, particle: Particle) {
+        if self.particles.len() >= MAX_PARTICLES {
+            self.particles.remove(0);
+        }
+        self.particles.push(particle);
+    }
+
+    /// Moves every particle on by `dt` seconds, and lets go of the ones
+    /// that have burnt out.
+    pub fn step(&mut self, dt: f32) {
+        for particle in &mut self.particles {
+            particle.velocity.1 += GRAVITY * dt;
+            particle.pos[0] += particle.velocity.0 * dt;
+            particle.pos[1] += particle.velocity.1 * dt;
+            particle.left -= dt;
+        }
+        self.particles.retain(|particle| particle.left > 0.0);
+    }
+
+    /// Draws the particles over a board with its top-left corner at
+    /// `origin` and cells `cell_size` across, fading as they burn out.
+    pub fn draw(&self, ctx: &mut Context, origin: Point2<f32>, cell_size: f32) -> GameResult {
+        if self.particles.is_empty() {
+            return Ok(());
+        }
+        let size = SIZE * cell_size;
+        let mut builder = MeshBuilder::new();
+        for particle in &self.particles {
+            let at = Rect::new(
+                origin[0] + particle.pos[0] * cell_size - size / 2.0,
+                origin[1] + particle.pos[1] * cell_size - size / 2.0,
+                size,
+                size,
+            );
+            let mut color = particle.color;
+            color.a *= particle.left / particle.life;
+            builder.rectangle(DrawMode::fill(), at, color);
+        }
+        let mesh = builder.build(ctx)?;
+        graphics::draw(ctx, &mesh, (Point2::new(0.0, 0.0),))
+    }
+}
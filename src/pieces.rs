@@ -0,0 +1,90 @@
+use crate::packs::Packs;
+use ggez::filesystem;
+use ggez::{Context, GameError, GameResult};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tetris_rs::tetromino::{self, PieceDef};
+use tetris_rs::{attack, gravity, kicks};
+
+const PIECES_FILE: &str = "pieces.toml";
+const KICKS_FILE: &str = "kicks.toml";
+const GRAVITY_FILE: &str = "gravity.toml";
+const ATTACK_FILE: &str = "attack.toml";
+
+/// The layout of `pieces.toml`, one `[[piece]]` table per shape:
+///
+/// ```toml
+/// [[piece]]
+/// name = "Tromino L"
+/// symbol = "a"
+/// blocks = [[0, 0], [1, 0], [0, -1]]
+/// spawn_facing = 0
+/// color = [255, 136, 0]
+/// ```
+#[derive(Deserialize)]
+struct PiecesFile {
+    #[serde(default)]
+    piece: Vec<PieceDef>,
+}
+
+/// `file` in the user config directory, or if the player has none, the one
+/// from the last pack that is on and has it. The tables can only be set
+/// once, so files aren't merged.
+fn find(ctx: &Context, packs: &Packs, file: &str) -> Option<PathBuf> {
+    let path = filesystem::user_config_dir(ctx).join(file);
+    if path.exists() {
+        Some(path)
+    } else {
+        packs.file(file)
+    }
+}
+
+fn invalid(path: &Path, e: String) -> GameError {
+    GameError::ResourceLoadError(format!("Invalid {}: {}", path.display(), e))
+}
+
+/// Adds the pieces in `pieces.toml` in the user config directory, next to
+/// `config.toml`, or in a pack, as the custom piece set. Having no file is
+/// fine; one that can't be read or defines a bad piece stops the game
+/// starting.
+pub fn load(ctx: &Context, packs: &Packs) -> GameResult {
+    let path = match find(ctx, packs, PIECES_FILE) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let file: PiecesFile =
+        toml::from_str(&fs::read_to_string(&path)?).map_err(|e| invalid(&path, e.to_string()))?;
+    tetromino::define_custom(file.piece).map_err(|e| invalid(&path, e))
+}
+
+/// Swaps in the kick tables in `kicks.toml`, found the same way, for the
+/// built-in ones of the same names. As with `load`, no file is fine but a
+/// bad one stops the game starting.
+pub fn load_kicks(ctx: &Context, packs: &Packs) -> GameResult {
+    let path = match find(ctx, packs, KICKS_FILE) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    kicks::define(&fs::read_to_string(&path)?).map_err(|e| invalid(&path, e))
+}
+
+/// Swaps in the gravity curves in `gravity.toml` the same way `load_kicks`
+/// does the kick tables.
+pub fn load_gravity(ctx: &Context, packs: &Packs) -> GameResult {
+    let path = match find(ctx, packs, GRAVITY_FILE) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    gravity::define(&fs::read_to_string(&path)?).map_err(|e| invalid(&path, e))
+}
+
+/// Swaps in the attack tables in `attack.toml` the same way `load_kicks`
+/// does the kick tables.
+pub fn load_attack(ctx: &Context, packs: &Packs) -> GameResult {
+    let path = match find(ctx, packs, ATTACK_FILE) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    attack::define(&fs::read_to_string(&path)?).map_err(|e| invalid(&path, e))
+}
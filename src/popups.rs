@@ -0,0 +1,108 @@
+use crate::config::Config;
+use crate::locale::Locale;
+use crate::text;
+use ggez::graphics::{self, Color, Rect};
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+use std::time::{Duration, Instant};
+use tetris_rs::events::Observer;
+use tetris_rs::game::{Event, Game};
+
+/// How long a popup takes to rise and fade out.
+const POPUP_MILLIS: u64 = 1000;
+/// How far it rises over that time, in cells.
+const RISE_CELLS: f32 = 2.0;
+const LABEL_SIZE: f32 = 24.0;
+const POINTS_SIZE: f32 = 16.0;
+
+struct Popup {
+    lines: usize,
+    points: u32,
+    /// The middle of the rows cleared, in board cells.
+    row: f32,
+    at: Instant,
+}
+
+/// Floating text over the rows a piece clears, naming the clear and the
+/// points it scored. Like `Particles` it's in board cells, but it's shown
+/// with reduced motion too, only without rising.
+#[derive(Default)]
+pub struct Popups {
+    popups: Vec<Popup>,
+    /// The rows of the clear under way, until its `LineClear` comes.
+    rows: Vec<i32>,
+}
+
+impl Popups {
+    /// Draws the popups still showing at `now`, centered over the board
+    /// at `board` on screen with cells `cell_size` across, and lets go of
+    /// the ones that have faded.
+    pub fn draw(
+        &mut self,
+        ctx: &mut Context,
+        now: Instant,
+        board: Rect,
+        cell_size: f32,
+        locale: &Locale,
+        config: &Config,
+    ) -> GameResult {
+        let length = Duration::from_millis(POPUP_MILLIS).as_secs_f32();
+        self.popups
+            .retain(|popup| now.saturating_duration_since(popup.at).as_secs_f32() < length);
+        let center = board.x + board.w / 2.0;
+        for popup in &self.popups {
+            let t = now.saturating_duration_since(popup.at).as_secs_f32() / length;
+            let rise = if config.reduced_motion {
+                0.0
+            } else {
+                t * RISE_CELLS
+            };
+            let y = board.y + (popup.row - rise) * cell_size;
+            let label = locale.get(match popup.lines {
+                1 => "popup.single",
+                2 => "popup.double",
+                3 => "popup.triple",
+                _ => "popup.tetris",
+            });
+            let color = if popup.lines >= 4 {
+                config.colors.accent()
+            } else {
+                graphics::WHITE
+            };
+            let points = locale.format("popup.points", &[&popup.points]);
+            let mut top = y - LABEL_SIZE;
+            for &(line, size) in &[(label, LABEL_SIZE), (points.as_str(), POINTS_SIZE)] {
+                let shown = text::line(line, size);
+                let (line_width, line_height) = shown.dimensions(ctx);
+                let dest = Point2::new(center - line_width as f32 / 2.0, top);
+                let faded = Color {
+                    a: 1.0 - t,
+                    ..color
+                };
+                graphics::draw(ctx, &shown, (dest, faded))?;
+                top += line_height as f32;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Observer for Popups {
+    fn notify(&mut self, event: Event, _: u64, game: &Game) {
+        match event {
+            Event::RowCleared(y) => self.rows.push(y),
+            Event::LineClear(lines) => {
+                let rows = self.rows.len().max(1) as f32;
+                let row = self.rows.iter().sum::<i32>() as f32 / rows + 0.5;
+                self.rows.clear();
+                self.popups.push(Popup {
+                    lines,
+                    points: game.clear_points(),
+                    row,
+                    at: Instant::now(),
+                });
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::board::{Board, FixedBlock};
+use crate::game::Game;
+use crate::tetromino::Tetromino;
+use nalgebra::Point2;
+use serde::{Deserialize, Serialize};
+
+/// What a puzzle or mission asks for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Goal {
+    /// Clear the board of every block.
+    PerfectClear,
+    /// Clear at least this many lines.
+    Lines(u32),
+    /// Land at least this many T-spins.
+    TSpins(u32),
+    /// Keep going for this many seconds.
+    Survive(u32),
+}
+
+impl Goal {
+    pub fn is_met(self, game: &Game) -> bool {
+        match self {
+            Goal::PerfectClear => game.lines > 0 && game.board.is_empty(),
+            Goal::Lines(goal) => game.lines >= goal,
+            Goal::TSpins(goal) => game.stats().t_spins >= goal,
+            Goal::Survive(secs) => game.elapsed().as_secs() >= secs.into(),
+        }
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            Goal::PerfectClear => "Perfect clear".to_string(),
+            Goal::Lines(1) => "Clear a line".to_string(),
+            Goal::Lines(goal) => format!("Clear {} lines", goal),
+            Goal::TSpins(1) => "Land a T-spin".to_string(),
+            Goal::TSpins(goal) => format!("Land {} T-spins", goal),
+            Goal::Survive(secs) => format!("Survive {}:{:02}", secs / 60, secs % 60),
+        }
+    }
+}
+
+/// A set challenge: a board to start from, the only pieces there are to
+/// play on it, in order, and what has to be done with them.
+#[derive(Clone, Debug)]
+pub struct Puzzle {
+    pub name: String,
+    pub board: Board,
+    pub pieces: Vec<Tetromino>,
+    pub goal: Goal,
+    /// The fewest pieces it can be solved with, if that's been worked out.
+    pub par: Option<u32>,
+}
+
+/// How a puzzle is written in a file, the board as its bottom rows with a
+/// piece's letter or `G` for each filled cell and `.` for each empty one,
+/// the pieces as their letters in the order they're dealt, and the par
+/// optional:
+///
+/// ```toml
+/// name = "Two by four"
+/// pieces = "OO"
+/// par = 2
+/// rows = ["GGGGGG....", "GGGGGG...."]
+/// goal = "perfect_clear" # or goal = { lines = 2 }
+/// ```
+#[derive(Serialize, Deserialize)]
+struct SavedPuzzle {
+    name: String,
+    pieces: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    par: Option<u32>,
+    rows: Vec<String>,
+    goal: Goal,
+}
+
+/// A drill: the pieces to play, in order, and the board to play them on.
+/// Once the pieces run out the round deals at random, unless they repeat.
+#[derive(Clone, Debug)]
+pub struct Practice {
+    pub board: Board,
+    pub queue: Vec<Tetromino>,
+    pub repeat: bool,
+}
+
+impl Practice {
+    /// A drill from a piece order written as letters, as in
+    /// `"IJLOSTZ repeated"`, and the bottom rows of a `width` by `height`
+    /// board as in a saved board.
+    pub fn parse(
+        queue: &str,
+        rows: &[String],
+        width: usize,
+        height: usize,
+    ) -> Result<Practice, String> {
+        let (letters, repeat) = match queue.trim().strip_suffix("repeated") {
+            Some(letters) => (letters, true),
+            None => (queue, false),
+        };
+        let letters: String = letters.chars().filter(|c| !c.is_whitespace()).collect();
+        let queue =
+            parse_pieces(&letters).ok_or_else(|| format!("unknown piece in {:?}", letters))?;
+        if repeat && queue.is_empty() {
+            return Err("there are no pieces to repeat".to_string());
+        }
+        let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+        let board = bottom_rows(&rows, width, height)
+            .ok_or_else(|| format!("the board doesn't fit {} by {}", width, height))?;
+        Ok(Practice {
+            board,
+            queue,
+            repeat,
+        })
+    }
+}
+
+impl Puzzle {
+    /// A puzzle from a puzzle file, on a board `width` by `height`.
+    pub fn parse(text: &str, width: usize, height: usize) -> Result<Puzzle, String> {
+        let saved: SavedPuzzle = toml::from_str(text).map_err(|e| e.to_string())?;
+        let pieces = parse_pieces(&saved.pieces)
+            .ok_or_else(|| format!("unknown piece in {:?}", saved.pieces))?;
+        if pieces.is_empty() {
+            return Err("there are no pieces to play".to_string());
+        }
+        let rows: Vec<&str> = saved.rows.iter().map(String::as_str).collect();
+        let board = bottom_rows(&rows, width, height)
+            .ok_or_else(|| format!("the board doesn't fit {} by {}", width, height))?;
+        Ok(Puzzle {
+            name: saved.name,
+            board,
+            pieces,
+            goal: saved.goal,
+            par: saved.par,
+        })
+    }
+
+    /// The puzzle as a puzzle file.
+    pub fn to_toml(&self) -> Result<String, String> {
+        let saved = SavedPuzzle {
+            name: self.name.clone(),
+            pieces: self.pieces.iter().map(|piece| piece.symbol()).collect(),
+            par: self.par,
+            rows: board_rows(&self.board),
+            goal: self.goal,
+        };
+        toml::Value::try_from(&saved)
+            .and_then(|value| toml::to_string(&value))
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// The visible rows of `board` from the highest with anything in it down,
+/// as puzzle files and `Practice::parse` take them.
+pub fn board_rows(board: &Board) -> Vec<String> {
+    let rows: Vec<String> = board.to_string().lines().map(str::to_string).collect();
+    let top = rows
+        .iter()
+        .position(|row| row.chars().any(|c| c != '.'))
+        .unwrap_or(rows.len());
+    rows[top..].to_vec()
+}
+
+/// A `width` by `height` board with `rows` along the bottom, or `None` if
+/// they don't fit.
+pub fn bottom_rows(rows: &[&str], width: usize, height: usize) -> Option<Board> {
+    if rows.len() > height || rows.iter().any(|row| row.chars().count() != width) {
+        return None;
+    }
+    let mut board = Board::new(width, height);
+    let top = height - rows.len();
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            if c != '.' {
+                let block = FixedBlock::from_char(c)?;
+                board.set(Point2::new(x as i32, (top + y) as i32), block);
+            }
+        }
+    }
+    Some(board)
+}
+
+/// Pieces from their letters, as in `FixedBlock::to_char`.
+pub fn parse_pieces(letters: &str) -> Option<Vec<Tetromino>> {
+    letters
+        .chars()
+        .map(|c| match FixedBlock::from_char(c) {
+            Some(FixedBlock::Piece(tetromino)) => Some(tetromino),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,148 @@
+use crate::highscores;
+use ggez::filesystem;
+use ggez::{Context, GameError, GameResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tetris_rs::puzzle::Puzzle;
+
+const PUZZLE_DIR: &str = "puzzles";
+const PUZZLE_EXTENSION: &str = "toml";
+const PROGRESS_FILE: &str = "puzzles.toml";
+
+fn is_puzzle(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == PUZZLE_EXTENSION)
+}
+
+/// Adds the puzzle in `text`, read from `path`, to `puzzles`, in place of
+/// one of the same name.
+fn add(puzzles: &mut Vec<Puzzle>, path: &Path, text: &str, width: usize, height: usize) {
+    match Puzzle::parse(text, width, height) {
+        Ok(puzzle) => match puzzles.iter_mut().find(|p| p.name == puzzle.name) {
+            Some(same) => *same = puzzle,
+            None => puzzles.push(puzzle),
+        },
+        Err(e) => eprintln!("Invalid puzzle {}: {}", path.display(), e),
+    }
+}
+
+/// The files in `dir` of the resources and the packs that are on with the
+/// extension puzzles are written with, in order of name, and what's in
+/// them. Those that can't be read are left out.
+pub fn read_bundled(ctx: &mut Context, dir: &str) -> Vec<(PathBuf, String)> {
+    let mut paths: Vec<PathBuf> = filesystem::read_dir(ctx, format!("/{}", dir))
+        .map(|paths| paths.filter(|path| is_puzzle(path)).collect())
+        .unwrap_or_default();
+    paths.sort();
+    let mut files = Vec::new();
+    for path in paths {
+        let read = |ctx: &mut Context| -> GameResult<String> {
+            let mut text = String::new();
+            filesystem::open(ctx, &path)?
+                .read_to_string(&mut text)
+                .map_err(GameError::from)?;
+            Ok(text)
+        };
+        match read(ctx) {
+            Ok(text) => files.push((path, text)),
+            Err(e) => eprintln!("Could not read {}: {}", path.display(), e),
+        }
+    }
+    files
+}
+
+/// The puzzles in `resources/puzzles` and the packs that are on, then
+/// those in `puzzles` in the user config directory, each in order of file
+/// name, on a board `width` by `height`. A player's puzzle replaces a
+/// built-in one of the same name. Those that can't be read or don't fit
+/// are left out.
+pub fn load(ctx: &mut Context, width: usize, height: usize) -> Vec<Puzzle> {
+    let mut puzzles = Vec::new();
+    for (path, text) in read_bundled(ctx, PUZZLE_DIR) {
+        add(&mut puzzles, &path, &text, width, height);
+    }
+
+    let dir = filesystem::user_config_dir(ctx).join(PUZZLE_DIR);
+    let mut mine: Vec<PathBuf> = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| is_puzzle(path))
+                .collect()
+        })
+        .unwrap_or_default();
+    mine.sort();
+    for path in mine {
+        match fs::read_to_string(&path) {
+            Ok(text) => add(&mut puzzles, &path, &text, width, height),
+            Err(e) => eprintln!("Could not read puzzle {}: {}", path.display(), e),
+        }
+    }
+    puzzles
+}
+
+/// Writes `puzzle` to a new file in the `puzzles` folder of the user
+/// config directory, named after the time it was saved, and returns the
+/// path.
+pub fn save(ctx: &Context, puzzle: &Puzzle) -> GameResult<PathBuf> {
+    let contents = puzzle
+        .to_toml()
+        .map_err(|e| GameError::ResourceLoadError(format!("Could not write puzzle: {}", e)))?;
+    let dir = filesystem::user_config_dir(ctx).join(PUZZLE_DIR);
+    fs::create_dir_all(&dir)?;
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let path = dir.join(format!("{}.{}", secs, PUZZLE_EXTENSION));
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+/// The puzzles solved so far, by name, with the fewest pieces each took,
+/// stored in the user data directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Progress {
+    #[serde(default)]
+    solved: BTreeMap<String, u32>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Progress {
+    pub fn load(ctx: &Context) -> GameResult<Progress> {
+        let path = filesystem::user_data_dir(ctx).join(PROGRESS_FILE);
+        let mut progress: Progress = highscores::load_table(&path)?;
+        progress.path = path;
+        Ok(progress)
+    }
+
+    /// The fewest pieces the puzzle called `name` has been solved with, if
+    /// it has been.
+    pub fn best(&self, name: &str) -> Option<u32> {
+        self.solved.get(name).copied()
+    }
+
+    /// How many of `puzzles` have been solved.
+    pub fn count(&self, puzzles: &[Puzzle]) -> usize {
+        puzzles
+            .iter()
+            .filter(|puzzle| self.solved.contains_key(&puzzle.name))
+            .count()
+    }
+
+    /// Marks the puzzle called `name` solved with `pieces`, saving it if
+    /// that's the fewest yet.
+    pub fn record(&mut self, name: &str, pieces: u32) {
+        if self.best(name).is_some_and(|best| best <= pieces) {
+            return;
+        }
+        self.solved.insert(name.to_string(), pieces);
+        if let Err(e) = highscores::save_table(&self.path, self) {
+            eprintln!("Could not save puzzle progress: {}", e);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Seed races: 40 line sprints dealt from a seed anyone can type in, so
+//! players on the same machine or far apart can race the same pieces
+//! without a connection. Each run on a seed is kept here to compare.
+
+use crate::highscores;
+use ggez::filesystem;
+use ggez::{Context, GameResult};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tetris_rs::game::Game;
+
+const RACES_FILE: &str = "races.toml";
+/// The longest seed that can be typed.
+pub const MAX_SEED_LEN: usize = 24;
+
+/// The piece seed for a race on `text`: the number itself if it is one, so
+/// a `--seed` can be raced, or otherwise a hash of it, ignoring case.
+pub fn seed(text: &str) -> u64 {
+    if let Ok(seed) = text.parse() {
+        return seed;
+    }
+    // FNV-1a, which is the same everywhere the game runs.
+    text.to_ascii_uppercase()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// How one run went.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Run {
+    /// Whether it cleared all 40 lines rather than topping out.
+    pub finished: bool,
+    pub millis: u64,
+    pub lines: u32,
+    pub pieces: u32,
+}
+
+impl Run {
+    pub fn time(&self) -> Duration {
+        Duration::from_millis(self.millis)
+    }
+
+    /// Finished runs first, fastest first, then the rest by lines cleared.
+    fn compare(&self, other: &Run) -> Ordering {
+        other.finished.cmp(&self.finished).then_with(|| {
+            if self.finished {
+                self.millis.cmp(&other.millis)
+            } else {
+                other.lines.cmp(&self.lines)
+            }
+        })
+    }
+}
+
+/// Every run on each seed, by the seed as typed, stored in the user data
+/// directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Races {
+    #[serde(default)]
+    runs: BTreeMap<String, Vec<Run>>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Races {
+    pub fn load(ctx: &Context) -> GameResult<Races> {
+        let path = filesystem::user_data_dir(ctx).join(RACES_FILE);
+        let mut races: Races = highscores::load_table(&path)?;
+        races.path = path;
+        Ok(races)
+    }
+
+    /// The runs on `seed`, numbered from 1 in the order they were played,
+    /// best first.
+    pub fn standings(&self, seed: &str) -> Vec<(usize, Run)> {
+        let mut runs: Vec<(usize, Run)> = self
+            .runs
+            .get(seed)
+            .map_or(&[][..], Vec::as_slice)
+            .iter()
+            .enumerate()
+            .map(|(at, &run)| (at + 1, run))
+            .collect();
+        runs.sort_by(|a, b| a.1.compare(&b.1));
+        runs
+    }
+
+    /// Adds how `game` went to the runs on `seed`, returning its number.
+    pub fn record(&mut self, seed: &str, game: &Game) -> usize {
+        let run = Run {
+            finished: game.cleared_goal(),
+            millis: game.elapsed().as_millis() as u64,
+            lines: game.lines,
+            pieces: game.stats().pieces,
+        };
+        let runs = self.runs.entry(seed.to_string()).or_default();
+        runs.push(run);
+        let number = runs.len();
+        if let Err(e) = highscores::save_table(&self.path, self) {
+            eprintln!("Could not save the races: {}", e);
+        }
+        number
+    }
+}
@@ -0,0 +1,123 @@
+//! The randomizers a `Randomizer` picks between. Each deals from the
+//! round's piece set with the round's generator, keeping what it needs to
+//! between pieces, so a seed always deals the same sequence.
+
+use crate::tetromino::{PieceSet, Tetromino};
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+
+/// Rolls a `History` randomizer takes to find a piece it hasn't dealt
+/// lately before it settles for the last one.
+const HISTORY_ROLLS: usize = 4;
+
+/// TGM2's history to start from, which makes S and Z unlikely to come
+/// first.
+const FIRST_HISTORY: [Tetromino; 4] = [
+    Tetromino::ZBlock,
+    Tetromino::SBlock,
+    Tetromino::ZBlock,
+    Tetromino::SBlock,
+];
+
+/// Picks each next piece of a round.
+pub trait Dealer {
+    fn deal(&mut self, pieces: PieceSet, rng: &mut XorShiftRng) -> Tetromino;
+
+    /// What it has to remember to carry on where it is, to save with the
+    /// round.
+    fn remembered(&self) -> Vec<Tetromino>;
+
+    /// Carries on from what `remembered` gave. Nothing remembered, as in
+    /// saves from before it was kept, starts it afresh.
+    fn recall(&mut self, remembered: Vec<Tetromino>);
+}
+
+/// Every piece as likely as any other, every time.
+pub struct Uniform;
+
+impl Dealer for Uniform {
+    fn deal(&mut self, pieces: PieceSet, rng: &mut XorShiftRng) -> Tetromino {
+        pieces.deal(rng)
+    }
+
+    fn remembered(&self) -> Vec<Tetromino> {
+        Vec::new()
+    }
+
+    fn recall(&mut self, _: Vec<Tetromino>) {}
+}
+
+/// Deals a bag holding `copies` of each piece in the set, in a random
+/// order, before filling it again.
+pub struct Bag {
+    copies: usize,
+    left: Vec<Tetromino>,
+}
+
+impl Bag {
+    pub fn new(copies: usize) -> Bag {
+        Bag {
+            copies,
+            left: Vec::new(),
+        }
+    }
+}
+
+impl Dealer for Bag {
+    fn deal(&mut self, pieces: PieceSet, rng: &mut XorShiftRng) -> Tetromino {
+        if self.left.is_empty() {
+            for _ in 0..self.copies {
+                self.left.extend_from_slice(pieces.dealt());
+            }
+        }
+        let at = rng.gen_range(0, self.left.len());
+        self.left.swap_remove(at)
+    }
+
+    fn remembered(&self) -> Vec<Tetromino> {
+        self.left.clone()
+    }
+
+    fn recall(&mut self, remembered: Vec<Tetromino>) {
+        self.left = remembered;
+    }
+}
+
+/// TGM's: rolls up to `HISTORY_ROLLS` times for a piece that isn't one of
+/// the last four dealt.
+pub struct History {
+    last: Vec<Tetromino>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History {
+            last: FIRST_HISTORY.to_vec(),
+        }
+    }
+}
+
+impl Dealer for History {
+    fn deal(&mut self, pieces: PieceSet, rng: &mut XorShiftRng) -> Tetromino {
+        let mut dealt = pieces.deal(rng);
+        for _ in 1..HISTORY_ROLLS {
+            if !self.last.contains(&dealt) {
+                break;
+            }
+            dealt = pieces.deal(rng);
+        }
+        self.last.remove(0);
+        self.last.push(dealt);
+        dealt
+    }
+
+    fn remembered(&self) -> Vec<Tetromino> {
+        self.last.clone()
+    }
+
+    fn recall(&mut self, remembered: Vec<Tetromino>) {
+        if remembered.len() == FIRST_HISTORY.len() {
+            self.last = remembered;
+        }
+    }
+}
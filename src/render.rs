@@ -0,0 +1,918 @@
+use crate::config::{Colors, Config, Marks};
+use crate::layout::{Layout, PANEL_TOP, PREVIEW_CELL_SIZE, PREVIEW_HEIGHT};
+use crate::locale::Locale;
+use crate::text;
+use ggez::graphics::spritebatch::SpriteBatch;
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Image, Mesh, MeshBuilder, Rect};
+use ggez::nalgebra::{Point2, Vector2};
+use ggez::{Context, GameResult};
+use std::collections::HashMap;
+use std::iter;
+use tetris_rs::board::{Board, FixedBlock};
+use tetris_rs::game::{Game, Heatmap};
+use tetris_rs::tetromino::Tetromino;
+
+/// The bar along the board's left edge showing incoming garbage, in this
+/// color once the next lock that doesn't clear would take it in.
+const GARBAGE_METER: Color = Color {
+    r: 0.9,
+    g: 0.1,
+    b: 0.1,
+    a: 0.8,
+};
+
+/// Garbage just sent, which turns to `GARBAGE_METER` over the garbage
+/// delay.
+const GARBAGE_SENT: Color = Color {
+    r: 0.9,
+    g: 0.8,
+    b: 0.1,
+    a: 0.6,
+};
+
+/// Pixels between batches on the garbage meter.
+const GARBAGE_GAP: f32 = 2.0;
+
+/// The tint for the piece's landing spot.
+const GHOST: Color = Color {
+    r: 1.0,
+    g: 1.0,
+    b: 1.0,
+    a: 0.25,
+};
+
+/// The ghost in high contrast, filled in more so it stands out.
+const HIGH_CONTRAST_GHOST: Color = Color {
+    r: 1.0,
+    g: 1.0,
+    b: 1.0,
+    a: 0.45,
+};
+
+/// How thick the outlines are in high contrast, around minos and the
+/// ghost's cells, as a fraction of a cell.
+const OUTLINE_WIDTH: f32 = 0.1;
+const GHOST_OUTLINE_WIDTH: f32 = 0.15;
+/// The tutor's suggested placement, outlined whatever the contrast.
+const SUGGESTION_OUTLINE_WIDTH: f32 = 0.12;
+const SUGGESTION_ALPHA: f32 = 0.7;
+
+/// The color of the marks drawn over minos, darker than any of them.
+const MARK: Color = Color {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 0.45,
+};
+
+/// How thick the frame around the board is.
+const BORDER_WIDTH: f32 = 2.0;
+
+/// Behind the board while the stack is in danger.
+const DANGER_TINT: Color = Color {
+    r: 0.6,
+    g: 0.0,
+    b: 0.0,
+    a: 0.3,
+};
+/// The warning frame around the board then, pulsing this many times a
+/// second, or steady with reduced motion.
+const DANGER_BORDER: Color = Color {
+    r: 1.0,
+    g: 0.15,
+    b: 0.15,
+    a: 1.0,
+};
+const DANGER_BORDER_WIDTH: f32 = 4.0;
+const DANGER_PULSES_PER_SECOND: f32 = 2.0;
+
+/// The tint for a held piece that can't be swapped back yet.
+const HOLD_USED: Color = Color {
+    r: 1.0,
+    g: 1.0,
+    b: 1.0,
+    a: 0.35,
+};
+
+/// A skin sheet with this many tiles joins minos to their neighbours.
+const CONNECTED_TILES: u16 = 16;
+/// The neighbours of a cell, in the order of their bits in a tile number.
+const SIDES: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+/// Mino tiles drawn from a skin image, a strip of square tiles in greys
+/// that are tinted with each block's color. A sheet of one tile draws every
+/// mino with it. One of 16 joins minos of the same piece: tile `n` is the
+/// mino with neighbours on the sides set in `n`, 1 up, 2 right, 4 down and
+/// 8 left.
+struct Skin {
+    image: Image,
+    tiles: u16,
+}
+
+impl Skin {
+    /// Loads `/skins/<name>.png` from the resources or a pack, or nothing
+    /// for flat colors if there's no name or no such image.
+    fn load(ctx: &mut Context, name: &str) -> Option<Skin> {
+        if name.is_empty() {
+            return None;
+        }
+        let path = format!("/skins/{}.png", name);
+        match Image::new(ctx, &path) {
+            Ok(image) => {
+                let tiles = (image.width() / image.height().max(1)).max(1);
+                Some(Skin { image, tiles })
+            }
+            Err(e) => {
+                eprintln!("Could not load {}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// The part of the sheet to draw a mino joined on the sides in
+    /// `joined` with, in fractions of the sheet as ggez wants them.
+    fn src(&self, joined: u16) -> Rect {
+        let tile = if self.tiles >= CONNECTED_TILES {
+            joined
+        } else {
+            0
+        };
+        let width = 1.0 / self.tiles as f32;
+        Rect::new(tile as f32 * width, 0.0, width, 1.0)
+    }
+
+    /// How much to scale a tile by to draw it `cell_size` across.
+    fn scale(&self, cell_size: f32) -> f32 {
+        cell_size / self.image.height() as f32
+    }
+}
+
+/// The sides of `block` that `is_same` says have more of its piece, as a
+/// skin's tile number.
+fn joined(block: Point2<i32>, is_same: impl Fn(Point2<i32>) -> bool) -> u16 {
+    SIDES
+        .iter()
+        .enumerate()
+        .filter(|(_, &(x, y))| is_same(Point2::new(block[0] + x, block[1] + y)))
+        .map(|(bit, _)| 1 << bit)
+        .sum()
+}
+
+/// A mesh for a mino of each piece, `cell_size` across, in its color.
+fn piece_meshes(
+    ctx: &mut Context,
+    cell_size: i32,
+    colors: &Colors,
+) -> GameResult<HashMap<Tetromino, Mesh>> {
+    let cell = Rect::new(0.0, 0.0, cell_size as f32, cell_size as f32);
+    let mut pieces = HashMap::new();
+    for tetromino in Tetromino::every() {
+        let mesh = Mesh::new_rectangle(ctx, DrawMode::fill(), cell, colors.piece(tetromino))?;
+        pieces.insert(tetromino, mesh);
+    }
+    Ok(pieces)
+}
+
+/// The fixed blocks batched into one draw.
+enum Fixed {
+    Flat(Mesh),
+    Skinned(SpriteBatch),
+}
+
+/// Draws the board and the falling piece from meshes built up front, or
+/// from a skin's tiles if one is set. The piece is drawn cell by cell with
+/// one cached mesh per piece, and the fixed blocks are batched into one
+/// mesh or sprite batch that is only rebuilt when the board changes.
+pub struct BoardRenderer {
+    cell_size: i32,
+    /// The theme's colors, and those in use: the same, or for rules that
+    /// change the pieces' colors with the level, the ones for
+    /// `colored_for`.
+    theme: Colors,
+    colors: Colors,
+    colored_for: Option<u32>,
+    pieces: HashMap<Tetromino, Mesh>,
+    skin: Option<Skin>,
+    marks: Marks,
+    /// Whether minos and the ghost are outlined to stand out.
+    high_contrast: bool,
+    reduced_motion: bool,
+    /// The board the cached blocks were built from.
+    fixed_board: Option<Board>,
+    /// The fixed blocks of `fixed_board`, or `None` if it was empty.
+    fixed: Option<Fixed>,
+    show_grid: bool,
+    /// The grid, cached with the board size it was built for.
+    grid_mesh: Option<((usize, usize), Mesh)>,
+    border_mesh: Option<((usize, usize), Mesh)>,
+}
+
+impl BoardRenderer {
+    pub fn new(ctx: &mut Context, config: &Config) -> GameResult<BoardRenderer> {
+        BoardRenderer::sized(ctx, config, config.cell_size)
+    }
+
+    /// A renderer drawing cells `cell_size` across rather than the
+    /// config's, for boards shown smaller than the player's.
+    pub fn sized(ctx: &mut Context, config: &Config, cell_size: i32) -> GameResult<BoardRenderer> {
+        let colors = &config.colors;
+        Ok(BoardRenderer {
+            cell_size,
+            theme: colors.clone(),
+            colors: colors.clone(),
+            colored_for: None,
+            pieces: piece_meshes(ctx, cell_size, colors)?,
+            skin: Skin::load(ctx, &config.skin),
+            marks: config.marks,
+            high_contrast: config.high_contrast,
+            reduced_motion: config.reduced_motion,
+            fixed_board: None,
+            fixed: None,
+            show_grid: config.show_grid,
+            grid_mesh: None,
+            border_mesh: None,
+        })
+    }
+
+    /// Draws `game` with the board's top-left corner at `origin`. In a mode
+    /// that hides the stack only the pieces still fading out are drawn,
+    /// until the round is over and the whole stack is shown. Modes with big
+    /// minos draw each board cell `Mode::scale` times the size.
+    pub fn draw(&mut self, ctx: &mut Context, game: &Game, origin: Point2<f32>) -> GameResult {
+        let level = Some(game.level).filter(|_| game.ruleset().level_colors);
+        if level != self.colored_for {
+            self.colors = match level {
+                Some(level) => self.theme.at_level(level),
+                None => self.theme.clone(),
+            };
+            self.colored_for = level;
+            self.pieces = piece_meshes(ctx, self.cell_size, &self.colors)?;
+            self.fixed_board = None;
+        }
+        let scale = game.mode().scale() as f32;
+        let size = (game.board.width(), game.board.height());
+        let whole_board = DrawParam::new()
+            .dest(origin)
+            .scale(Vector2::new(scale, scale));
+        let danger = game.board.in_danger() && !game.game_over;
+        if danger {
+            let tint = self.build_frame(ctx, size, None, DANGER_TINT)?;
+            graphics::draw(ctx, &tint, whole_board)?;
+        }
+        if self.show_grid {
+            if self.grid_mesh.as_ref().map(|(built, _)| *built) != Some(size) {
+                self.grid_mesh = Some((size, self.build_grid(ctx, size)?));
+            }
+            if let Some((_, ref mesh)) = self.grid_mesh {
+                graphics::draw(ctx, mesh, whole_board)?;
+            }
+        }
+        let cell_size = self.cell_size as f32 * scale;
+        let on_screen = |origin: Point2<f32>, block: Point2<i32>| {
+            Point2::new(
+                origin[0] + block[0] as f32 * cell_size,
+                origin[1] + block[1] as f32 * cell_size,
+            )
+        };
+        // The cells to mark and outline, with where they go on screen.
+        let mut marked = Vec::new();
+        if game.mode().fade(game.level).is_some() && !game.game_over {
+            for (tetromino, cells, left) in game.fading() {
+                let tint = Color::new(1.0, 1.0, 1.0, left);
+                self.draw_cells(ctx, tetromino, cells, origin, scale, tint)?;
+            }
+        } else {
+            if self.fixed_board.as_ref() != Some(&game.board) {
+                self.fixed = self.build_fixed(ctx, &game.board)?;
+                self.fixed_board = Some(game.board.clone());
+            }
+            match self.fixed {
+                Some(Fixed::Flat(ref mesh)) => graphics::draw(ctx, mesh, whole_board)?,
+                Some(Fixed::Skinned(ref batch)) => graphics::draw(ctx, batch, whole_board)?,
+                None => (),
+            }
+            if self.marks != Marks::Off || self.high_contrast {
+                marked.extend(
+                    game.board
+                        .visible_blocks()
+                        .map(|(block, fixed)| (on_screen(origin, block), fixed)),
+                );
+            }
+        }
+
+        if game.ruleset().ghost {
+            let ghost = game.ghost_blocks();
+            let tint = if self.high_contrast {
+                HIGH_CONTRAST_GHOST
+            } else {
+                GHOST
+            };
+            self.draw_cells(ctx, game.tetromino(), &ghost, origin, scale, tint)?;
+            if self.high_contrast {
+                let cells: Vec<_> = ghost
+                    .iter()
+                    .map(|&block| on_screen(origin, block))
+                    .collect();
+                self.draw_outlines(ctx, &cells, cell_size, GHOST_OUTLINE_WIDTH, 1.0)?;
+            }
+        }
+        // Part way to the next row so the fall looks smooth, while the
+        // ghost stays put.
+        let fall = if self.reduced_motion {
+            0.0
+        } else {
+            game.fall_progress() * cell_size
+        };
+        let falling = Point2::new(origin[0], origin[1] + fall);
+        let active = game.active_blocks();
+        let tint = graphics::WHITE;
+        self.draw_cells(ctx, game.tetromino(), &active, falling, scale, tint)?;
+        let piece = FixedBlock::Piece(game.tetromino());
+        marked.extend(
+            active
+                .iter()
+                .filter(|block| block[1] >= 0)
+                .map(|&block| (on_screen(falling, block), piece)),
+        );
+        if self.high_contrast {
+            let cells: Vec<_> = marked.iter().map(|&(at, _)| at).collect();
+            self.draw_outlines(ctx, &cells, cell_size, OUTLINE_WIDTH, 1.0)?;
+        }
+        self.draw_marks(ctx, &marked, cell_size, 1.0)?;
+        // Over the edge cells rather than outside them, where there may be
+        // no room.
+        if self.border_mesh.as_ref().map(|(built, _)| *built) != Some(size) {
+            self.border_mesh = Some((size, self.build_border(ctx, size)?));
+        }
+        if let Some((_, ref mesh)) = self.border_mesh {
+            graphics::draw(ctx, mesh, whole_board)?;
+        }
+        if danger {
+            let strength = if self.reduced_motion {
+                1.0
+            } else {
+                let phase = game.elapsed().as_secs_f32() * DANGER_PULSES_PER_SECOND;
+                0.6 + 0.4 * (phase * std::f32::consts::TAU).cos()
+            };
+            let color = Color {
+                a: DANGER_BORDER.a * strength,
+                ..DANGER_BORDER
+            };
+            let border = self.build_frame(ctx, size, Some(DANGER_BORDER_WIDTH), color)?;
+            graphics::draw(ctx, &border, whole_board)?;
+        }
+        self.draw_garbage_meter(ctx, game, origin, scale)
+    }
+
+    /// Outlines board `cells` of `game`, drawn with the board's top-left
+    /// corner at `origin`, where the tutor would put the piece.
+    pub fn draw_suggestion(
+        &self,
+        ctx: &mut Context,
+        game: &Game,
+        cells: &[Point2<i32>],
+        origin: Point2<f32>,
+    ) -> GameResult {
+        let cell_size = self.cell_size as f32 * game.mode().scale() as f32;
+        let cells: Vec<_> = cells
+            .iter()
+            .filter(|block| block[1] >= 0)
+            .map(|block| {
+                Point2::new(
+                    origin[0] + block[0] as f32 * cell_size,
+                    origin[1] + block[1] as f32 * cell_size,
+                )
+            })
+            .collect();
+        let width = SUGGESTION_OUTLINE_WIDTH;
+        self.draw_outlines(ctx, &cells, cell_size, width, SUGGESTION_ALPHA)
+    }
+
+    /// Draws just `game`'s falling piece and its landing spot, over a board
+    /// `draw` has drawn with its top-left corner at `origin`: the second
+    /// player's piece on a shared board.
+    pub fn draw_piece(&self, ctx: &mut Context, game: &Game, origin: Point2<f32>) -> GameResult {
+        let scale = game.mode().scale() as f32;
+        if game.ruleset().ghost {
+            let tint = if self.high_contrast {
+                HIGH_CONTRAST_GHOST
+            } else {
+                GHOST
+            };
+            let ghost = game.ghost_blocks();
+            self.draw_cells(ctx, game.tetromino(), &ghost, origin, scale, tint)?;
+        }
+        let fall = if self.reduced_motion {
+            0.0
+        } else {
+            game.fall_progress() * self.cell_size as f32 * scale
+        };
+        let falling = Point2::new(origin[0], origin[1] + fall);
+        let active = game.active_blocks();
+        self.draw_cells(
+            ctx,
+            game.tetromino(),
+            &active,
+            falling,
+            scale,
+            graphics::WHITE,
+        )
+    }
+
+    /// Draws the cells of a `tetromino` at board positions `blocks`,
+    /// tinted by `color`.
+    fn draw_cells(
+        &self,
+        ctx: &mut Context,
+        tetromino: Tetromino,
+        blocks: &[Point2<i32>],
+        origin: Point2<f32>,
+        scale: f32,
+        color: Color,
+    ) -> GameResult {
+        // Nothing in the hidden rows above the board is drawn.
+        for &block in blocks.iter().filter(|block| block[1] >= 0) {
+            let cell = self.cell_origin(block);
+            let dest = Point2::new(origin[0] + cell[0] * scale, origin[1] + cell[1] * scale);
+            let joined = joined(block, |side| blocks.contains(&side));
+            self.draw_mino(ctx, tetromino, joined, dest, scale, color)?;
+        }
+        Ok(())
+    }
+
+    /// Draws a mino of `tetromino` with its top-left corner at `dest`,
+    /// `scale` times the size of a board cell.
+    fn draw_mino(
+        &self,
+        ctx: &mut Context,
+        tetromino: Tetromino,
+        joined: u16,
+        dest: Point2<f32>,
+        scale: f32,
+        color: Color,
+    ) -> GameResult {
+        match self.skin {
+            Some(ref skin) => {
+                let scale = scale * skin.scale(self.cell_size as f32);
+                let param = DrawParam::new()
+                    .src(skin.src(joined))
+                    .dest(dest)
+                    .scale(Vector2::new(scale, scale))
+                    .color(tinted(self.colors.piece(tetromino), color));
+                graphics::draw(ctx, &skin.image, param)
+            }
+            None => {
+                let param = DrawParam::new()
+                    .dest(dest)
+                    .scale(Vector2::new(scale, scale))
+                    .color(color);
+                graphics::draw(ctx, &self.pieces[&tetromino], param)
+            }
+        }
+    }
+
+    /// Draws the hold box in `layout`'s left panel and the next queue,
+    /// score, level and lines in its right, returning how far down the
+    /// right panel they reach.
+    pub fn draw_panels(
+        &self,
+        ctx: &mut Context,
+        game: &Game,
+        locale: &Locale,
+        layout: Layout,
+    ) -> GameResult<f32> {
+        let left = layout.left_panel();
+        text::draw(
+            ctx,
+            locale.get("hud.hold"),
+            14.0,
+            self.colors.subdued(),
+            Point2::new(left.x + 8.0, PANEL_TOP),
+        )?;
+        let preview_middle = PANEL_TOP + 24.0 + PREVIEW_HEIGHT / 2.0;
+        if let Some(held) = game.hold {
+            let tint = if game.hold_used() {
+                HOLD_USED
+            } else {
+                graphics::WHITE
+            };
+            let center = Point2::new(left.x + left.w / 2.0, preview_middle);
+            self.draw_preview(ctx, game, held, center, tint)?;
+        }
+
+        let right = layout.right_panel();
+        text::draw(
+            ctx,
+            locale.get("hud.next"),
+            14.0,
+            self.colors.subdued(),
+            Point2::new(right.x + 8.0, PANEL_TOP),
+        )?;
+        let mut y = preview_middle;
+        let previews = game.ruleset().previews;
+        let shown = game
+            .handicap()
+            .preview
+            .map_or(previews, |shown| shown.min(previews));
+        for tetromino in iter::once(game.next()).chain(game.queued()).take(shown) {
+            let center = Point2::new(right.x + right.w / 2.0, y);
+            self.draw_preview(ctx, game, tetromino, center, graphics::WHITE)?;
+            y += PREVIEW_HEIGHT;
+        }
+        y += 16.0 - PREVIEW_HEIGHT / 2.0;
+        let numbers = [
+            ("hud.score", game.score),
+            ("hud.level", game.level),
+            ("hud.lines", game.lines),
+        ];
+        for &(name, value) in &numbers {
+            text::draw(
+                ctx,
+                locale.get(name),
+                14.0,
+                self.colors.subdued(),
+                Point2::new(right.x + 8.0, y),
+            )?;
+            let value = value.to_string();
+            let at = Point2::new(right.x + right.w - 8.0, y + 18.0);
+            text::draw_right(ctx, &value, 20.0, graphics::WHITE, at)?;
+            y += 52.0;
+        }
+        Ok(y)
+    }
+
+    /// Draws the second player's hold box and next piece down `panel`, for
+    /// co-op, where the player's own are in the usual places.
+    pub fn draw_partner_panel(
+        &self,
+        ctx: &mut Context,
+        game: &Game,
+        locale: &Locale,
+        panel: Rect,
+    ) -> GameResult {
+        let center_x = panel.x + panel.w / 2.0;
+        let mut top = panel.y;
+        let hold_tint = if game.hold_used() {
+            HOLD_USED
+        } else {
+            graphics::WHITE
+        };
+        let previews = [
+            ("hud.partner_hold", game.hold, hold_tint),
+            ("hud.partner_next", Some(game.next()), graphics::WHITE),
+        ];
+        for &(heading, tetromino, tint) in &previews {
+            text::draw(
+                ctx,
+                locale.get(heading),
+                14.0,
+                self.colors.subdued(),
+                Point2::new(panel.x + 8.0, top),
+            )?;
+            if let Some(tetromino) = tetromino {
+                let center = Point2::new(center_x, top + 24.0 + PREVIEW_HEIGHT / 2.0);
+                self.draw_preview(ctx, game, tetromino, center, tint)?;
+            }
+            top += 24.0 + PREVIEW_HEIGHT;
+        }
+        Ok(())
+    }
+
+    /// Draws `tetromino` as it spawns, small and centered on `center`.
+    fn draw_preview(
+        &self,
+        ctx: &mut Context,
+        game: &Game,
+        tetromino: Tetromino,
+        center: Point2<f32>,
+        color: Color,
+    ) -> GameResult {
+        let facing = game.ruleset().rotation.spawn_facing(tetromino);
+        let blocks = tetromino.blocks(Point2::new(0, 0), facing);
+        let span = |axis: usize| {
+            let low = blocks.iter().map(|b| b[axis]).min().unwrap_or(0);
+            let high = blocks.iter().map(|b| b[axis]).max().unwrap_or(0);
+            (low + high + 1) as f32 / 2.0
+        };
+        let (middle_x, middle_y) = (span(0), span(1));
+        let scale = PREVIEW_CELL_SIZE / self.cell_size as f32;
+        let mut marked = Vec::new();
+        for &block in &blocks {
+            let dest = Point2::new(
+                center[0] + (block[0] as f32 - middle_x) * PREVIEW_CELL_SIZE,
+                center[1] + (block[1] as f32 - middle_y) * PREVIEW_CELL_SIZE,
+            );
+            let joined = joined(block, |side| blocks.contains(&side));
+            self.draw_mino(ctx, tetromino, joined, dest, scale, color)?;
+            marked.push((dest, FixedBlock::Piece(tetromino)));
+        }
+        if self.high_contrast {
+            let cells: Vec<_> = marked.iter().map(|&(at, _)| at).collect();
+            self.draw_outlines(ctx, &cells, PREVIEW_CELL_SIZE, OUTLINE_WIDTH, color.a)?;
+        }
+        self.draw_marks(ctx, &marked, PREVIEW_CELL_SIZE, color.a)
+    }
+
+    /// Outlines `cells`, the top-left corners on screen of cells `size`
+    /// across, in lines `width` of a cell thick and as see-through as
+    /// `alpha`.
+    fn draw_outlines(
+        &self,
+        ctx: &mut Context,
+        cells: &[Point2<f32>],
+        size: f32,
+        width: f32,
+        alpha: f32,
+    ) -> GameResult {
+        if cells.is_empty() {
+            return Ok(());
+        }
+        let width = width * size;
+        let color = Color::new(1.0, 1.0, 1.0, alpha);
+        let mut builder = MeshBuilder::new();
+        for at in cells {
+            // Inside the cell, so neighbours' outlines don't overlap.
+            let inset = width / 2.0;
+            let outline = Rect::new(at[0] + inset, at[1] + inset, size - width, size - width);
+            builder.rectangle(DrawMode::stroke(width), outline, color);
+        }
+        let mesh = builder.build(ctx)?;
+        graphics::draw(ctx, &mesh, (Point2::new(0.0, 0.0),))
+    }
+
+    /// Draws the marks over `cells`, minos at points on screen `size`
+    /// across, as see-through as `alpha`.
+    fn draw_marks(
+        &self,
+        ctx: &mut Context,
+        cells: &[(Point2<f32>, FixedBlock)],
+        size: f32,
+        alpha: f32,
+    ) -> GameResult {
+        if self.marks == Marks::Off {
+            return Ok(());
+        }
+        let color = Color {
+            a: MARK.a * alpha,
+            ..MARK
+        };
+        let mut patterns = MeshBuilder::new();
+        let mut any_patterns = false;
+        let mut letters = Vec::new();
+        for &(at, block) in cells {
+            let tetromino = match block {
+                FixedBlock::Piece(tetromino) => tetromino,
+                FixedBlock::Garbage => continue,
+            };
+            if self.marks == Marks::Patterns
+                && add_pattern(&mut patterns, tetromino, at, size, color)?
+            {
+                any_patterns = true;
+            } else {
+                letters.push((at, tetromino));
+            }
+        }
+        // ggez refuses to build a mesh with no vertices.
+        if any_patterns {
+            let mesh = patterns.build(ctx)?;
+            graphics::draw(ctx, &mesh, (Point2::new(0.0, 0.0),))?;
+        }
+        if letters.is_empty() {
+            return Ok(());
+        }
+        for (at, tetromino) in letters {
+            let text = text::line(&tetromino.symbol().to_string(), size * 0.6);
+            let (width, height) = text.dimensions(ctx);
+            let at = Point2::new(
+                at[0] + (size - width as f32) / 2.0,
+                at[1] + (size - height as f32) / 2.0,
+            );
+            graphics::queue_text(ctx, &text, at, Some(color));
+        }
+        graphics::draw_queued_text(ctx, DrawParam::new())
+    }
+
+    fn draw_garbage_meter(
+        &self,
+        ctx: &mut Context,
+        game: &Game,
+        origin: Point2<f32>,
+        scale: f32,
+    ) -> GameResult {
+        if game.pending_garbage() == 0 {
+            return Ok(());
+        }
+        let cell_size = self.cell_size as f32 * scale;
+        let top = origin[1];
+        let mut bottom = origin[1] + game.board.height() as f32 * cell_size;
+        let mut builder = MeshBuilder::new();
+        // The batch that comes in first at the bottom, each piled on the
+        // one before up to the top of the board.
+        for (rows, ready) in game.incoming_garbage() {
+            if bottom <= top {
+                break;
+            }
+            let height = (rows as f32 * cell_size).min(bottom - top);
+            let bar = Rect::new(
+                origin[0],
+                bottom - height,
+                cell_size / 4.0,
+                (height - GARBAGE_GAP).max(0.0),
+            );
+            let color = blended(GARBAGE_SENT, GARBAGE_METER, ready);
+            builder.rectangle(DrawMode::fill(), bar, color);
+            bottom -= height;
+        }
+        let mesh = builder.build(ctx)?;
+        graphics::draw(ctx, &mesh, (Point2::new(0.0, 0.0),))
+    }
+
+    fn build_fixed(&self, ctx: &mut Context, board: &Board) -> GameResult<Option<Fixed>> {
+        if board.visible_blocks().next().is_none() {
+            // ggez refuses to build a mesh with no vertices.
+            return Ok(None);
+        }
+        if let Some(ref skin) = self.skin {
+            let scale = skin.scale(self.cell_size as f32);
+            let mut batch = SpriteBatch::new(skin.image.clone());
+            for (position, block) in board.visible_blocks() {
+                let same = Some(Some(block));
+                let joined = joined(position, |side| board.get(side) == same);
+                batch.add(
+                    DrawParam::new()
+                        .src(skin.src(joined))
+                        .dest(self.cell_origin(position))
+                        .scale(Vector2::new(scale, scale))
+                        .color(self.colors.block(block)),
+                );
+            }
+            return Ok(Some(Fixed::Skinned(batch)));
+        }
+        let mut builder = MeshBuilder::new();
+        for (position, block) in board.visible_blocks() {
+            builder.rectangle(
+                DrawMode::fill(),
+                block_rect(position, self.cell_size),
+                self.colors.block(block),
+            );
+        }
+        builder.build(ctx).map(|mesh| Some(Fixed::Flat(mesh)))
+    }
+
+    fn build_grid(&self, ctx: &mut Context, (width, height): (usize, usize)) -> GameResult<Mesh> {
+        let cell_size = self.cell_size as f32;
+        let (right, bottom) = (width as f32 * cell_size, height as f32 * cell_size);
+        let mut builder = MeshBuilder::new();
+        for x in 1..width {
+            let x = x as f32 * cell_size;
+            builder.line(
+                &[Point2::new(x, 0.0), Point2::new(x, bottom)],
+                1.0,
+                self.colors.grid(),
+            )?;
+        }
+        for y in 1..height {
+            let y = y as f32 * cell_size;
+            builder.line(
+                &[Point2::new(0.0, y), Point2::new(right, y)],
+                1.0,
+                self.colors.grid(),
+            )?;
+        }
+        builder.build(ctx)
+    }
+
+    fn build_border(&self, ctx: &mut Context, size: (usize, usize)) -> GameResult<Mesh> {
+        self.build_frame(ctx, size, Some(BORDER_WIDTH), self.colors.border())
+    }
+
+    /// A rectangle over a board `width` by `height`, filled or, given a
+    /// `stroke` width, outlined inside the edge cells.
+    fn build_frame(
+        &self,
+        ctx: &mut Context,
+        (width, height): (usize, usize),
+        stroke: Option<f32>,
+        color: Color,
+    ) -> GameResult<Mesh> {
+        let cell_size = self.cell_size as f32;
+        let thickness = stroke.unwrap_or(0.0);
+        let inset = thickness / 2.0;
+        let frame = Rect::new(
+            inset,
+            inset,
+            width as f32 * cell_size - thickness,
+            height as f32 * cell_size - thickness,
+        );
+        let mode = stroke.map_or_else(DrawMode::fill, DrawMode::stroke);
+        Mesh::new_rectangle(ctx, mode, frame, color)
+    }
+
+    fn cell_origin(&self, block: Point2<i32>) -> Point2<f32> {
+        let cell_size = self.cell_size as f32;
+        Point2::new(cell_size * block[0] as f32, cell_size * block[1] as f32)
+    }
+}
+
+/// Adds the pattern for `tetromino` to `builder`, over a mino `size` across
+/// with its top-left corner at `at`, or returns false if it has none.
+fn add_pattern(
+    builder: &mut MeshBuilder,
+    tetromino: Tetromino,
+    at: Point2<f32>,
+    size: f32,
+    color: Color,
+) -> GameResult<bool> {
+    let width = size * 0.1;
+    let point = |x: f32, y: f32| Point2::new(at[0] + x * size, at[1] + y * size);
+    let (near, far) = (0.25, 0.75);
+    match tetromino {
+        Tetromino::IBlock => {
+            builder.line(&[point(near, 0.5), point(far, 0.5)], width, color)?;
+        }
+        Tetromino::JBlock => {
+            builder.line(&[point(0.5, near), point(0.5, far)], width, color)?;
+        }
+        Tetromino::SBlock => {
+            builder.line(&[point(near, far), point(far, near)], width, color)?;
+        }
+        Tetromino::ZBlock => {
+            builder.line(&[point(near, near), point(far, far)], width, color)?;
+        }
+        Tetromino::LBlock => {
+            builder.line(&[point(near, far), point(far, near)], width, color)?;
+            builder.line(&[point(near, near), point(far, far)], width, color)?;
+        }
+        Tetromino::TBlock => {
+            builder.circle(DrawMode::fill(), point(0.5, 0.5), size * 0.12, 0.5, color);
+        }
+        Tetromino::OBlock => {
+            let inset = size * near;
+            let square = Rect::new(at[0] + inset, at[1] + inset, size / 2.0, size / 2.0);
+            builder.rectangle(DrawMode::stroke(width), square, color);
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// `color` tinted by `tint`, channel by channel.
+fn tinted(color: Color, tint: Color) -> Color {
+    Color::new(
+        color.r * tint.r,
+        color.g * tint.g,
+        color.b * tint.b,
+        color.a * tint.a,
+    )
+}
+
+/// The color `t` of the way from `from` to `to`.
+fn blended(from: Color, to: Color, t: f32) -> Color {
+    let mix = |a: f32, b: f32| a + (b - a) * t;
+    Color::new(
+        mix(from.r, to.r),
+        mix(from.g, to.g),
+        mix(from.b, to.b),
+        mix(from.a, to.a),
+    )
+}
+
+fn block_rect(block: Point2<i32>, cell_size: i32) -> Rect {
+    let cell_size = cell_size as f32;
+    Rect::new(
+        cell_size * block[0] as f32,
+        cell_size * block[1] as f32,
+        cell_size,
+        cell_size,
+    )
+}
+
+/// Draws `heatmap` with its top-left corner at `origin`, each cell shaded
+/// from cool to hot by how often pieces locked there against the busiest
+/// cell. Cells never used are left out.
+pub fn draw_heatmap(
+    ctx: &mut Context,
+    heatmap: &Heatmap,
+    cell_size: i32,
+    origin: Point2<f32>,
+) -> GameResult {
+    let max = heatmap.max();
+    if max == 0 {
+        return Ok(());
+    }
+    let mut builder = MeshBuilder::new();
+    for y in 0..heatmap.height() {
+        for x in 0..heatmap.width() {
+            let count = heatmap.get(x, y);
+            if count == 0 {
+                continue;
+            }
+            let heat = count as f32 / max as f32;
+            let color = Color::new(heat, 0.2, 1.0 - heat, 0.3 + 0.7 * heat);
+            let block = Point2::new(x as i32, y as i32);
+            builder.rectangle(DrawMode::fill(), block_rect(block, cell_size), color);
+        }
+    }
+    let mesh = builder.build(ctx)?;
+    graphics::draw(ctx, &mesh, (origin,))
+}
@@ -0,0 +1,546 @@
+use crate::board::Board;
+use crate::clock::Instant;
+use crate::events::Observer;
+use crate::game::{Event, Game, Input, Settings};
+use crate::tetromino::Tetromino;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Game logic runs in fixed steps of this rate so a recording made on one
+/// machine plays back the same on another.
+pub const FRAMES_PER_SECOND: u32 = 60;
+
+/// The replay format this build writes. It goes up when a change means an
+/// older build would play a file back wrong. Fields added with a default
+/// don't need it: older files take the default, and older builds ignore
+/// fields they don't know.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Whether this build can play back a replay of format `version`.
+pub fn check_version(version: u32) -> Result<(), String> {
+    if version > FORMAT_VERSION {
+        Err(format!(
+            "it's format {}, from a newer version of the game, and this one reads up to {}",
+            version, FORMAT_VERSION
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Frames between the checksums a recording keeps of the round.
+pub const CHECKSUM_FRAMES: u64 = 60;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Folds `value` into an FNV-1a `hash` a byte at a time.
+fn mix(hash: u64, value: u64) -> u64 {
+    value.to_le_bytes().iter().fold(hash, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// A hash of where `game` stands: every locked cell, the falling piece,
+/// score, lines and level. It comes out the same on every machine and
+/// build, so a replay can carry it to check playback against.
+pub fn checksum(game: &Game) -> u64 {
+    let mut hash = FNV_OFFSET;
+    for (block, fixed) in game.board.blocks() {
+        hash = mix(hash, block[0] as u64);
+        hash = mix(hash, block[1] as u64);
+        hash = mix(hash, u64::from(fixed.to_char()));
+    }
+    hash = mix(hash, u64::from(game.tetromino().symbol()));
+    for block in game.active_blocks() {
+        hash = mix(hash, block[0] as u64);
+        hash = mix(hash, block[1] as u64);
+    }
+    for &value in &[game.score, game.lines, game.level] {
+        hash = mix(hash, u64::from(value));
+    }
+    hash
+}
+
+/// Time from the start of a round to the start of `frame`.
+pub fn frame_time(frame: u64) -> Duration {
+    Duration::from_nanos(frame * 1_000_000_000 / u64::from(FRAMES_PER_SECOND))
+}
+
+/// Plays `actions` into `game` a frame at a time, the way the game loop
+/// does, until the game ends or the frame of the last action has been
+/// stepped. Returns how many frames were run.
+pub fn simulate(game: &mut Game, actions: &[TimedAction]) -> u64 {
+    let last = actions.last().map_or(0, |timed| timed.frame);
+    let mut frame = 0;
+    let mut next = 0;
+    while !game.game_over && frame <= last {
+        let start = next;
+        while actions.get(next).is_some_and(|timed| timed.frame <= frame) {
+            next += 1;
+        }
+        let on_frame: Vec<Action> = actions[start..next].iter().map(|t| t.action).collect();
+        // Nobody is listening for events here.
+        step(game, frame, &on_frame);
+        frame += 1;
+    }
+    frame
+}
+
+/// Plays `actions` into `game` at the start of `frame`, then runs the
+/// frame, and returns what happened. Time is only ever counted in frames
+/// from the start of the round, so the same actions on the same frames
+/// always play out the same.
+pub fn step(game: &mut Game, frame: u64, actions: &[Action]) -> Vec<Event> {
+    let now = game.started_at() + frame_time(frame);
+    for action in actions {
+        action.apply(game, now);
+    }
+    game.update(game.started_at() + frame_time(frame + 1));
+    game.take_events()
+}
+
+/// A round played a frame at a time with no clock of its own, for tools and
+/// checks that need the same inputs to play out the same every time.
+pub struct GameState {
+    game: Game,
+    frame: u64,
+}
+
+impl GameState {
+    pub fn new(board: Board, settings: Settings) -> GameState {
+        GameState {
+            game: Game::new(board, settings),
+            frame: 0,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// The frame the next `step` runs.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Plays `actions` and then one frame, returning what happened.
+    pub fn step(&mut self, actions: &[Action]) -> Vec<Event> {
+        let events = step(&mut self.game, self.frame, actions);
+        self.frame += 1;
+        events
+    }
+}
+
+/// Something the player did that changes how the round plays out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "input")]
+pub enum Action {
+    Press(Input),
+    Release(Input),
+    /// Coming back from pause, which drops any held movement key.
+    Resume,
+    /// Garbage rows sent by the opponent in a versus round.
+    Garbage(u32),
+}
+
+impl Action {
+    pub fn apply(self, game: &mut Game, now: Instant) {
+        match self {
+            Action::Press(input) => game.press(input, now),
+            Action::Release(input) => game.release(input),
+            Action::Resume => {
+                game.pause(now);
+                game.resume(now);
+            }
+            Action::Garbage(rows) => game.receive_garbage(rows),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct TimedAction {
+    pub frame: u64,
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct TimedSpawn {
+    pub frame: u64,
+    pub piece: Tetromino,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct TimedChecksum {
+    pub frame: u64,
+    pub checksum: u64,
+}
+
+/// A recorded round: how it was set up and every action taken, each on the
+/// frame it happened. The pieces dealt are kept too, and a checksum of the
+/// round every `CHECKSUM_FRAMES`, so playback can tell when it no longer
+/// matches the original.
+///
+/// Saved as TOML. The header is `version`, `game_version`, the board size
+/// and the round's `settings`: seed, mode, rules, handling and the rest.
+/// Then come `actions`, `spawns` and `checksums`, each with the frame it
+/// happened on, at `FRAMES_PER_SECOND`. Files from before the format had a
+/// version read as version 0, which is laid out like version 1.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    #[serde(default)]
+    pub version: u32,
+    /// The build that recorded it, to say which to play it back in.
+    #[serde(default)]
+    pub game_version: String,
+    pub board_width: usize,
+    pub board_height: usize,
+    pub settings: Settings,
+    pub actions: Vec<TimedAction>,
+    pub spawns: Vec<TimedSpawn>,
+    /// Replays from before they were kept have none, and play back
+    /// unchecked.
+    #[serde(default)]
+    pub checksums: Vec<TimedChecksum>,
+}
+
+impl Replay {
+    pub fn new(board: &Board, settings: Settings) -> Replay {
+        Replay {
+            version: FORMAT_VERSION,
+            game_version: env!("CARGO_PKG_VERSION").to_string(),
+            board_width: board.width(),
+            board_height: board.height(),
+            settings,
+            actions: Vec::new(),
+            spawns: Vec::new(),
+            checksums: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, frame: u64, action: Action) {
+        self.actions.push(TimedAction { frame, action });
+    }
+
+    pub fn record_spawn(&mut self, frame: u64, piece: Tetromino) {
+        self.spawns.push(TimedSpawn { frame, piece });
+    }
+
+    /// Keeps a checksum of `game` as it stands after `frame`, if it's one
+    /// of the frames they're kept for.
+    pub fn record_checksum(&mut self, frame: u64, game: &Game) {
+        if frame.is_multiple_of(CHECKSUM_FRAMES) {
+            let checksum = checksum(game);
+            self.checksums.push(TimedChecksum { frame, checksum });
+        }
+    }
+
+    /// A fresh round set up the way the recorded one was.
+    pub fn new_game(&self) -> Game {
+        Game::new(
+            Board::new(self.board_width, self.board_height),
+            self.settings,
+        )
+    }
+}
+
+/// Keeps each piece dealt, for playback to check against.
+impl Observer for Replay {
+    fn notify(&mut self, event: Event, frame: u64, _: &Game) {
+        if let Event::Spawn(piece) = event {
+            self.record_spawn(frame, piece);
+        }
+    }
+}
+
+/// Steps through a replay's actions alongside the game being played back.
+pub struct Playback {
+    replay: Replay,
+    next_action: usize,
+    next_spawn: usize,
+    next_checksum: usize,
+    /// The first frame a piece was dealt that the recording didn't have, or
+    /// the round stood other than it did in the recording.
+    pub desynced_at: Option<u64>,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Playback {
+        Playback {
+            replay,
+            next_action: 0,
+            next_spawn: 0,
+            next_checksum: 0,
+            desynced_at: None,
+        }
+    }
+
+    pub fn new_game(&self) -> Game {
+        self.replay.new_game()
+    }
+
+    /// The actions recorded on `frame`, in order. Frames must be asked for
+    /// in increasing order.
+    pub fn actions_at(&mut self, frame: u64) -> Vec<Action> {
+        let mut actions = Vec::new();
+        while let Some(timed) = self.replay.actions.get(self.next_action) {
+            if timed.frame > frame {
+                break;
+            }
+            actions.push(timed.action);
+            self.next_action += 1;
+        }
+        actions
+    }
+
+    /// Compares a piece dealt during playback with the recording. A
+    /// script, with no pieces recorded, is never out of sync.
+    pub fn check_spawn(&mut self, frame: u64, piece: Tetromino) {
+        if self.replay.spawns.is_empty() {
+            return;
+        }
+        let expected = self.replay.spawns.get(self.next_spawn);
+        self.next_spawn += 1;
+        let matches = expected.is_some_and(|spawn| spawn.frame == frame && spawn.piece == piece);
+        if !matches && self.desynced_at.is_none() {
+            self.desynced_at = Some(frame);
+        }
+    }
+
+    /// Compares `game` as it stands after `frame` with the checksum
+    /// recorded for that frame, if there is one.
+    pub fn check_checksum(&mut self, frame: u64, game: &Game) {
+        let expected = match self.replay.checksums.get(self.next_checksum) {
+            Some(expected) if expected.frame == frame => expected.checksum,
+            _ => return,
+        };
+        self.next_checksum += 1;
+        if checksum(game) != expected && self.desynced_at.is_none() {
+            self.desynced_at = Some(frame);
+        }
+    }
+
+    /// Whether every recorded action has been played.
+    pub fn finished(&self) -> bool {
+        self.next_action >= self.replay.actions.len()
+    }
+}
+
+/// Checks each piece dealt against the recording.
+impl Observer for Playback {
+    fn notify(&mut self, event: Event, frame: u64, _: &Game) {
+        if let Event::Spawn(piece) = event {
+            self.check_spawn(frame, piece);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{FixedBlock, DEFAULT_HEIGHT, DEFAULT_WIDTH, HIDDEN_ROWS};
+    use nalgebra::Point2;
+    use proptest::prelude::*;
+    use proptest::sample::select;
+
+    /// How far either way from the falling piece's centre has to be open
+    /// for it to turn all the way round without a kick.
+    const TURN_ROOM: i32 = 3;
+
+    fn state(seed: u64) -> GameState {
+        let settings = Settings {
+            seed,
+            // Pieces only come down when dropped, and never lock, so one
+            // turning in the air stays where it is.
+            gravity: Duration::from_secs(3600),
+            ..Settings::default()
+        };
+        GameState::new(Board::new(DEFAULT_WIDTH, DEFAULT_HEIGHT), settings)
+    }
+
+    /// A round dealt from `seed` where pieces fall about a row a frame and
+    /// lock as they land, on garbage rows with holes in the columns of `holes`, from
+    /// the bottom up, for drops to fill.
+    fn stacked(seed: u64, holes: &[usize]) -> GameState {
+        let settings = Settings {
+            seed,
+            gravity: Duration::from_millis(20),
+            // Full rows stay up for a few frames, to be seen before the
+            // stack comes down.
+            line_clear_delay: Duration::from_millis(100),
+            ..Settings::default()
+        };
+        let mut board = Board::new(DEFAULT_WIDTH, DEFAULT_HEIGHT);
+        for &hole in holes.iter().rev() {
+            board.push_garbage(1, hole);
+        }
+        GameState::new(board, settings)
+    }
+
+    /// Holes for a stack low enough to play on, often a well down one
+    /// of the middle columns, where pieces come in and most drops land.
+    fn holes() -> impl Strategy<Value = Vec<usize>> {
+        let rows = 0..6usize;
+        let middle = DEFAULT_WIDTH / 2 - 2..DEFAULT_WIDTH / 2 + 2;
+        prop_oneof![
+            prop::collection::vec(0..DEFAULT_WIDTH, rows.clone()),
+            (middle, rows).prop_map(|(column, rows)| vec![column; rows]),
+        ]
+    }
+
+    fn action() -> impl Strategy<Value = Action> {
+        let input = select(&Input::ALL[..]);
+        prop_oneof![
+            6 => input.clone().prop_map(Action::Press),
+            4 => input.prop_map(Action::Release),
+            1 => Just(Action::Resume),
+            1 => (1..3u32).prop_map(Action::Garbage),
+        ]
+    }
+
+    /// What's played on each frame of a round, one list a frame.
+    fn frames() -> impl Strategy<Value = Vec<Vec<Action>>> {
+        prop::collection::vec(prop::collection::vec(action(), 0..3), 1..300)
+    }
+
+    /// Every row of `board`, the hidden ones first, cell by cell.
+    fn rows(board: &Board) -> Vec<Vec<Option<FixedBlock>>> {
+        (-(HIDDEN_ROWS as i32)..board.height() as i32)
+            .map(|y| {
+                (0..board.width() as i32)
+                    .map(|x| board.get(Point2::new(x, y)).flatten())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Whether the falling piece is out in the open, with nothing within
+    /// `TURN_ROOM` of its centre, so turning it can't kick it or bring it
+    /// to rest.
+    fn in_the_open(game: &Game) -> bool {
+        let (pos, _) = game.piece();
+        let room = -TURN_ROOM..=TURN_ROOM;
+        !game.active_blocks().is_empty()
+            && room.clone().all(|dx| {
+                room.clone()
+                    .all(|dy| game.board.is_free(Point2::new(pos[0] + dx, pos[1] + dy)))
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn four_turns_come_back_round(seed in any::<u64>(), frames in frames()) {
+            let mut state = state(seed);
+            for actions in &frames {
+                state.step(actions);
+                if state.game().game_over || !in_the_open(state.game()) {
+                    continue;
+                }
+                let before = (state.game().piece(), state.game().active_blocks());
+                // Let go of everything first, so nothing held moves it.
+                let mut turns: Vec<Action> =
+                    Input::ALL.iter().map(|&input| Action::Release(input)).collect();
+                for _ in 0..4 {
+                    turns.push(Action::Press(Input::RotateCw));
+                    turns.push(Action::Release(Input::RotateCw));
+                }
+                state.step(&turns);
+                let after = (state.game().piece(), state.game().active_blocks());
+                prop_assert_eq!(before, after);
+            }
+        }
+
+        #[test]
+        fn turning_back_undoes_a_turn(seed in any::<u64>(), frames in frames()) {
+            let mut state = state(seed);
+            for actions in &frames {
+                state.step(actions);
+                if state.game().game_over || !in_the_open(state.game()) {
+                    continue;
+                }
+                let before = (state.game().piece(), state.game().active_blocks());
+                let mut turns: Vec<Action> =
+                    Input::ALL.iter().map(|&input| Action::Release(input)).collect();
+                for &input in &[Input::RotateCw, Input::RotateCcw] {
+                    turns.push(Action::Press(input));
+                    turns.push(Action::Release(input));
+                }
+                state.step(&turns);
+                let after = (state.game().piece(), state.game().active_blocks());
+                prop_assert_eq!(before, after);
+            }
+        }
+
+        #[test]
+        fn falling_piece_never_overlaps_the_stack(
+            seed in any::<u64>(),
+            holes in holes(),
+            frames in frames(),
+        ) {
+            let mut state = stacked(seed, &holes);
+            // A piece can come in on top of the stack, which tops out when
+            // it locks there, but once clear of it one never goes back.
+            let mut on_the_stack_since_spawning = false;
+            for actions in &frames {
+                let events = state.step(actions);
+                let game = state.game();
+                let overlapping: Vec<_> = game
+                    .active_blocks()
+                    .into_iter()
+                    .filter(|&block| matches!(game.board.get(block), Some(Some(_))))
+                    .collect();
+                let spawned = events.iter().any(|event| matches!(event, Event::Spawn(_)));
+                on_the_stack_since_spawning = !overlapping.is_empty()
+                    && (spawned || on_the_stack_since_spawning);
+                prop_assert!(
+                    on_the_stack_since_spawning || overlapping.is_empty(),
+                    "{:?} are on locked cells",
+                    overlapping
+                );
+            }
+        }
+
+        #[test]
+        fn only_full_rows_are_cleared(
+            seed in any::<u64>(),
+            holes in holes(),
+            frames in frames(),
+        ) {
+            let mut state = stacked(seed, &holes);
+            let is_full = |row: &Vec<Option<FixedBlock>>| row.iter().all(Option::is_some);
+            for actions in &frames {
+                let (full, kept): (Vec<_>, Vec<_>) =
+                    rows(&state.game().board).into_iter().partition(is_full);
+                let events = state.step(actions);
+                let after = rows(&state.game().board);
+                // The rows a lock fills stay up until the clear delay is
+                // over: they're the ones it says it cleared.
+                let full_after: Vec<i32> = (0..after.len())
+                    .filter(|&y| is_full(&after[y]))
+                    .map(|y| y as i32 - HIDDEN_ROWS as i32)
+                    .collect();
+                let reported: Vec<i32> = events
+                    .iter()
+                    .filter_map(|event| match *event {
+                        Event::RowCleared(y) => Some(y),
+                        _ => None,
+                    })
+                    .collect();
+                let cleared = events.iter().find_map(|event| match *event {
+                    Event::LineClear(cleared) => Some(cleared),
+                    _ => None,
+                });
+                if let Some(cleared) = cleared {
+                    prop_assert_eq!(cleared, full_after.len());
+                    prop_assert_eq!(&reported, &full_after);
+                } else if !full.is_empty() && full_after.is_empty() {
+                    // The stack came down over just the full rows.
+                    let mut expected = vec![vec![None; state.game().board.width()]; full.len()];
+                    expected.extend(kept);
+                    prop_assert_eq!(after, expected);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use ggez::filesystem;
+use ggez::{Context, GameError, GameResult};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tetris_rs::board::Board;
+use tetris_rs::game::Settings;
+use tetris_rs::replay::{self, Replay};
+use tetris_rs::script;
+
+const REPLAY_DIR: &str = "replays";
+
+/// Writes `replay` to a new file in the `replays` folder of the user data
+/// directory, named after the time it was saved, and returns the path.
+pub fn save(ctx: &Context, replay: &Replay) -> GameResult<PathBuf> {
+    let contents = toml::Value::try_from(replay)
+        .and_then(|value| toml::to_string(&value))
+        .map_err(|e| GameError::ResourceLoadError(format!("Could not write replay: {}", e)))?;
+    let dir = filesystem::user_data_dir(ctx).join(REPLAY_DIR);
+    fs::create_dir_all(&dir)?;
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let path = dir.join(format!("{}.replay", secs));
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+/// Just the part of a replay that says what format it's in.
+#[derive(Deserialize)]
+struct Header {
+    #[serde(default)]
+    version: u32,
+}
+
+pub fn load(path: &Path) -> GameResult<Replay> {
+    let text = fs::read_to_string(path)?;
+    let invalid = |e: String| {
+        GameError::ResourceLoadError(format!("Invalid replay {}: {}", path.display(), e))
+    };
+    // The version is checked first, so a newer file is turned down for
+    // being newer rather than for whatever about it changed.
+    let header: Header = toml::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+    replay::check_version(header.version).map_err(invalid)?;
+    toml::from_str(&text).map_err(|e| invalid(e.to_string()))
+}
+
+/// The input script at `path` as a replay of a round set up with
+/// `settings` on the standard board, with no pieces to check against.
+pub fn load_script(path: &Path, settings: Settings) -> GameResult<Replay> {
+    let actions = script::parse(&fs::read_to_string(path)?)
+        .map_err(|e| GameError::ResourceLoadError(format!("{}: {}", path.display(), e)))?;
+    let mut replay = Replay::new(&Board::default(), settings);
+    replay.actions = actions;
+    Ok(replay)
+}
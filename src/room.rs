@@ -0,0 +1,81 @@
+//! Room codes: a host's address written short enough to read out to the
+//! other player. The code is the host's IPv4 address, and its port if it
+//! isn't the default, in Crockford's base 32, so it's case-blind and has no
+//! letters that pass for digits. A host behind a router still needs its
+//! port forwarded for players outside to reach it.
+
+use crate::net::DEFAULT_PORT;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Characters in a code for the default port, and for any other.
+const SHORT_LEN: usize = 7;
+const LONG_LEN: usize = 10;
+
+/// Where codes are split to be read out.
+const GROUP_LEN: usize = 4;
+
+/// Any outside address, to find which of this machine's addresses faces
+/// out. Nothing is sent to it.
+const PROBE_ADDR: &str = "192.0.2.1:9";
+
+/// The code for a host listening at `addr`, split in two with a dash.
+pub fn code(addr: SocketAddrV4) -> String {
+    let mut value = u64::from(u32::from(*addr.ip()));
+    let len = if addr.port() == DEFAULT_PORT {
+        SHORT_LEN
+    } else {
+        value = value << 16 | u64::from(addr.port());
+        LONG_LEN
+    };
+    let mut code: Vec<u8> = (0..len)
+        .rev()
+        .map(|i| ALPHABET[(value >> (5 * i) & 0x1f) as usize])
+        .collect();
+    code.insert(GROUP_LEN, b'-');
+    String::from_utf8(code).unwrap_or_default()
+}
+
+/// The host address `code` stands for, if it is a room code. Dashes and
+/// spaces are skipped and case doesn't matter.
+pub fn addr(code: &str) -> Option<SocketAddr> {
+    let digits: Vec<u64> = code
+        .chars()
+        .filter(|&c| c != '-' && c != ' ')
+        .map(|c| {
+            let c = c.to_ascii_uppercase() as u8;
+            ALPHABET.iter().position(|&a| a == c).map(|i| i as u64)
+        })
+        .collect::<Option<_>>()?;
+    let value = digits.iter().fold(0, |value, &digit| value << 5 | digit);
+    let (ip, port) = match digits.len() {
+        SHORT_LEN => (value, DEFAULT_PORT),
+        LONG_LEN => (value >> 16, (value & 0xffff) as u16),
+        _ => return None,
+    };
+    if ip > u64::from(u32::MAX) {
+        return None;
+    }
+    let ip = Ipv4Addr::from(ip as u32);
+    Some(SocketAddr::V4(SocketAddrV4::new(ip, port)))
+}
+
+/// The address other machines can reach a server listening at `listening`
+/// on: the one it's bound to, or, bound to every interface, the one this
+/// machine sends from.
+pub fn reachable(listening: SocketAddr) -> Option<SocketAddrV4> {
+    let ip = match listening.ip() {
+        IpAddr::V4(ip) if !ip.is_unspecified() => ip,
+        IpAddr::V4(_) => {
+            let probe = UdpSocket::bind("0.0.0.0:0").ok()?;
+            probe.connect(PROBE_ADDR).ok()?;
+            match probe.local_addr().ok()?.ip() {
+                IpAddr::V4(ip) => ip,
+                IpAddr::V6(_) => return None,
+            }
+        }
+        IpAddr::V6(_) => return None,
+    };
+    Some(SocketAddrV4::new(ip, listening.port()))
+}
@@ -0,0 +1,353 @@
+//! The rules a round is played by, gathered in a `Ruleset`: how pieces are
+//! drawn and turn, what clears are worth, how fast pieces fall and which
+//! extras are on. `Rules` names the presets a player picks from, and a
+//! `Mode` can change any of them for its rounds.
+
+use crate::attack::{self, AttackTable};
+use crate::gravity;
+use crate::kicks::{self, KickTable};
+use crate::master;
+use crate::randomizer::{Bag, Dealer, History, Uniform};
+use crate::tetromino::{Rotation, Tetromino, Turn};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const LINE_CLEAR_SCORES: [u32; 5] = [0, 100, 300, 500, 800];
+const PERFECT_CLEAR_BONUS: u32 = 3000;
+/// Points for each row a piece is soft or hard dropped.
+const SOFT_DROP_POINTS: u32 = 1;
+const HARD_DROP_POINTS: u32 = 2;
+
+/// The NES's points for a single, double, triple and tetris, times the
+/// level counted from 1.
+const NINTENDO_LINE_CLEAR_SCORES: [u32; 5] = [0, 40, 100, 300, 1200];
+
+/// Moves and turns that can put off a resting piece's lock under
+/// `LockReset::Move`, until it reaches a row lower than it has been.
+pub const MOVE_RESET_LIMIT: u32 = 15;
+
+/// The most upcoming pieces a round can show.
+pub const MAX_PREVIEWS: usize = 6;
+
+/// What starts a resting piece's lock delay over.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LockReset {
+    /// Moves and turns do, up to `MOVE_RESET_LIMIT` of them.
+    #[default]
+    Move,
+    /// Any move or turn does, for as long as the player keeps it up.
+    Infinite,
+    /// Only the piece dropping a row does.
+    Step,
+}
+
+impl LockReset {
+    pub fn label(self) -> &'static str {
+        match self {
+            LockReset::Move => "Move (15)",
+            LockReset::Infinite => "Infinite",
+            LockReset::Step => "Step",
+        }
+    }
+}
+
+/// How the next piece is picked from the round's piece set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Randomizer {
+    /// Every piece as likely as any other, every time.
+    Uniform,
+    /// Each piece once in every bag of the whole set, seven for the
+    /// tetrominoes.
+    Bag,
+    /// Each piece twice in every bag, fourteen for the tetrominoes.
+    DoubleBag,
+    /// TGM's, which rerolls pieces dealt among the last four.
+    History,
+}
+
+impl Randomizer {
+    pub const ALL: [Randomizer; 4] = [
+        Randomizer::Uniform,
+        Randomizer::Bag,
+        Randomizer::DoubleBag,
+        Randomizer::History,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Randomizer::Uniform => "Random",
+            Randomizer::Bag => "7-bag",
+            Randomizer::DoubleBag => "14-bag",
+            Randomizer::History => "TGM history",
+        }
+    }
+
+    /// A dealer of this kind, starting a round.
+    pub fn dealer(self) -> Box<dyn Dealer> {
+        match self {
+            Randomizer::Uniform => Box::new(Uniform),
+            Randomizer::Bag => Box::new(Bag::new(1)),
+            Randomizer::DoubleBag => Box::new(Bag::new(2)),
+            Randomizer::History => Box::new(History::default()),
+        }
+    }
+}
+
+/// How pieces turn and which way up they spawn. Each system's kicks come
+/// from the kick table of its name; out of the box the free and Nintendo
+/// ones have none, so a turn that doesn't fit where the piece is doesn't
+/// happen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RotationSystem {
+    /// Every piece turns all four ways about its centre and spawns flat
+    /// side down.
+    Free,
+    /// The NES's: the O doesn't turn, I, S and Z flip between two states,
+    /// and T, J and L spawn flat side up.
+    Nintendo,
+    /// The guideline's, with pieces spawning flat side down and the
+    /// kicks T-spins are built on.
+    Srs,
+    /// The arcade's, turning like the NES's but keeping flat pieces to the
+    /// bottom of their box and kicking a column either way.
+    Ars,
+}
+
+impl RotationSystem {
+    /// The order the options screen steps through them in.
+    pub const ALL: [RotationSystem; 4] = [
+        RotationSystem::Srs,
+        RotationSystem::Ars,
+        RotationSystem::Nintendo,
+        RotationSystem::Free,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RotationSystem::Free => "Free",
+            RotationSystem::Nintendo => "Classic (no kicks)",
+            RotationSystem::Srs => "SRS",
+            RotationSystem::Ars => "ARS",
+        }
+    }
+
+    /// Whether the NES's way of turning is what this one builds on.
+    fn turns_like_nintendo(self) -> bool {
+        self == RotationSystem::Nintendo || self == RotationSystem::Ars
+    }
+
+    pub fn spawn_facing(self, tetromino: Tetromino) -> Rotation {
+        match tetromino {
+            Tetromino::TBlock | Tetromino::JBlock | Tetromino::LBlock
+                if self.turns_like_nintendo() =>
+            {
+                tetromino.spawn_facing().flip()
+            }
+            _ => tetromino.spawn_facing(),
+        }
+    }
+
+    /// Where to try a turn.
+    pub fn kicks(self) -> &'static KickTable {
+        kicks::table(match self {
+            RotationSystem::Free => "free",
+            RotationSystem::Nintendo => "nintendo",
+            RotationSystem::Srs => "srs",
+            RotationSystem::Ars => "ars",
+        })
+    }
+
+    /// The facing after turning `turn` from `facing`.
+    pub fn rotate(self, tetromino: Tetromino, facing: Rotation, turn: Turn) -> Rotation {
+        if !self.turns_like_nintendo() {
+            return facing.turn(turn);
+        }
+        match tetromino {
+            Tetromino::OBlock => facing,
+            Tetromino::IBlock | Tetromino::SBlock | Tetromino::ZBlock => match facing {
+                Rotation::North | Rotation::South => Rotation::East,
+                Rotation::East | Rotation::West => Rotation::North,
+            },
+            _ => facing.turn(turn),
+        }
+    }
+}
+
+/// What clears are worth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scoring {
+    /// 100, 300, 500 and 800 a level, and a bonus for emptying the board.
+    Guideline,
+    /// The NES's 40, 100, 300 and 1200 a level.
+    Nintendo,
+}
+
+impl Scoring {
+    /// Points at level 1 for clearing `lines` rows with one piece.
+    pub fn line_clear(self, lines: usize) -> u32 {
+        let scores = match self {
+            Scoring::Guideline => &LINE_CLEAR_SCORES,
+            Scoring::Nintendo => &NINTENDO_LINE_CLEAR_SCORES,
+        };
+        scores[lines.min(scores.len() - 1)]
+    }
+
+    /// Points for dropping a piece `rows` rows by hand, hard or soft.
+    /// Unlike clears these don't go up with the level.
+    pub fn drop(self, rows: u32, hard: bool) -> u32 {
+        match self {
+            Scoring::Guideline if hard => rows * HARD_DROP_POINTS,
+            Scoring::Guideline => rows * SOFT_DROP_POINTS,
+            Scoring::Nintendo => 0,
+        }
+    }
+
+    /// Points at level 1 for emptying the board, if it earns any.
+    pub fn perfect_clear_bonus(self) -> Option<u32> {
+        match self {
+            Scoring::Guideline => Some(PERFECT_CLEAR_BONUS),
+            Scoring::Nintendo => None,
+        }
+    }
+}
+
+/// How the fall speed changes with the level. The curves themselves are in
+/// `gravity.toml`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GravityCurve {
+    /// The speed the round was set up with, at every level.
+    Fixed,
+    /// The guideline's curve, reaching its fastest at level 15.
+    Guideline,
+    /// The NES's frames per row.
+    Nintendo,
+    /// 20G: pieces fall to the stack the moment they're free to.
+    Instant,
+    /// Master mode's, over its levels from 0, reaching 20G at
+    /// `master::TWENTY_G_LEVEL`.
+    Master,
+}
+
+impl GravityCurve {
+    /// The name of its curve in `gravity.toml`, if it has one.
+    pub fn table_name(self) -> Option<&'static str> {
+        match self {
+            GravityCurve::Fixed | GravityCurve::Instant => None,
+            GravityCurve::Guideline => Some("guideline"),
+            GravityCurve::Nintendo => Some("nintendo"),
+            GravityCurve::Master => Some("master"),
+        }
+    }
+
+    /// The fall speed at `level`, or `None` to keep the round's own. No
+    /// time at all between steps is instant gravity.
+    pub fn at(self, level: u32) -> Option<Duration> {
+        let entry = match self {
+            GravityCurve::Instant => return Some(Duration::from_millis(0)),
+            GravityCurve::Master if level >= master::TWENTY_G_LEVEL => {
+                return Some(Duration::from_millis(0))
+            }
+            GravityCurve::Master => level / master::GRAVITY_LEVELS + 1,
+            GravityCurve::Fixed | GravityCurve::Guideline | GravityCurve::Nintendo => level,
+        };
+        Some(gravity::table(self.table_name()?)?.at(entry))
+    }
+}
+
+/// What clears send to the other side in versus, and how the garbage that
+/// comes in is laid out. The tables themselves are in `attack.toml`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Attack {
+    /// The guideline's, with T-spin and combo bonuses and each batch's
+    /// rows sharing a gap.
+    Guideline,
+    /// Older head to head games', with no bonuses and a gap of its own in
+    /// every row.
+    Classic,
+}
+
+impl Attack {
+    /// Its table in `attack.toml`.
+    pub fn table(self) -> &'static AttackTable {
+        attack::table(match self {
+            Attack::Guideline => "guideline",
+            Attack::Classic => "classic",
+        })
+    }
+}
+
+/// Everything about how a round plays that isn't down to the player's own
+/// handling settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ruleset {
+    pub randomizer: Randomizer,
+    pub rotation: RotationSystem,
+    pub scoring: Scoring,
+    pub gravity: GravityCurve,
+    pub attack: Attack,
+    pub hold: bool,
+    pub hard_drop: bool,
+    /// Whether to show where the piece would land.
+    pub ghost: bool,
+    /// Whether the pieces change color with the level, as on the NES.
+    pub level_colors: bool,
+    /// How long a piece may rest on the stack before it locks. With none,
+    /// it locks on the first gravity step that finds it resting.
+    pub lock_delay: Option<Duration>,
+    pub lock_reset: LockReset,
+    /// How many of the pieces to come are shown, up to `MAX_PREVIEWS`.
+    pub previews: usize,
+}
+
+/// The rulesets a player picks between.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Rules {
+    /// Hold, hard drop, a ghost piece and perfect clear bonuses.
+    #[default]
+    Modern,
+    /// Close to the NES game: no hold, hard drop or ghost, with its
+    /// rotation, scoring and fall speeds.
+    Classic,
+}
+
+impl Rules {
+    pub fn label(self) -> &'static str {
+        match self {
+            Rules::Modern => "Modern",
+            Rules::Classic => "Classic",
+        }
+    }
+
+    pub fn ruleset(self) -> Ruleset {
+        match self {
+            Rules::Modern => Ruleset {
+                randomizer: Randomizer::Bag,
+                rotation: RotationSystem::Free,
+                scoring: Scoring::Guideline,
+                gravity: GravityCurve::Fixed,
+                attack: Attack::Guideline,
+                hold: true,
+                hard_drop: true,
+                ghost: true,
+                level_colors: false,
+                lock_delay: None,
+                lock_reset: LockReset::Move,
+                previews: 5,
+            },
+            Rules::Classic => Ruleset {
+                randomizer: Randomizer::Uniform,
+                rotation: RotationSystem::Nintendo,
+                scoring: Scoring::Nintendo,
+                gravity: GravityCurve::Nintendo,
+                attack: Attack::Classic,
+                hold: false,
+                hard_drop: false,
+                ghost: false,
+                level_colors: true,
+                lock_delay: None,
+                lock_reset: LockReset::Move,
+                previews: 1,
+            },
+        }
+    }
+}
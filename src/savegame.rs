@@ -0,0 +1,71 @@
+use ggez::filesystem;
+use ggez::{Context, GameError, GameResult};
+use std::fs;
+use std::path::PathBuf;
+use tetris_rs::game::Snapshot;
+
+/// Where a game in progress can be kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Slot {
+    /// Put away on purpose, to continue from the menu.
+    Kept,
+    /// Written every so often while playing and removed when the round
+    /// ends or is put away, so one still there on launch means the last
+    /// run didn't get that far.
+    Autosave,
+}
+
+impl Slot {
+    fn file(self) -> &'static str {
+        match self {
+            Slot::Kept => "savegame.toml",
+            Slot::Autosave => "autosave.toml",
+        }
+    }
+}
+
+/// Where the game in `slot` is kept between sessions, in the user data
+/// directory next to the high scores.
+fn path(ctx: &Context, slot: Slot) -> PathBuf {
+    filesystem::user_data_dir(ctx).join(slot.file())
+}
+
+pub fn exists(ctx: &Context, slot: Slot) -> bool {
+    path(ctx, slot).exists()
+}
+
+pub fn save(ctx: &Context, slot: Slot, snapshot: &Snapshot) -> GameResult {
+    // Going through `Value` puts plain values ahead of tables, which TOML
+    // requires and the struct's field order doesn't guarantee.
+    let contents = toml::Value::try_from(snapshot)
+        .and_then(|value| toml::to_string(&value))
+        .map_err(|e| {
+            GameError::ResourceLoadError(format!("Could not write {}: {}", slot.file(), e))
+        })?;
+    let path = path(ctx, slot);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Written alongside and moved over, so a crash part way through leaves
+    // the last autosave whole.
+    let written = path.with_extension("tmp");
+    fs::write(&written, contents)?;
+    fs::rename(written, path)?;
+    Ok(())
+}
+
+pub fn load(ctx: &Context, slot: Slot) -> GameResult<Snapshot> {
+    toml::from_str(&fs::read_to_string(path(ctx, slot))?)
+        .map_err(|e| GameError::ResourceLoadError(format!("Invalid {}: {}", slot.file(), e)))
+}
+
+/// Removes the game in `slot`, once it has been resumed or replaced by a
+/// new one.
+pub fn delete(ctx: &Context, slot: Slot) {
+    let path = path(ctx, slot);
+    if path.exists() {
+        if let Err(e) = fs::remove_file(&path) {
+            eprintln!("Could not remove {}: {}", path.display(), e);
+        }
+    }
+}
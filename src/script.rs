@@ -0,0 +1,38 @@
+//! Input scripts: plain text, one action per line as
+//! `<frame> <press|release> <action>`, for example `12 press hard_drop`.
+//! Action names are those of `Input::name`. Blank lines and anything after
+//! a `#` are ignored, and frames may not go backwards.
+
+use crate::game::Input;
+use crate::replay::{Action, TimedAction};
+
+pub fn parse(text: &str) -> Result<Vec<TimedAction>, String> {
+    let mut actions: Vec<TimedAction> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |message: String| format!("line {}: {}", i + 1, message);
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (frame, kind, input) = match words.as_slice() {
+            [frame, kind, input] => (frame, kind, input),
+            _ => return Err(error("expected `<frame> <press|release> <action>`".into())),
+        };
+        let frame: u64 = frame
+            .parse()
+            .map_err(|_| error(format!("bad frame number {:?}", frame)))?;
+        let input =
+            Input::from_name(input).ok_or_else(|| error(format!("unknown action {:?}", input)))?;
+        let action = match *kind {
+            "press" => Action::Press(input),
+            "release" => Action::Release(input),
+            _ => return Err(error(format!("expected press or release, not {:?}", kind))),
+        };
+        if actions.last().is_some_and(|last| last.frame > frame) {
+            return Err(error("frames must not go backwards".into()));
+        }
+        actions.push(TimedAction { frame, action });
+    }
+    Ok(actions)
+}
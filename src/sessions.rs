@@ -0,0 +1,78 @@
+use crate::highscores;
+use ggez::filesystem;
+use ggez::{Context, GameError, GameResult};
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use tetris_rs::game::Game;
+use tetris_rs::mode::Mode;
+
+const SESSIONS_FILE: &str = "sessions.jsonl";
+
+/// One finished round, as a line of `sessions.jsonl`.
+#[derive(Serialize)]
+struct Session {
+    /// Day the round finished, as `YYYY-MM-DD`.
+    date: String,
+    mode: Mode,
+    seed: u64,
+    /// Whether the round ended by meeting its goal rather than topping out.
+    cleared_goal: bool,
+    score: u32,
+    lines: u32,
+    level: u32,
+    /// Time played, pauses aside.
+    seconds: f32,
+    pieces: u32,
+    pps: f32,
+    attack: u32,
+    apm: f32,
+    inputs: u32,
+    singles: u32,
+    doubles: u32,
+    triples: u32,
+    /// Clears of four rows or more.
+    tetrises: u32,
+    t_spins: u32,
+    perfect_clears: u32,
+}
+
+/// Adds `game` to the end of `sessions.jsonl` in the user data directory,
+/// one JSON object a line, for looking over in other tools. Earlier lines
+/// are never rewritten.
+pub fn record(ctx: &Context, game: &Game) -> GameResult {
+    let stats = game.stats();
+    let played = game.elapsed();
+    let [singles, doubles, triples, tetrises] = stats.clears;
+    let session = Session {
+        date: highscores::today(),
+        mode: game.mode(),
+        seed: game.seed(),
+        cleared_goal: game.cleared_goal(),
+        score: game.score,
+        lines: game.lines,
+        level: game.level,
+        seconds: played.as_secs_f32(),
+        pieces: stats.pieces,
+        pps: stats.pieces_per_second(played),
+        attack: stats.attack,
+        apm: stats.attack_per_minute(played),
+        inputs: stats.inputs,
+        singles,
+        doubles,
+        triples,
+        tetrises,
+        t_spins: stats.t_spins,
+        perfect_clears: stats.perfect_clears,
+    };
+    let line = serde_json::to_string(&session)
+        .map_err(|e| GameError::ResourceLoadError(format!("Could not write session: {}", e)))?;
+    let dir = filesystem::user_data_dir(ctx);
+    fs::create_dir_all(dir)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(SESSIONS_FILE))?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
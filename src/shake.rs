@@ -0,0 +1,48 @@
+use ggez::nalgebra::Vector2;
+use std::time::{Duration, Instant};
+
+/// How long a shake takes to die down.
+const SHAKE_MILLIS: u64 = 250;
+
+/// Jolts the whole screen for a moment, by an offset that dies down to
+/// nothing over `SHAKE_MILLIS`.
+#[derive(Default)]
+pub struct Shake {
+    /// When the shake started, and how many pixels either way it started
+    /// at.
+    started: Option<(Instant, f32)>,
+}
+
+impl Shake {
+    /// Starts a shake of `strength` pixels, unless the one going is still
+    /// stronger.
+    pub fn kick(&mut self, now: Instant, strength: f32) {
+        if self.strength(now) < strength {
+            self.started = Some((now, strength));
+        }
+    }
+
+    fn strength(&self, now: Instant) -> f32 {
+        let length = Duration::from_millis(SHAKE_MILLIS).as_secs_f32();
+        match self.started {
+            Some((at, strength)) => {
+                let gone = now.saturating_duration_since(at).as_secs_f32() / length;
+                strength * (1.0 - gone).max(0.0)
+            }
+            None => 0.0,
+        }
+    }
+
+    /// How far to move everything drawn at `now`.
+    pub fn offset(&self, now: Instant) -> Vector2<f32> {
+        let (at, _) = match self.started {
+            Some(started) => started,
+            None => return Vector2::new(0.0, 0.0),
+        };
+        let strength = self.strength(now);
+        let t = now.saturating_duration_since(at).as_secs_f32();
+        // Two rates that don't line up, so it doesn't just go back and forth
+        // along one line.
+        Vector2::new(strength * (t * 90.0).sin(), strength * (t * 70.0).cos())
+    }
+}
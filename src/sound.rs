@@ -0,0 +1,351 @@
+use crate::config::Config;
+use crate::packs::Packs;
+use crate::soundpacks::SoundPack;
+use ggez::audio::{self, SoundData, SoundSource, SpatialSource};
+use ggez::{Context, GameResult};
+use std::fs;
+use std::path::Path;
+use tetris_rs::events::Observer;
+use tetris_rs::game::{Event, Game};
+
+/// How much faster the music plays for each level gained, up to the limit.
+const TEMPO_PER_LEVEL: f32 = 0.03;
+const MAX_TEMPO: f32 = 1.5;
+/// How much faster still the music plays while the stack is in danger.
+const DANGER_TEMPO: f32 = 1.1;
+/// How far to either side a sound at the board's edge is placed, between
+/// the listener's ears at -1 and 1; less than all the way, to keep it
+/// subtle.
+const PAN_SPREAD: f32 = 0.6;
+
+/// Reads `chosen` from the sound pack in use if there is one and it can
+/// be played, or else `path` from the resources, or the file there from
+/// the last pack that is on and has it instead.
+fn load_data(
+    ctx: &mut Context,
+    packs: &Packs,
+    chosen: Option<&Path>,
+    path: &str,
+) -> GameResult<SoundData> {
+    if let Some(file) = chosen {
+        match fs::read(file) {
+            Ok(bytes) => {
+                let data = SoundData::from_bytes(&bytes);
+                if data.can_play() {
+                    return Ok(data);
+                }
+                eprintln!("Could not decode {}", file.display());
+            }
+            Err(e) => eprintln!("Could not load {}: {}", file.display(), e),
+        }
+    }
+    match packs.file(path.trim_start_matches('/')) {
+        Some(file) => Ok(SoundData::from_bytes(&fs::read(file)?)),
+        None => SoundData::new(ctx, path),
+    }
+}
+
+fn loaded<T>(path: &str, source: GameResult<T>) -> Option<T> {
+    match source {
+        Ok(source) => Some(source),
+        Err(e) => {
+            eprintln!("Could not load {}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Where the cells in `columns` sit across a board `width` wide, from -1
+/// at the left edge to 1 at the right.
+fn across(columns: impl Iterator<Item = i32>, width: usize) -> f32 {
+    let (sum, count) = columns.fold((0.0, 0), |(sum, count), x| {
+        (sum + x as f32 + 0.5, count + 1)
+    });
+    let center = sum / count.max(1) as f32;
+    (center / width.max(1) as f32 * 2.0 - 1.0).clamp(-1.0, 1.0)
+}
+
+/// Converts a volume setting in percent to the gain ggez expects.
+fn gain(percent: u32) -> f32 {
+    percent.min(100) as f32 / 100.0
+}
+
+/// All of the game's audio, with the player's mute and volume settings
+/// applied in one place.
+pub struct Audio {
+    sounds: Sounds,
+    music: Music,
+    muted: bool,
+    /// Whether sound effects are panned to where on the board they
+    /// happened.
+    panned: bool,
+    /// Where the piece last moved, turned or locked, from -1 at the left
+    /// to 1 at the right, for the clear that follows a lock to come from.
+    pan: f32,
+    music_volume: u32,
+    sfx_volume: u32,
+}
+
+impl Audio {
+    /// Loads the sounds and music, from `sound_pack` where it has them.
+    pub fn load(
+        ctx: &mut Context,
+        config: &Config,
+        packs: &Packs,
+        sound_pack: Option<&SoundPack>,
+    ) -> Audio {
+        let mut audio = Audio {
+            sounds: Sounds::load(ctx, packs, sound_pack),
+            music: Music::load(ctx, packs, sound_pack),
+            muted: config.muted,
+            panned: config.pan_sounds,
+            pan: 0.0,
+            music_volume: config.music_volume,
+            sfx_volume: config.sfx_volume,
+        };
+        audio.music.set_volume(gain(audio.music_volume));
+        audio
+    }
+
+    pub fn muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        if muted {
+            self.music.stop();
+        }
+    }
+
+    pub fn set_panned(&mut self, panned: bool) {
+        self.panned = panned;
+    }
+
+    pub fn music_volume(&self) -> u32 {
+        self.music_volume
+    }
+
+    pub fn set_music_volume(&mut self, percent: u32) {
+        self.music_volume = percent.min(100);
+        self.music.set_volume(gain(self.music_volume));
+    }
+
+    pub fn sfx_volume(&self) -> u32 {
+        self.sfx_volume
+    }
+
+    pub fn set_sfx_volume(&mut self, percent: u32) {
+        self.sfx_volume = percent.min(100);
+    }
+
+    /// Plays the sound for `event`, from `pan` between -1 at the left and
+    /// 1 at the right if sounds are panned.
+    pub fn play(&mut self, event: Event, pan: f32) {
+        if !self.muted {
+            let pan = if self.panned { pan } else { 0.0 };
+            self.sounds.play(event, gain(self.sfx_volume), pan);
+        }
+    }
+
+    pub fn update_music(&mut self, game: &Game) {
+        if !self.muted {
+            self.music.update(game);
+        }
+    }
+
+    pub fn pause_music(&mut self) {
+        self.music.pause();
+    }
+
+    pub fn resume_music(&mut self) {
+        self.music.resume();
+    }
+
+    pub fn stop_music(&mut self) {
+        self.music.stop();
+    }
+}
+
+/// Plays each event's sound from the column the piece was in.
+impl Observer for Audio {
+    fn notify(&mut self, event: Event, _: u64, game: &Game) {
+        let width = game.board.width();
+        match event {
+            Event::Lock {
+                tetromino,
+                pos,
+                facing,
+                ..
+            } => {
+                let columns = tetromino.blocks(pos, facing).into_iter().map(|b| b[0]);
+                self.pan = across(columns, width);
+            }
+            Event::Move | Event::Rotate => {
+                let (pos, facing) = game.piece();
+                let blocks = game.tetromino().blocks(pos, facing);
+                self.pan = across(blocks.into_iter().map(|b| b[0]), width);
+            }
+            _ => (),
+        }
+        let pan = match event {
+            Event::GameOver => 0.0,
+            _ => self.pan,
+        };
+        self.play(event, pan);
+    }
+}
+
+/// Sound effects for game events, loaded from the sound pack in use,
+/// `resources/sounds` or a pack's `sounds`. Any file that is missing or
+/// can't be decoded is left silent. Each is placed between the listener's
+/// ears to pan it.
+struct Sounds {
+    movement: Option<SpatialSource>,
+    rotate: Option<SpatialSource>,
+    lock: Option<SpatialSource>,
+    line_clear: Option<SpatialSource>,
+    tetris: Option<SpatialSource>,
+    game_over: Option<SpatialSource>,
+}
+
+impl Sounds {
+    fn load(ctx: &mut Context, packs: &Packs, sound_pack: Option<&SoundPack>) -> Sounds {
+        let mut load = |name: &str| {
+            let chosen = sound_pack.and_then(|pack| pack.sound(name));
+            let path = format!("/sounds/{}.wav", name);
+            let source = load_data(ctx, packs, chosen, &path)
+                .and_then(|data| SpatialSource::from_data(ctx, data));
+            loaded(&path, source)
+        };
+        Sounds {
+            movement: load("move"),
+            rotate: load("rotate"),
+            lock: load("lock"),
+            line_clear: load("line_clear"),
+            tetris: load("tetris"),
+            game_over: load("game_over"),
+        }
+    }
+
+    fn play(&mut self, event: Event, volume: f32, pan: f32) {
+        let source = match event {
+            Event::Move => &mut self.movement,
+            Event::Rotate => &mut self.rotate,
+            Event::Lock { .. } => &mut self.lock,
+            Event::LineClear(4) => &mut self.tetris,
+            Event::LineClear(_) => &mut self.line_clear,
+            Event::GameOver => &mut self.game_over,
+            Event::Spawn(_)
+            | Event::Attack(_)
+            | Event::RowCleared(_)
+            | Event::HardDrop { .. }
+            | Event::TSpin => return,
+        };
+        if let Some(source) = source {
+            // Detaching hands the source a fresh sink at full volume, so set
+            // it every time. Detached so quick repeats overlap instead of
+            // cutting each other off.
+            source.set_volume(volume);
+            source.set_position([pan * PAN_SPREAD, 0.0, 0.0]);
+            if let Err(e) = source.play_detached() {
+                eprintln!("Could not play sound: {}", e);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Track {
+    Theme,
+    Danger,
+}
+
+/// Looping background music, loaded from the sound pack in use,
+/// `resources/music` or a pack's `music`. The theme speeds up as the level
+/// rises and gives way to the danger track, faster again, while the stack
+/// is near the top.
+struct Music {
+    theme: Option<audio::Source>,
+    danger: Option<audio::Source>,
+    /// The track currently playing and the tempo it was started at.
+    playing: Option<(Track, f32)>,
+}
+
+impl Music {
+    fn load(ctx: &mut Context, packs: &Packs, sound_pack: Option<&SoundPack>) -> Music {
+        let mut load = |name: &str| {
+            let chosen = sound_pack.and_then(|pack| pack.music(name));
+            let path = format!("/music/{}.wav", name);
+            let source = load_data(ctx, packs, chosen, &path)
+                .and_then(|data| audio::Source::from_data(ctx, data));
+            loaded(&path, source)
+        };
+        Music {
+            theme: load("theme"),
+            danger: load("danger"),
+            playing: None,
+        }
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        for source in self.theme.iter_mut().chain(self.danger.iter_mut()) {
+            source.set_volume(volume);
+        }
+    }
+
+    fn source(&mut self, track: Track) -> &mut Option<audio::Source> {
+        match track {
+            Track::Theme => &mut self.theme,
+            Track::Danger => &mut self.danger,
+        }
+    }
+
+    /// Switches to the track and tempo that suit `game`, restarting the
+    /// music only when either changes.
+    fn update(&mut self, game: &Game) {
+        let mut tempo = (1.0 + TEMPO_PER_LEVEL * (game.level - 1) as f32).min(MAX_TEMPO);
+        let track = if game.board.in_danger() {
+            tempo *= DANGER_TEMPO;
+            Track::Danger
+        } else {
+            Track::Theme
+        };
+        if self.playing == Some((track, tempo)) {
+            return;
+        }
+        self.stop();
+        if let Some(source) = self.source(track) {
+            source.set_repeat(true);
+            source.set_pitch(tempo);
+            if let Err(e) = source.play() {
+                eprintln!("Could not play music: {}", e);
+            }
+        }
+        self.playing = Some((track, tempo));
+    }
+
+    fn pause(&mut self) {
+        if let Some((track, _)) = self.playing {
+            if let Some(source) = self.source(track) {
+                source.pause();
+            }
+        }
+    }
+
+    fn resume(&mut self) {
+        if let Some((track, _)) = self.playing {
+            if let Some(source) = self.source(track) {
+                source.resume();
+            }
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Some((track, _)) = self.playing.take() {
+            if let Some(source) = self.source(track) {
+                source.stop();
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Sound packs: folders in the `soundpacks` folder of the user config
+//! directory that swap out the game's sound effects and music, picked on
+//! the options screen. A `soundpack.toml` names the pack and which of its
+//! files stands in for which sound:
+//!
+//! ```toml
+//! name = "Chiptune"
+//!
+//! [sounds]
+//! move = "blip.ogg"
+//! line_clear = "clear.wav"
+//!
+//! [music]
+//! theme = "loop.ogg"
+//! ```
+//!
+//! The sounds are `move`, `rotate`, `lock`, `line_clear`, `tetris` and
+//! `game_over`, and the music `theme` and `danger`. Any a pack leaves out,
+//! or whose file is missing or can't be decoded, is the game's own.
+
+use ggez::filesystem;
+use ggez::Context;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SOUND_PACK_DIR: &str = "soundpacks";
+const MANIFEST_FILE: &str = "soundpack.toml";
+
+/// The layout of `soundpack.toml`.
+#[derive(Deserialize)]
+struct Manifest {
+    name: String,
+    #[serde(default)]
+    sounds: BTreeMap<String, String>,
+    #[serde(default)]
+    music: BTreeMap<String, String>,
+}
+
+pub struct SoundPack {
+    /// The pack's folder name, which the config keeps to pick it.
+    pub id: String,
+    pub name: String,
+    sounds: BTreeMap<String, PathBuf>,
+    music: BTreeMap<String, PathBuf>,
+}
+
+impl SoundPack {
+    /// The file standing in for the sound effect `name`, if the pack has
+    /// one.
+    pub fn sound(&self, name: &str) -> Option<&Path> {
+        self.sounds.get(name).map(PathBuf::as_path)
+    }
+
+    /// The file standing in for the music track `name`, if the pack has
+    /// one.
+    pub fn music(&self, name: &str) -> Option<&Path> {
+        self.music.get(name).map(PathBuf::as_path)
+    }
+}
+
+fn read(dir: &Path) -> Option<SoundPack> {
+    let path = dir.join(MANIFEST_FILE);
+    let text = fs::read_to_string(&path).ok()?;
+    let manifest: Manifest = match toml::from_str(&text) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("Invalid sound pack {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    let files = |names: BTreeMap<String, String>| {
+        names
+            .into_iter()
+            .map(|(name, file)| (name, dir.join(file)))
+            .collect()
+    };
+    Some(SoundPack {
+        id: dir.file_name()?.to_string_lossy().into_owned(),
+        name: manifest.name,
+        sounds: files(manifest.sounds),
+        music: files(manifest.music),
+    })
+}
+
+/// The sound packs in the user config directory, in order of their folder
+/// names. Folders without a readable `soundpack.toml` are left out.
+pub fn load(ctx: &Context) -> Vec<SoundPack> {
+    let dir = filesystem::user_config_dir(ctx).join(SOUND_PACK_DIR);
+    let mut dirs: Vec<PathBuf> = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs.iter().filter_map(|dir| read(dir)).collect()
+}
@@ -0,0 +1,106 @@
+use crate::config::Config;
+use crate::layout::Layout;
+use crate::locale::Locale;
+use crate::net::{Connection, Message};
+use crate::versus::{Controller, Opponent, Outcome, Remote};
+use ggez::graphics::Color;
+use ggez::{Context, GameResult};
+use tetris_rs::board::Board;
+
+/// Looking on at a network game, running both players' games from what the
+/// host passes on.
+pub struct Spectator {
+    connection: Connection,
+    /// The host's game, then the other player's, once their hellos arrive.
+    sides: [Option<Opponent>; 2],
+}
+
+impl Spectator {
+    pub fn new(connection: Connection) -> Spectator {
+        Spectator {
+            connection,
+            sides: [None, None],
+        }
+    }
+
+    /// Whether both games have been set up.
+    pub fn is_ready(&self) -> bool {
+        self.sides.iter().all(Option::is_some)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.connection.is_closed()
+    }
+
+    /// The board both players play on, once it's known.
+    pub fn board(&self) -> Option<&Board> {
+        self.sides[0].as_ref().map(|side| &side.game.board)
+    }
+
+    /// Takes in what the host has sent and runs both games as far as it
+    /// goes.
+    pub fn update(&mut self, ctx: &mut Context, config: &Config) {
+        for (side, message) in self.connection.receive_about() {
+            // Lines not about either player, like the host's own hello, are
+            // meant for the other player.
+            let slot = match side.checked_sub(1).and_then(|i| self.sides.get_mut(i)) {
+                Some(slot) => slot,
+                None => continue,
+            };
+            match (slot.as_mut(), message) {
+                (None, Message::Hello(hello)) => {
+                    let board = Board::new(hello.board_width, hello.board_height);
+                    let controller = Controller::Remote(Remote::relayed());
+                    *slot = Opponent::new(
+                        ctx,
+                        config,
+                        board,
+                        hello.settings,
+                        controller,
+                        config.cell_size,
+                    )
+                    .map_err(|e| eprintln!("Could not set up player {}: {}", side, e))
+                    .ok();
+                }
+                (Some(player), message) => player.feed(message),
+                (None, _) => (),
+            }
+        }
+        // Each side's garbage comes in with its own actions, so the attacks
+        // they send each other don't need passing across.
+        for player in self.sides.iter_mut().flatten() {
+            player.advance(u64::MAX);
+        }
+    }
+
+    /// How the game stands for the host.
+    pub fn outcome(&self) -> Outcome {
+        match self.sides {
+            [Some(ref host), Some(ref other)] => other.outcome(host.over_at()),
+            _ => Outcome::Undecided,
+        }
+    }
+
+    /// Draws the two boards side by side, the host's on the left.
+    pub fn draw(
+        &mut self,
+        ctx: &mut Context,
+        cell_size: i32,
+        locale: &Locale,
+        color: Color,
+    ) -> GameResult {
+        let mut layout = None;
+        for (i, player) in self.sides.iter_mut().enumerate() {
+            if let Some(player) = player {
+                let here = match layout {
+                    Some(previous) => Layout::beside(previous),
+                    None => Layout::new(&player.game.board, cell_size),
+                };
+                let label = locale.format("spectating.player", &[&(i + 1)]);
+                player.draw_labelled(ctx, here, locale, &label, color)?;
+                layout = Some(here);
+            }
+        }
+        Ok(())
+    }
+}
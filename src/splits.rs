@@ -0,0 +1,155 @@
+//! Split times: how long a run took to reach every `SPLIT_LINES` lines,
+//! shown as it goes against the player's best run in the same mode.
+
+use crate::highscores;
+use crate::locale::Locale;
+use crate::text;
+use ggez::filesystem;
+use ggez::graphics::{self, Color};
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tetris_rs::events::Observer;
+use tetris_rs::game::{Event, Game};
+use tetris_rs::mode::Mode;
+
+const SPLITS_FILE: &str = "splits.toml";
+/// Lines to each split.
+pub const SPLIT_LINES: u32 = 10;
+/// The most recent splits shown; marathons have more than fit.
+const SHOWN_SPLITS: usize = 4;
+const ROW_HEIGHT: f32 = 18.0;
+const SIZE: f32 = 14.0;
+
+/// Splits ahead of the best run's, and behind.
+const AHEAD: Color = Color {
+    r: 0.3,
+    g: 0.9,
+    b: 0.3,
+    a: 1.0,
+};
+const BEHIND: Color = Color {
+    r: 0.95,
+    g: 0.3,
+    b: 0.3,
+    a: 1.0,
+};
+
+/// Whether runs in `mode` are split, those run for time or lines.
+pub fn is_split(mode: Mode) -> bool {
+    matches!(mode, Mode::Endless | Mode::Marathon | Mode::Sprint)
+}
+
+fn key(mode: Mode) -> String {
+    format!("{:?}", mode)
+}
+
+/// The splits of the best run in each mode, in milliseconds, stored in the
+/// user data directory. The best run is the one that got furthest, and of
+/// those the one that got there fastest.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Splits {
+    #[serde(default)]
+    best: BTreeMap<String, Vec<u64>>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Splits {
+    pub fn load(ctx: &Context) -> GameResult<Splits> {
+        let path = filesystem::user_data_dir(ctx).join(SPLITS_FILE);
+        let mut splits: Splits = highscores::load_table(&path)?;
+        splits.path = path;
+        Ok(splits)
+    }
+
+    pub fn best(&self, mode: Mode) -> &[u64] {
+        self.best.get(&key(mode)).map_or(&[], Vec::as_slice)
+    }
+
+    /// Keeps `run`'s splits as the best in `mode` if they are.
+    pub fn record(&mut self, mode: Mode, run: &SplitTimer) {
+        if !run.whole {
+            return;
+        }
+        let splits: Vec<u64> = run
+            .splits
+            .iter()
+            .map(|split| split.as_millis() as u64)
+            .collect();
+        let best = self.best(mode);
+        let better = match (splits.last(), best.last()) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(time), Some(best_time)) => {
+                splits.len() > best.len() || splits.len() == best.len() && time < best_time
+            }
+        };
+        if !better {
+            return;
+        }
+        self.best.insert(key(mode), splits);
+        if let Err(e) = highscores::save_table(&self.path, self) {
+            eprintln!("Could not save the splits: {}", e);
+        }
+    }
+}
+
+/// Times the round in play to each split.
+#[derive(Default)]
+pub struct SplitTimer {
+    splits: Vec<Duration>,
+    /// When the round being timed started, to start over with the next.
+    round: Option<Instant>,
+    /// Whether it was timed from the start, rather than picked up from a
+    /// saved game part way.
+    whole: bool,
+}
+
+impl SplitTimer {
+    /// Draws the latest splits from `top` down between `left` and `right`,
+    /// each with how far ahead of or behind `best` it was.
+    pub fn draw(
+        &self,
+        ctx: &mut Context,
+        (left, right): (f32, f32),
+        top: f32,
+        best: &[u64],
+        locale: &Locale,
+    ) -> GameResult {
+        let from = self.splits.len().saturating_sub(SHOWN_SPLITS);
+        for (row, (at, split)) in self.splits.iter().enumerate().skip(from).enumerate() {
+            let y = top + ROW_HEIGHT * row as f32;
+            let lines = (at as u32 + 1) * SPLIT_LINES;
+            let time = highscores::format_time(*split);
+            let shown = locale.format("splits.split", &[&lines, &time]);
+            text::draw(ctx, &shown, SIZE, graphics::WHITE, Point2::new(left, y))?;
+            if let Some(&best) = best.get(at) {
+                let millis = split.as_millis() as i64 - best as i64;
+                let color = if millis <= 0 { AHEAD } else { BEHIND };
+                let delta = format!("{:+.2}", millis as f64 / 1000.0);
+                text::draw_right(ctx, &delta, SIZE, color, Point2::new(right, y))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Observer for SplitTimer {
+    fn notify(&mut self, event: Event, _: u64, game: &Game) {
+        if self.round != Some(game.started_at()) {
+            self.round = Some(game.started_at());
+            self.splits.clear();
+            self.whole = game.lines == 0;
+        }
+        if let (Event::LineClear(_), true) = (event, self.whole) {
+            // A big clear can pass more than one split.
+            while (self.splits.len() as u32 + 1) * SPLIT_LINES <= game.lines {
+                self.splits.push(game.elapsed());
+            }
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Who each player's attacks go to in a match of more than two. Players
+//! are numbered by place in the match; everyone targets someone still in
+//! other than themselves, picked by the strategy they play.
+
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+
+/// How a player picks who to send garbage to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Targeting {
+    /// Someone still in, picked afresh after each attack.
+    #[default]
+    Random,
+    /// Whoever still in has sent the most.
+    Leader,
+    /// Everyone targeting this player, or someone at random if nobody is.
+    Attackers,
+    /// The one picked by hand, stepped through the others with a key.
+    Manual,
+}
+
+impl Targeting {
+    pub const ALL: [Targeting; 4] = [
+        Targeting::Random,
+        Targeting::Leader,
+        Targeting::Attackers,
+        Targeting::Manual,
+    ];
+}
+
+/// What targeting needs to know about each player in a match.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Rival {
+    /// Whether they haven't topped out.
+    alive: bool,
+    /// Rows of garbage they've sent so far.
+    sent: u32,
+    /// Who they're aiming at.
+    targets: Vec<usize>,
+}
+
+/// One player's choice of targets over a match.
+struct Targeter {
+    strategy: Targeting,
+    /// The pick for `Targeting::Random` and `Targeting::Manual`, kept
+    /// until it's used or they're out.
+    picked: Option<usize>,
+    rng: XorShiftRng,
+}
+
+impl Targeter {
+    /// Steps the pick of player `me` on to the next other player still
+    /// in, switching to manual targeting.
+    fn cycle(&mut self, me: usize, players: &[Rival]) {
+        self.strategy = Targeting::Manual;
+        let count = players.len();
+        let from = self.picked.unwrap_or(me);
+        self.picked = (1..=count)
+            .map(|step| (from + step) % count)
+            .find(|&at| at != me && players[at].alive);
+    }
+
+    /// Who player `me` is aiming at, by place in `players`. Empty once
+    /// nobody else is left.
+    fn targets(&mut self, me: usize, players: &[Rival]) -> Vec<usize> {
+        let others: Vec<usize> = (0..players.len())
+            .filter(|&at| at != me && players[at].alive)
+            .collect();
+        if others.is_empty() {
+            return others;
+        }
+        let picked = self.picked.filter(|picked| others.contains(picked));
+        match self.strategy {
+            Targeting::Leader => {
+                // The first of those level on rows sent, so it doesn't flit.
+                let most = others.iter().map(|&at| players[at].sent).max();
+                others
+                    .into_iter()
+                    .find(|&at| Some(players[at].sent) == most)
+                    .into_iter()
+                    .collect()
+            }
+            Targeting::Attackers => {
+                let attackers: Vec<usize> = others
+                    .iter()
+                    .copied()
+                    .filter(|&at| players[at].targets.contains(&me))
+                    .collect();
+                if attackers.is_empty() {
+                    vec![self.pick(picked, &others)]
+                } else {
+                    attackers
+                }
+            }
+            Targeting::Manual if picked.is_none() => {
+                self.cycle(me, players);
+                self.picked.into_iter().collect()
+            }
+            Targeting::Random | Targeting::Manual => vec![self.pick(picked, &others)],
+        }
+    }
+
+    /// `picked` if there is one, or someone from `others` at random, kept
+    /// for next time.
+    fn pick(&mut self, picked: Option<usize>, others: &[usize]) -> usize {
+        let pick = picked.unwrap_or_else(|| others[self.rng.gen_range(0, others.len())]);
+        self.picked = Some(pick);
+        pick
+    }
+}
+
+/// Everyone's targets over a match.
+pub struct Field {
+    targeters: Vec<Targeter>,
+    rivals: Vec<Rival>,
+}
+
+impl Field {
+    /// A match of a player for each of `strategies`, all still in, with
+    /// random picks made from `seed`.
+    pub fn new(strategies: &[Targeting], seed: u64) -> Field {
+        let targeters = strategies
+            .iter()
+            .enumerate()
+            .map(|(at, &strategy)| Targeter {
+                strategy,
+                picked: None,
+                rng: XorShiftRng::seed_from_u64(seed.wrapping_add(at as u64)),
+            })
+            .collect();
+        let rivals = vec![
+            Rival {
+                alive: true,
+                ..Rival::default()
+            };
+            strategies.len()
+        ];
+        let mut field = Field { targeters, rivals };
+        field.aim();
+        field
+    }
+
+    /// How many players are in the match, out or not.
+    pub fn players(&self) -> usize {
+        self.rivals.len()
+    }
+
+    pub fn strategy(&self, player: usize) -> Option<Targeting> {
+        self.targeters.get(player).map(|targeter| targeter.strategy)
+    }
+
+    /// Who `player` is aiming at.
+    pub fn targets(&self, player: usize) -> &[usize] {
+        self.rivals
+            .get(player)
+            .map_or(&[], |rival| rival.targets.as_slice())
+    }
+
+    /// Takes `player` out of the match.
+    pub fn knock_out(&mut self, player: usize) {
+        if let Some(rival) = self.rivals.get_mut(player) {
+            rival.alive = false;
+        }
+        self.aim();
+    }
+
+    /// Steps `player`'s pick on to the next one still in, by hand.
+    pub fn cycle(&mut self, player: usize) {
+        if let Some(targeter) = self.targeters.get_mut(player) {
+            targeter.cycle(player, &self.rivals);
+        }
+        self.aim();
+    }
+
+    /// Sends `rows` of garbage from `player`, returning who they go to.
+    pub fn attack(&mut self, player: usize, rows: u32) -> Vec<usize> {
+        let rival = match self.rivals.get_mut(player) {
+            Some(rival) => rival,
+            None => return Vec::new(),
+        };
+        rival.sent = rival.sent.saturating_add(rows);
+        let targets = rival.targets.clone();
+        let targeter = &mut self.targeters[player];
+        if targeter.strategy != Targeting::Manual {
+            targeter.picked = None;
+        }
+        self.aim();
+        targets
+    }
+
+    /// Settles who everyone still in is aiming at, after anything that
+    /// might change it.
+    fn aim(&mut self) {
+        for at in 0..self.rivals.len() {
+            let targets = if self.rivals[at].alive {
+                self.targeters[at].targets(at, &self.rivals)
+            } else {
+                Vec::new()
+            };
+            self.rivals[at].targets = targets;
+        }
+    }
+}
@@ -0,0 +1,578 @@
+#[cfg(not(target_arch = "wasm32"))]
+use ggez::graphics;
+use na::{Point2, Vector2};
+use nalgebra as na;
+use rand::Rng;
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Neg;
+use std::sync::OnceLock;
+
+/// Which way a piece faces: how far it has been turned clockwise from its
+/// shape as defined. Saved as the number of quarter turns, 0 to 3.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "u8", into = "u8")]
+pub enum Rotation {
+    #[default]
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [
+        Rotation::North,
+        Rotation::East,
+        Rotation::South,
+        Rotation::West,
+    ];
+
+    /// A quarter turn clockwise.
+    pub fn cw(self) -> Rotation {
+        match self {
+            Rotation::North => Rotation::East,
+            Rotation::East => Rotation::South,
+            Rotation::South => Rotation::West,
+            Rotation::West => Rotation::North,
+        }
+    }
+
+    /// A quarter turn counter-clockwise.
+    pub fn ccw(self) -> Rotation {
+        match self {
+            Rotation::North => Rotation::West,
+            Rotation::East => Rotation::North,
+            Rotation::South => Rotation::East,
+            Rotation::West => Rotation::South,
+        }
+    }
+
+    /// A half turn.
+    pub fn flip(self) -> Rotation {
+        self.cw().cw()
+    }
+
+    /// A quarter turn `turn`'s way.
+    pub fn turn(self, turn: Turn) -> Rotation {
+        match turn {
+            Turn::Cw => self.cw(),
+            Turn::Ccw => self.ccw(),
+        }
+    }
+
+    /// Quarter turns clockwise from `North`.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Which way a piece is turned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Turn {
+    Cw,
+    Ccw,
+}
+
+/// Any number of quarter turns, so files can say 4 for 0.
+impl From<u8> for Rotation {
+    fn from(turns: u8) -> Rotation {
+        Rotation::ALL[turns as usize % 4]
+    }
+}
+
+impl From<Rotation> for u8 {
+    fn from(rotation: Rotation) -> u8 {
+        rotation as u8
+    }
+}
+
+pub trait Rotate90 {
+    fn rotate_90(self, rotation: Rotation) -> Self;
+}
+
+impl<T: na::Scalar + Neg<Output = T>> Rotate90 for Vector2<T> {
+    fn rotate_90(self, rotation: Rotation) -> Self {
+        match rotation {
+            Rotation::North => self,
+            Rotation::East => Vector2::new(-self[1], self[0]),
+            Rotation::South => Vector2::new(-self[0], -self[1]),
+            Rotation::West => Vector2::new(self[1], -self[0]),
+        }
+    }
+}
+
+/// The most pieces a custom set can add to the built-in ones.
+pub const MAX_CUSTOM_PIECES: usize = 200;
+
+/// How far a custom piece's minos may sit from its centre, in either
+/// direction, so the bot's search still covers every column.
+const MAX_REACH: i32 = 4;
+
+/// How a piece looks and where its minos sit, one entry of the shape table.
+#[derive(Clone)]
+struct Shape {
+    /// Stable name written to saves and replays.
+    name: Cow<'static, str>,
+    /// The letter used for the piece in board text, see `FixedBlock::to_char`.
+    symbol: char,
+    /// Minos relative to the centre the piece turns about, `y` pointing
+    /// down.
+    offsets: Cow<'static, [(i32, i32)]>,
+    /// The facing a piece spawns in: flat side down, matching the guideline
+    /// orientation of each tetromino.
+    spawn_facing: Rotation,
+    color: (u8, u8, u8),
+}
+
+/// The built-in pieces: the seven tetrominoes, then the eighteen one-sided
+/// pentominoes, mirrored ones marked with a `'`.
+const SHAPES: [Shape; 25] = [
+    Shape {
+        name: Cow::Borrowed("IBlock"),
+        symbol: 'I',
+        offsets: Cow::Borrowed(&[(0, 0), (1, 0), (2, 0), (-1, 0)]),
+        spawn_facing: Rotation::North,
+        color: (66, 241, 244),
+    },
+    Shape {
+        name: Cow::Borrowed("OBlock"),
+        symbol: 'O',
+        offsets: Cow::Borrowed(&[(0, 0), (1, 0), (0, 1), (1, 1)]),
+        spawn_facing: Rotation::North,
+        color: (233, 237, 42),
+    },
+    Shape {
+        name: Cow::Borrowed("TBlock"),
+        symbol: 'T',
+        offsets: Cow::Borrowed(&[(0, 0), (0, 1), (-1, 0), (1, 0)]),
+        spawn_facing: Rotation::South,
+        color: (182, 42, 237),
+    },
+    Shape {
+        name: Cow::Borrowed("SBlock"),
+        symbol: 'S',
+        offsets: Cow::Borrowed(&[(0, 0), (0, 1), (1, 0), (-1, 1)]),
+        spawn_facing: Rotation::North,
+        color: (88, 237, 42),
+    },
+    Shape {
+        name: Cow::Borrowed("ZBlock"),
+        symbol: 'Z',
+        offsets: Cow::Borrowed(&[(0, 0), (0, 1), (-1, 0), (1, 1)]),
+        spawn_facing: Rotation::North,
+        color: (226, 50, 27),
+    },
+    Shape {
+        name: Cow::Borrowed("JBlock"),
+        symbol: 'J',
+        offsets: Cow::Borrowed(&[(0, 0), (0, 1), (0, -1), (1, -1)]),
+        spawn_facing: Rotation::West,
+        color: (22, 75, 221),
+    },
+    Shape {
+        name: Cow::Borrowed("LBlock"),
+        symbol: 'L',
+        offsets: Cow::Borrowed(&[(0, 0), (0, 1), (0, -1), (-1, -1)]),
+        spawn_facing: Rotation::East,
+        color: (219, 108, 17),
+    },
+    Shape {
+        name: Cow::Borrowed("F5"),
+        symbol: 'f',
+        offsets: Cow::Borrowed(&[(0, -1), (1, -1), (-1, 0), (0, 0), (0, 1)]),
+        spawn_facing: Rotation::North,
+        color: (240, 128, 160),
+    },
+    Shape {
+        name: Cow::Borrowed("F5'"),
+        symbol: 'e',
+        offsets: Cow::Borrowed(&[(-1, -1), (0, -1), (0, 0), (1, 0), (0, 1)]),
+        spawn_facing: Rotation::North,
+        color: (200, 90, 130),
+    },
+    Shape {
+        name: Cow::Borrowed("I5"),
+        symbol: 'i',
+        offsets: Cow::Borrowed(&[(-2, 0), (-1, 0), (0, 0), (1, 0), (2, 0)]),
+        spawn_facing: Rotation::North,
+        color: (120, 220, 250),
+    },
+    Shape {
+        name: Cow::Borrowed("L5"),
+        symbol: 'l',
+        offsets: Cow::Borrowed(&[(-1, 0), (0, 0), (1, 0), (2, 0), (2, -1)]),
+        spawn_facing: Rotation::North,
+        color: (245, 160, 60),
+    },
+    Shape {
+        name: Cow::Borrowed("L5'"),
+        symbol: 'j',
+        offsets: Cow::Borrowed(&[(-1, -1), (-1, 0), (0, 0), (1, 0), (2, 0)]),
+        spawn_facing: Rotation::North,
+        color: (70, 110, 235),
+    },
+    Shape {
+        name: Cow::Borrowed("N5"),
+        symbol: 'n',
+        offsets: Cow::Borrowed(&[(-1, -1), (0, -1), (0, 0), (1, 0), (2, 0)]),
+        spawn_facing: Rotation::North,
+        color: (160, 200, 60),
+    },
+    Shape {
+        name: Cow::Borrowed("N5'"),
+        symbol: 'm',
+        offsets: Cow::Borrowed(&[(1, -1), (0, -1), (0, 0), (-1, 0), (-2, 0)]),
+        spawn_facing: Rotation::North,
+        color: (110, 160, 40),
+    },
+    Shape {
+        name: Cow::Borrowed("P5"),
+        symbol: 'p',
+        offsets: Cow::Borrowed(&[(-1, -1), (0, -1), (-1, 0), (0, 0), (1, 0)]),
+        spawn_facing: Rotation::North,
+        color: (250, 210, 90),
+    },
+    Shape {
+        name: Cow::Borrowed("P5'"),
+        symbol: 'q',
+        offsets: Cow::Borrowed(&[(0, -1), (1, -1), (-1, 0), (0, 0), (1, 0)]),
+        spawn_facing: Rotation::North,
+        color: (210, 170, 50),
+    },
+    Shape {
+        name: Cow::Borrowed("T5"),
+        symbol: 't',
+        offsets: Cow::Borrowed(&[(0, -1), (0, 0), (-1, 1), (0, 1), (1, 1)]),
+        spawn_facing: Rotation::North,
+        color: (150, 80, 220),
+    },
+    Shape {
+        name: Cow::Borrowed("U5"),
+        symbol: 'u',
+        offsets: Cow::Borrowed(&[(-1, -1), (1, -1), (-1, 0), (0, 0), (1, 0)]),
+        spawn_facing: Rotation::North,
+        color: (90, 200, 170),
+    },
+    Shape {
+        name: Cow::Borrowed("V5"),
+        symbol: 'v',
+        offsets: Cow::Borrowed(&[(-1, -1), (-1, 0), (-1, 1), (0, 1), (1, 1)]),
+        spawn_facing: Rotation::North,
+        color: (60, 150, 200),
+    },
+    Shape {
+        name: Cow::Borrowed("W5"),
+        symbol: 'w',
+        offsets: Cow::Borrowed(&[(-1, -1), (-1, 0), (0, 0), (0, 1), (1, 1)]),
+        spawn_facing: Rotation::North,
+        color: (230, 90, 200),
+    },
+    Shape {
+        name: Cow::Borrowed("X5"),
+        symbol: 'x',
+        offsets: Cow::Borrowed(&[(0, -1), (-1, 0), (0, 0), (1, 0), (0, 1)]),
+        spawn_facing: Rotation::North,
+        color: (240, 240, 240),
+    },
+    Shape {
+        name: Cow::Borrowed("Y5"),
+        symbol: 'y',
+        offsets: Cow::Borrowed(&[(0, -1), (-1, 0), (0, 0), (1, 0), (2, 0)]),
+        spawn_facing: Rotation::North,
+        color: (190, 140, 100),
+    },
+    Shape {
+        name: Cow::Borrowed("Y5'"),
+        symbol: 'h',
+        offsets: Cow::Borrowed(&[(0, -1), (1, 0), (0, 0), (-1, 0), (-2, 0)]),
+        spawn_facing: Rotation::North,
+        color: (150, 100, 70),
+    },
+    Shape {
+        name: Cow::Borrowed("Z5"),
+        symbol: 'z',
+        offsets: Cow::Borrowed(&[(-1, -1), (0, -1), (0, 0), (0, 1), (1, 1)]),
+        spawn_facing: Rotation::North,
+        color: (235, 80, 60),
+    },
+    Shape {
+        name: Cow::Borrowed("Z5'"),
+        symbol: 's',
+        offsets: Cow::Borrowed(&[(1, -1), (0, -1), (0, 0), (0, 1), (-1, 1)]),
+        spawn_facing: Rotation::North,
+        color: (100, 220, 90),
+    },
+];
+
+/// A piece shape as written in a data file, to be added with
+/// `define_custom`. Blocks are `[x, y]` from the centre the piece turns
+/// about, `y` pointing down.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PieceDef {
+    pub name: String,
+    /// The letter for the piece in board text; it can't be one already
+    /// taken.
+    pub symbol: char,
+    pub blocks: Vec<[i32; 2]>,
+    #[serde(default)]
+    pub spawn_facing: Rotation,
+    pub color: [u8; 3],
+}
+
+/// The shape table in use: the built-in shapes, then any custom ones.
+struct Table {
+    shapes: Vec<Shape>,
+    custom: Vec<Tetromino>,
+}
+
+static TABLE: OnceLock<Table> = OnceLock::new();
+
+fn table() -> &'static Table {
+    TABLE.get_or_init(|| Table {
+        shapes: SHAPES.to_vec(),
+        custom: Vec::new(),
+    })
+}
+
+/// Adds `defs` to the shape table after the built-in pieces, as the
+/// `PieceSet::Custom` set. The table is fixed once any piece has been
+/// looked at, so this has to be called first thing.
+pub fn define_custom(defs: Vec<PieceDef>) -> Result<(), String> {
+    if defs.len() > MAX_CUSTOM_PIECES {
+        return Err(format!("at most {} pieces can be added", MAX_CUSTOM_PIECES));
+    }
+    let mut shapes = SHAPES.to_vec();
+    let mut custom = Vec::new();
+    for def in defs {
+        if shapes.iter().any(|shape| shape.name == def.name) {
+            return Err(format!("piece {:?} is defined twice", def.name));
+        }
+        if def.symbol == 'G' || def.symbol == '.' || def.symbol.is_whitespace() {
+            return Err(format!(
+                "{:?} can't use {:?} as its letter",
+                def.name, def.symbol
+            ));
+        }
+        if let Some(other) = shapes.iter().find(|shape| shape.symbol == def.symbol) {
+            return Err(format!(
+                "{:?} and {:?} both use {:?}",
+                other.name, def.name, def.symbol
+            ));
+        }
+        if def.blocks.is_empty() {
+            return Err(format!("{:?} has no blocks", def.name));
+        }
+        let reach = def.blocks.iter().flatten().map(|v| v.abs()).max();
+        if reach > Some(MAX_REACH) {
+            return Err(format!(
+                "{:?} has blocks more than {} from its centre",
+                def.name, MAX_REACH
+            ));
+        }
+        let mut offsets: Vec<(i32, i32)> = def.blocks.iter().map(|&[x, y]| (x, y)).collect();
+        offsets.sort_unstable();
+        offsets.dedup();
+        custom.push(Tetromino(shapes.len() as u8));
+        shapes.push(Shape {
+            name: Cow::Owned(def.name),
+            symbol: def.symbol,
+            offsets: Cow::Owned(offsets),
+            spawn_facing: def.spawn_facing,
+            color: (def.color[0], def.color[1], def.color[2]),
+        });
+    }
+    TABLE
+        .set(Table { shapes, custom })
+        .map_err(|_| "pieces are already in use".to_string())
+}
+
+/// A piece, naming one entry of the shape table. The name is from when the
+/// tetrominoes were all there was; pentominoes are pieces too.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Tetromino(u8);
+
+#[allow(non_upper_case_globals)]
+impl Tetromino {
+    pub const IBlock: Tetromino = Tetromino(0);
+    pub const OBlock: Tetromino = Tetromino(1);
+    pub const TBlock: Tetromino = Tetromino(2);
+    pub const SBlock: Tetromino = Tetromino(3);
+    pub const ZBlock: Tetromino = Tetromino(4);
+    pub const JBlock: Tetromino = Tetromino(5);
+    pub const LBlock: Tetromino = Tetromino(6);
+
+    pub const ALL: [Tetromino; 7] = [
+        Tetromino::IBlock,
+        Tetromino::OBlock,
+        Tetromino::TBlock,
+        Tetromino::SBlock,
+        Tetromino::ZBlock,
+        Tetromino::JBlock,
+        Tetromino::LBlock,
+    ];
+
+    /// The eighteen one-sided pentominoes.
+    pub const PENTOMINOES: [Tetromino; 18] = [
+        Tetromino(7),
+        Tetromino(8),
+        Tetromino(9),
+        Tetromino(10),
+        Tetromino(11),
+        Tetromino(12),
+        Tetromino(13),
+        Tetromino(14),
+        Tetromino(15),
+        Tetromino(16),
+        Tetromino(17),
+        Tetromino(18),
+        Tetromino(19),
+        Tetromino(20),
+        Tetromino(21),
+        Tetromino(22),
+        Tetromino(23),
+        Tetromino(24),
+    ];
+
+    /// Every piece in the shape table, whichever set it belongs to.
+    pub fn every() -> impl Iterator<Item = Tetromino> {
+        (0..table().shapes.len() as u8).map(Tetromino)
+    }
+
+    fn shape(self) -> &'static Shape {
+        &table().shapes[self.0 as usize]
+    }
+
+    pub fn name(self) -> &'static str {
+        &self.shape().name
+    }
+
+    pub fn from_name(name: &str) -> Option<Tetromino> {
+        Tetromino::every().find(|piece| piece.name() == name)
+    }
+
+    /// The piece's letter in board text.
+    pub fn symbol(self) -> char {
+        self.shape().symbol
+    }
+
+    pub fn from_symbol(symbol: char) -> Option<Tetromino> {
+        Tetromino::every().find(|piece| piece.symbol() == symbol)
+    }
+
+    pub fn block_offsets(self) -> Vec<Vector2<i32>> {
+        self.shape()
+            .offsets
+            .iter()
+            .map(|&(x, y)| Vector2::new(x, y))
+            .collect()
+    }
+
+    pub fn spawn_facing(self) -> Rotation {
+        self.shape().spawn_facing
+    }
+
+    pub fn blocks(self, pos: Point2<i32>, facing: Rotation) -> Vec<Point2<i32>> {
+        self.block_offsets()
+            .iter()
+            .map(|block_vector| pos + block_vector.rotate_90(facing))
+            .collect()
+    }
+    pub fn rgb(self) -> (u8, u8, u8) {
+        self.shape().color
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn color(self) -> graphics::Color {
+        graphics::Color::from(self.rgb())
+    }
+
+    pub fn min_x(self, facing: Rotation) -> i32 {
+        self.blocks(Point2::new(0, 0), facing)
+            .into_iter()
+            .map(|block| block[0])
+            .min()
+            .unwrap()
+    }
+    pub fn max_x(self, facing: Rotation) -> i32 {
+        self.blocks(Point2::new(0, 0), facing)
+            .into_iter()
+            .map(|block| block[0])
+            .max()
+            .unwrap()
+    }
+    pub fn min_y(self, facing: Rotation) -> i32 {
+        self.blocks(Point2::new(0, 0), facing)
+            .into_iter()
+            .map(|block| block[1])
+            .min()
+            .unwrap()
+    }
+}
+
+impl fmt::Debug for Tetromino {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Serialize for Tetromino {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Tetromino {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Tetromino::from_name(&name)
+            .ok_or_else(|| de::Error::custom(format!("unknown piece {:?}", name)))
+    }
+}
+
+/// Which pieces a round deals from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PieceSet {
+    #[default]
+    Tetrominoes,
+    Pentominoes,
+    /// The pieces added with `define_custom`.
+    Custom,
+}
+
+impl PieceSet {
+    pub fn pieces(self) -> &'static [Tetromino] {
+        match self {
+            PieceSet::Tetrominoes => &Tetromino::ALL,
+            PieceSet::Pentominoes => &Tetromino::PENTOMINOES,
+            PieceSet::Custom => &table().custom,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PieceSet::Tetrominoes => "Tetrominoes",
+            PieceSet::Pentominoes => "Pentominoes",
+            PieceSet::Custom => "Custom",
+        }
+    }
+
+    /// The pieces the set deals: its own, or the tetrominoes for a custom
+    /// set with nothing in it.
+    pub fn dealt(self) -> &'static [Tetromino] {
+        match self.pieces() {
+            [] => &Tetromino::ALL,
+            pieces => pieces,
+        }
+    }
+
+    /// Draws a piece from the set, each as likely as the others.
+    pub fn deal<R: Rng + ?Sized>(self, rng: &mut R) -> Tetromino {
+        let pieces = self.dealt();
+        // Sampled as an `i32` so seeds deal the same tetrominoes they
+        // always have.
+        pieces[rng.gen_range(0, pieces.len() as i32) as usize]
+    }
+}
@@ -0,0 +1,112 @@
+//! Drawing text in the game's own font, DejaVu Sans, bundled in
+//! `resources/fonts` so the HUD, menus and popups look the same wherever
+//! the game runs. Until it's loaded, or if it can't be, text falls back to
+//! ggez's built-in font.
+
+use ggez::graphics::{self, Color, Font, Scale, Text, TextFragment};
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+use std::sync::OnceLock;
+
+const FONT_PATH: &str = "/fonts/DejaVuSans.ttf";
+
+static FONT: OnceLock<Font> = OnceLock::new();
+
+/// Loads the bundled font for all text drawn after. A missing font file
+/// leaves the built-in one rather than stopping the game.
+pub fn load(ctx: &mut Context) {
+    match Font::new(ctx, FONT_PATH) {
+        Ok(font) => {
+            let _ = FONT.set(font);
+        }
+        Err(e) => eprintln!("Could not load {}: {}", FONT_PATH, e),
+    }
+}
+
+/// `text` as one line `size` high, in the game's font.
+pub fn line(text: &str, size: f32) -> Text {
+    let mut fragment = TextFragment::new(text).scale(Scale::uniform(size));
+    if let Some(&font) = FONT.get() {
+        fragment = fragment.font(font);
+    }
+    Text::new(fragment)
+}
+
+/// Draws `text` with its top-left corner at `dest`.
+pub fn draw(
+    ctx: &mut Context,
+    text: &str,
+    size: f32,
+    color: Color,
+    dest: Point2<f32>,
+) -> GameResult {
+    graphics::draw(ctx, &line(text, size), (dest, color))
+}
+
+/// Draws `text` with its top-right corner at `dest`, so numbers in a
+/// column line up on their last digit.
+pub fn draw_right(
+    ctx: &mut Context,
+    text: &str,
+    size: f32,
+    color: Color,
+    dest: Point2<f32>,
+) -> GameResult {
+    let text = line(text, size);
+    let dest = Point2::new(dest.x - text.width(ctx) as f32, dest.y);
+    graphics::draw(ctx, &text, (dest, color))
+}
+
+/// Where `text` goes to be centered horizontally on the screen and
+/// vertically on `center_y`.
+fn centered(ctx: &mut Context, text: &Text, center_y: f32) -> Point2<f32> {
+    let (width, height) = text.dimensions(ctx);
+    Point2::new(
+        (crate::view(ctx).w - width as f32) / 2.0,
+        center_y - height as f32 / 2.0,
+    )
+}
+
+/// Draws a line of text centered horizontally on the screen and vertically
+/// on `center_y`.
+pub fn draw_centered(
+    ctx: &mut Context,
+    text: &str,
+    size: f32,
+    color: Color,
+    center_y: f32,
+) -> GameResult {
+    let text = line(text, size);
+    let dest = centered(ctx, &text, center_y);
+    graphics::draw(ctx, &text, (dest, color))
+}
+
+/// Draws a line of text centered as `draw_centered` does, edged in
+/// `outline` so it stands out over the board and whatever is on it.
+pub fn draw_outlined(
+    ctx: &mut Context,
+    text: &str,
+    size: f32,
+    color: Color,
+    outline: Color,
+    center_y: f32,
+) -> GameResult {
+    let text = line(text, size);
+    let dest = centered(ctx, &text, center_y);
+    let width = (size / 16.0).max(1.0);
+    for &(dx, dy) in &[
+        (-1.0, -1.0),
+        (0.0, -1.0),
+        (1.0, -1.0),
+        (-1.0, 0.0),
+        (1.0, 0.0),
+        (-1.0, 1.0),
+        (0.0, 1.0),
+        (1.0, 1.0),
+    ] {
+        let at = Point2::new(dest.x + dx * width, dest.y + dy * width);
+        graphics::queue_text(ctx, &text, at, Some(outline));
+    }
+    graphics::queue_text(ctx, &text, dest, Some(color));
+    graphics::draw_queued_text(ctx, graphics::DrawParam::new())
+}
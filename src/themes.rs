@@ -0,0 +1,100 @@
+use crate::config::Colors;
+use ggez::filesystem;
+use ggez::{Context, GameError, GameResult};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const THEME_DIR: &str = "themes";
+
+/// The layout of a theme file: the config's `[colors]` table, and
+/// optionally a `backdrop` as the config takes it.
+#[derive(Deserialize)]
+struct ThemeFile {
+    #[serde(flatten)]
+    colors: Colors,
+    #[serde(default)]
+    backdrop: String,
+}
+
+/// A named set of colors, read from a `.toml` file laid out like the
+/// config's `[colors]` table, and what's drawn around the board with them.
+/// Anything a theme leaves out is the classic color, and the plain
+/// background.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub name: String,
+    pub colors: Colors,
+    pub backdrop: String,
+}
+
+fn parse(path: &Path, text: &str) -> Option<Theme> {
+    let name = path.file_stem()?.to_string_lossy().into_owned();
+    match toml::from_str::<ThemeFile>(text) {
+        Ok(file) => Some(Theme {
+            name,
+            colors: file.colors,
+            backdrop: file.backdrop,
+        }),
+        Err(e) => {
+            eprintln!("Invalid theme {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn is_theme(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "toml")
+}
+
+/// The themes in `resources/themes` and the packs that are on, then those
+/// in `themes` in the user config directory, in order of name. A player's
+/// theme replaces a built-in one of the same name. Files that can't be
+/// read are left out.
+pub fn load(ctx: &mut Context) -> Vec<Theme> {
+    let mut themes = BTreeMap::new();
+    let built_in: Vec<PathBuf> = filesystem::read_dir(ctx, format!("/{}", THEME_DIR))
+        .map(|paths| paths.filter(|path| is_theme(path)).collect())
+        .unwrap_or_default();
+    for path in built_in {
+        let read = |ctx: &mut Context| -> GameResult<String> {
+            let mut text = String::new();
+            filesystem::open(ctx, &path)?
+                .read_to_string(&mut text)
+                .map_err(GameError::from)?;
+            Ok(text)
+        };
+        match read(ctx) {
+            Ok(text) => {
+                if let Some(theme) = parse(&path, &text) {
+                    themes.insert(theme.name.clone(), theme);
+                }
+            }
+            Err(e) => eprintln!("Could not read theme {}: {}", path.display(), e),
+        }
+    }
+
+    let dir = filesystem::user_config_dir(ctx).join(THEME_DIR);
+    let mine: Vec<PathBuf> = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| is_theme(path))
+                .collect()
+        })
+        .unwrap_or_default();
+    for path in mine {
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                if let Some(theme) = parse(&path, &text) {
+                    themes.insert(theme.name.clone(), theme);
+                }
+            }
+            Err(e) => eprintln!("Could not read theme {}: {}", path.display(), e),
+        }
+    }
+    themes.into_values().collect()
+}
@@ -0,0 +1,125 @@
+use ggez::graphics::{self, Color, DrawMode, MeshBuilder, Rect};
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+use std::time::{Duration, Instant};
+use tetris_rs::tetromino::{Rotation, Tetromino};
+
+/// How long a trail and its landing flash take to fade out.
+const TRAIL_MILLIS: u64 = 150;
+/// How bright the streak starts out, at the bottom next to the piece.
+const TRAIL_ALPHA: f32 = 0.5;
+/// How bright the landed piece flashes.
+const FLASH_ALPHA: f32 = 0.6;
+
+struct Trail {
+    /// For each column the piece covers, the rows the streak runs from and
+    /// to: from the piece's top in that column where the drop started down
+    /// to its top where it landed.
+    columns: Vec<(i32, i32, i32)>,
+    /// The cells the piece landed in.
+    landed: Vec<Point2<i32>>,
+    color: Color,
+    at: Instant,
+}
+
+/// Streaks down the columns a hard-dropped piece fell through, and a flash
+/// where it landed, so a drop that happens in a single step can still be
+/// followed by eye. In board cells, like `Particles`.
+#[derive(Default)]
+pub struct Trails {
+    trails: Vec<Trail>,
+}
+
+impl Trails {
+    /// Adds a trail for `tetromino`, facing `facing`, hard dropped from row
+    /// `from` to `pos`.
+    pub fn add(
+        &mut self,
+        now: Instant,
+        tetromino: Tetromino,
+        pos: Point2<i32>,
+        facing: Rotation,
+        from: i32,
+        color: Color,
+    ) {
+        let landed = tetromino.blocks(pos, facing);
+        let mut columns: Vec<(i32, i32, i32)> = Vec::new();
+        for block in &landed {
+            let top = block[1] - (pos[1] - from);
+            match columns.iter_mut().find(|column| column.0 == block[0]) {
+                Some(column) => {
+                    column.1 = column.1.min(top);
+                    column.2 = column.2.min(block[1]);
+                }
+                None => columns.push((block[0], top, block[1])),
+            }
+        }
+        self.trails.push(Trail {
+            columns,
+            landed,
+            color,
+            at: now,
+        });
+    }
+
+    /// Draws the trails still fading at `now` over a board with its
+    /// top-left corner at `origin` and cells `cell_size` across, and lets go
+    /// of the ones that have faded.
+    pub fn draw(
+        &mut self,
+        ctx: &mut Context,
+        now: Instant,
+        origin: Point2<f32>,
+        cell_size: f32,
+    ) -> GameResult {
+        let length = Duration::from_millis(TRAIL_MILLIS).as_secs_f32();
+        self.trails
+            .retain(|trail| now.saturating_duration_since(trail.at).as_secs_f32() < length);
+        if self.trails.is_empty() {
+            return Ok(());
+        }
+        let mut builder = MeshBuilder::new();
+        let mut cells = 0;
+        for trail in &self.trails {
+            let left = 1.0 - now.saturating_duration_since(trail.at).as_secs_f32() / length;
+            for &(x, from, to) in &trail.columns {
+                // Nothing in the hidden rows above the board is drawn.
+                let from = from.max(0);
+                if from >= to {
+                    continue;
+                }
+                // Stacked in slices that brighten towards the piece.
+                for y in from..to {
+                    let along = (y - from + 1) as f32 / (to - from) as f32;
+                    let mut color = trail.color;
+                    color.a = TRAIL_ALPHA * along * left;
+                    let at = Rect::new(
+                        origin[0] + x as f32 * cell_size,
+                        origin[1] + y as f32 * cell_size,
+                        cell_size,
+                        cell_size,
+                    );
+                    builder.rectangle(DrawMode::fill(), at, color);
+                    cells += 1;
+                }
+            }
+            let flash = Color::new(1.0, 1.0, 1.0, FLASH_ALPHA * left);
+            for block in trail.landed.iter().filter(|block| block[1] >= 0) {
+                let at = Rect::new(
+                    origin[0] + block[0] as f32 * cell_size,
+                    origin[1] + block[1] as f32 * cell_size,
+                    cell_size,
+                    cell_size,
+                );
+                builder.rectangle(DrawMode::fill(), at, flash);
+                cells += 1;
+            }
+        }
+        // ggez refuses to build a mesh with no vertices.
+        if cells == 0 {
+            return Ok(());
+        }
+        let mesh = builder.build(ctx)?;
+        graphics::draw(ctx, &mesh, (Point2::new(0.0, 0.0),))
+    }
+}
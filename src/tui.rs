@@ -0,0 +1,200 @@
+use crate::cli::Cli;
+use crate::locale::Locale;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{Color, ResetColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType};
+use ggez::GameResult;
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+use tetris_rs::board::Board;
+use tetris_rs::game::{Game, Handling, Input, Settings};
+
+/// How often the game is stepped and redrawn.
+const FRAME: Duration = Duration::from_millis(16);
+/// Terminals only report key presses, so soft drop is let go once its key
+/// hasn't repeated for this long.
+const SOFT_DROP_HOLD: Duration = Duration::from_millis(120);
+/// The interval between rows while soft drop is held. Terminals repeat keys
+/// slower than the game drops, so the game keeps time rather than the key.
+const SOFT_DROP_RATE: Duration = Duration::from_millis(50);
+
+/// What one cell of the board shows.
+#[derive(Clone, Copy)]
+enum Cell {
+    Empty,
+    Ghost((u8, u8, u8)),
+    Block((u8, u8, u8)),
+}
+
+fn input_for(key: KeyCode) -> Option<Input> {
+    match key {
+        KeyCode::Left => Some(Input::Left),
+        KeyCode::Right => Some(Input::Right),
+        KeyCode::Up => Some(Input::RotateCw),
+        KeyCode::Char('x') => Some(Input::RotateCcw),
+        KeyCode::Down => Some(Input::SoftDrop),
+        KeyCode::Char(' ') => Some(Input::HardDrop),
+        KeyCode::Char('c') => Some(Input::Hold),
+        KeyCode::Char('z') => Some(Input::Undo),
+        _ => None,
+    }
+}
+
+fn new_game(cli: &Cli) -> Game {
+    let settings = Settings {
+        handling: Handling {
+            soft_drop: Some(SOFT_DROP_RATE),
+            ..Handling::default()
+        },
+        start_level: cli.level.unwrap_or(1),
+        seed: cli.seed.unwrap_or_else(rand::random),
+        ..Settings::default()
+    };
+    Game::new(Board::default(), settings)
+}
+
+/// The board and the pieces on it as lines of text, two columns a cell.
+fn draw(game: &Game, paused: bool, locale: &Locale) -> String {
+    let (width, height) = (game.board.width(), game.board.height());
+    let mut cells = vec![vec![Cell::Empty; width]; height];
+    let mut put = |x: i32, y: i32, cell: Cell| {
+        if x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+            cells[y as usize][x as usize] = cell;
+        }
+    };
+    if !game.game_over {
+        let rgb = game.tetromino().rgb();
+        for block in game.ghost_blocks() {
+            put(block[0], block[1], Cell::Ghost(rgb));
+        }
+        for block in game.active_blocks() {
+            put(block[0], block[1], Cell::Block(rgb));
+        }
+    }
+    for (block, fixed) in game.board.blocks() {
+        put(block[0], block[1], Cell::Block(fixed.rgb()));
+    }
+
+    let side = [
+        locale.format("tui.score", &[&game.score]),
+        locale.format("tui.lines", &[&game.lines]),
+        locale.format("tui.level", &[&game.level]),
+        String::new(),
+        locale.format("tui.next", &[&game.next().symbol()]),
+        String::new(),
+        if game.game_over {
+            locale.get("tui.game_over").to_string()
+        } else if paused {
+            locale.get("tui.paused").to_string()
+        } else {
+            String::new()
+        },
+        locale.get("tui.quit").to_string(),
+    ];
+    // Drawn over the last frame rather than cleared first, which flickers.
+    let mut out = MoveTo(0, 0).to_string();
+    for (y, row) in cells.iter().enumerate() {
+        out.push('|');
+        for cell in row {
+            match *cell {
+                Cell::Empty => out.push_str(" ."),
+                Cell::Ghost((r, g, b)) => out.push_str(&format!(
+                    "{}[]{}",
+                    SetForegroundColor(Color::Rgb { r, g, b }),
+                    ResetColor
+                )),
+                Cell::Block((r, g, b)) => out.push_str(&format!(
+                    "{}\u{2588}\u{2588}{}",
+                    SetForegroundColor(Color::Rgb { r, g, b }),
+                    ResetColor
+                )),
+            }
+        }
+        out.push('|');
+        if let Some(line) = side.get(y) {
+            out.push_str("  ");
+            out.push_str(line);
+        }
+        out.push_str(&format!("{}\r\n", Clear(ClearType::UntilNewLine)));
+    }
+    out.push('+');
+    out.push_str(&"--".repeat(width));
+    out.push_str("+\r\n");
+    out
+}
+
+/// Plays a round in the terminal, drawn with block characters, until the
+/// player quits. Settings come from the command line only.
+pub fn run(cli: &Cli) -> GameResult {
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    write!(stdout, "{}{}", Clear(ClearType::All), Hide)?;
+    let played = play(cli, &mut stdout);
+    // The terminal is put back however the round ended.
+    write!(stdout, "{}{}{}", Clear(ClearType::All), MoveTo(0, 0), Show)?;
+    stdout.flush()?;
+    terminal::disable_raw_mode()?;
+    played
+}
+
+fn play(cli: &Cli, stdout: &mut impl Write) -> GameResult {
+    let locale = Locale::english();
+    let mut game = new_game(cli);
+    let mut paused = false;
+    let mut soft_drop_until: Option<Instant> = None;
+    'play: loop {
+        let now = Instant::now();
+        while event::poll(Duration::from_millis(0))? {
+            let key = match event::read()? {
+                // Some terminals report letting go of keys too.
+                Event::Key(key) if key.kind != KeyEventKind::Release => key,
+                _ => continue,
+            };
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+            match key.code {
+                KeyCode::Char('c') if ctrl => break 'play,
+                KeyCode::Char('q') | KeyCode::Esc => break 'play,
+                KeyCode::Char('r') if game.game_over => {
+                    game = new_game(cli);
+                    soft_drop_until = None;
+                }
+                KeyCode::Char('p') if !game.game_over => {
+                    if paused {
+                        game.resume(now);
+                    } else {
+                        game.pause(now);
+                    }
+                    paused = !paused;
+                }
+                _ if paused => (),
+                code => match input_for(code) {
+                    Some(Input::SoftDrop) => {
+                        if soft_drop_until.is_none() {
+                            game.press(Input::SoftDrop, now);
+                        }
+                        soft_drop_until = Some(now + SOFT_DROP_HOLD);
+                    }
+                    Some(input) => {
+                        game.press(input, now);
+                        game.release(input);
+                    }
+                    None => (),
+                },
+            }
+        }
+        if soft_drop_until.is_some_and(|until| now >= until) {
+            game.release(Input::SoftDrop);
+            soft_drop_until = None;
+        }
+        if !paused {
+            game.update(now);
+            game.take_events();
+        }
+        write!(stdout, "{}", draw(&game, paused, &locale))?;
+        stdout.flush()?;
+        thread::sleep(FRAME);
+    }
+    Ok(())
+}
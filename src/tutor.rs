@@ -0,0 +1,146 @@
+//! A learning aid: where the built-in bot would put the piece in play, and
+//! once the player's piece locks, how its placement compared.
+
+use crate::ai::{self, Ai, Weights};
+use crate::board::{Board, FixedBlock};
+use crate::clock::Instant;
+use crate::events::Observer;
+use crate::game::{Event, Game};
+use crate::tetromino::{Rotation, Tetromino};
+use nalgebra::Point2;
+use std::cmp::Ordering;
+
+/// How close two scores have to be to count as the same.
+const TIE: f32 = 1e-4;
+/// The most placements that can do better for one to still be good.
+const GOOD_BEHIND: usize = 2;
+
+/// How a placement compared with the others the piece had.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grade {
+    /// Nothing did better.
+    Best,
+    /// Only a couple did better.
+    Good,
+    /// In the better third.
+    Fair,
+    Poor,
+}
+
+/// The piece in play, rated for where it could go.
+struct Rated {
+    tetromino: Tetromino,
+    /// The board before it locks, the next piece and the row it came in
+    /// on, for scoring where it's put.
+    board: Board,
+    next: Tetromino,
+    from_y: i32,
+    /// The cells of the best placement.
+    best: Vec<Point2<i32>>,
+    /// The score of each placement, those covering the same cells once.
+    scores: Vec<f32>,
+}
+
+pub struct Tutor {
+    ai: Ai,
+    /// When the round being watched started, to start over with the next.
+    round: Option<Instant>,
+    rated: Option<Rated>,
+    grade: Option<Grade>,
+}
+
+impl Tutor {
+    pub fn new(weights: Weights) -> Tutor {
+        Tutor {
+            ai: Ai::new(weights),
+            round: None,
+            rated: None,
+            grade: None,
+        }
+    }
+
+    /// The cells of the suggested placement for the piece in play, until
+    /// it locks.
+    pub fn suggestion(&self) -> Option<&[Point2<i32>]> {
+        self.rated.as_ref().map(|rated| &rated.best[..])
+    }
+
+    /// How the last piece placed compared.
+    pub fn grade(&self) -> Option<Grade> {
+        self.grade
+    }
+
+    /// Rates each place the piece in play in `game` could go.
+    fn rate(&mut self, game: &Game) {
+        let tetromino = game.tetromino();
+        let (pos, _) = game.piece();
+        let next = game.next();
+        let mut options: Vec<(Vec<Point2<i32>>, f32)> = Vec::new();
+        for (placement, after, cleared) in ai::placements(&game.board, tetromino, pos[1]) {
+            let mut cells = tetromino.blocks(placement.pos, placement.facing);
+            cells.sort_by_key(|cell| (cell[0], cell[1]));
+            if options.iter().any(|(seen, _)| *seen == cells) {
+                continue;
+            }
+            let score = self.ai.rate(&after, cleared, next, pos[1]);
+            options.push((cells, score));
+        }
+        self.rated = options
+            .iter()
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+            .map(|(best, _)| Rated {
+                tetromino,
+                board: game.board.clone(),
+                next,
+                from_y: pos[1],
+                best: best.clone(),
+                scores: options.iter().map(|&(_, score)| score).collect(),
+            });
+    }
+
+    /// Grades `tetromino` locked at `pos` facing `facing` against the
+    /// other places it could have gone.
+    fn mark(&mut self, tetromino: Tetromino, pos: Point2<i32>, facing: Rotation) {
+        let rated = match self.rated.take() {
+            Some(rated) if rated.tetromino == tetromino => rated,
+            _ => return,
+        };
+        let mut after = rated.board;
+        for block in tetromino.blocks(pos, facing) {
+            after.set(block, FixedBlock::Piece(tetromino));
+        }
+        let cleared = after.clear_lines();
+        let score = self.ai.rate(&after, cleared, rated.next, rated.from_y);
+        let ahead = rated
+            .scores
+            .iter()
+            .filter(|&&other| other > score + TIE)
+            .count();
+        self.grade = Some(match ahead {
+            0 => Grade::Best,
+            _ if ahead <= GOOD_BEHIND => Grade::Good,
+            _ if ahead * 3 <= rated.scores.len() => Grade::Fair,
+            _ => Grade::Poor,
+        });
+    }
+}
+
+impl Observer for Tutor {
+    fn notify(&mut self, event: Event, _: u64, game: &Game) {
+        if self.round != Some(game.started_at()) {
+            self.round = Some(game.started_at());
+            self.rated = None;
+            self.grade = None;
+        }
+        match event {
+            Event::Spawn(_) => self.rate(game),
+            Event::Lock {
+                tetromino,
+                pos,
+                facing,
+                ..
+            } => self.mark(tetromino, pos, facing),
+            _ => (),
+        }
+    }
+}
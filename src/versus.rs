@@ -0,0 +1,444 @@
+use crate::config::Config;
+use crate::layout::{Layout, MINI_LABEL_HEIGHT, MINI_SCALE};
+use crate::locale::Locale;
+use crate::net::{Connection, Message};
+use crate::render::BoardRenderer;
+use crate::text;
+use ggez::graphics::Color;
+use ggez::nalgebra::Point2;
+use ggez::{Context, GameResult};
+use std::collections::VecDeque;
+use tetris_rs::board::Board;
+use tetris_rs::bot::BotDriver;
+use tetris_rs::events::Observer;
+use tetris_rs::game::{Event, Game, Settings};
+use tetris_rs::replay::{self, TimedAction};
+use tetris_rs::targeting::{Field, Targeting};
+
+/// The most CPUs a versus round can be played against.
+pub const MAX_CPUS: usize = 3;
+/// How the CPUs pick who to attack, by their place in the round, so a
+/// round against several has a mix.
+const CPU_TARGETING: [Targeting; 3] = [Targeting::Random, Targeting::Attackers, Targeting::Leader];
+
+/// Who plays the other side of a versus round.
+pub enum Controller {
+    Bot(BotDriver),
+    /// Another player over the network, whose game is run here from the
+    /// actions they send.
+    Remote(Remote),
+}
+
+pub struct Remote {
+    /// Where the messages come from, or `None` if they're fed in by hand.
+    link: Option<Connection>,
+    /// Actions received but not yet reached.
+    actions: VecDeque<TimedAction>,
+    /// The other side has sent everything before this frame.
+    confirmed: u64,
+    /// Messages read off the link, kept to pass on to spectators.
+    received: Vec<Message>,
+}
+
+impl Remote {
+    pub fn new(connection: Connection) -> Remote {
+        Remote {
+            link: Some(connection),
+            ..Remote::relayed()
+        }
+    }
+
+    /// A player being watched, whose messages come through `Opponent::feed`.
+    pub fn relayed() -> Remote {
+        Remote {
+            link: None,
+            actions: VecDeque::new(),
+            confirmed: 0,
+            received: Vec::new(),
+        }
+    }
+
+    fn feed(&mut self, message: Message) {
+        match message {
+            Message::Act(action) => self.actions.push_back(action),
+            Message::Frame(frame) => self.confirmed = frame,
+            // Only sent to set the round up.
+            Message::Hello(_) | Message::Watch | Message::Ready => (),
+            // Chat is for the player to read, not the game.
+            Message::Say(_) => (),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// Both sides are still playing, or it isn't known yet whether the
+    /// other side outlasted this one.
+    Undecided,
+    Win,
+    Lose,
+    /// Both topped out on the same frame.
+    Draw,
+    /// The other player disconnected.
+    Left,
+}
+
+/// The other side of a versus round, on a board of its own, played in step
+/// with this side's frames.
+pub struct Opponent {
+    pub game: Game,
+    controller: Controller,
+    renderer: BoardRenderer,
+    /// Logic steps run on the opponent's game so far.
+    frame: u64,
+    /// The frame the opponent topped out on.
+    over_at: Option<u64>,
+}
+
+impl Opponent {
+    pub fn new(
+        ctx: &mut Context,
+        config: &Config,
+        board: Board,
+        settings: Settings,
+        controller: Controller,
+        cell_size: i32,
+    ) -> GameResult<Opponent> {
+        Ok(Opponent {
+            game: Game::new(board, settings),
+            controller,
+            renderer: BoardRenderer::sized(ctx, config, cell_size)?,
+            frame: 0,
+            over_at: None,
+        })
+    }
+
+    pub fn is_remote(&self) -> bool {
+        match self.controller {
+            Controller::Bot(_) => false,
+            Controller::Remote(_) => true,
+        }
+    }
+
+    /// Tells a remote opponent about this side's game.
+    pub fn send(&mut self, message: &Message) {
+        if let Controller::Remote(Remote {
+            link: Some(ref mut connection),
+            ..
+        }) = self.controller
+        {
+            connection.send(message);
+        }
+    }
+
+    /// Hands a message to a remote opponent fed by hand.
+    pub fn feed(&mut self, message: Message) {
+        if let Controller::Remote(ref mut remote) = self.controller {
+            remote.feed(message);
+        }
+    }
+
+    /// The messages a remote opponent has sent since the last call.
+    pub fn take_received(&mut self) -> Vec<Message> {
+        match self.controller {
+            Controller::Remote(ref mut remote) => std::mem::take(&mut remote.received),
+            Controller::Bot(_) => Vec::new(),
+        }
+    }
+
+    pub fn over_at(&self) -> Option<u64> {
+        self.over_at
+    }
+
+    /// Sends garbage from this side. A remote opponent's own game takes
+    /// it, from its copy of this side.
+    pub fn attack(&mut self, rows: u32) {
+        if let Controller::Bot(_) = self.controller {
+            self.game.receive_garbage(rows);
+        }
+    }
+
+    /// Runs the opponent's game up to frame `until`, or as far as a remote
+    /// opponent has got, returning the garbage it sent this side.
+    pub fn advance(&mut self, until: u64) -> Vec<u32> {
+        let mut attacks = Vec::new();
+        if let Controller::Remote(ref mut remote) = self.controller {
+            let messages = match remote.link {
+                Some(ref mut connection) => connection.receive(),
+                None => Vec::new(),
+            };
+            for message in messages {
+                remote.feed(message.clone());
+                remote.received.push(message);
+            }
+        }
+        while self.frame < until && !self.game.game_over {
+            let now = self.game.started_at() + replay::frame_time(self.frame);
+            match self.controller {
+                Controller::Bot(ref mut bot) => bot.step(&mut self.game, now),
+                Controller::Remote(ref mut remote) => {
+                    if self.frame >= remote.confirmed {
+                        break;
+                    }
+                    while let Some(&timed) = remote.actions.front() {
+                        if timed.frame > self.frame {
+                            break;
+                        }
+                        timed.action.apply(&mut self.game, now);
+                        remote.actions.pop_front();
+                    }
+                }
+            }
+            self.frame += 1;
+            self.game
+                .update(self.game.started_at() + replay::frame_time(self.frame));
+            // Only this side's game makes sounds.
+            for event in self.game.take_events() {
+                if let Event::Attack(rows) = event {
+                    attacks.push(rows);
+                }
+            }
+            if self.game.game_over {
+                self.over_at = Some(self.frame);
+            }
+        }
+        attacks
+    }
+
+    /// How the round stands, given the frame this side topped out on if it
+    /// has. Whoever lasts more frames wins, so both ends of a network game
+    /// agree however far behind either one's copy of the other is.
+    pub fn outcome(&self, over_at: Option<u64>) -> Outcome {
+        match (over_at, self.over_at) {
+            (None, Some(_)) => Outcome::Win,
+            (Some(ours), Some(theirs)) if theirs < ours => Outcome::Win,
+            (Some(ours), Some(theirs)) if theirs == ours => Outcome::Draw,
+            (Some(ours), _) if self.frame >= ours => Outcome::Lose,
+            _ => match self.controller {
+                Controller::Remote(Remote {
+                    link: Some(ref connection),
+                    ..
+                }) if connection.is_closed() => Outcome::Left,
+                _ => Outcome::Undecided,
+            },
+        }
+    }
+
+    /// Draws the opponent's board on its own, live with their piece and
+    /// the garbage coming at them, at `origin` with `label` over it.
+    /// Their panels are left out to keep it small beside the player's.
+    fn draw_mini(
+        &mut self,
+        ctx: &mut Context,
+        origin: Point2<f32>,
+        label: &str,
+        color: Color,
+    ) -> GameResult {
+        self.renderer.draw(ctx, &self.game, origin)?;
+        let at = Point2::new(origin[0], origin[1] - MINI_LABEL_HEIGHT + 4.0);
+        text::draw(ctx, label, 14.0, color, at)
+    }
+
+    pub fn draw_labelled(
+        &mut self,
+        ctx: &mut Context,
+        layout: Layout,
+        locale: &Locale,
+        label: &str,
+        color: Color,
+    ) -> GameResult {
+        let origin = layout.board();
+        self.renderer.draw(ctx, &self.game, origin)?;
+        self.renderer.draw_panels(ctx, &self.game, locale, layout)?;
+        text::draw(ctx, label, 14.0, color, Point2::new(origin[0] + 4.0, 4.0))
+    }
+}
+
+/// Everyone this side plays against in a versus round, one player online
+/// or one or more CPUs, and who everyone is sending garbage to. In the
+/// field this side is player 0 and the opponents follow, in order.
+pub struct Versus {
+    opponents: Vec<Opponent>,
+    field: Field,
+}
+
+impl Versus {
+    /// A round against `opponents`, this side targeting by `strategy`,
+    /// with random picks made from `seed`.
+    pub fn new(opponents: Vec<Opponent>, strategy: Targeting, seed: u64) -> Versus {
+        let strategies: Vec<Targeting> = std::iter::once(strategy)
+            .chain((0..opponents.len()).map(|at| CPU_TARGETING[at % CPU_TARGETING.len()]))
+            .collect();
+        Versus {
+            field: Field::new(&strategies, seed),
+            opponents,
+        }
+    }
+
+    /// How many opponents there are.
+    pub fn opponents(&self) -> usize {
+        self.opponents.len()
+    }
+
+    pub fn is_remote(&self) -> bool {
+        self.opponents.iter().any(Opponent::is_remote)
+    }
+
+    /// Whether there's more than one opponent, so a choice of who to
+    /// attack.
+    pub fn is_crowded(&self) -> bool {
+        self.opponents.len() > 1
+    }
+
+    /// Tells a remote opponent about this side's game.
+    pub fn send(&mut self, message: &Message) {
+        for opponent in self.opponents.iter_mut() {
+            opponent.send(message);
+        }
+    }
+
+    /// The messages a remote opponent has sent since the last call.
+    pub fn take_received(&mut self) -> Vec<Message> {
+        self.opponents
+            .iter_mut()
+            .flat_map(Opponent::take_received)
+            .collect()
+    }
+
+    /// Runs the opponents' games up to frame `until`, passing the garbage
+    /// they send each other on, and returns the garbage they sent this
+    /// side.
+    pub fn advance(&mut self, until: u64) -> Vec<u32> {
+        let mut ours = Vec::new();
+        for at in 0..self.opponents.len() {
+            let player = at + 1;
+            let was_over = self.opponents[at].over_at().is_some();
+            for rows in self.opponents[at].advance(until) {
+                for target in self.field.attack(player, rows) {
+                    match target {
+                        0 => ours.push(rows),
+                        _ => self.opponents[target - 1].attack(rows),
+                    }
+                }
+            }
+            if !was_over && self.opponents[at].over_at().is_some() {
+                self.field.knock_out(player);
+            }
+        }
+        ours
+    }
+
+    /// Sends garbage from this side to whoever it's aimed at.
+    pub fn attack(&mut self, rows: u32) {
+        for target in self.field.attack(0, rows) {
+            if let Some(opponent) = target
+                .checked_sub(1)
+                .and_then(|at| self.opponents.get_mut(at))
+            {
+                opponent.attack(rows);
+            }
+        }
+    }
+
+    /// Steps this side's target on to the next opponent still in, by hand.
+    pub fn cycle_target(&mut self) {
+        self.field.cycle(0);
+    }
+
+    /// How the round stands, given the frame this side topped out on if it
+    /// has. It's won once every opponent is out, and lost to any who
+    /// outlast this side.
+    pub fn outcome(&self, over_at: Option<u64>) -> Outcome {
+        let outcomes: Vec<Outcome> = self
+            .opponents
+            .iter()
+            .map(|opponent| opponent.outcome(over_at))
+            .collect();
+        let any = |outcome| outcomes.contains(&outcome);
+        if any(Outcome::Left) {
+            Outcome::Left
+        } else if any(Outcome::Lose) {
+            Outcome::Lose
+        } else if any(Outcome::Undecided) {
+            Outcome::Undecided
+        } else if any(Outcome::Draw) {
+            Outcome::Draw
+        } else {
+            Outcome::Win
+        }
+    }
+
+    /// What player `at` of the field goes by.
+    fn name(&self, locale: &Locale, at: usize) -> String {
+        match at {
+            0 => locale.get("versus.you").to_string(),
+            _ => locale.format("versus.cpu_numbered", &[&at]),
+        }
+    }
+
+    /// Draws the opponents' mini boards in a row beside `layout`'s. With
+    /// more than one, each is labelled with who it's aiming at, those this
+    /// side is aiming at in `accent`, and this side's targeting goes
+    /// under them.
+    pub fn draw(
+        &mut self,
+        ctx: &mut Context,
+        layout: Layout,
+        locale: &Locale,
+        color: Color,
+        accent: Color,
+    ) -> GameResult {
+        let crowded = self.is_crowded();
+        for at in 0..self.opponents.len() {
+            let player = at + 1;
+            let (label, color) = if !crowded {
+                let label = if self.opponents[at].is_remote() {
+                    "versus.opponent"
+                } else {
+                    "versus.cpu"
+                };
+                (locale.get(label).to_string(), color)
+            } else {
+                let targets: Vec<String> = self
+                    .field
+                    .targets(player)
+                    .iter()
+                    .map(|&target| self.name(locale, target))
+                    .collect();
+                let name = self.name(locale, player);
+                let label = if targets.is_empty() {
+                    name
+                } else {
+                    locale.format("versus.aiming", &[&name, &targets.join(", ")])
+                };
+                let aimed = self.field.targets(0).contains(&player);
+                (label, if aimed { accent } else { color })
+            };
+            let origin = layout.mini_board_at(at);
+            self.opponents[at].draw_mini(ctx, origin, &label, color)?;
+        }
+        if crowded {
+            let strategy = self.field.strategy(0).unwrap_or_default();
+            let lines = [
+                locale.format("versus.targeting", &[&locale.name("targeting", strategy)]),
+                locale.get("versus.cycle").to_string(),
+            ];
+            let origin = layout.mini_board_at(0);
+            let top = origin[1] + layout.height() / MINI_SCALE as f32 + 8.0;
+            for (i, line) in lines.iter().enumerate() {
+                let at = Point2::new(origin[0], top + 20.0 * i as f32);
+                text::draw(ctx, line, 14.0, color, at)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sends the garbage this side's clears make to whoever it's aimed at.
+impl Observer for Versus {
+    fn notify(&mut self, event: Event, _: u64, _: &Game) {
+        if let Event::Attack(rows) = event {
+            self.attack(rows);
+        }
+    }
+}
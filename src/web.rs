@@ -0,0 +1,184 @@
+//! The browser frontend's side of the game, for JavaScript to drive. The
+//! page (see `web/`) forwards key and touch events here, calls `update`
+//! from `requestAnimationFrame` and paints the cells it reads back onto a
+//! canvas.
+
+use crate::board::{Board, FixedBlock};
+use crate::clock::Instant;
+use crate::game::{Game, Input, Settings};
+use crate::gesture::Gestures;
+use crate::tetromino::Tetromino;
+use wasm_bindgen::prelude::*;
+
+/// Added to a piece's color index for the cells of its ghost.
+const GHOST: u8 = 0x80;
+
+/// The action bound to a key, by `KeyboardEvent.code`. The same keys as
+/// the desktop build's defaults.
+fn input_for(code: &str) -> Option<Input> {
+    match code {
+        "ArrowLeft" => Some(Input::Left),
+        "ArrowRight" => Some(Input::Right),
+        "ArrowUp" => Some(Input::RotateCw),
+        "KeyX" => Some(Input::RotateCcw),
+        "ArrowDown" => Some(Input::SoftDrop),
+        "Space" => Some(Input::HardDrop),
+        "KeyC" | "ShiftLeft" => Some(Input::Hold),
+        "KeyZ" => Some(Input::Undo),
+        _ => None,
+    }
+}
+
+/// Index into `WebGame::colors` for a piece.
+fn piece_index(tetromino: Tetromino) -> u8 {
+    let index = Tetromino::every().position(|t| t == tetromino).unwrap_or(0);
+    (index + 2) as u8
+}
+
+#[wasm_bindgen]
+pub struct WebGame {
+    game: Game,
+    gestures: Gestures,
+}
+
+#[wasm_bindgen]
+impl WebGame {
+    /// A standard endless round. The page picks the seed, as there's no OS
+    /// randomness to draw on.
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: u32) -> WebGame {
+        let settings = Settings {
+            seed: u64::from(seed),
+            ..Settings::default()
+        };
+        WebGame {
+            game: Game::new(Board::default(), settings),
+            gestures: Gestures::default(),
+        }
+    }
+
+    /// Handles a `keydown`, returning whether the key is one of the game's,
+    /// so the page knows to stop it scrolling.
+    pub fn key_down(&mut self, code: &str) -> bool {
+        match input_for(code) {
+            Some(input) => {
+                self.game.press(input, Instant::now());
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn key_up(&mut self, code: &str) -> bool {
+        match input_for(code) {
+            Some(input) => {
+                self.game.release(input);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Handles a touch going down on the board, at `x`, `y` in cells from
+    /// its top-left corner, as do `touch_move` and `touch_end`.
+    pub fn touch_start(&mut self, x: f32, y: f32) {
+        self.gestures.start(x, y);
+    }
+
+    pub fn touch_move(&mut self, x: f32, y: f32) {
+        for input in self.gestures.drag(x, y) {
+            self.tap(input);
+        }
+    }
+
+    pub fn touch_end(&mut self, x: f32, y: f32) {
+        if let Some(input) = self.gestures.end(x, y) {
+            self.tap(input);
+        }
+    }
+
+    /// Presses and lets go of `input` straight away, so a gesture's moves
+    /// never start auto-repeat.
+    fn tap(&mut self, input: Input) {
+        self.game.press(input, Instant::now());
+        self.game.release(input);
+    }
+
+    /// Advances the game to now. Called once an animation frame.
+    pub fn update(&mut self) {
+        self.game.update(Instant::now());
+        self.game.take_events();
+    }
+
+    pub fn width(&self) -> usize {
+        self.game.board.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.game.board.height()
+    }
+
+    /// The board row by row, top first, one byte a cell: 0 for empty, else
+    /// an index into `colors`, plus 0x80 where it's the ghost.
+    pub fn cells(&self) -> Vec<u8> {
+        let width = self.width();
+        let mut cells = vec![0; width * self.height()];
+        let mut fill = |x: i32, y: i32, value: u8| {
+            if x >= 0 && y >= 0 && (x as usize) < width {
+                if let Some(cell) = cells.get_mut(y as usize * width + x as usize) {
+                    *cell = value;
+                }
+            }
+        };
+        let piece = piece_index(self.game.tetromino());
+        for block in self.game.ghost_blocks() {
+            fill(block[0], block[1], piece | GHOST);
+        }
+        for (block, fixed) in self.game.board.blocks() {
+            let value = match fixed {
+                FixedBlock::Garbage => 1,
+                FixedBlock::Piece(tetromino) => piece_index(tetromino),
+            };
+            fill(block[0], block[1], value);
+        }
+        if !self.game.game_over {
+            for block in self.game.active_blocks() {
+                fill(block[0], block[1], piece);
+            }
+        }
+        cells
+    }
+
+    /// Red, green and blue for each color index `cells` uses.
+    pub fn colors(&self) -> Vec<u8> {
+        let mut colors = vec![0, 0, 0];
+        let (r, g, b) = FixedBlock::Garbage.rgb();
+        colors.extend_from_slice(&[r, g, b]);
+        for tetromino in Tetromino::every() {
+            let (r, g, b) = tetromino.rgb();
+            colors.extend_from_slice(&[r, g, b]);
+        }
+        colors
+    }
+
+    /// The next piece's color index.
+    pub fn next(&self) -> u8 {
+        piece_index(self.game.next())
+    }
+
+    pub fn score(&self) -> u32 {
+        self.game.score
+    }
+
+    pub fn lines(&self) -> u32 {
+        self.game.lines
+    }
+
+    pub fn level(&self) -> u32 {
+        self.game.level
+    }
+
+    pub fn game_over(&self) -> bool {
+        self.game.game_over
+    }
+}
@@ -0,0 +1,108 @@
+import init, { WebGame } from "./pkg/tetris_rs.js";
+
+const CELL_SIZE = 24;
+const GHOST = 0x80;
+
+function rgb(colors, index, alpha) {
+  const [r, g, b] = colors.slice(index * 3, index * 3 + 3);
+  return `rgba(${r}, ${g}, ${b}, ${alpha})`;
+}
+
+async function run() {
+  await init();
+  let game = new WebGame(Math.floor(Math.random() * 0xffffffff));
+  const colors = game.colors();
+  const canvas = document.getElementById("board");
+  const status = document.getElementById("status");
+  canvas.width = game.width() * CELL_SIZE;
+  canvas.height = game.height() * CELL_SIZE;
+  const context = canvas.getContext("2d");
+
+  function restart() {
+    game.free();
+    game = new WebGame(Math.floor(Math.random() * 0xffffffff));
+  }
+
+  document.addEventListener("keydown", (event) => {
+    if (event.code === "Enter" && game.game_over()) {
+      restart();
+    } else if (!event.repeat && game.key_down(event.code)) {
+      event.preventDefault();
+    }
+  });
+  document.addEventListener("keyup", (event) => {
+    if (game.key_up(event.code)) {
+      event.preventDefault();
+    }
+  });
+
+  // Touches, and the mouse with a button down, on the board, in cells.
+  function cell(event) {
+    const rect = canvas.getBoundingClientRect();
+    const scale = canvas.width / rect.width;
+    return [
+      ((event.clientX - rect.left) * scale) / CELL_SIZE,
+      ((event.clientY - rect.top) * scale) / CELL_SIZE,
+    ];
+  }
+  canvas.addEventListener("pointerdown", (event) => {
+    if (game.game_over()) {
+      restart();
+      return;
+    }
+    canvas.setPointerCapture(event.pointerId);
+    game.touch_start(...cell(event));
+  });
+  canvas.addEventListener("pointermove", (event) => {
+    if (canvas.hasPointerCapture(event.pointerId)) {
+      game.touch_move(...cell(event));
+    }
+  });
+  canvas.addEventListener("pointerup", (event) => {
+    game.touch_end(...cell(event));
+  });
+
+  // The on-screen buttons stand in for the keys they're marked with, held
+  // down for as long as they are.
+  const buttons = document.getElementById("buttons");
+  const showButtons = document.getElementById("show-buttons");
+  showButtons.checked = matchMedia("(pointer: coarse)").matches;
+  buttons.hidden = !showButtons.checked;
+  showButtons.addEventListener("change", () => {
+    buttons.hidden = !showButtons.checked;
+  });
+  for (const button of buttons.querySelectorAll("button")) {
+    const code = button.dataset.code;
+    button.addEventListener("pointerdown", (event) => {
+      event.preventDefault();
+      game.key_down(code);
+    });
+    for (const type of ["pointerup", "pointerleave", "pointercancel"]) {
+      button.addEventListener(type, () => game.key_up(code));
+    }
+  }
+
+  function frame() {
+    game.update();
+    const width = game.width();
+    const cells = game.cells();
+    context.clearRect(0, 0, canvas.width, canvas.height);
+    cells.forEach((cell, i) => {
+      if (cell === 0) {
+        return;
+      }
+      const ghost = (cell & GHOST) !== 0;
+      context.fillStyle = rgb(colors, cell & ~GHOST, ghost ? 0.3 : 1.0);
+      const x = (i % width) * CELL_SIZE;
+      const y = Math.floor(i / width) * CELL_SIZE;
+      context.fillRect(x, y, CELL_SIZE - 1, CELL_SIZE - 1);
+    });
+    status.textContent = game.game_over()
+      ? `Game over with ${game.score()} points. Enter or tap to play again.`
+      : `Score ${game.score()}  Lines ${game.lines()}  Level ${game.level()}`;
+    requestAnimationFrame(frame);
+  }
+  requestAnimationFrame(frame);
+}
+
+run();